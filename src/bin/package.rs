@@ -100,7 +100,7 @@ fn package_build_directory() {
                 .unwrap();
             let mut f = std::fs::File::open(path).unwrap();
             std::io::copy(&mut f, &mut zip).unwrap();
-        } else if relative_path.as_os_str().len() > 0 {
+        } else if !relative_path.as_os_str().is_empty() {
             zip.add_directory(relative_path.to_string_lossy(), options)
                 .unwrap();
         }
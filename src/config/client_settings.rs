@@ -1,12 +1,38 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// API 服务类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    /// OpenAI 兼容 API：`{api_base}/chat/completions`，Bearer 认证
+    #[default]
+    Openai,
+    /// Azure OpenAI：`{api_base}/openai/deployments/{deployment}/chat/completions?api-version=...`，
+    /// 以 `api-key` 头认证
+    Azure,
+}
 
 /// 大模型客户端设置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientSettings {
-    /// API基础URL（OpenAI兼容格式）
+    /// API 服务类型
+    #[serde(default)]
+    pub provider: Provider,
+
+    /// API基础URL（OpenAI兼容格式；Azure 为资源地址，例如 `https://<资源名>.openai.azure.com`）
     #[serde(default = "default_api_base")]
     pub api_base: String,
 
+    /// Azure OpenAI 的部署名称（默认与模型名称相同）
+    #[serde(default)]
+    pub azure_deployment: Option<String>,
+
+    /// Azure OpenAI 的 API 版本
+    #[serde(default = "default_azure_api_version")]
+    pub azure_api_version: String,
+
     /// 模型名称
     #[serde(default = "default_model")]
     pub model: String,
@@ -15,6 +41,27 @@ pub struct ClientSettings {
     #[serde(default = "default_temperature")]
     pub temperature: f32,
 
+    /// 核采样概率（0.0-1.0），未设置时使用模型的默认值
+    #[serde(default)]
+    pub top_p: Option<f32>,
+
+    /// 频率惩罚（-2.0-2.0），正值降低重复输出相同内容（例如重复的键）的概率
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+
+    /// 存在惩罚（-2.0-2.0），正值降低重复已出现内容的概率
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+
+    /// 停止序列（最多 4 个），模型输出其中之一时停止，可用于截断译文之后的说明文字
+    #[serde(default)]
+    pub stop: Vec<String>,
+
+    /// 随机种子（OpenAI、DeepSeek 等支持），设置后每个切片的种子由该值与切片内容共同决定，
+    /// 重新运行时模型尽量给出相同的译文
+    #[serde(default)]
+    pub seed: Option<u64>,
+
     /// 请求超时时间（秒）
     #[serde(default = "default_timeout")]
     pub timeout_secs: u64,
@@ -32,27 +79,104 @@ pub struct ClientSettings {
     #[serde(default = "default_max_chunk_tokens")]
     pub max_chunk_tokens: usize,
 
+    /// 相邻切片之间重叠的行数：每个切片附带前一个切片的最后几行与后一个切片的开头几行，
+    /// 只作为上下文发送，不翻译也不合并到译文中。默认 0，不重叠
+    #[serde(default)]
+    pub chunk_overlap: usize,
+
+    /// 模型的上下文长度（token），未设置时按模型名称推测。发送前估算提示词的 token 数，
+    /// 超出时先删减术语表，仍然超出再将切片拆小
+    #[serde(default)]
+    pub context_window: Option<u32>,
+
     /// 是否启用流式响应
     #[serde(default)]
     pub stream: bool,
 
+    /// 按文件保持会话：同一文件的切片依次在一个会话中翻译，系统提示词只随第一个切片发送，
+    /// 之后的切片附带之前的原文与译文。有助于译法一致，支持提示词缓存的服务商还能命中缓存；
+    /// 启用后同一文件的切片不再并发翻译
+    #[serde(default)]
+    pub conversation: bool,
+
     /// 并发请求数(默认2)
     #[serde(default = "default_concurrency")]
     pub concurrency: usize,
+
+    /// 自适应并发的最大并发数（可选）：设置后并发数从 `concurrency` 开始，请求顺利时逐步提高到该值，
+    /// 遇到限流（429）或超时时减半并按 `max_retries` 退避重试，延迟明显上升时降低
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+
+    /// 请求 API 使用的代理（`http://`、`https://` 或 `socks5://`），未设置时使用 `HTTPS_PROXY` 等环境变量
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+
+    /// 不经过代理的主机，以逗号分隔（与 `NO_PROXY` 环境变量格式相同），仅在设置 `proxy_url` 时生效
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+
+    /// 额外信任的 CA 证书文件（PEM 格式，可包含多个证书），用于自签名证书的代理或私有部署的 API
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+
+    /// 每个请求额外附带的 HTTP 头，例如 OpenRouter 的 `HTTP-Referer` 与 `X-Title`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// 每个请求额外附带的 URL 查询参数，例如 Azure OpenAI 的 `api-version`
+    #[serde(default)]
+    pub query: BTreeMap<String, String>,
+
+    /// 原样合并到请求体中的其他参数，例如 `enable_thinking`、`reasoning_effort` 或 OpenRouter 的 `provider` 路由设置
+    #[serde(default)]
+    pub extra_body: BTreeMap<String, serde_json::Value>,
 }
 
+/// 请求体中由其他设置决定的字段，不能在 `extra_body` 中指定
+const REQUEST_FIELDS: &[&str] = &[
+    "model",
+    "messages",
+    "temperature",
+    "top_p",
+    "frequency_penalty",
+    "presence_penalty",
+    "stop",
+    "max_tokens",
+    "stream",
+    "seed",
+];
+
 impl Default for ClientSettings {
     fn default() -> Self {
         Self {
+            provider: Provider::default(),
             api_base: default_api_base(),
+            azure_deployment: None,
+            azure_api_version: default_azure_api_version(),
             model: default_model(),
             temperature: default_temperature(),
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            stop: Vec::new(),
+            seed: None,
             timeout_secs: default_timeout(),
             max_retries: default_max_retries(),
             max_tokens: default_max_tokens(),
             max_chunk_tokens: default_max_chunk_tokens(),
+            chunk_overlap: 0,
+            context_window: None,
             stream: false,
+            conversation: false,
             concurrency: default_concurrency(),
+            max_concurrency: None,
+            proxy_url: None,
+            no_proxy: None,
+            ca_bundle: None,
+            headers: BTreeMap::new(),
+            query: BTreeMap::new(),
+            extra_body: BTreeMap::new(),
         }
     }
 }
@@ -61,6 +185,10 @@ fn default_api_base() -> String {
     "https://api.deepseek.com".to_string()
 }
 
+fn default_azure_api_version() -> String {
+    "2024-10-21".to_string()
+}
+
 fn default_model() -> String {
     "deepseek-reasoner".to_string()
 }
@@ -90,33 +218,148 @@ fn default_concurrency() -> usize {
     2
 }
 
+/// 未知模型的上下文长度
+const DEFAULT_CONTEXT_WINDOW: u32 = 32_768;
+
+/// 常见模型的上下文长度（token），按模型名称前缀匹配，更具体的前缀在前
+const KNOWN_CONTEXT_WINDOWS: &[(&str, u32)] = &[
+    ("deepseek", 65_536),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("claude", 200_000),
+    ("gemini", 1_048_576),
+    ("qwen", 32_768),
+];
+
+/// 按模型名称推测上下文长度：名称中带有 `-8k`、`-128k` 一类后缀时以后缀为准，
+/// 其次查找常见模型，都不匹配时使用保守的默认值
+pub fn known_context_window(model: &str) -> u32 {
+    let model = model.to_ascii_lowercase();
+    // 带服务商前缀的名称，例如 OpenRouter 的 `openai/gpt-4o`
+    let name = model.rsplit('/').next().unwrap_or(&model);
+    let suffix = name.split(['-', '_', ':']).find_map(|part| {
+        part.strip_suffix('k')
+            .and_then(|n| n.parse::<u32>().ok())
+            .map(|n| n * 1024)
+    });
+    suffix
+        .or_else(|| {
+            KNOWN_CONTEXT_WINDOWS
+                .iter()
+                .find(|(prefix, _)| name.starts_with(prefix))
+                .map(|(_, window)| *window)
+        })
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
 impl ClientSettings {
     /// 验证设置是否有效
     pub fn validate(&self) -> Result<(), crate::error::ConfigError> {
         let mut errors: Vec<crate::error::ConfigError> = Vec::new();
 
         if self.temperature < 0.0 || self.temperature > 2.0 {
-            errors.push(crate::error::ConfigError::InvalidValue(
-                "temperature must be between 0.0 and 2.0".to_string(),
-            ));
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.temperature_range"
+            )));
+        }
+
+        if let Some(top_p) = self.top_p
+            && !(0.0..=1.0).contains(&top_p)
+        {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.top_p_range"
+            )));
+        }
+
+        for (name, penalty) in [
+            ("frequency_penalty", self.frequency_penalty),
+            ("presence_penalty", self.presence_penalty),
+        ] {
+            if let Some(penalty) = penalty
+                && !(-2.0..=2.0).contains(&penalty)
+            {
+                errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                    "config.penalty_range",
+                    name
+                )));
+            }
+        }
+
+        if self.stop.len() > 4 || self.stop.iter().any(|s| s.is_empty()) {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.stop_invalid"
+            )));
         }
 
         if self.timeout_secs == 0 {
-            errors.push(crate::error::ConfigError::InvalidValue(
-                "timeout_secs must be greater than 0".to_string(),
-            ));
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.timeout_zero"
+            )));
         }
 
         if self.max_chunk_tokens < 100 {
-            errors.push(crate::error::ConfigError::InvalidValue(
-                "max_chunk_tokens must be at least 100 characters".to_string(),
-            ));
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.max_chunk_tokens_too_low"
+            )));
         }
 
         if self.concurrency < 1 {
-            errors.push(crate::error::ConfigError::InvalidValue(
-                "concurrency must be at least 1".to_string(),
-            ));
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.concurrency_zero"
+            )));
+        }
+
+        if let Some(max) = self.max_concurrency
+            && max < self.concurrency
+        {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.max_concurrency_too_low",
+                self.concurrency
+            )));
+        }
+
+        if let Some(proxy_url) = &self.proxy_url
+            && let Err(e) = reqwest::Proxy::all(proxy_url)
+        {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.proxy_url_invalid",
+                e
+            )));
+        }
+
+        if let Some(ca_bundle) = &self.ca_bundle
+            && !ca_bundle.is_file()
+        {
+            errors.push(crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.ca_bundle_missing",
+                ca_bundle.display()
+            )));
+        }
+
+        for (name, value) in &self.headers {
+            if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+                || reqwest::header::HeaderValue::from_str(value).is_err()
+            {
+                errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                    "config.header_invalid",
+                    name
+                )));
+            }
+        }
+
+        for name in self.extra_body.keys() {
+            if REQUEST_FIELDS.contains(&name.as_str()) {
+                errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                    "config.extra_body_reserved",
+                    name
+                )));
+            }
         }
 
         if errors.is_empty() {
@@ -126,8 +369,105 @@ impl ClientSettings {
         }
     }
 
-    /// 获取完整的API端点URL
+    /// 模型的上下文长度，未设置 `context_window` 时按模型名称推测
+    pub fn context_window(&self) -> u32 {
+        self.context_window
+            .unwrap_or_else(|| known_context_window(&self.model))
+    }
+
+    /// 获取完整的API端点URL（不含查询参数）
     pub fn chat_completions_url(&self) -> String {
-        format!("{}/chat/completions", self.api_base)
+        match self.provider {
+            Provider::Openai => format!("{}/chat/completions", self.api_base),
+            Provider::Azure => {
+                // 资源地址可以带或不带 `/openai`
+                let base = self.api_base.trim_end_matches('/');
+                let base = base.strip_suffix("/openai").unwrap_or(base);
+                let deployment = self.azure_deployment.as_deref().unwrap_or(&self.model);
+                format!(
+                    "{}/openai/deployments/{}/chat/completions",
+                    base, deployment
+                )
+            }
+        }
+    }
+
+    /// 每个请求附带的 URL 查询参数，Azure OpenAI 未在 `query` 中指定 `api-version` 时自动添加
+    pub fn request_query(&self) -> Vec<(&str, &str)> {
+        let mut query: Vec<(&str, &str)> = self
+            .query
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        if self.provider == Provider::Azure && !self.query.contains_key("api-version") {
+            query.push(("api-version", &self.azure_api_version));
+        }
+        query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_azure_endpoint() {
+        let mut settings = ClientSettings {
+            provider: Provider::Azure,
+            api_base: "https://contoso.openai.azure.com/openai/".to_string(),
+            model: "gpt-4o".to_string(),
+            ..ClientSettings::default()
+        };
+        assert_eq!(
+            settings.chat_completions_url(),
+            "https://contoso.openai.azure.com/openai/deployments/gpt-4o/chat/completions"
+        );
+        assert_eq!(settings.request_query(), [("api-version", "2024-10-21")]);
+
+        settings.azure_deployment = Some("translator".to_string());
+        settings
+            .query
+            .insert("api-version".to_string(), "2025-01-01-preview".to_string());
+        assert!(
+            settings
+                .chat_completions_url()
+                .ends_with("/deployments/translator/chat/completions")
+        );
+        assert_eq!(
+            settings.request_query(),
+            [("api-version", "2025-01-01-preview")]
+        );
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(known_context_window("deepseek-chat"), 65_536);
+        assert_eq!(known_context_window("openai/gpt-4o-mini"), 128_000);
+        assert_eq!(known_context_window("gpt-4-0613"), 8_192);
+        assert_eq!(known_context_window("moonshot-v1-128k"), 131_072);
+        assert_eq!(known_context_window("local-model"), DEFAULT_CONTEXT_WINDOW);
+
+        let settings = ClientSettings {
+            model: "gpt-4o".to_string(),
+            context_window: Some(16_000),
+            ..ClientSettings::default()
+        };
+        assert_eq!(settings.context_window(), 16_000);
+    }
+
+    #[test]
+    fn test_extra_body() {
+        let settings: ClientSettings = toml::from_str(
+            "[extra_body]\nenable_thinking = false\nprovider = { order = [\"deepseek\"] }",
+        )
+        .unwrap();
+        assert!(settings.validate().is_ok());
+        assert_eq!(
+            serde_json::to_value(&settings.extra_body).unwrap(),
+            serde_json::json!({"enable_thinking": false, "provider": {"order": ["deepseek"]}})
+        );
+
+        let settings: ClientSettings = toml::from_str("[extra_body]\nmodel = \"gpt-4o\"").unwrap();
+        assert!(settings.validate().is_err());
     }
 }
@@ -28,3 +28,16 @@ pub fn has_api_key() -> bool {
     let _ = dotenvy::dotenv();
     env::var("OPENAI_API_KEY").is_ok()
 }
+
+/// 从环境变量加载 Paratranz API 令牌
+///
+/// 与 `OPENAI_API_KEY` 相同，优先读取 `PARATRANZ_TOKEN` 环境变量，其次读取 `.env` 文件。
+pub fn load_paratranz_token() -> Result<String, crate::error::TranslationError> {
+    let _ = dotenvy::dotenv();
+
+    env::var("PARATRANZ_TOKEN").map_err(|_| {
+        crate::error::TranslationError::MissingEnvVar(
+            "PARATRANZ_TOKEN environment variable is required".to_string(),
+        )
+    })
+}
@@ -4,8 +4,16 @@
 
 mod client_settings;
 mod env;
+mod notifications;
+mod package;
+mod paratranz;
+mod project;
 mod task;
 
 pub use client_settings::*;
 pub use env::*;
+pub use notifications::*;
+pub use package::*;
+pub use paratranz::*;
+pub use project::*;
 pub use task::*;
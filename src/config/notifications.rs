@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// 触发通知的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// 任务完成
+    Finished,
+    /// 任务失败
+    Failed,
+    /// token 用量超出预算
+    BudgetExceeded,
+}
+
+impl NotificationEvent {
+    /// 事件名称，与配置文件中的写法一致
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationEvent::Finished => "finished",
+            NotificationEvent::Failed => "failed",
+            NotificationEvent::BudgetExceeded => "budget_exceeded",
+        }
+    }
+}
+
+/// 通知设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    /// Webhook 地址（例如 Discord 的 webhook URL）
+    pub webhook_url: String,
+
+    /// 请求体模板（JSON），支持占位符：
+    /// `{{event}}` `{{message}}` `{{task}}` `{{files}}` `{{tokens}}` `{{elapsed}}`
+    #[serde(default = "default_payload_template")]
+    pub payload_template: String,
+
+    /// 需要通知的事件（默认全部）
+    #[serde(default = "default_events")]
+    pub events: Vec<NotificationEvent>,
+
+    /// token 预算，总用量超出时发送一次 budget_exceeded 通知
+    #[serde(default)]
+    pub token_budget: Option<u64>,
+}
+
+// Discord webhook 接受 {"content": "..."}，同时也是大多数通用 webhook 能处理的格式
+fn default_payload_template() -> String {
+    r#"{"content": "{{message}}"}"#.to_string()
+}
+
+fn default_events() -> Vec<NotificationEvent> {
+    vec![
+        NotificationEvent::Finished,
+        NotificationEvent::Failed,
+        NotificationEvent::BudgetExceeded,
+    ]
+}
+
+impl NotificationSettings {
+    /// 验证设置是否有效
+    pub fn validate(&self) -> Result<(), crate::error::ConfigError> {
+        if !self.webhook_url.starts_with("http://") && !self.webhook_url.starts_with("https://") {
+            return Err(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.url_not_http",
+                "notifications.webhook_url"
+            )));
+        }
+        Ok(())
+    }
+
+    /// 是否需要通知指定事件
+    pub fn wants(&self, event: NotificationEvent) -> bool {
+        self.events.contains(&event)
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 翻译子 Mod 打包设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageSettings {
+    /// 子 Mod 名称（例如 "Planetary Features 简体中文翻译"）
+    pub name: String,
+
+    /// 子 Mod 版本
+    #[serde(default = "default_version")]
+    pub version: String,
+
+    /// 支持的游戏版本（例如 "v4.0.*"）
+    #[serde(default)]
+    pub supported_version: Option<String>,
+
+    /// 创意工坊标签
+    #[serde(default = "default_tags")]
+    pub tags: Vec<String>,
+
+    /// 依赖的 Mod 名称，通常为被翻译的原 Mod
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+
+    /// 缩略图路径，会被复制为 thumbnail.png
+    #[serde(default)]
+    pub thumbnail: Option<PathBuf>,
+
+    /// 输出目录
+    #[serde(default = "default_output_dir")]
+    pub output_dir: PathBuf,
+}
+
+fn default_version() -> String {
+    "1.0".to_string()
+}
+
+fn default_tags() -> Vec<String> {
+    vec!["Translation".to_string()]
+}
+
+fn default_output_dir() -> PathBuf {
+    PathBuf::from("dist")
+}
+
+impl PackageSettings {
+    /// 验证设置是否有效
+    pub fn validate(&self) -> Result<(), crate::error::ConfigError> {
+        if self.name.trim().is_empty() {
+            return Err(crate::error::ConfigError::MissingField(
+                "package.name".to_string(),
+            ));
+        }
+        if let Some(thumbnail) = &self.thumbnail
+            && !thumbnail.is_file()
+        {
+            return Err(crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.thumbnail_missing",
+                thumbnail.display()
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// Paratranz 项目同步设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParatranzSettings {
+    /// Paratranz 项目 ID
+    pub project_id: u64,
+
+    /// 项目对应的目标语言（Paratranz 的每个项目只有一个目标语言）
+    pub target_lang: String,
+
+    /// API 地址
+    #[serde(default = "default_api_base")]
+    pub api_base: String,
+
+    /// 拉取时是否也合并未经审核的译文（默认只合并已审核的译文）
+    #[serde(default)]
+    pub include_unreviewed: bool,
+}
+
+fn default_api_base() -> String {
+    "https://paratranz.cn/api".to_string()
+}
+
+impl ParatranzSettings {
+    /// 验证设置是否有效
+    pub fn validate(&self) -> Result<(), crate::error::ConfigError> {
+        if !self.api_base.starts_with("http://") && !self.api_base.starts_with("https://") {
+            return Err(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.url_not_http",
+                "paratranz.api_base"
+            )));
+        }
+        if self.target_lang.trim().is_empty() {
+            return Err(crate::error::ConfigError::MissingField(
+                "paratranz.target_lang".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
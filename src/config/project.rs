@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::config::{ClientSettings, NotificationSettings};
+use crate::translate::DEFAULT_MEMORY_PATH;
+
+/// 项目中的一个 Mod
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectMod {
+    /// Mod 名称（用于日志与通知）
+    pub name: String,
+    /// 该 Mod 的任务配置文件路径，其中的客户端设置会被项目设置覆盖
+    pub task_file: PathBuf,
+}
+
+/// 多 Mod 项目配置（project.toml）
+#[derive(Debug, Deserialize)]
+pub struct ProjectConfig {
+    /// 所有 Mod 共用的大模型客户端设置（可选，使用默认值）
+    #[serde(default)]
+    pub client_settings: ClientSettings,
+    /// 所有 Mod 共用的翻译记忆文件路径
+    #[serde(default = "default_memory")]
+    pub memory: PathBuf,
+    /// 完成或失败时的通知设置（可选）
+    #[serde(default)]
+    pub notifications: Option<NotificationSettings>,
+    /// Mod 列表
+    #[serde(rename = "mod")]
+    pub mods: Vec<ProjectMod>,
+}
+
+fn default_memory() -> PathBuf {
+    PathBuf::from(DEFAULT_MEMORY_PATH)
+}
+
+impl ProjectConfig {
+    /// 从TOML文件加载项目配置
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, crate::error::ConfigError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| crate::error::ConfigError::InvalidPath(e.to_string()))?;
+
+        let config: ProjectConfig =
+            toml::from_str(&content).map_err(crate::error::ConfigError::TomlParse)?;
+
+        if config.mods.is_empty() {
+            return Err(crate::error::ConfigError::MissingField(crate::tr!(
+                "config.no_mods"
+            )));
+        }
+
+        config.client_settings.validate()?;
+        if let Some(notifications) = &config.notifications {
+            notifications.validate()?;
+        }
+        for m in &config.mods {
+            if !m.task_file.is_file() {
+                return Err(crate::error::ConfigError::InvalidPath(crate::tr!(
+                    "config.mod_task_file_missing",
+                    m.name,
+                    m.task_file.display()
+                )));
+            }
+        }
+
+        Ok(config)
+    }
+}
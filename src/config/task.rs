@@ -1,14 +1,27 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-use crate::config::ClientSettings;
+use crate::config::{ClientSettings, NotificationSettings, PackageSettings, ParatranzSettings};
 
 /// 从TOML文件加载的翻译任务配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranslationTask {
     /// 源语言代码（例如："english"）
+    ///
+    /// 未设置时按本地化文件的 `l_<lang>:` 语言头自动检测，并逐个文件确定其源语言，
+    /// 部分文件为英文、部分为中文的 Mod 也可以在一个任务中翻译
+    #[serde(default)]
     pub source_lang: String,
 
+    /// 按优先级排列的源语言（可选，例如 `["simp_chinese", "english"]`）
+    ///
+    /// 第一种语言为主要源语言（未设置 `source_lang` 时即为 `source_lang`），主要源文件缺少的键
+    /// 依次从其后语言的同名文件中补充，只有后面的语言才有的文件也一并翻译。
+    /// 适用于以中文编写、只有部分英文的 Mod。
+    #[serde(default)]
+    pub source_langs: Vec<String>,
+
     /// 目标语言代码列表（例如：["simp_chinese"]）
     pub target_langs: Vec<String>,
 
@@ -16,32 +29,234 @@ pub struct TranslationTask {
     pub glossaries: Vec<String>,
 
     /// 本地化文件目录路径
+    ///
+    /// 也可以是 Mod 压缩包（`.zip`，或旧式创意工坊物品的 `.bin`）、`.mod` 描述文件或只含压缩包的创意工坊物品目录，
+    /// 此时直接从压缩包中读取源文件，须设置 `output_dir`。
     pub localisation_dir: PathBuf,
+
+    /// 游戏本体的本地化目录（例如 `<游戏安装目录>/localisation`，可选）
+    ///
+    /// 设置后，Mod 覆盖的原版键直接复用官方译文，不再交给大模型翻译。
+    #[serde(default)]
+    pub vanilla_localisation_dir: Option<PathBuf>,
+
+    /// 是否复用游戏本体的官方译文（未设置 `vanilla_localisation_dir` 时使用游戏安装目录下的本地化）
+    #[serde(default)]
+    pub reuse_vanilla: bool,
+
+    /// 游戏安装目录（可选），未设置时自动在本机 Steam 库中查找
+    #[serde(default)]
+    pub game_install_dir: Option<PathBuf>,
+
+    /// 翻译轮数：1 为直接翻译，2 为先出初稿、再对照原文润色（默认 1）
+    #[serde(default = "default_passes")]
+    pub passes: u32,
+
+    /// 初稿使用的模型（可选，默认使用客户端设置中的模型）
+    #[serde(default)]
+    pub draft_model: Option<String>,
+
+    /// 润色使用的模型（可选），设置后即启用润色轮
+    #[serde(default)]
+    pub refine_model: Option<String>,
+
+    /// 多模型投票使用的模型（2 至 3 个，可选），每个切片由这些模型分别翻译后择优采用
+    #[serde(default)]
+    pub ensemble_models: Vec<String>,
+
+    /// 多模型投票的评审模型（可选），未设置时采用验证问题最少的译文
+    #[serde(default)]
+    pub judge_model: Option<String>,
+
+    /// 中转语言（可选，例如 `english`）
+    ///
+    /// 设置后源语言与目标语言都不是中转语言的文件分两次翻译：先译为中转语言，再由中转语言译为目标语言，
+    /// 适用于缺少语料的语言对（例如 polish 到 korean）。中转译文保存在 `pivot_dir` 中，源文件未改动时直接复用。
+    #[serde(default)]
+    pub pivot_lang: Option<String>,
+
+    /// 中转译文的保存目录（默认 `.pmt/pivot`），其下每种中转语言一个子目录
+    #[serde(default = "default_pivot_dir")]
+    pub pivot_dir: PathBuf,
+
+    /// 译文在 `<目标语言>/` 之下的子路径（默认 `replace`），留空时直接写入 `<目标语言>/`
+    #[serde(default = "default_output_subdir")]
+    pub output_subdir: PathBuf,
+
+    /// 译文的输出根目录（可选，例如独立翻译子 Mod 的 localisation 目录），默认为 `localisation_dir`
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+
+    /// 翻译完成后将译文打包写入的 zip 压缩包（可选），译文位于其中的 `localisation/<目标语言>/` 下
+    #[serde(default)]
+    pub output_archive: Option<PathBuf>,
+
+    /// 目标文件名模板（可选），可以使用 `{stem}`、`{source}` 与 `{target}`，例如 `{stem}_l_{target}.yml`
+    ///
+    /// 未设置时将文件名中的 `l_<源语言>` 替换为 `l_<目标语言>`。
+    #[serde(default)]
+    pub filename_template: Option<String>,
+
+    /// 在生成的目标文件中标注译文来源（默认 false）：文件头注释记录工具版本、模型、日期与源文件哈希，
+    /// 每个条目行尾标注 `# pmt:ai`、`# pmt:tm`、`# pmt:vanilla`、`# pmt:human` 或 `# pmt:kept`
+    #[serde(default)]
+    pub provenance: bool,
+
+    /// 切片前按键名前缀将条目分组（默认 false），例如所有 `tech_` 名称排在一起，主题相近的条目在同一切片中翻译，
+    /// 术语更统一；目标文件仍按源文件的顺序写入
+    #[serde(default)]
+    pub group_by_prefix: bool,
+
+    /// 小文件合并翻译的阈值（可选）：待翻译内容估算不超过该 token 数的文件合并为一个请求，
+    /// 各文件之间以分隔注释隔开，每个请求不超过 `max_chunk_tokens`，译文按键拆回各文件
+    #[serde(default)]
+    pub micro_batch_tokens: Option<usize>,
+
+    /// 人工维护的译文目录（默认 `overrides`），其中 `<目标语言>/` 下 YAML 文件的条目总是优先于 AI 译文
+    #[serde(default = "default_overrides_dir")]
+    pub overrides_dir: PathBuf,
+
+    /// 界面文本（按钮、特质名称等）的长度限制，译文超出时验证给出警告
+    #[serde(default)]
+    pub length_budgets: Vec<LengthBudget>,
+
+    /// 每个目标文件的最大条目数（可选），译文超出时拆分为 `_part1`、`_part2` 等多个文件写入
+    #[serde(default)]
+    pub max_entries_per_file: Option<usize>,
+
+    /// Mod 自定义标记的正则表达式（例如 `@[a-z_]+!`、`<K_[A-Za-z_]+>`），翻译时原样保留，验证时检查是否与原文一致
+    #[serde(default)]
+    pub custom_markers: Vec<String>,
+
+    /// 验证时比较格式标记的方式：`ordered`（默认）要求标记保持原文的顺序，`multiset` 只比较标记的种类与数量
+    #[serde(default)]
+    pub marker_check: MarkerCheck,
+
+    /// 验证忽略列表的路径（默认 `validation_ignore.toml`），文件不存在时不忽略任何警告
+    #[serde(default = "default_validation_ignore")]
+    pub validation_ignore: PathBuf,
+
+    /// 翻译完成后删除源文件已不存在的目标文件（默认 false），避免 Mod 更新删除的源文件留下的旧译文覆盖已删除的键
+    #[serde(default)]
+    pub prune_stale_outputs: bool,
+
+    /// 某个文件翻译失败时继续翻译其余文件（默认 false），全部完成后汇总失败的文件并返回错误
+    #[serde(default)]
+    pub continue_on_error: bool,
+
+    /// 自动检测源语言或设置 `source_langs` 时每个源文件的语言（源文件 -> 语言），否则为空
+    #[serde(skip)]
+    pub file_langs: BTreeMap<PathBuf, String>,
+
+    /// 设置 `source_langs` 时每个源文件的其他语言版本（源文件 -> 按优先级排列的文件），用于补充缺少的键
+    #[serde(skip)]
+    pub fallback_files: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+/// 格式标记的比较方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerCheck {
+    /// 标记的种类、数量与顺序都须与原文一致
+    #[default]
+    Ordered,
+    /// 只比较标记的种类与数量，允许译文调整语序（适用于语序与原文差异较大的语言）
+    Multiset,
+}
+
+/// 一类界面文本的长度限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LengthBudget {
+    /// 键名的正则表达式（需匹配完整的键名），例如 `trait_.*`
+    pub keys: String,
+    /// 译文的最大显示宽度：中日韩文字等全角字符计为 2，其他字符计为 1
+    pub max_width: usize,
+}
+
+impl LengthBudget {
+    /// 匹配完整键名的正则表达式
+    pub fn key_pattern(&self) -> Result<regex::Regex, regex::Error> {
+        regex::Regex::new(&format!("^(?:{})$", self.keys))
+    }
+}
+
+fn default_passes() -> u32 {
+    1
+}
+
+fn default_output_subdir() -> PathBuf {
+    PathBuf::from("replace")
+}
+
+fn default_pivot_dir() -> PathBuf {
+    PathBuf::from(".pmt/pivot")
+}
+
+fn default_overrides_dir() -> PathBuf {
+    PathBuf::from("overrides")
+}
+
+fn default_validation_ignore() -> PathBuf {
+    PathBuf::from("validation_ignore.toml")
 }
 
 /// 完整的任务配置文件结构
 #[derive(Debug, Deserialize)]
-struct TaskFileConfig {
+pub struct TaskFileConfig {
     /// 大模型客户端设置（可选，使用默认值）
     #[serde(default)]
-    client_settings: ClientSettings,
+    pub client_settings: ClientSettings,
+    /// 完成或失败时的通知设置（可选）
+    #[serde(default)]
+    pub notifications: Option<NotificationSettings>,
+    /// Paratranz 项目同步设置（可选）
+    #[serde(default)]
+    pub paratranz: Option<ParatranzSettings>,
+    /// 翻译子 Mod 打包设置（可选）
+    #[serde(default)]
+    pub package: Option<PackageSettings>,
     /// 翻译任务列表
-    task: Vec<TranslationTask>,
+    pub task: Vec<TranslationTask>,
 }
 
 impl TaskFileConfig {
     /// 从TOML文件加载配置
-    fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, crate::error::ConfigError> {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, crate::error::ConfigError> {
         let content = std::fs::read_to_string(path)
             .map_err(|e| crate::error::ConfigError::InvalidPath(e.to_string()))?;
 
-        let config: TaskFileConfig =
+        let mut config: TaskFileConfig =
             toml::from_str(&content).map_err(crate::error::ConfigError::TomlParse)?;
 
+        if config.task.is_empty() {
+            return Err(crate::error::ConfigError::MissingField(crate::tr!(
+                "config.no_tasks"
+            )));
+        }
+
         // 验证客户端设置
         config.client_settings.validate()?;
 
-        // 验证每个任务
+        // 验证通知设置
+        if let Some(notifications) = &config.notifications {
+            notifications.validate()?;
+        }
+
+        // 验证 Paratranz 设置
+        if let Some(paratranz) = &config.paratranz {
+            paratranz.validate()?;
+        }
+
+        // 验证打包设置
+        if let Some(package) = &config.package {
+            package.validate()?;
+        }
+
+        // 解析多个源语言、检测未设置源语言的任务的源语言，再验证每个任务
+        for task in &mut config.task {
+            task.resolve_fallback_langs()?;
+            task.detect_source_langs()?;
+        }
         for task in &config.task {
             task.validate()?;
         }
@@ -56,47 +271,296 @@ impl TranslationTask {
         path: P,
     ) -> Result<(ClientSettings, Vec<Self>), crate::error::ConfigError> {
         let config = TaskFileConfig::from_file(path)?;
+        Ok((config.client_settings, config.task))
+    }
 
-        if config.task.is_empty() {
-            return Err(crate::error::ConfigError::MissingField(
-                "配置文件中未找到任务".to_string(),
-            ));
+    /// 由源语言目录（例如 `localisation/english`）直接构造翻译任务，无需任务配置文件
+    ///
+    /// 源语言取目录名，本地化目录取其上级目录。
+    pub fn from_source_dir<P: AsRef<Path>>(
+        source_dir: P,
+        target_langs: Vec<String>,
+        glossaries: Vec<String>,
+    ) -> Result<Self, crate::error::ConfigError> {
+        let source_dir = source_dir.as_ref();
+        let source_lang = source_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| {
+                crate::error::ConfigError::InvalidPath(crate::tr!(
+                    "config.source_lang_unknown",
+                    source_dir.display()
+                ))
+            })?
+            .to_string();
+        let localisation_dir = match source_dir.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let task = Self::with_defaults(source_lang, target_langs, glossaries, localisation_dir);
+        task.validate()?;
+        Ok(task)
+    }
+
+    /// 除语言、术语表与本地化目录外都使用默认值的任务
+    fn with_defaults(
+        source_lang: String,
+        target_langs: Vec<String>,
+        glossaries: Vec<String>,
+        localisation_dir: PathBuf,
+    ) -> Self {
+        Self {
+            source_lang,
+            source_langs: Vec::new(),
+            target_langs,
+            glossaries,
+            localisation_dir,
+            vanilla_localisation_dir: None,
+            reuse_vanilla: false,
+            game_install_dir: None,
+            passes: default_passes(),
+            draft_model: None,
+            refine_model: None,
+            ensemble_models: Vec::new(),
+            judge_model: None,
+            pivot_lang: None,
+            pivot_dir: default_pivot_dir(),
+            output_subdir: default_output_subdir(),
+            output_dir: None,
+            output_archive: None,
+            filename_template: None,
+            provenance: false,
+            group_by_prefix: false,
+            micro_batch_tokens: None,
+            overrides_dir: default_overrides_dir(),
+            length_budgets: Vec::new(),
+            max_entries_per_file: None,
+            custom_markers: Vec::new(),
+            marker_check: MarkerCheck::default(),
+            validation_ignore: default_validation_ignore(),
+            prune_stale_outputs: false,
+            continue_on_error: false,
+            file_langs: BTreeMap::new(),
+            fallback_files: BTreeMap::new(),
         }
+    }
 
-        Ok((config.client_settings, config.task))
+    /// 由整个 Mod（压缩包、`.mod` 描述文件或 Mod 目录）直接构造翻译任务，无需事先解压
+    ///
+    /// 源语言优先取 english，否则取第一个语言目录；译文写入当前目录下的 `localisation/`。
+    pub fn from_mod<P: AsRef<Path>>(
+        mod_path: P,
+        target_langs: Vec<String>,
+        glossaries: Vec<String>,
+    ) -> crate::error::Result<Self> {
+        let mod_path = mod_path.as_ref();
+        let source = crate::source::open_source(mod_path)?;
+        let source_lang = source.detect_source_lang()?.ok_or_else(|| {
+            crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.source_lang_unknown",
+                source.origin().display()
+            ))
+        })?;
+        let mut task = Self::with_defaults(
+            source_lang,
+            target_langs,
+            glossaries,
+            mod_path.to_path_buf(),
+        );
+        task.output_dir = Some(PathBuf::from("localisation"));
+        task.validate()?;
+        Ok(task)
+    }
+
+    /// 是否启用润色轮
+    pub fn refine_enabled(&self) -> bool {
+        self.passes >= 2 || self.refine_model.is_some()
     }
 
     /// 验证配置
     pub fn validate(&self) -> Result<(), crate::error::ConfigError> {
+        let mut errors: Vec<crate::error::ConfigError> = Vec::new();
+
         if self.source_lang.is_empty() {
-            return Err(crate::error::ConfigError::MissingField(
+            errors.push(crate::error::ConfigError::MissingField(
                 "source_lang".to_string(),
             ));
         }
 
+        if self
+            .source_langs
+            .first()
+            .is_some_and(|first| *first != self.source_lang)
+        {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.source_langs_mismatch",
+                self.source_lang,
+                self.source_langs.join(", ")
+            )));
+        }
+
         if self.target_langs.is_empty() {
-            return Err(crate::error::ConfigError::MissingField(
+            errors.push(crate::error::ConfigError::MissingField(
                 "target_lang".to_string(),
             ));
         }
 
         if !self.localisation_dir.exists() {
-            return Err(crate::error::ConfigError::InvalidPath(format!(
-                "本地化目录不存在: {:?}",
-                self.localisation_dir
+            errors.push(crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.localisation_dir_missing",
+                self.localisation_dir.display()
             )));
         }
 
         // 检查源语言目录是否存在
         let source_dir = self.localisation_dir.join(&self.source_lang);
-        if !source_dir.exists() {
-            return Err(crate::error::ConfigError::InvalidPath(format!(
-                "源语言目录不存在: {:?}",
-                source_dir
+        if self.reads_archive() {
+            match self.source().and_then(|s| s.languages()) {
+                Ok(langs) if !langs.contains(&self.source_lang) => {
+                    errors.push(crate::error::ConfigError::InvalidPath(crate::tr!(
+                        "config.source_dir_missing",
+                        source_dir.display()
+                    )));
+                }
+                Ok(_) => {}
+                Err(e) => errors.push(crate::error::ConfigError::InvalidPath(e.to_string())),
+            }
+            if self.output_dir.is_none() {
+                errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                    "config.archive_output_dir_missing",
+                    self.localisation_dir.display()
+                )));
+            }
+        } else if self.localisation_dir.exists() && !source_dir.exists() {
+            errors.push(crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.source_dir_missing",
+                source_dir.display()
             )));
         }
 
-        Ok(())
+        if !(1..=2).contains(&self.passes) {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.passes_invalid"
+            )));
+        }
+
+        if !self.ensemble_models.is_empty() && !(2..=3).contains(&self.ensemble_models.len()) {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.ensemble_models_count"
+            )));
+        }
+
+        if self.judge_model.is_some() && self.ensemble_models.is_empty() {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.judge_without_ensemble"
+            )));
+        }
+
+        if self.output_subdir.is_absolute()
+            || self
+                .output_subdir
+                .components()
+                .any(|c| !matches!(c, std::path::Component::Normal(_)))
+        {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.output_subdir_invalid",
+                self.output_subdir.display()
+            )));
+        }
+
+        if let Some(template) = &self.filename_template
+            && (!template.contains("{target}") || template.contains(['/', '\\']))
+        {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.filename_template_invalid",
+                template
+            )));
+        }
+
+        for budget in &self.length_budgets {
+            if let Err(e) = budget.key_pattern() {
+                errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                    "config.length_budget_pattern_invalid",
+                    budget.keys,
+                    e
+                )));
+            }
+            if budget.max_width == 0 {
+                errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                    "config.length_budget_width_zero",
+                    budget.keys
+                )));
+            }
+        }
+
+        for marker in &self.custom_markers {
+            if let Err(e) = regex::Regex::new(marker) {
+                errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                    "config.custom_marker_invalid",
+                    marker,
+                    e
+                )));
+            }
+        }
+
+        if self.micro_batch_tokens == Some(0) {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.micro_batch_tokens_zero"
+            )));
+        }
+
+        if self.max_entries_per_file == Some(0) {
+            errors.push(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.max_entries_per_file_zero"
+            )));
+        }
+
+        if let Some(vanilla_dir) = &self.vanilla_localisation_dir
+            && !vanilla_dir.is_dir()
+        {
+            errors.push(crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.vanilla_dir_missing",
+                vanilla_dir.display()
+            )));
+        }
+
+        if let Some(game_dir) = &self.game_install_dir
+            && !game_dir.join("localisation").is_dir()
+        {
+            errors.push(crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.game_dir_invalid",
+                game_dir.display()
+            )));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::error::ConfigError::MultipleErrors(errors))
+        }
+    }
+
+    /// 游戏安装目录：优先使用配置，否则自动在本机 Steam 库中查找
+    pub fn game_dir(&self) -> Option<PathBuf> {
+        self.game_install_dir
+            .clone()
+            .or_else(crate::steam::find_game_install_dir)
+    }
+
+    /// 用于复用官方译文的游戏本地化目录，未启用时返回 None
+    pub fn vanilla_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = &self.vanilla_localisation_dir {
+            return Some(dir.clone());
+        }
+        if !self.reuse_vanilla {
+            return None;
+        }
+        let dir = self.game_dir().map(|d| d.join("localisation"));
+        if dir.is_none() {
+            log::warn!("reuse_vanilla is enabled but the game install directory was not found");
+        }
+        dir
     }
 
     /// 获取源语言目录路径
@@ -104,8 +568,465 @@ impl TranslationTask {
         self.localisation_dir.join(&self.source_lang)
     }
 
-    /// 获取特定目标语言的目标目录路径
+    /// 源语言目录中的所有本地化文件，不是本地化文件的 YAML 文件会被跳过；
+    /// 自动检测源语言时为检测到的全部源文件
+    pub fn source_files(&self) -> crate::error::Result<Vec<PathBuf>> {
+        self.source_files_in(self.source()?.as_ref())
+    }
+
+    /// 与 [`source_files`](Self::source_files) 相同，从已打开的来源中读取
+    pub fn source_files_in(
+        &self,
+        source: &dyn crate::source::LocalisationSource,
+    ) -> crate::error::Result<Vec<PathBuf>> {
+        if !self.file_langs.is_empty() {
+            return Ok(self.file_langs.keys().cloned().collect());
+        }
+        source.list_files(&self.source_lang)
+    }
+
+    /// 与 [`source_files`](Self::source_files) 相同，从已打开的来源中并行读取
+    pub async fn list_source_files(
+        &self,
+        source: &dyn crate::source::LocalisationSource,
+    ) -> crate::error::Result<Vec<PathBuf>> {
+        if !self.file_langs.is_empty() {
+            return Ok(self.file_langs.keys().cloned().collect());
+        }
+        source.list_files_parallel(&self.source_lang).await
+    }
+
+    /// 源文件的语言：自动检测时为该文件语言头中的语言，设置 `source_langs` 时为文件所在的语言，否则为 `source_lang`
+    pub fn file_source_lang(&self, source_file: &Path) -> &str {
+        self.file_langs
+            .get(source_file)
+            .map(String::as_str)
+            .unwrap_or(&self.source_lang)
+    }
+
+    /// 未设置 `source_lang` 时按本地化文件的语言头检测源语言
+    ///
+    /// 扫描各语言目录（本任务的输出目录除外），按每个文件的 `l_<lang>:` 语言头确定其语言。
+    /// 去除语言标记后同名的文件视为同一文件的不同语言版本，只翻译其中一份：优先取文件最多的语言
+    /// （同时作为任务的 `source_lang`），其次是 english，再按语言名称。
+    /// 本地化目录不存在时不做检测，由 [`validate`](Self::validate) 报告错误。
+    pub fn detect_source_langs(&mut self) -> Result<(), crate::error::ConfigError> {
+        if !self.source_lang.is_empty() || !self.localisation_dir.exists() {
+            return Ok(());
+        }
+        let unknown = || {
+            crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.source_lang_unknown",
+                self.localisation_dir.display()
+            ))
+        };
+        let found = self.scan_source_langs().map_err(|e| {
+            log::error!("Failed to detect source languages: {}", e);
+            unknown()
+        })?;
+
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, lang) in &found {
+            *counts.entry(lang.as_str()).or_default() += 1;
+        }
+        // 文件最多的语言，数量相同时优先 english，再按语言名称
+        let primary = counts
+            .iter()
+            .max_by_key(|(lang, count)| (**count, **lang == "english", std::cmp::Reverse(**lang)))
+            .map(|(lang, _)| lang.to_string())
+            .ok_or_else(unknown)?;
+        log::info!(
+            "Detected source languages: {}",
+            counts
+                .iter()
+                .map(|(lang, count)| format!("{} ({} files)", lang, count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let rank = |lang: &str| (lang != primary, lang != "english", lang.to_string());
+        let mut versions: BTreeMap<String, (PathBuf, String)> = BTreeMap::new();
+        for (file, lang) in found {
+            let name = file
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let stem = crate::preprocess::filename_stem(&name, &lang);
+            match versions.get(&stem) {
+                Some((_, existing)) if rank(existing) <= rank(&lang) => {}
+                _ => {
+                    versions.insert(stem, (file, lang));
+                }
+            }
+        }
+        self.file_langs = versions.into_values().collect();
+        self.source_lang = primary;
+        Ok(())
+    }
+
+    /// 本任务单独的输出目录（与语言目录相同的除外），其中的文件是上次运行的译文而不是源文件
+    fn output_dirs(&self) -> Vec<PathBuf> {
+        self.target_langs
+            .iter()
+            .map(|lang| self.target_dir(lang))
+            .filter(|dir| {
+                self.target_langs
+                    .iter()
+                    .all(|lang| *dir != self.language_dir(lang))
+            })
+            .collect()
+    }
+
+    /// 设置 `source_langs` 时确定每个源文件的语言及其其他语言版本
+    ///
+    /// 去除语言标记后同名的文件视为同一文件的不同语言版本，翻译优先级最高的一份，
+    /// 其余版本按优先级记录在 `fallback_files` 中，读取源文件时补充缺少的键。
+    /// 本地化目录不存在时不做处理，由 [`validate`](Self::validate) 报告错误。
+    pub fn resolve_fallback_langs(&mut self) -> Result<(), crate::error::ConfigError> {
+        let Some(primary) = self.source_langs.first() else {
+            return Ok(());
+        };
+        if self.source_lang.is_empty() {
+            self.source_lang = primary.clone();
+        }
+        if self.source_langs.len() < 2 || !self.localisation_dir.exists() {
+            return Ok(());
+        }
+        let versions = self.scan_fallback_langs().map_err(|e| {
+            crate::error::ConfigError::InvalidPath(crate::tr!(
+                "config.source_files_unreadable",
+                self.localisation_dir.display(),
+                e
+            ))
+        })?;
+        for mut files in versions.into_values() {
+            let (file, lang) = files.remove(0);
+            if !files.is_empty() {
+                self.fallback_files
+                    .insert(file.clone(), files.into_iter().map(|(f, _)| f).collect());
+            }
+            self.file_langs.insert(file, lang);
+        }
+        log::info!(
+            "Using {} source files, {} with fallbacks in {}",
+            self.file_langs.len(),
+            self.fallback_files.len(),
+            self.source_langs[1..].join(", ")
+        );
+        Ok(())
+    }
+
+    /// `source_langs` 中各语言的本地化文件，按去除语言标记后的文件名分组，组内按语言优先级排列
+    fn scan_fallback_langs(
+        &self,
+    ) -> crate::error::Result<BTreeMap<String, Vec<(PathBuf, String)>>> {
+        let source = self.source()?;
+        let languages = source.languages()?;
+        let outputs = self.output_dirs();
+        let mut versions: BTreeMap<String, Vec<(PathBuf, String)>> = BTreeMap::new();
+        for lang in self.source_langs.iter().filter(|l| languages.contains(l)) {
+            for file in source.list_files(lang)? {
+                if outputs.iter().any(|dir| file.starts_with(dir)) {
+                    continue;
+                }
+                let name = file
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                versions
+                    .entry(crate::preprocess::filename_stem(&name, lang))
+                    .or_default()
+                    .push((file, lang.clone()));
+            }
+        }
+        Ok(versions)
+    }
+
+    /// 各语言目录中的本地化文件及其语言头中的语言，跳过本任务的输出目录与没有语言头的文件
+    fn scan_source_langs(&self) -> crate::error::Result<Vec<(PathBuf, String)>> {
+        let source = self.source()?;
+        let outputs = self.output_dirs();
+        let mut found = Vec::new();
+        for lang in source.languages()? {
+            for file in source.list_files(&lang)? {
+                if outputs.iter().any(|dir| file.starts_with(dir)) {
+                    continue;
+                }
+                if let Some(header) =
+                    crate::preprocess::detect_lang_header(&source.read_file(&file)?)
+                {
+                    found.push((file, header));
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// `localisation_dir` 是否指向压缩包、`.mod` 描述文件或 Mod 目录，而不是本地化目录本身
+    pub fn reads_archive(&self) -> bool {
+        crate::source::is_mod_package(&self.localisation_dir)
+    }
+
+    /// 检查任务能否按 git 修订版本比较源文件（`pmt translate --since`）
+    ///
+    /// 比较的是本地化目录下各语言目录中的文件，`localisation_dir` 指向压缩包、`.mod` 描述文件或 Mod 目录时无法比较。
+    pub fn check_git_source(&self) -> Result<(), crate::error::ConfigError> {
+        if self.reads_archive() {
+            return Err(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.since_archive",
+                self.localisation_dir.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// 本地化目录对应的来源，`localisation_dir` 也可以是 Mod 压缩包或 `.mod` 描述文件
+    ///
+    /// 设置了多个源语言时，读取源文件会补充其他语言版本中的键。
+    pub fn source(&self) -> crate::error::Result<Box<dyn crate::source::LocalisationSource>> {
+        let source = crate::source::open_source(&self.localisation_dir)?;
+        if self.fallback_files.is_empty() {
+            return Ok(source);
+        }
+        Ok(Box::new(crate::source::FallbackSource::new(
+            source,
+            self.fallback_files.clone(),
+        )))
+    }
+
+    /// 目标语言的本地化目录：`<输出根目录>/<目标语言>`，其中也可能包含 Mod 自带的译文
+    pub fn language_dir(&self, target_lang: &str) -> PathBuf {
+        self.output_dir
+            .as_ref()
+            .unwrap_or(&self.localisation_dir)
+            .join(target_lang)
+    }
+
+    /// 获取特定目标语言的目标目录路径：`<输出根目录>/<目标语言>/<output_subdir>`
     pub fn target_dir(&self, target_lang: &str) -> PathBuf {
-        self.localisation_dir.join(target_lang).join("replace")
+        let dir = self.language_dir(target_lang);
+        if self.output_subdir.as_os_str().is_empty() {
+            dir
+        } else {
+            dir.join(&self.output_subdir)
+        }
+    }
+
+    /// 源文件对应的目标文件名，设置了 `filename_template` 时按模板生成
+    pub fn target_filename(&self, source_filename: &str, target_lang: &str) -> String {
+        self.renamed_filename(source_filename, &self.source_lang, target_lang)
+    }
+
+    fn renamed_filename(
+        &self,
+        source_filename: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> String {
+        match &self.filename_template {
+            Some(template) => crate::preprocess::render_filename_template(
+                template,
+                source_filename,
+                source_lang,
+                target_lang,
+            ),
+            None => crate::preprocess::generate_target_filename(
+                source_filename,
+                source_lang,
+                target_lang,
+            ),
+        }
+    }
+
+    /// 从 `source_lang` 翻译到 `target_lang` 时经由的中转语言，两者之一就是中转语言时直接翻译
+    pub fn pivot_for(&self, source_lang: &str, target_lang: &str) -> Option<&str> {
+        self.pivot_lang
+            .as_deref()
+            .filter(|pivot| *pivot != source_lang && *pivot != target_lang)
+    }
+
+    /// 源文件译为中转语言后的保存路径：`<pivot_dir>/<中转语言>/<文件名>`
+    pub fn pivot_file(&self, source_file: &Path, pivot_lang: &str) -> PathBuf {
+        let filename = source_file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.pivot_dir.join(pivot_lang).join(self.renamed_filename(
+            &filename,
+            self.file_source_lang(source_file),
+            pivot_lang,
+        ))
+    }
+
+    /// 源文件对应的目标文件路径
+    pub fn target_file(&self, source_file: &Path, target_lang: &str) -> PathBuf {
+        let filename = source_file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.target_dir(target_lang).join(self.renamed_filename(
+            &filename,
+            self.file_source_lang(source_file),
+            target_lang,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_git_source() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("english")).unwrap();
+        let task = TranslationTask::with_defaults(
+            "english".to_string(),
+            vec!["simp_chinese".to_string()],
+            Vec::new(),
+            dir.path().to_path_buf(),
+        );
+        assert!(task.check_git_source().is_ok());
+
+        // 压缩包中的源文件无法按 git 修订版本比较
+        let archive = dir.path().join("mod.zip");
+        std::fs::write(&archive, b"").unwrap();
+        let task = TranslationTask::with_defaults(
+            "english".to_string(),
+            vec!["simp_chinese".to_string()],
+            Vec::new(),
+            archive,
+        );
+        assert!(matches!(
+            task.check_git_source(),
+            Err(crate::error::ConfigError::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_source_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("english")).unwrap();
+        // 源语言取自目录名，本地化目录为其上级目录，末尾的路径分隔符不影响结果
+        for source_dir in [
+            dir.path().join("english"),
+            PathBuf::from(format!("{}/", dir.path().join("english").display())),
+        ] {
+            let task = TranslationTask::from_source_dir(
+                &source_dir,
+                vec!["simp_chinese".to_string()],
+                vec!["stellaris".to_string()],
+            )
+            .unwrap();
+            assert_eq!(task.source_lang, "english");
+            assert_eq!(task.localisation_dir, dir.path());
+            assert_eq!(task.source_dir(), dir.path().join("english"));
+            assert_eq!(task.target_langs, ["simp_chinese"]);
+            assert_eq!(task.glossaries, ["stellaris"]);
+        }
+
+        // 源语言目录不存在时验证失败
+        assert!(
+            TranslationTask::from_source_dir(
+                dir.path().join("german"),
+                vec!["simp_chinese".to_string()],
+                vec![],
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_target_dir_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("english")).unwrap();
+        let mut task = TranslationTask::from_source_dir(
+            dir.path().join("english"),
+            vec!["simp_chinese".to_string()],
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(
+            task.target_dir("simp_chinese"),
+            dir.path().join("simp_chinese/replace")
+        );
+
+        task.output_subdir = PathBuf::new();
+        assert_eq!(
+            task.target_dir("simp_chinese"),
+            dir.path().join("simp_chinese")
+        );
+
+        task.output_subdir = PathBuf::from("replace/zz_mod");
+        task.output_dir = Some(PathBuf::from("submod/localisation"));
+        assert_eq!(
+            task.target_dir("simp_chinese"),
+            PathBuf::from("submod/localisation/simp_chinese/replace/zz_mod")
+        );
+        assert!(task.validate().is_ok());
+
+        task.filename_template = Some("zz_{stem}_l_{target}.yml".to_string());
+        assert_eq!(
+            task.target_file(Path::new("english/l_english_techs.yml"), "simp_chinese"),
+            PathBuf::from(
+                "submod/localisation/simp_chinese/replace/zz_mod/zz_techs_l_simp_chinese.yml"
+            )
+        );
+        assert!(task.validate().is_ok());
+        task.filename_template = Some("{stem}.yml".to_string());
+        assert!(task.validate().is_err());
+        task.filename_template = None;
+
+        task.output_subdir = PathBuf::from("../escape");
+        assert!(task.validate().is_err());
+    }
+
+    #[test]
+    fn test_detect_source_langs() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("english/a_l_english.yml", "l_english:\n a:0 \"A\"\n");
+        write("english/b_l_english.yml", "l_english:\n b:0 \"B\"\n");
+        // 中文版的 a 与英文版重复，只翻译英文版；c 只有中文版
+        write(
+            "simp_chinese/a_l_simp_chinese.yml",
+            "l_simp_chinese:\n a:0 \"甲\"\n",
+        );
+        write(
+            "simp_chinese/c_l_simp_chinese.yml",
+            "l_simp_chinese:\n c:0 \"丙\"\n",
+        );
+        // 上次运行的输出不是源文件
+        write(
+            "simp_chinese/replace/b_l_simp_chinese.yml",
+            "l_simp_chinese:\n b:0 \"乙\"\n",
+        );
+
+        let mut task: TranslationTask = toml::from_str(&format!(
+            "target_langs = [\"simp_chinese\", \"german\"]\nglossaries = []\nlocalisation_dir = {:?}",
+            dir.path()
+        ))
+        .unwrap();
+        task.detect_source_langs().unwrap();
+        assert!(task.validate().is_ok());
+        assert_eq!(task.source_lang, "english");
+        let chinese = dir.path().join("simp_chinese/c_l_simp_chinese.yml");
+        assert_eq!(
+            task.source_files().unwrap(),
+            [
+                dir.path().join("english/a_l_english.yml"),
+                dir.path().join("english/b_l_english.yml"),
+                chinese.clone(),
+            ]
+        );
+        assert_eq!(task.file_source_lang(&chinese), "simp_chinese");
+        assert_eq!(
+            task.target_file(&chinese, "german"),
+            dir.path().join("german/replace/c_l_german.yml")
+        );
     }
 }
@@ -34,6 +34,119 @@ pub enum TranslationError {
 
     #[error("Async task error: {0}")]
     AsyncError(String),
+
+    #[error(
+        "{} file(s) failed to translate:\n{}",
+        .0.len(),
+        .0.iter().map(|f| format!("  {}", f)).collect::<Vec<_>>().join("\n")
+    )]
+    FileFailures(Vec<FileFailure>),
+
+    #[error("{source} ({context})")]
+    Context {
+        context: ErrorContext,
+        source: Box<TranslationError>,
+    },
+}
+
+impl TranslationError {
+    /// 为错误补充发生的位置；错误已有位置时只补充其中缺少的部分，内层记录的位置优先
+    pub fn with_context(self, update: impl FnOnce(&mut ErrorContext)) -> Self {
+        let (mut context, source) = match self {
+            TranslationError::Context { context, source } => (context, source),
+            error => (ErrorContext::default(), Box::new(error)),
+        };
+        let mut outer = ErrorContext::default();
+        update(&mut outer);
+        context.file = context.file.or(outer.file);
+        context.chunk = context.chunk.or(outer.chunk);
+        context.key = context.key.or(outer.key);
+        TranslationError::Context { context, source }
+    }
+
+    /// 错误发生的位置，未记录时为 `None`
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            TranslationError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// 去除位置信息后的原始错误
+    pub fn root(&self) -> &TranslationError {
+        match self {
+            TranslationError::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// 是否表示服务商过载：限流（429）或请求超时
+    pub fn is_overloaded(&self) -> bool {
+        match self.root() {
+            TranslationError::Translate(TranslateError::RateLimited) => true,
+            TranslationError::Translate(TranslateError::ApiRequest(e)) => e.is_timeout(),
+            _ => false,
+        }
+    }
+}
+
+/// 错误发生的位置：文件、切片与键，没有记录的部分为 `None`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub file: Option<std::path::PathBuf>,
+    pub chunk: Option<crate::translate::ChunkId>,
+    pub key: Option<String>,
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(file) = &self.file {
+            parts.push(format!("file {}", file.display()));
+        }
+        if let Some(chunk) = &self.chunk {
+            parts.push(format!("chunk {}", chunk));
+        }
+        if let Some(key) = &self.key {
+            parts.push(format!("key {}", key));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// 为 `Result` 中的错误补充发生的位置
+pub trait ResultExt<T> {
+    /// 出错的文件
+    fn in_file(self, file: &std::path::Path) -> Result<T>;
+    /// 出错的切片
+    fn in_chunk(self, chunk: crate::translate::ChunkId) -> Result<T>;
+    /// 出错的键
+    fn for_key(self, key: &str) -> Result<T>;
+}
+
+impl<T, E: Into<TranslationError>> ResultExt<T> for std::result::Result<T, E> {
+    fn in_file(self, file: &std::path::Path) -> Result<T> {
+        self.map_err(|e| e.into().with_context(|c| c.file = Some(file.to_path_buf())))
+    }
+
+    fn in_chunk(self, chunk: crate::translate::ChunkId) -> Result<T> {
+        self.map_err(|e| e.into().with_context(|c| c.chunk = Some(chunk)))
+    }
+
+    fn for_key(self, key: &str) -> Result<T> {
+        self.map_err(|e| e.into().with_context(|c| c.key = Some(key.to_string())))
+    }
+}
+
+/// 启用 `continue_on_error` 时一个文件翻译失败的原因
+#[derive(Error, Debug)]
+#[error("{file:?} -> {target_lang}: {error}")]
+pub struct FileFailure {
+    /// 源文件
+    pub file: std::path::PathBuf,
+    /// 目标语言
+    pub target_lang: String,
+    pub error: TranslationError,
 }
 
 #[derive(Error, Debug)]
@@ -83,6 +196,9 @@ pub enum TranslateError {
     #[error("Validation failed: {0}")]
     ValidationFailed(String),
 
+    #[error("Translation memory error: {0}")]
+    MemoryError(String),
+
     #[error("Rate limited")]
     RateLimited,
 
@@ -100,6 +216,45 @@ pub enum PostprocessError {
 
     #[error("Inconsistent translation slices")]
     InconsistentSlices,
+
+    #[error("Review sheet error: {0}")]
+    ReviewSheet(String),
+
+    #[error("Exchange format error: {0}")]
+    Exchange(String),
 }
 
 pub type Result<T> = std::result::Result<T, TranslationError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::ChunkId;
+
+    #[test]
+    fn test_error_context() {
+        let chunk = ChunkId {
+            file: "a_l_simp_chinese.yml".to_string(),
+            start_line: 1,
+            end_line: 40,
+        };
+        let result: Result<()> = Err(TranslationError::ApiError("timeout".to_string()));
+        let error = result
+            .in_chunk(chunk.clone())
+            .in_file(std::path::Path::new("loc/english/a_l_english.yml"))
+            .in_file(std::path::Path::new("outer.yml"))
+            .unwrap_err();
+
+        let context = error.context().unwrap();
+        assert_eq!(context.chunk, Some(chunk));
+        assert_eq!(
+            context.file.as_deref(),
+            Some(std::path::Path::new("loc/english/a_l_english.yml"))
+        );
+        assert!(matches!(error.root(), TranslationError::ApiError(_)));
+        assert_eq!(
+            error.to_string(),
+            "API error: timeout (file loc/english/a_l_english.yml, chunk a_l_simp_chinese.yml(1:40))"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! 交换格式模块
+//!
+//! 将本地化条目导出为计算机辅助翻译工具使用的交换格式（XLIFF、PO 等），
+//! 并将完成的译文导回 Paradox 本地化文件。
+
+mod paratranz;
+mod po;
+mod xliff;
+
+pub use paratranz::*;
+pub use po::*;
+pub use xliff::*;
+
+use crate::config::TranslationTask;
+use crate::error::Result;
+use crate::postprocess::{read_translated_output, write_translated_file};
+use crate::preprocess::parse_entries;
+use crate::review::write_entry;
+use crate::translate::{MemoryOrigin, TranslationMemory};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 一个待交换的条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeUnit {
+    /// 键名
+    pub key: String,
+    /// 原文
+    pub source: String,
+    /// 译文（尚未翻译时为 None）
+    pub target: Option<String>,
+}
+
+/// 一个源文件在某个目标语言下的全部条目
+#[derive(Debug, Clone)]
+pub struct ExchangeFile {
+    /// 源语言
+    pub source_lang: String,
+    /// 目标语言
+    pub target_lang: String,
+    /// 目标文件路径
+    pub target_file: PathBuf,
+    /// 条目，按源文件中的顺序排列
+    pub units: Vec<ExchangeUnit>,
+}
+
+/// Paradox 语言代码与 BCP 47 语言标签的对照
+const LANGUAGE_TAGS: &[(&str, &str)] = &[
+    ("english", "en"),
+    ("simp_chinese", "zh-CN"),
+    ("spanish", "es"),
+    ("french", "fr"),
+    ("braz_por", "pt-BR"),
+    ("russian", "ru"),
+    ("german", "de"),
+    ("japanese", "ja"),
+    ("korean", "ko"),
+    ("polish", "pl"),
+];
+
+/// 将 Paradox 语言代码转换为 BCP 47 语言标签，未知语言原样返回
+pub fn language_tag(lang: &str) -> &str {
+    LANGUAGE_TAGS
+        .iter()
+        .find(|(name, _)| *name == lang)
+        .map(|(_, tag)| *tag)
+        .unwrap_or(lang)
+}
+
+/// 将 BCP 47 语言标签转换为 Paradox 语言代码，未知标签原样返回
+pub fn paradox_language(tag: &str) -> &str {
+    LANGUAGE_TAGS
+        .iter()
+        .find(|(_, t)| t.eq_ignore_ascii_case(tag))
+        .map(|(name, _)| *name)
+        .unwrap_or(tag)
+}
+
+/// 收集任务中所有源文件在每个目标语言下的条目，目标文件不存在时译文为空
+pub fn collect_exchange_files(task: &TranslationTask) -> Result<Vec<ExchangeFile>> {
+    let source = task.source()?;
+    let source_files = task.source_files_in(source.as_ref())?;
+    let mut files = Vec::new();
+
+    for target_lang in &task.target_langs {
+        for source_file in &source_files {
+            let source_lang = task.file_source_lang(source_file);
+            if source_lang == target_lang {
+                continue;
+            }
+            let target_file = task.target_file(source_file, target_lang);
+            let translated: HashMap<String, String> = match read_translated_output(&target_file)? {
+                Some(target) => parse_entries(&target)
+                    .into_iter()
+                    .map(|e| (e.key, e.value))
+                    .collect(),
+                None => HashMap::new(),
+            };
+            let units = parse_entries(&source.read_file(source_file)?)
+                .into_iter()
+                .map(|e| ExchangeUnit {
+                    target: translated.get(&e.key).cloned(),
+                    key: e.key,
+                    source: e.value,
+                })
+                .collect();
+            files.push(ExchangeFile {
+                source_lang: source_lang.to_string(),
+                target_lang: target_lang.clone(),
+                target_file,
+                units,
+            });
+        }
+    }
+    Ok(files)
+}
+
+/// 将导入的译文写入目标文件
+///
+/// 目标文件不存在时按源文件的条目顺序新建；已存在时只就地修改有变化的条目。
+/// 返回写入的条目数。
+pub fn apply_exchange_file(file: &ExchangeFile) -> Result<usize> {
+    let translated: Vec<(&str, &str)> = file
+        .units
+        .iter()
+        .filter_map(|u| {
+            u.target
+                .as_deref()
+                .filter(|t| !t.trim().is_empty())
+                .map(|t| (u.key.as_str(), t))
+        })
+        .collect();
+    if translated.is_empty() {
+        return Ok(0);
+    }
+
+    let Some(target) = read_translated_output(&file.target_file)? else {
+        let mut content = format!("l_{}:", file.target_lang);
+        for (key, value) in &translated {
+            content.push_str(&format!("\n  {}: \"{}\"", key, value));
+        }
+        write_translated_file(&content, &file.target_file, true)?;
+        return Ok(translated.len());
+    };
+
+    let current: HashMap<String, String> = parse_entries(&target)
+        .into_iter()
+        .map(|e| (e.key, e.value))
+        .collect();
+    let mut changed = 0;
+    for (key, value) in translated {
+        if current.get(key).map(String::as_str) != Some(value) {
+            write_entry(&file.target_file, key, value)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// 按语言对分组导出，每个语言对写一个文件 `<source>-<target>.<ext>`，返回写出的文件路径
+pub fn export_grouped(
+    files: &[ExchangeFile],
+    output_dir: &Path,
+    extension: &str,
+    render: fn(&[ExchangeFile]) -> String,
+) -> Result<Vec<PathBuf>> {
+    let mut groups: Vec<((&str, &str), Vec<ExchangeFile>)> = Vec::new();
+    for file in files {
+        let pair = (file.source_lang.as_str(), file.target_lang.as_str());
+        match groups.iter_mut().find(|(p, _)| *p == pair) {
+            Some((_, group)) => group.push(file.clone()),
+            None => groups.push((pair, vec![file.clone()])),
+        }
+    }
+
+    std::fs::create_dir_all(output_dir)?;
+    let mut written = Vec::new();
+    for ((source_lang, target_lang), group) in groups {
+        let path = output_dir.join(format!("{}-{}.{}", source_lang, target_lang, extension));
+        std::fs::write(&path, render(&group))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// 将导入的译文写入目标文件，并以人工来源记入翻译记忆，返回写入的条目数
+pub fn import_exchange_files(
+    files: &[ExchangeFile],
+    memory: &mut TranslationMemory,
+) -> Result<usize> {
+    let mut changed = 0;
+    for file in files {
+        changed += apply_exchange_file(file)?;
+        for unit in &file.units {
+            if let Some(target) = unit.target.as_deref().filter(|t| !t.trim().is_empty()) {
+                memory.record(
+                    &file.source_lang,
+                    &file.target_lang,
+                    &unit.source,
+                    target,
+                    MemoryOrigin::Human,
+                );
+            }
+        }
+    }
+    Ok(changed)
+}
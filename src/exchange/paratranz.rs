@@ -0,0 +1,303 @@
+//! Paratranz 项目同步
+//!
+//! 将源文件的条目上传到 Paratranz 项目（每个目标文件对应一个 `<目标文件名>.json` 词条文件），
+//! 并拉取校对过的译文覆盖 AI 译文写回目标文件。
+
+use super::{ExchangeFile, ExchangeUnit, collect_exchange_files, import_exchange_files};
+use crate::config::{ParatranzSettings, TranslationTask};
+use crate::error::{Result, TranslateError, TranslationError};
+use crate::translate::TranslationMemory;
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 已审核及以上的词条阶段：3 已审核、5 已复核、9 已锁定
+const REVIEWED_STAGES: &[i32] = &[3, 5, 9];
+
+/// 已翻译但未审核的词条阶段
+const TRANSLATED_STAGE: i32 = 1;
+
+/// Paratranz 词条文件中的一个词条
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParatranzString {
+    pub key: String,
+    pub original: String,
+    #[serde(default)]
+    pub translation: String,
+    /// 词条阶段，上传时不需要
+    #[serde(default, skip_serializing)]
+    pub stage: i32,
+}
+
+/// Paratranz 项目中的文件
+#[derive(Debug, Clone, Deserialize)]
+pub struct ParatranzFile {
+    pub id: u64,
+    pub name: String,
+}
+
+/// Paratranz API 客户端
+pub struct ParatranzClient {
+    client: reqwest::Client,
+    settings: ParatranzSettings,
+    token: String,
+}
+
+fn request_error(e: reqwest::Error) -> TranslationError {
+    TranslationError::Translate(TranslateError::ApiRequest(e))
+}
+
+/// 检查响应状态，失败时带上响应内容
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(TranslationError::ApiError(format!(
+        "Paratranz API returned {}: {}",
+        status, body
+    )))
+}
+
+impl ParatranzClient {
+    /// 创建新的 Paratranz 客户端
+    pub fn new(settings: ParatranzSettings, token: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            settings,
+            token,
+        }
+    }
+
+    fn project_url(&self, path: &str) -> String {
+        format!(
+            "{}/projects/{}{}",
+            self.settings.api_base.trim_end_matches('/'),
+            self.settings.project_id,
+            path
+        )
+    }
+
+    /// 列出项目中的所有文件
+    pub async fn list_files(&self) -> Result<Vec<ParatranzFile>> {
+        let response = self
+            .client
+            .get(self.project_url("/files"))
+            .header("Authorization", &self.token)
+            .send()
+            .await
+            .map_err(request_error)?;
+        check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(request_error)
+    }
+
+    /// 上传词条文件，`existing` 为已存在的文件 ID 时更新该文件的原文
+    pub async fn upload_file(
+        &self,
+        name: &str,
+        strings: &[ParatranzString],
+        existing: Option<u64>,
+    ) -> Result<()> {
+        let body = serde_json::to_vec_pretty(strings)
+            .map_err(|e| TranslationError::ApiError(e.to_string()))?;
+        let part = Part::bytes(body)
+            .file_name(name.to_string())
+            .mime_str("application/json")
+            .map_err(request_error)?;
+        let mut form = Form::new().part("file", part);
+        let url = match existing {
+            Some(id) => self.project_url(&format!("/files/{}", id)),
+            None => {
+                form = form.text("path", "");
+                self.project_url("/files")
+            }
+        };
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", &self.token)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(request_error)?;
+        check_status(response).await?;
+        Ok(())
+    }
+
+    /// 获取文件的全部词条及译文
+    pub async fn fetch_strings(&self, file_id: u64) -> Result<Vec<ParatranzString>> {
+        let response = self
+            .client
+            .get(self.project_url(&format!("/files/{}/translation", file_id)))
+            .header("Authorization", &self.token)
+            .send()
+            .await
+            .map_err(request_error)?;
+        check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(request_error)
+    }
+}
+
+/// 词条文件在 Paratranz 中的名称：目标文件名加 `.json` 后缀
+fn paratranz_name(file: &ExchangeFile) -> String {
+    let filename = file
+        .target_file
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    format!("{}.json", filename)
+}
+
+/// 将交换条目转换为 Paratranz 词条，已有译文一并上传作为初始译文
+pub fn to_paratranz_strings(file: &ExchangeFile) -> Vec<ParatranzString> {
+    file.units
+        .iter()
+        .map(|u| ParatranzString {
+            key: u.key.clone(),
+            original: u.source.clone(),
+            translation: u.target.clone().unwrap_or_default(),
+            stage: 0,
+        })
+        .collect()
+}
+
+/// 从 Paratranz 词条中挑出需要合并的译文
+pub fn merge_paratranz_strings(
+    file: &ExchangeFile,
+    strings: &[ParatranzString],
+    include_unreviewed: bool,
+) -> ExchangeFile {
+    let units = strings
+        .iter()
+        .filter(|s| !s.translation.trim().is_empty())
+        .filter(|s| {
+            REVIEWED_STAGES.contains(&s.stage)
+                || (include_unreviewed && s.stage == TRANSLATED_STAGE)
+        })
+        .map(|s| ExchangeUnit {
+            key: s.key.clone(),
+            source: s.original.clone(),
+            target: Some(s.translation.clone()),
+        })
+        .collect();
+    ExchangeFile {
+        units,
+        ..file.clone()
+    }
+}
+
+/// 任务中属于该 Paratranz 项目目标语言的文件
+fn project_files(
+    task: &TranslationTask,
+    settings: &ParatranzSettings,
+) -> Result<Vec<ExchangeFile>> {
+    Ok(collect_exchange_files(task)?
+        .into_iter()
+        .filter(|f| f.target_lang == settings.target_lang)
+        .collect())
+}
+
+/// 上传任务的源文件，返回上传的文件数
+pub async fn push_to_paratranz(client: &ParatranzClient, task: &TranslationTask) -> Result<usize> {
+    let remote: HashMap<String, u64> = client
+        .list_files()
+        .await?
+        .into_iter()
+        .map(|f| (f.name, f.id))
+        .collect();
+
+    let files = project_files(task, &client.settings)?;
+    for file in &files {
+        let name = paratranz_name(file);
+        let existing = remote.get(&name).copied();
+        client
+            .upload_file(&name, &to_paratranz_strings(file), existing)
+            .await?;
+        log::info!(
+            "{} {} on Paratranz",
+            if existing.is_some() {
+                "Updated"
+            } else {
+                "Created"
+            },
+            name
+        );
+    }
+    Ok(files.len())
+}
+
+/// 拉取审核过的译文写回目标文件，并以人工来源记入翻译记忆，返回写入的条目数
+pub async fn pull_from_paratranz(
+    client: &ParatranzClient,
+    task: &TranslationTask,
+    memory: &mut TranslationMemory,
+) -> Result<usize> {
+    let remote: HashMap<String, u64> = client
+        .list_files()
+        .await?
+        .into_iter()
+        .map(|f| (f.name, f.id))
+        .collect();
+
+    let mut merged = Vec::new();
+    for file in project_files(task, &client.settings)? {
+        let name = paratranz_name(&file);
+        let Some(&id) = remote.get(&name) else {
+            log::warn!("{} not found on Paratranz, skipping", name);
+            continue;
+        };
+        let strings = client.fetch_strings(id).await?;
+        merged.push(merge_paratranz_strings(
+            &file,
+            &strings,
+            client.settings.include_unreviewed,
+        ));
+    }
+    import_exchange_files(&merged, memory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn string(key: &str, translation: &str, stage: i32) -> ParatranzString {
+        ParatranzString {
+            key: key.to_string(),
+            original: format!("{} source", key),
+            translation: translation.to_string(),
+            stage,
+        }
+    }
+
+    #[test]
+    fn test_merge_only_reviewed_strings() {
+        let file = ExchangeFile {
+            source_lang: "english".to_string(),
+            target_lang: "simp_chinese".to_string(),
+            target_file: PathBuf::from("l_simp_chinese_a.yml"),
+            units: vec![],
+        };
+        let strings = vec![
+            string("a", "已审核", 3),
+            string("b", "已翻译", 1),
+            string("c", "", 0),
+            string("d", "已锁定", 9),
+        ];
+
+        let merged = merge_paratranz_strings(&file, &strings, false);
+        let keys: Vec<&str> = merged.units.iter().map(|u| u.key.as_str()).collect();
+        assert_eq!(keys, ["a", "d"]);
+
+        let merged = merge_paratranz_strings(&file, &strings, true);
+        assert_eq!(merged.units.len(), 3);
+        assert_eq!(paratranz_name(&file), "l_simp_chinese_a.yml.json");
+    }
+}
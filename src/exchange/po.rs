@@ -0,0 +1,247 @@
+//! gettext PO 导出与导入
+//!
+//! 每个语言对生成一个 `.po` 文件：键名写入 `msgctxt`，原文与译文分别写入 `msgid`、`msgstr`，
+//! 目标文件路径写入引用注释 `#:`，源语言与目标语言写入文件头。
+
+use super::{ExchangeFile, ExchangeUnit, language_tag};
+use crate::error::{PostprocessError, Result, TranslationError};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+fn po_error(line: usize, message: &str) -> TranslationError {
+    TranslationError::Postprocess(PostprocessError::Exchange(format!(
+        "invalid PO at line {}: {}",
+        line, message
+    )))
+}
+
+/// 按 PO 规则转义字符串
+fn escape_po(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+/// 还原 PO 转义
+fn unescape_po(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// 将同一语言对的文件写为一个 PO 文档
+pub fn to_po(files: &[ExchangeFile]) -> String {
+    let (source_lang, target_lang) = files
+        .first()
+        .map(|f| (f.source_lang.as_str(), f.target_lang.as_str()))
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    out.push_str("msgid \"\"\nmsgstr \"\"\n");
+    out.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+    let _ = writeln!(
+        out,
+        "\"Language: {}\\n\"",
+        language_tag(target_lang).replace('-', "_")
+    );
+    let _ = writeln!(out, "\"X-Source-Language: {}\\n\"", source_lang);
+    let _ = writeln!(out, "\"X-Target-Language: {}\\n\"", target_lang);
+
+    for file in files {
+        for unit in &file.units {
+            out.push('\n');
+            let _ = writeln!(out, "#: {}", file.target_file.display());
+            let _ = writeln!(out, "msgctxt \"{}\"", escape_po(&unit.key));
+            let _ = writeln!(out, "msgid \"{}\"", escape_po(&unit.source));
+            let _ = writeln!(
+                out,
+                "msgstr \"{}\"",
+                escape_po(unit.target.as_deref().unwrap_or_default())
+            );
+        }
+    }
+    out
+}
+
+/// 正在解析的 PO 条目
+#[derive(Default)]
+struct PendingEntry {
+    reference: Option<String>,
+    msgctxt: Option<String>,
+    msgid: Option<String>,
+    msgstr: Option<String>,
+}
+
+/// 当前续行所属的字段
+#[derive(Clone, Copy)]
+enum Field {
+    Ctxt,
+    Id,
+    Str,
+}
+
+/// 解析 PO 文档
+pub fn from_po(content: &str) -> Result<Vec<ExchangeFile>> {
+    let mut source_lang = String::new();
+    let mut target_lang = String::new();
+    let mut files: Vec<ExchangeFile> = Vec::new();
+    let mut entry = PendingEntry::default();
+    let mut field: Option<Field> = None;
+
+    let flush = |entry: PendingEntry, files: &mut Vec<ExchangeFile>| {
+        let (Some(key), Some(msgid)) = (entry.msgctxt, entry.msgid) else {
+            // 没有 msgctxt 的条目（文件头或非本工具生成的条目）
+            return;
+        };
+        let target_file = PathBuf::from(entry.reference.unwrap_or_default());
+        let target = entry.msgstr.filter(|s| !s.is_empty());
+        if files.last().is_none_or(|f| f.target_file != target_file) {
+            files.push(ExchangeFile {
+                source_lang: String::new(),
+                target_lang: String::new(),
+                target_file,
+                units: Vec::new(),
+            });
+        }
+        if let Some(file) = files.last_mut() {
+            file.units.push(ExchangeUnit {
+                key,
+                source: msgid,
+                target,
+            });
+        }
+    };
+
+    for (i, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        let line_no = i + 1;
+
+        if line.is_empty() {
+            flush(std::mem::take(&mut entry), &mut files);
+            field = None;
+            continue;
+        }
+        if let Some(reference) = line.strip_prefix("#:") {
+            if entry.msgid.is_some() {
+                flush(std::mem::take(&mut entry), &mut files);
+            }
+            entry.reference = Some(reference.trim().to_string());
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let (next_field, rest) = if let Some(rest) = line.strip_prefix("msgctxt ") {
+            if entry.msgid.is_some() {
+                flush(std::mem::take(&mut entry), &mut files);
+            }
+            (Some(Field::Ctxt), rest)
+        } else if let Some(rest) = line.strip_prefix("msgid ") {
+            (Some(Field::Id), rest)
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            (Some(Field::Str), rest)
+        } else {
+            (None, line)
+        };
+        let value = rest
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .ok_or_else(|| po_error(line_no, "expected a quoted string"))?;
+        let value = unescape_po(value);
+
+        if next_field.is_some() {
+            field = next_field;
+        }
+        let slot = match field {
+            Some(Field::Ctxt) => &mut entry.msgctxt,
+            Some(Field::Id) => &mut entry.msgid,
+            Some(Field::Str) => &mut entry.msgstr,
+            None => return Err(po_error(line_no, "string without a keyword")),
+        };
+        if next_field.is_some() {
+            *slot = Some(value);
+        } else {
+            slot.get_or_insert_with(String::new).push_str(&value);
+        }
+
+        // 文件头中的语言信息
+        if matches!(field, Some(Field::Str)) && entry.msgid.as_deref() == Some("") {
+            for header in entry.msgstr.as_deref().unwrap_or_default().lines() {
+                if let Some(lang) = header.strip_prefix("X-Source-Language:") {
+                    source_lang = lang.trim().to_string();
+                } else if let Some(lang) = header.strip_prefix("X-Target-Language:") {
+                    target_lang = lang.trim().to_string();
+                }
+            }
+        }
+    }
+    flush(entry, &mut files);
+
+    for file in &mut files {
+        file.source_lang = source_lang.clone();
+        file.target_lang = target_lang.clone();
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_po_round_trip() {
+        let files = vec![ExchangeFile {
+            source_lang: "english".to_string(),
+            target_lang: "simp_chinese".to_string(),
+            target_file: PathBuf::from("loc/simp_chinese/replace/l_simp_chinese_a.yml"),
+            units: vec![
+                ExchangeUnit {
+                    key: "key_a".to_string(),
+                    source: "Say \"hi\"\\nnext line".to_string(),
+                    target: Some("说「你好」\\n下一行".to_string()),
+                },
+                ExchangeUnit {
+                    key: "key_b".to_string(),
+                    source: "Untranslated".to_string(),
+                    target: None,
+                },
+            ],
+        }];
+        let po = to_po(&files);
+        assert!(po.contains("msgctxt \"key_a\""));
+        assert!(po.contains("\"Language: zh_CN\\n\""));
+
+        let parsed = from_po(&po).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].source_lang, "english");
+        assert_eq!(parsed[0].target_lang, "simp_chinese");
+        assert_eq!(parsed[0].target_file, files[0].target_file);
+        assert_eq!(parsed[0].units, files[0].units);
+    }
+
+    #[test]
+    fn test_po_multiline_strings() {
+        let po = "msgid \"\"\nmsgstr \"\"\n\"X-Source-Language: english\\n\"\n\"X-Target-Language: french\\n\"\n\n#: a.yml\nmsgctxt \"k\"\nmsgid \"\"\n\"Hello \"\n\"world\"\nmsgstr \"\"\n\"Bonjour \"\n\"le monde\"\n";
+        let parsed = from_po(po).unwrap();
+        assert_eq!(parsed[0].target_lang, "french");
+        assert_eq!(parsed[0].units[0].source, "Hello world");
+        assert_eq!(
+            parsed[0].units[0].target.as_deref(),
+            Some("Bonjour le monde")
+        );
+    }
+}
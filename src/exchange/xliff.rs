@@ -0,0 +1,181 @@
+//! XLIFF 2.0 导出与导入
+//!
+//! 每个目标语言生成一个 XLIFF 文档，每个源文件对应一个 `<file>`，`original` 属性记录目标文件路径，
+//! 每个条目对应一个以键名为 id 的 `<unit>`。
+
+use super::{ExchangeFile, ExchangeUnit, language_tag, paradox_language};
+use crate::error::{PostprocessError, Result, TranslationError};
+use quick_xml::Reader;
+use quick_xml::escape::{escape, resolve_predefined_entity};
+use quick_xml::events::{BytesStart, Event};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+fn xliff_error(e: impl std::fmt::Display) -> TranslationError {
+    TranslationError::Postprocess(PostprocessError::Exchange(format!("invalid XLIFF: {}", e)))
+}
+
+/// 将同一目标语言的文件写为一个 XLIFF 2.0 文档
+pub fn to_xliff(files: &[ExchangeFile]) -> String {
+    let (source_lang, target_lang) = files
+        .first()
+        .map(|f| (f.source_lang.as_str(), f.target_lang.as_str()))
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<xliff xmlns=\"urn:oasis:names:tc:xliff:document:2.0\" version=\"2.0\" srcLang=\"{}\" trgLang=\"{}\">",
+        escape(language_tag(source_lang)),
+        escape(language_tag(target_lang))
+    );
+    for (i, file) in files.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "  <file id=\"f{}\" original=\"{}\">",
+            i + 1,
+            escape(file.target_file.to_string_lossy())
+        );
+        for unit in &file.units {
+            let _ = writeln!(out, "    <unit id=\"{}\">", escape(&unit.key));
+            let state = if unit.target.is_some() {
+                "translated"
+            } else {
+                "initial"
+            };
+            let _ = writeln!(out, "      <segment state=\"{}\">", state);
+            let _ = writeln!(out, "        <source>{}</source>", escape(&unit.source));
+            if let Some(target) = &unit.target {
+                let _ = writeln!(out, "        <target>{}</target>", escape(target));
+            }
+            out.push_str("      </segment>\n    </unit>\n");
+        }
+        out.push_str("  </file>\n");
+    }
+    out.push_str("</xliff>\n");
+    out
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Result<Option<String>> {
+    match e.try_get_attribute(name).map_err(xliff_error)? {
+        Some(attr) => Ok(Some(
+            attr.unescape_value().map_err(xliff_error)?.into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// 解析 XLIFF 2.0 文档
+///
+/// 只读取 `<source>` 与 `<target>` 的文本内容，CAT 工具插入的行内元素会被忽略。
+pub fn from_xliff(content: &str) -> Result<Vec<ExchangeFile>> {
+    let mut reader = Reader::from_str(content);
+    let mut files: Vec<ExchangeFile> = Vec::new();
+    let mut source_lang = String::new();
+    let mut target_lang = String::new();
+    // 当前正在读取的文本：Some(true) 为 target，Some(false) 为 source
+    let mut reading: Option<bool> = None;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event().map_err(xliff_error)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"xliff" => {
+                    source_lang = attribute(&e, "srcLang")?.unwrap_or_default();
+                    target_lang = attribute(&e, "trgLang")?.unwrap_or_default();
+                }
+                b"file" => files.push(ExchangeFile {
+                    source_lang: paradox_language(&source_lang).to_string(),
+                    target_lang: paradox_language(&target_lang).to_string(),
+                    target_file: PathBuf::from(attribute(&e, "original")?.unwrap_or_default()),
+                    units: Vec::new(),
+                }),
+                b"unit" => {
+                    let key = attribute(&e, "id")?.unwrap_or_default();
+                    if let Some(file) = files.last_mut() {
+                        file.units.push(ExchangeUnit {
+                            key,
+                            source: String::new(),
+                            target: None,
+                        });
+                    }
+                }
+                b"source" => {
+                    reading = Some(false);
+                    text.clear();
+                }
+                b"target" => {
+                    reading = Some(true);
+                    text.clear();
+                }
+                _ => {}
+            },
+            Event::Text(t) if reading.is_some() => {
+                text.push_str(&t.decode().map_err(xliff_error)?);
+            }
+            Event::GeneralRef(r) if reading.is_some() => {
+                if let Some(c) = r.resolve_char_ref().map_err(xliff_error)? {
+                    text.push(c);
+                } else {
+                    let name = r.decode().map_err(xliff_error)?;
+                    let resolved = resolve_predefined_entity(&name)
+                        .ok_or_else(|| xliff_error(format!("unknown entity &{};", name)))?;
+                    text.push_str(resolved);
+                }
+            }
+            Event::End(e) => {
+                let name = e.local_name();
+                if matches!(name.as_ref(), b"source" | b"target") {
+                    let unit = files.last_mut().and_then(|f| f.units.last_mut());
+                    if let Some(unit) = unit {
+                        if reading == Some(true) {
+                            unit.target = Some(std::mem::take(&mut text));
+                        } else {
+                            unit.source = std::mem::take(&mut text);
+                        }
+                    }
+                    reading = None;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xliff_round_trip() {
+        let files = vec![ExchangeFile {
+            source_lang: "english".to_string(),
+            target_lang: "simp_chinese".to_string(),
+            target_file: PathBuf::from("loc/simp_chinese/replace/l_simp_chinese_a.yml"),
+            units: vec![
+                ExchangeUnit {
+                    key: "key_a".to_string(),
+                    source: "§Y$name$§! & <friends>".to_string(),
+                    target: Some("§Y$name$§! 与 <朋友>".to_string()),
+                },
+                ExchangeUnit {
+                    key: "key_b".to_string(),
+                    source: "Untranslated".to_string(),
+                    target: None,
+                },
+            ],
+        }];
+        let xml = to_xliff(&files);
+        assert!(xml.contains("srcLang=\"en\" trgLang=\"zh-CN\""));
+
+        let parsed = from_xliff(&xml).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].target_lang, "simp_chinese");
+        assert_eq!(parsed[0].target_file, files[0].target_file);
+        assert_eq!(parsed[0].units, files[0].units);
+    }
+}
@@ -0,0 +1,421 @@
+//! 界面语言
+//!
+//! 简单的消息目录：每条面向用户的消息以 ID 标识，在目录中给出各界面语言的文本，
+//! 文本中以 `{0}`、`{1}` 等引用参数，不同语言可以调整参数的顺序。
+//! 界面语言依次取自 `--ui-lang`、`PMT_LANG` 环境变量与系统的 `LANG` 环境变量，都未设置时使用英语。
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 界面语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiLang {
+    English,
+    Chinese,
+}
+
+impl UiLang {
+    /// 由语言代码解析界面语言，例如 `en`、`zh`、`zh_CN.UTF-8`、`english`、`simp_chinese`
+    pub fn parse(code: &str) -> Option<Self> {
+        let code = code.trim().to_lowercase();
+        if code.starts_with("zh") || code.contains("chinese") {
+            Some(UiLang::Chinese)
+        } else if code.starts_with("en") {
+            Some(UiLang::English)
+        } else {
+            None
+        }
+    }
+
+    /// 由命令行参数与环境变量确定界面语言
+    pub fn detect(flag: Option<&str>) -> Self {
+        let env = |name: &str| std::env::var(name).ok();
+        flag.map(str::to_string)
+            .into_iter()
+            .chain(env("PMT_LANG"))
+            .chain(env("LANG"))
+            .find_map(|code| Self::parse(&code))
+            .unwrap_or(UiLang::English)
+    }
+}
+
+static UI_LANG: AtomicU8 = AtomicU8::new(0);
+
+/// 设置全局的界面语言
+pub fn set_ui_lang(lang: UiLang) {
+    UI_LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+/// 当前的界面语言
+pub fn ui_lang() -> UiLang {
+    match UI_LANG.load(Ordering::Relaxed) {
+        1 => UiLang::Chinese,
+        _ => UiLang::English,
+    }
+}
+
+/// 消息目录：(ID, 英语, 中文)
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // 配置
+    (
+        "config.no_tasks",
+        "No task found in the configuration file",
+        "配置文件中未找到任务",
+    ),
+    (
+        "config.no_mods",
+        "No mod found in the project configuration file",
+        "项目配置文件中未找到 Mod",
+    ),
+    (
+        "config.mod_task_file_missing",
+        "Task file of mod {0} does not exist: {1}",
+        "Mod {0} 的任务配置文件不存在: {1}",
+    ),
+    (
+        "config.thumbnail_missing",
+        "Thumbnail does not exist: {0}",
+        "缩略图不存在: {0}",
+    ),
+    (
+        "config.source_lang_unknown",
+        "Cannot infer the source language from path: {0}",
+        "无法从路径推断源语言: {0}",
+    ),
+    (
+        "config.localisation_dir_missing",
+        "Localisation directory does not exist: {0}",
+        "本地化目录不存在: {0}",
+    ),
+    (
+        "config.source_langs_mismatch",
+        "source_langs must start with source_lang {0}: {1}",
+        "source_langs 的第一种语言须为 source_lang {0}: {1}",
+    ),
+    (
+        "config.source_dir_missing",
+        "Source language directory does not exist: {0}",
+        "源语言目录不存在: {0}",
+    ),
+    (
+        "config.archive_output_dir_missing",
+        "output_dir is required when localisation_dir is a mod archive or package: {0}",
+        "localisation_dir 指向 Mod 压缩包或 Mod 目录时须设置 output_dir: {0}",
+    ),
+    (
+        "config.vanilla_dir_missing",
+        "Game localisation directory does not exist: {0}",
+        "游戏本地化目录不存在: {0}",
+    ),
+    (
+        "config.game_dir_invalid",
+        "Invalid game install directory (no localisation directory): {0}",
+        "游戏安装目录无效（缺少 localisation 目录）: {0}",
+    ),
+    (
+        "config.max_entries_per_file_zero",
+        "max_entries_per_file must be greater than 0",
+        "max_entries_per_file 必须大于 0",
+    ),
+    (
+        "config.micro_batch_tokens_zero",
+        "micro_batch_tokens must be greater than 0",
+        "micro_batch_tokens 必须大于 0",
+    ),
+    (
+        "config.max_concurrency_too_low",
+        "max_concurrency must be at least concurrency ({0})",
+        "max_concurrency 不能小于 concurrency（{0}）",
+    ),
+    (
+        "config.temperature_range",
+        "temperature must be between 0.0 and 2.0",
+        "temperature 必须在 0.0 到 2.0 之间",
+    ),
+    (
+        "config.top_p_range",
+        "top_p must be between 0.0 and 1.0",
+        "top_p 必须在 0.0 到 1.0 之间",
+    ),
+    (
+        "config.stop_invalid",
+        "stop must contain at most 4 non-empty sequences",
+        "stop 最多包含 4 个非空序列",
+    ),
+    (
+        "config.timeout_zero",
+        "timeout_secs must be greater than 0",
+        "timeout_secs 必须大于 0",
+    ),
+    (
+        "config.max_chunk_tokens_too_low",
+        "max_chunk_tokens must be at least 100 characters",
+        "max_chunk_tokens 不能小于 100",
+    ),
+    (
+        "config.concurrency_zero",
+        "concurrency must be at least 1",
+        "concurrency 不能小于 1",
+    ),
+    (
+        "config.penalty_range",
+        "{0} must be between -2.0 and 2.0",
+        "{0} 必须在 -2.0 到 2.0 之间",
+    ),
+    (
+        "config.proxy_url_invalid",
+        "proxy_url is not a valid proxy URL: {0}",
+        "proxy_url 不是有效的代理地址: {0}",
+    ),
+    (
+        "config.ca_bundle_missing",
+        "ca_bundle does not exist: {0}",
+        "ca_bundle 不存在: {0}",
+    ),
+    (
+        "config.header_invalid",
+        "headers contains an invalid HTTP header: {0}",
+        "headers 中有无效的 HTTP 头: {0}",
+    ),
+    (
+        "config.extra_body_reserved",
+        "extra_body cannot override the {0} request field",
+        "extra_body 不能覆盖请求字段 {0}",
+    ),
+    (
+        "config.passes_invalid",
+        "passes must be 1 or 2",
+        "passes 只能是 1 或 2",
+    ),
+    (
+        "config.ensemble_models_count",
+        "ensemble_models must list 2 or 3 models",
+        "ensemble_models 须列出 2 或 3 个模型",
+    ),
+    (
+        "config.judge_without_ensemble",
+        "judge_model requires ensemble_models",
+        "设置 judge_model 时须同时设置 ensemble_models",
+    ),
+    (
+        "config.output_subdir_invalid",
+        "output_subdir must be a relative path without '..': {0}",
+        "output_subdir 必须是不含 '..' 的相对路径: {0}",
+    ),
+    (
+        "config.filename_template_invalid",
+        "filename_template must contain {target} and no path separators: \"{0}\"",
+        "filename_template 必须包含 {target} 且不含路径分隔符: \"{0}\"",
+    ),
+    (
+        "config.length_budget_pattern_invalid",
+        "length_budgets has an invalid keys pattern \"{0}\": {1}",
+        "length_budgets 的 keys 正则表达式无效 \"{0}\": {1}",
+    ),
+    (
+        "config.length_budget_width_zero",
+        "length_budgets max_width for \"{0}\" must be greater than 0",
+        "length_budgets 中 \"{0}\" 的 max_width 必须大于 0",
+    ),
+    (
+        "config.custom_marker_invalid",
+        "custom_markers has an invalid pattern \"{0}\": {1}",
+        "custom_markers 中的正则表达式无效 \"{0}\": {1}",
+    ),
+    (
+        "config.source_files_unreadable",
+        "Failed to list source files in {0}: {1}",
+        "无法列出 {0} 中的源文件: {1}",
+    ),
+    (
+        "config.url_not_http",
+        "{0} must be an http(s) URL",
+        "{0} 必须是 http(s) 地址",
+    ),
+    (
+        "config.ignore_pattern_invalid",
+        "validation ignore has an invalid {0} pattern \"{1}\": {2}",
+        "忽略列表中的 {0} 正则表达式无效 \"{1}\": {2}",
+    ),
+    (
+        "config.ignore_rule_unknown",
+        "validation ignore has an unknown rule \"{0}\", expected one of: {1}",
+        "忽略列表中有未知的规则 \"{0}\"，可用的规则: {1}",
+    ),
+    (
+        "config.since_archive",
+        "--since requires localisation_dir to be a localisation directory in a git work tree, not a mod archive, .mod descriptor or mod directory: {0}",
+        "使用 --since 时 localisation_dir 须为 git 工作区中的本地化目录，而不是 Mod 压缩包、.mod 描述文件或 Mod 目录: {0}",
+    ),
+    (
+        "config.package_dir_overlaps",
+        "Translation mod directory {0} is removed before packaging, but a task reads or writes inside it: {1}",
+        "打包前会清空翻译子 Mod 目录 {0}，但有任务读取或写入其中的文件: {1}",
+    ),
+    // 术语表
+    (
+        "glossary.bad_entry",
+        "Cannot parse glossary entry: key={0}, error={1}",
+        "无法解析术语表条目: key={0}, error={1}",
+    ),
+    (
+        "glossary.not_object",
+        "A glossary file must be a JSON object",
+        "术语表文件必须是JSON对象",
+    ),
+    (
+        "fs.no_user_data_dir",
+        "[user data directory unavailable]",
+        "[无法获取用户数据目录]",
+    ),
+    // 命令行
+    (
+        "cli.api_key_missing",
+        "OPENAI_API_KEY environment variable is not set",
+        "未设置 OPENAI_API_KEY 环境变量",
+    ),
+    (
+        "cli.api_key_hint",
+        "Please set OPENAI_API_KEY environment variable or create a .env file",
+        "请设置 OPENAI_API_KEY 环境变量，或创建 .env 文件",
+    ),
+    (
+        "cli.api_key_required",
+        "OPENAI_API_KEY environment variable is required",
+        "需要设置 OPENAI_API_KEY 环境变量",
+    ),
+    (
+        "cli.interrupted",
+        "Interrupted, cancelling in-flight requests",
+        "已中断，正在取消进行中的请求",
+    ),
+    (
+        "cli.resume_hint",
+        "Files written so far are complete, run `{0}` again to continue",
+        "已写入的文件都是完整的，再次运行 `{0}` 即可继续",
+    ),
+    ("cli.issues_header", "Validation issues:", "验证问题："),
+    (
+        "cli.show_all_hint",
+        "Run with --show-all-warnings to list every issue",
+        "添加 --show-all-warnings 可列出全部问题",
+    ),
+    (
+        "cli.nothing_to_clean",
+        "Nothing to clean",
+        "没有需要清理的文件",
+    ),
+    (
+        "cli.would_remove",
+        "Would remove {0} file {1}",
+        "将删除{0}文件 {1}",
+    ),
+    (
+        "cli.removing",
+        "Removing {0} file {1}",
+        "正在删除{0}文件 {1}",
+    ),
+    (
+        "cli.would_remove_total",
+        "Would remove {0} files",
+        "将删除 {0} 个文件",
+    ),
+    (
+        "cli.removed_total",
+        "Removed {0} files",
+        "已删除 {0} 个文件",
+    ),
+    (
+        "cli.task_completed",
+        "Translation task completed successfully!",
+        "翻译任务已完成！",
+    ),
+    (
+        "cli.tasks_completed",
+        "All translation tasks completed!",
+        "全部翻译任务已完成！",
+    ),
+    (
+        "cli.quick_completed",
+        "Quick translation completed!",
+        "快速翻译已完成！",
+    ),
+    (
+        "cli.mods_completed",
+        "All mods translated!",
+        "全部 Mod 已翻译完成！",
+    ),
+    (
+        "cli.term_candidate",
+        "{0} -> {1} ({2} occurrences, first in {3})",
+        "{0} -> {1}（出现 {2} 次，首次出现于 {3}）",
+    ),
+    (
+        "cli.term_candidates_written",
+        "Wrote {0} candidate terms to {1}, review them and move the file to data/glossary_custom/ to use it",
+        "已将 {0} 个候选术语写入 {1}，审阅后将文件移到 data/glossary_custom/ 即可使用",
+    ),
+    (
+        "cli.ab_tally",
+        "{0}: {1} wins, {2} ties in {3} judged entries, {4} validation issues",
+        "{0}：{3} 个已评判的条目中胜 {1} 次、平 {2} 次，验证问题 {4} 个",
+    ),
+    ("cleanup.checkpoint", "checkpoint", "检查点"),
+    ("cleanup.temp", "temp", "临时"),
+    ("cleanup.stale", "stale", "残留"),
+];
+
+/// 当前界面语言下的消息模板，目录中没有该 ID 时返回 ID 本身
+pub fn message(id: &str) -> &str {
+    match MESSAGES.iter().find(|(key, _, _)| *key == id) {
+        Some((_, en, zh)) => match ui_lang() {
+            UiLang::English => en,
+            UiLang::Chinese => zh,
+        },
+        None => id,
+    }
+}
+
+/// 将参数代入消息模板中的 `{0}`、`{1}` 等占位符
+pub fn format_message(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = template.to_string();
+    for (i, arg) in args.iter().enumerate() {
+        out = out.replace(&format!("{{{}}}", i), &arg.to_string());
+    }
+    out
+}
+
+/// 取得当前界面语言下的消息：`tr!("id")` 或 `tr!("id", 参数...)`
+#[macro_export]
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::i18n::message($id).to_string()
+    };
+    ($id:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::format_message($crate::i18n::message($id), &[$(&$arg),+])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(UiLang::parse("zh_CN.UTF-8"), Some(UiLang::Chinese));
+        assert_eq!(UiLang::parse("en_US"), Some(UiLang::English));
+        assert_eq!(UiLang::parse("C"), None);
+        assert_eq!(UiLang::detect(Some("simp_chinese")), UiLang::Chinese);
+
+        // 目录中的 ID 不重复
+        for (i, (id, _, _)) in MESSAGES.iter().enumerate() {
+            assert!(MESSAGES[i + 1..].iter().all(|(other, _, _)| other != id));
+        }
+
+        assert_eq!(
+            format_message("Mod {0} 的任务配置文件不存在: {1}", &[&"a", &"\"b.toml\""]),
+            "Mod a 的任务配置文件不存在: \"b.toml\""
+        );
+        assert_eq!(message("no.such.message"), "no.such.message");
+    }
+}
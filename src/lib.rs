@@ -1,8 +1,14 @@
 //! Paradox Mod Translator - AI-powered translation tool for Paradox game mods.
 
 pub mod config;
+pub mod exchange;
+pub mod i18n;
+pub mod metrics;
 pub mod postprocess;
 pub mod preprocess;
+pub mod review;
+pub mod source;
+pub mod steam;
 pub mod translate;
 pub mod utils;
 
@@ -12,9 +18,11 @@ pub mod error;
 pub use error::{Result, TranslationError};
 
 use crate::{
+    error::ResultExt,
     preprocess::{fix_yaml_content, trim_lang_header},
-    translate::{FileChunk, FormatValidator},
+    translate::{FileChunk, FormatValidator, StructureDiff, ValidationIgnore},
 };
+use std::collections::HashMap;
 
 /// 执行翻译任务
 pub async fn translate_task(
@@ -22,9 +30,18 @@ pub async fn translate_task(
     client_settings: config::ClientSettings,
     concurrent: bool,
 ) -> Result<()> {
-    use crate::translate::{Translator, load_glossaries_from_task};
+    translate_task_with_memory(task, client_settings, concurrent, None).await
+}
+
+/// 执行翻译任务，并使用翻译记忆：源文本已有译文的条目直接复用，新的 AI 译文记入其中
+pub async fn translate_task_with_memory(
+    task: config::TranslationTask,
+    client_settings: config::ClientSettings,
+    concurrent: bool,
+    memory: Option<translate::SharedMemory>,
+) -> Result<()> {
+    use crate::translate::{ReuseSources, Translator, load_glossaries_from_task};
     use std::fs;
-    use walkdir::WalkDir;
 
     log::info!("Starting translation task");
     log::info!("Source language: {}", task.source_lang);
@@ -35,35 +52,36 @@ pub async fn translate_task(
 
     // 2. 创建翻译器
     let max_chunk_tokens = client_settings.max_chunk_tokens;
-    let concurrency = client_settings.concurrency;
-    let translator = Translator::from_settings(client_settings, merged_glossary)?;
+    // 启用自适应并发时切片最多按上限同时进行，实际的请求数由翻译器调整
+    let concurrency = client_settings
+        .max_concurrency
+        .unwrap_or(client_settings.concurrency);
+    let translator = Translator::for_task(client_settings, merged_glossary, &task)?;
+    let reuse = ReuseSources {
+        overrides: translate::HumanOverrides::for_task(&task)?,
+        vanilla: load_vanilla(&task)?,
+        memory,
+    };
 
     // 3. 遍历源目录中的文件
-    let source_dir = task.source_dir();
-    log::info!("Reading source files from: {:?}", source_dir);
+    let source = task.source()?;
+    log::info!(
+        "Reading source files from: {:?}",
+        source.origin().join(&task.source_lang)
+    );
 
-    let mut source_files = Vec::new();
-    for entry in WalkDir::new(&source_dir) {
-        let entry = entry.map_err(|e| {
-            TranslationError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("WalkDir error: {}", e),
-            ))
-        })?;
-        if entry.file_type().is_file() {
-            let path = entry.path();
-            if let Some(ext) = path.extension() {
-                if ext == "yml" || ext == "yaml" {
-                    source_files.push(path.to_path_buf());
-                }
-            }
-        }
-    }
+    let source_files = task.list_source_files(source.as_ref()).await?;
 
     log::info!("Found {} source files", source_files.len());
 
     let total = task.target_langs.len() * source_files.len();
+    metrics::metrics().add_files_total(total);
     let mut count = 0;
+    // 启用 continue_on_error 时翻译失败的文件
+    let mut failures = Vec::new();
+    // 经中转语言翻译时读取中转译文的来源，以及用到的中转译文
+    let pivot_source = source::DirectorySource::new(&task.pivot_dir);
+    let mut pivots = std::collections::BTreeSet::new();
     // 4. 对每个目标语言进行翻译
     for target_lang in &task.target_langs {
         log::info!("Translating to: {}", target_lang);
@@ -74,168 +92,1091 @@ pub async fn translate_task(
         // 创建目标目录
         fs::create_dir_all(&target_dir)?;
 
+        // 先合并翻译小文件，已写入译文的文件在下面逐个翻译时跳过
+        let batched = translate_small_files(
+            &translator,
+            &task,
+            source.as_ref(),
+            &source_files,
+            target_lang,
+            max_chunk_tokens,
+            &reuse,
+        )
+        .await;
+
         for source_file in &source_files {
+            let source_lang = task.file_source_lang(source_file);
+            if source_lang == target_lang {
+                log::info!("Skipping {:?}: already in {}", source_file, target_lang);
+                count += 1;
+                metrics::metrics().file_completed();
+                continue;
+            }
+            if batched.contains(source_file) {
+                count += 1;
+                metrics::metrics().file_completed();
+                log::info!("Progress: {}/{} files translated", count, total);
+                continue;
+            }
             log::info!("Processing file: {:?}", source_file);
-            if concurrent {
-                translate_one_file_batch(
+            let result = async {
+                let output_path = task.target_file(source_file, target_lang);
+                let mut passes = Vec::new();
+                // 需要重新翻译中转语言时源文件的内容，译完后记录其哈希
+                let mut pivot_content = None;
+                match task.pivot_for(source_lang, target_lang) {
+                    Some(pivot_lang) => {
+                        let pivot_file = task.pivot_file(source_file, pivot_lang);
+                        let content = source.read_file(source_file)?;
+                        if translate::pivot_is_current(&pivot_file, &content) {
+                            log::info!("Reusing {} pivot translation {:?}", pivot_lang, pivot_file);
+                        } else {
+                            if let Some(parent) = pivot_file.parent() {
+                                fs::create_dir_all(parent)?;
+                            }
+                            passes.push(FilePass {
+                                source_lang,
+                                target_lang: pivot_lang,
+                                source: source.as_ref(),
+                                input: source_file.clone(),
+                                output: pivot_file.clone(),
+                                max_entries: None,
+                            });
+                            pivot_content = Some(content);
+                        }
+                        pivots.insert(pivot_file.clone());
+                        passes.push(FilePass {
+                            source_lang: pivot_lang,
+                            target_lang,
+                            source: &pivot_source,
+                            input: pivot_file,
+                            output: output_path,
+                            max_entries: task.max_entries_per_file,
+                        });
+                    }
+                    None => passes.push(FilePass {
+                        source_lang,
+                        target_lang,
+                        source: source.as_ref(),
+                        input: source_file.clone(),
+                        output: output_path,
+                        max_entries: task.max_entries_per_file,
+                    }),
+                }
+                for (index, pass) in passes.into_iter().enumerate() {
+                    if concurrent {
+                        translate_one_file_batch(
+                            &translator,
+                            pass.source_lang,
+                            pass.target_lang,
+                            max_chunk_tokens,
+                            concurrency,
+                            &pass.output,
+                            pass.source,
+                            &pass.input,
+                            &reuse,
+                            pass.max_entries,
+                        )
+                        .await?;
+                    } else {
+                        translate_one_file(
+                            &translator,
+                            pass.source_lang,
+                            pass.target_lang,
+                            max_chunk_tokens,
+                            &pass.output,
+                            pass.source,
+                            &pass.input,
+                            &reuse,
+                            pass.max_entries,
+                        )
+                        .await?;
+                    }
+                    if index == 0
+                        && let Some(content) = &pivot_content
+                    {
+                        translate::record_pivot_source(&pass.output, content)?;
+                    }
+                }
+                Ok::<(), TranslationError>(())
+            }
+            .await
+            .in_file(source_file);
+            match result {
+                Ok(()) => {
+                    count += 1;
+                    metrics::metrics().file_completed();
+                    log::info!("Progress: {}/{} files translated", count, total);
+                }
+                Err(error) if task.continue_on_error => {
+                    log::error!(
+                        "Failed to translate {:?} to {}, continuing: {}",
+                        source_file,
+                        target_lang,
+                        error
+                    );
+                    metrics::metrics().record_error();
+                    failures.push(error::FileFailure {
+                        file: source_file.clone(),
+                        target_lang: target_lang.clone(),
+                        error,
+                    });
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    if !pivots.is_empty() {
+        log::info!("Intermediate translations ({} files):", pivots.len());
+        for path in &pivots {
+            log::info!("  {:?}", path);
+        }
+    }
+
+    if !failures.is_empty() {
+        log::error!("{} of {} files failed to translate:", failures.len(), total);
+        for failure in &failures {
+            log::error!("  {}", failure);
+        }
+        return Err(TranslationError::FileFailures(failures));
+    }
+    write_output_archive(&task)?;
+    log::info!("{}", crate::tr!("cli.task_completed"));
+    Ok(())
+}
+
+/// 翻译一个文件的一次翻译：直接由源语言译为目标语言，或经中转语言翻译时的其中一步
+struct FilePass<'a> {
+    source_lang: &'a str,
+    target_lang: &'a str,
+    /// 读取 `input` 的来源
+    source: &'a dyn source::LocalisationSource,
+    input: std::path::PathBuf,
+    output: std::path::PathBuf,
+    /// 每个目标文件的最大条目数，中转译文不拆分
+    max_entries: Option<usize>,
+}
+
+/// 任务设置了 `output_archive` 时将译文打包写入其中
+fn write_output_archive(task: &config::TranslationTask) -> Result<()> {
+    if let Some(archive) = &task.output_archive {
+        let count = postprocess::zip_task_outputs(task, archive)?;
+        log::info!("Packed {} translated files into {:?}", count, archive);
+    }
+    Ok(())
+}
+
+/// 删除设置了 `prune_stale_outputs` 的任务中源文件已不存在的目标文件
+///
+/// `tasks` 为同一配置文件中的全部任务，在它们都翻译完成后调用，写入同一目标目录的其他任务的文件不会被删除。
+pub fn prune_stale_outputs(tasks: &[config::TranslationTask]) -> Result<()> {
+    for task in tasks.iter().filter(|t| t.prune_stale_outputs) {
+        for path in postprocess::find_stale_outputs(task, tasks)? {
+            log::info!("Removing stale output without a source file: {:?}", path);
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// 任务启用复用官方译文时，加载各目标语言的原版本地化
+fn load_vanilla(task: &config::TranslationTask) -> Result<Option<translate::VanillaLocalisation>> {
+    task.vanilla_dir()
+        .map(|dir| {
+            log::info!("Reusing official translations from {:?}", dir);
+            translate::VanillaLocalisation::load(dir, &task.target_langs)
+        })
+        .transpose()
+}
+
+/// 只翻译源语言文件中自 git 修订版本 `since` 以来新增或修改的条目
+///
+/// 目标文件已存在时将译文合并到其中，其余条目保持不变；目标文件不存在时翻译整个文件。
+pub async fn translate_task_since(
+    task: config::TranslationTask,
+    client_settings: config::ClientSettings,
+    since: &str,
+) -> Result<()> {
+    use crate::postprocess::{output_exists, read_translated_output, write_translated_output};
+    use crate::preprocess::{changed_keys, detect_renamed_keys, filter_entries, parse_entries};
+    use crate::review::{remove_keys, write_entries};
+    use crate::source::merge_missing_entries;
+    use crate::translate::{ReuseSources, Translator, load_glossaries_from_task};
+    use crate::utils::{changed_files, file_at_revision};
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::PathBuf;
+
+    task.check_git_source()?;
+    log::info!("Starting translation of keys changed since {}", since);
+
+    let merged_glossary = load_glossaries_from_task(&task)?;
+    let max_chunk_tokens = client_settings.max_chunk_tokens;
+    let translator = Translator::for_task(client_settings, merged_glossary, &task)?;
+    let reuse = ReuseSources {
+        overrides: translate::HumanOverrides::for_task(&task)?,
+        vanilla: load_vanilla(&task)?,
+        memory: None,
+    };
+
+    // 自动检测源语言或设置了多个源语言时，源文件分布在多个语言目录中，逐个目录比较；
+    // 源文件的其他语言版本（`source_langs`）有变化时，补充了其中的键的源文件也视为有变化
+    let source = task.source()?;
+    let all_files = task.list_source_files(source.as_ref()).await?;
+    let versions = |file: &PathBuf| -> Vec<PathBuf> {
+        std::iter::once(file.clone())
+            .chain(task.fallback_files.get(file).into_iter().flatten().cloned())
+            .collect()
+    };
+    let lang_dirs: Vec<PathBuf> = source
+        .languages()?
+        .into_iter()
+        .map(|lang| task.localisation_dir.join(lang))
+        .filter(|dir| {
+            all_files
+                .iter()
+                .flat_map(versions)
+                .any(|file| file.starts_with(dir))
+        })
+        .collect();
+    let lang_dir = |file: &PathBuf| {
+        lang_dirs
+            .iter()
+            .find(|dir| file.starts_with(dir))
+            .expect("source files are in a language directory")
+    };
+    let mut changed = HashSet::new();
+    for dir in &lang_dirs {
+        changed.extend(changed_files(dir, since)?);
+    }
+    let source_files: Vec<PathBuf> = all_files
+        .into_iter()
+        .filter(|file| versions(file).iter().any(|f| changed.contains(f)))
+        .collect();
+    log::info!("Found {} changed source files", source_files.len());
+
+    metrics::metrics().add_files_total(task.target_langs.len() * source_files.len());
+    // 译文随重命名的键转移：（目标文件，旧键，新键）
+    let mut carried = Vec::new();
+    for source_file in &source_files {
+        // 与当前内容一样，修订版本中的内容也补充其他语言版本中的键
+        let content = source.read_file(source_file)?;
+        let old = match file_at_revision(lang_dir(source_file), since, source_file)? {
+            Some(primary) => {
+                let mut fallbacks = Vec::new();
+                for file in &versions(source_file)[1..] {
+                    fallbacks.extend(file_at_revision(lang_dir(file), since, file)?);
+                }
+                Some(merge_missing_entries(&primary, &fallbacks))
+            }
+            None => None,
+        };
+        let (keys, renamed) = match old {
+            Some(old) => (
+                changed_keys(&old, &content),
+                detect_renamed_keys(&old, &content),
+            ),
+            None => (
+                parse_entries(&content).into_iter().map(|e| e.key).collect(),
+                Vec::new(),
+            ),
+        };
+        log::info!("{:?}: {} changed keys", source_file, keys.len());
+
+        let source_lang = task.file_source_lang(source_file);
+        for target_lang in &task.target_langs {
+            if keys.is_empty() || source_lang == target_lang {
+                metrics::metrics().file_completed();
+                continue;
+            }
+            fs::create_dir_all(task.target_dir(target_lang))?;
+            let output_path = task.target_file(source_file, target_lang);
+            let target_filename = output_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let subset = if output_exists(&output_path) {
+                filter_entries(&content, |k| keys.iter().any(|c| c == k))
+            } else {
+                content.clone()
+            };
+            let mut known = reuse.known_translations(&subset, source_lang, target_lang);
+            // 译文已转移到新键的旧键，合并后从目标文件中删除
+            let mut old_keys = Vec::new();
+            let pinned = postprocess::pinned_in_output(&output_path)?;
+            reuse.keep_pinned(&mut known, &pinned, target_lang);
+            if !renamed.is_empty() && output_exists(&output_path) {
+                // 文本未变、只是键被重命名的条目沿用旧键的译文
+                let existing: HashMap<String, String> =
+                    parse_entries(&read_translated_output(&output_path)?.unwrap_or_default())
+                        .into_iter()
+                        .map(|e| (e.key, e.value))
+                        .collect();
+                for (old_key, new_key) in &renamed {
+                    if let Some(value) = existing.get(old_key) {
+                        known.insert(new_key.clone(), value.clone());
+                        old_keys.push(old_key.clone());
+                        carried.push((output_path.clone(), old_key.clone(), new_key.clone()));
+                    }
+                }
+            }
+            let translated = translate_content(
+                &translator,
+                source_lang,
+                target_lang,
+                max_chunk_tokens,
+                &target_filename,
+                &subset,
+                &known,
+            )
+            .await
+            .in_file(source_file)?;
+
+            if output_exists(&output_path) {
+                let entries: Vec<(String, String)> = parse_entries(&translated)
+                    .into_iter()
+                    .map(|e| (e.key, e.value))
+                    .collect();
+                write_entries(&output_path, &entries)?;
+                remove_keys(&output_path, &old_keys)?;
+                annotate_merged(
                     &translator,
-                    &task.source_lang,
+                    &reuse,
+                    &content,
+                    &output_path,
+                    &entries,
+                    &known,
+                    &pinned,
                     target_lang,
-                    max_chunk_tokens,
-                    concurrency,
-                    &target_dir,
-                    source_file,
-                )
-                .await?;
+                )?;
+                log::info!("Merged {} entries into {:?}", entries.len(), output_path);
             } else {
-                translate_one_file(
+                let translated = annotate_provenance(
                     &translator,
-                    &task.source_lang,
+                    &reuse,
+                    &content,
+                    translated,
+                    &known,
+                    &pinned,
                     target_lang,
-                    max_chunk_tokens,
-                    &target_dir,
-                    source_file,
-                )
-                .await?;
+                );
+                write_translated_output(&translated, &output_path, task.max_entries_per_file)?;
+                log::info!("Successfully translated: {:?}", output_path);
             }
-            count += 1;
-            log::info!("Progress: {}/{} files translated", count, total);
+            metrics::metrics().file_completed();
         }
     }
 
-    log::info!("Translation task completed successfully!");
+    if !carried.is_empty() {
+        log::info!(
+            "Carried {} translations over to renamed keys:",
+            carried.len()
+        );
+        for (file, old_key, new_key) in &carried {
+            log::info!("  {:?}: {} -> {}", file, old_key, new_key);
+        }
+    }
+    write_output_archive(&task)?;
+    log::info!("{}", crate::tr!("cli.task_completed"));
     Ok(())
 }
 
+/// 只翻译键名与正则表达式 `keys` 匹配的条目
+///
+/// 目标文件已存在时将译文合并到其中，其余条目保持不变；目标文件不存在时写入完整的文件，
+/// 其中不匹配的条目保留原文。
+pub async fn translate_task_keys(
+    task: config::TranslationTask,
+    client_settings: config::ClientSettings,
+    keys: &regex::Regex,
+) -> Result<()> {
+    use crate::postprocess::{output_exists, write_translated_output};
+    use crate::preprocess::{filter_entries, parse_entries};
+    use crate::review::write_entries;
+    use crate::translate::{ReuseSources, Translator, load_glossaries_from_task};
+    use std::fs;
+
+    log::info!("Starting translation of keys matching {}", keys.as_str());
+
+    let merged_glossary = load_glossaries_from_task(&task)?;
+    let max_chunk_tokens = client_settings.max_chunk_tokens;
+    let translator = Translator::for_task(client_settings, merged_glossary, &task)?;
+    let reuse = ReuseSources {
+        overrides: translate::HumanOverrides::for_task(&task)?,
+        vanilla: load_vanilla(&task)?,
+        memory: None,
+    };
+
+    let source = task.source()?;
+    let source_files = task.list_source_files(source.as_ref()).await?;
+    log::info!("Found {} source files", source_files.len());
+
+    metrics::metrics().add_files_total(task.target_langs.len() * source_files.len());
+    for source_file in &source_files {
+        let content = source.read_file(source_file)?;
+        let entries = parse_entries(&content);
+        let matched = entries.iter().filter(|e| keys.is_match(&e.key)).count();
+        log::info!("{:?}: {} matching keys", source_file, matched);
+
+        let source_lang = task.file_source_lang(source_file);
+        for target_lang in &task.target_langs {
+            if matched == 0 || source_lang == target_lang {
+                metrics::metrics().file_completed();
+                continue;
+            }
+            fs::create_dir_all(task.target_dir(target_lang))?;
+            let output_path = task.target_file(source_file, target_lang);
+            let target_filename = output_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let subset = if output_exists(&output_path) {
+                filter_entries(&content, |k| keys.is_match(k))
+            } else {
+                content.clone()
+            };
+            let mut known = reuse.known_translations(&subset, source_lang, target_lang);
+            let pinned = postprocess::pinned_in_output(&output_path)?;
+            reuse.keep_pinned(&mut known, &pinned, target_lang);
+            if !output_exists(&output_path) {
+                // 不匹配的条目不发送给大模型，保留原文
+                for entry in &entries {
+                    if !keys.is_match(&entry.key) {
+                        known
+                            .entry(entry.key.clone())
+                            .or_insert_with(|| entry.value.clone());
+                    }
+                }
+            }
+            let translated = translate_content(
+                &translator,
+                source_lang,
+                target_lang,
+                max_chunk_tokens,
+                &target_filename,
+                &subset,
+                &known,
+            )
+            .await
+            .in_file(source_file)?;
+
+            if output_exists(&output_path) {
+                let entries: Vec<(String, String)> = parse_entries(&translated)
+                    .into_iter()
+                    .map(|e| (e.key, e.value))
+                    .collect();
+                write_entries(&output_path, &entries)?;
+                annotate_merged(
+                    &translator,
+                    &reuse,
+                    &content,
+                    &output_path,
+                    &entries,
+                    &known,
+                    &pinned,
+                    target_lang,
+                )?;
+                log::info!("Merged {} entries into {:?}", entries.len(), output_path);
+            } else {
+                let translated = annotate_provenance(
+                    &translator,
+                    &reuse,
+                    &content,
+                    translated,
+                    &known,
+                    &pinned,
+                    target_lang,
+                );
+                write_translated_output(&translated, &output_path, task.max_entries_per_file)?;
+                log::info!("Successfully translated: {:?}", output_path);
+            }
+            metrics::metrics().file_completed();
+        }
+    }
+
+    write_output_archive(&task)?;
+    log::info!("{}", crate::tr!("cli.task_completed"));
+    Ok(())
+}
+
+/// 翻译一个源文件并写入目标文件，设置了 `max_entries_per_file` 时译文超出该条目数即拆分为多个文件
+#[allow(clippy::too_many_arguments)]
 pub async fn translate_one_file(
     translator: &translate::Translator,
     source_lang: &str,
     target_lang: &str,
     max_chunk_tokens: usize,
-    target_dir: &std::path::PathBuf,
-    source_file: &std::path::PathBuf,
+    output_path: &std::path::Path,
+    source: &dyn source::LocalisationSource,
+    source_file: &std::path::Path,
+    reuse: &translate::ReuseSources,
+    max_entries_per_file: Option<usize>,
 ) -> Result<()> {
-    use crate::postprocess::{reconstruct_yaml_file, write_translated_file};
-    use crate::preprocess::{fix_yaml_content, generate_target_filename, trim_lang_header};
-    use crate::translate::split_yaml_content;
-    use std::fs;
+    use crate::postprocess::write_translated_output;
 
-    // 算出输出文件路径
-    let filename = source_file
+    let target_filename = output_path
         .file_name()
         .and_then(|n| n.to_str())
         .ok_or_else(|| TranslationError::FileNotFound("Invalid filename".to_string()))?;
-    let target_filename = generate_target_filename(filename, &source_lang, target_lang);
-    let output_path = target_dir.join(&target_filename);
 
     // 读取源文件内容
-    let content = fs::read_to_string(source_file)?;
-    // 去除 BOM 头
-    let content = if content.starts_with("\u{FEFF}") {
-        content.trim_start_matches("\u{FEFF}")
-    } else {
-        &content
+    let content = source.read_file(source_file)?;
+    let mut known = reuse.known_translations(&content, source_lang, target_lang);
+    let pinned = postprocess::pinned_in_output(output_path)?;
+    reuse.keep_pinned(&mut known, &pinned, target_lang);
+    let reconstructed = translate_content(
+        translator,
+        source_lang,
+        target_lang,
+        max_chunk_tokens,
+        target_filename,
+        &content,
+        &known,
+    )
+    .await?;
+    reuse.record(&content, &reconstructed, &known, source_lang, target_lang);
+    let reconstructed = postprocess::mark_pinned(&reconstructed, &pinned);
+    let reconstructed = annotate_provenance(
+        translator,
+        reuse,
+        &content,
+        reconstructed,
+        &known,
+        &pinned,
+        target_lang,
+    );
+
+    write_translated_output(&reconstructed, output_path, max_entries_per_file)?;
+    log::info!("Successfully translated: {:?}", output_path);
+    Ok(())
+}
+
+/// 合并翻译的小文件
+struct SmallFile<'a> {
+    source_file: &'a std::path::Path,
+    output: std::path::PathBuf,
+    content: String,
+    known: HashMap<String, String>,
+    pinned: HashMap<String, String>,
+    prepared: PreparedContent,
+}
+
+/// 任务设置了 `micro_batch_tokens` 时，将待翻译内容不超过该 token 数的小文件合并翻译，返回已写入译文的源文件
+///
+/// 源语言相同的小文件按 [`translate::micro_batches`] 装入批次，每个批次作为一段内容翻译，译文按键拆回各文件。
+/// 经中转语言翻译的文件与只装入一个文件的批次不合并；批次翻译失败时只记录警告，其中的文件随后逐个翻译。
+async fn translate_small_files(
+    translator: &translate::Translator,
+    task: &config::TranslationTask,
+    source: &dyn source::LocalisationSource,
+    source_files: &[std::path::PathBuf],
+    target_lang: &str,
+    max_chunk_tokens: usize,
+    reuse: &translate::ReuseSources,
+) -> std::collections::HashSet<std::path::PathBuf> {
+    use crate::translate::{BatchFile, combine_batch, micro_batches};
+    use crate::utils::estimate_mixed_tokens;
+
+    let mut done = std::collections::HashSet::new();
+    let Some(limit) = task.micro_batch_tokens else {
+        return done;
+    };
+    let mut groups: std::collections::BTreeMap<&str, Vec<SmallFile>> = Default::default();
+    for source_file in source_files {
+        let source_lang = task.file_source_lang(source_file);
+        if source_lang == target_lang || task.pivot_for(source_lang, target_lang).is_some() {
+            continue;
+        }
+        let small = (|| -> Result<SmallFile> {
+            let output = task.target_file(source_file, target_lang);
+            let content = source.read_file(source_file)?;
+            let mut known = reuse.known_translations(&content, source_lang, target_lang);
+            let pinned = postprocess::pinned_in_output(&output)?;
+            reuse.keep_pinned(&mut known, &pinned, target_lang);
+            let prepared = prepare_content(
+                &source_file.display().to_string(),
+                source_lang,
+                &content,
+                &known,
+            )?;
+            Ok(SmallFile {
+                source_file,
+                output,
+                content,
+                known,
+                pinned,
+                prepared,
+            })
+        })();
+        // 出错的文件留待逐个翻译时报告
+        if let Ok(small) = small
+            && estimate_mixed_tokens(&small.prepared.pending) <= limit
+        {
+            groups.entry(source_lang).or_default().push(small);
+        }
+    }
+
+    for (source_lang, files) in &groups {
+        let batch_files: Vec<BatchFile> = files
+            .iter()
+            .map(|f| BatchFile {
+                name: f
+                    .output
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                pending: f.prepared.pending.clone(),
+            })
+            .collect();
+        for batch in micro_batches(&batch_files, max_chunk_tokens) {
+            if batch.len() < 2 {
+                continue;
+            }
+            let parts: Vec<&BatchFile> = batch.iter().map(|&i| &batch_files[i]).collect();
+            let name = parts
+                .iter()
+                .map(|f| f.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            log::info!(
+                "Translating {} small files in one request: {}",
+                parts.len(),
+                name
+            );
+            let translations = match translate_pending(
+                translator,
+                source_lang,
+                target_lang,
+                max_chunk_tokens,
+                1,
+                &name,
+                &combine_batch(&parts),
+            )
+            .await
+            {
+                Ok(translations) => translations,
+                Err(error) => {
+                    log::warn!(
+                        "Batched translation of {} failed, translating the files separately: {}",
+                        name,
+                        error
+                    );
+                    continue;
+                }
+            };
+            for &index in &batch {
+                let file = &files[index];
+                match write_small_file(
+                    translator,
+                    reuse,
+                    file,
+                    translations.clone(),
+                    source_lang,
+                    target_lang,
+                    task.max_entries_per_file,
+                ) {
+                    Ok(()) => {
+                        done.insert(file.source_file.to_path_buf());
+                    }
+                    Err(error) => log::warn!(
+                        "Failed to write batched translation {:?}, translating it separately: {}",
+                        file.output,
+                        error
+                    ),
+                }
+            }
+        }
     }
-    .to_string();
+    done
+}
+
+/// 由合并翻译的译文（键 -> 译文，包含同一批次中其他文件的条目）重建一个小文件的译文并写入目标文件
+fn write_small_file(
+    translator: &translate::Translator,
+    reuse: &translate::ReuseSources,
+    file: &SmallFile,
+    translations: HashMap<String, String>,
+    source_lang: &str,
+    target_lang: &str,
+    max_entries_per_file: Option<usize>,
+) -> Result<()> {
+    let reconstructed = finish_content(
+        translations,
+        source_lang,
+        target_lang,
+        &file.prepared,
+        &file.known,
+    )?;
+    reuse.record(
+        &file.content,
+        &reconstructed,
+        &file.known,
+        source_lang,
+        target_lang,
+    );
+    let reconstructed = postprocess::mark_pinned(&reconstructed, &file.pinned);
+    let reconstructed = annotate_provenance(
+        translator,
+        reuse,
+        &file.content,
+        reconstructed,
+        &file.known,
+        &file.pinned,
+        target_lang,
+    );
+    postprocess::write_translated_output(&reconstructed, &file.output, max_entries_per_file)?;
+    log::info!("Successfully translated: {:?}", file.output);
+    Ok(())
+}
+
+/// 翻译器启用来源标注时，为译文加上文件头注释与每个条目的来源标记
+fn annotate_provenance(
+    translator: &translate::Translator,
+    reuse: &translate::ReuseSources,
+    source: &str,
+    translated: String,
+    known: &HashMap<String, String>,
+    pinned: &HashMap<String, String>,
+    target_lang: &str,
+) -> String {
+    let Some(model) = translator.provenance_model() else {
+        return translated;
+    };
+    postprocess::annotate_provenance(&translated, &model, source, unix_secs(), |key| {
+        reuse.origin(key, target_lang, known, pinned)
+    })
+}
+
+/// 翻译器启用来源标注时，为合并到目标文件中的条目 `merged` 加上来源标记并更新文件头注释
+#[allow(clippy::too_many_arguments)]
+fn annotate_merged(
+    translator: &translate::Translator,
+    reuse: &translate::ReuseSources,
+    source: &str,
+    output_path: &std::path::Path,
+    merged: &[(String, String)],
+    known: &HashMap<String, String>,
+    pinned: &HashMap<String, String>,
+    target_lang: &str,
+) -> Result<()> {
+    let Some(model) = translator.provenance_model() else {
+        return Ok(());
+    };
+    postprocess::annotate_merged_output(output_path, &model, source, unix_secs(), |key| {
+        merged
+            .iter()
+            .any(|(k, _)| k == key)
+            .then(|| reuse.origin(key, target_lang, known, pinned))
+    })
+    .in_file(output_path)
+}
+
+/// 当前的 Unix 时间戳（秒）
+fn unix_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// 预处理后的文件内容
+struct PreparedContent {
+    /// 源文件的语言头行（例如 `l_english:`），没有语言头时为空
+    header: String,
+    /// 修复格式后的完整内容（不含语言头）
+    fixed: String,
+    /// 需要翻译的内容
+    pending: String,
+    /// 源文件中条目的追踪号（键 -> 追踪号），修复格式时会去除追踪号，重建时据此恢复
+    versions: HashMap<String, String>,
+}
+
+/// 翻译前的预处理：规范化文本、去除语言头、修复格式，并去除已有译文的条目
+///
+/// `name` 仅用于在日志中标识文件。
+fn prepare_content(
+    name: &str,
+    source_lang: &str,
+    content: &str,
+    known: &HashMap<String, String>,
+) -> Result<PreparedContent> {
+    use crate::preprocess::{normalize_text, parse_entries, remove_entries};
+
+    // 去除 BOM 头（可能重复出现）、统一换行与缩进并合并重复的语言头，写入时只写一个 BOM
+    let content = normalize_text(content);
     // 去除语言头标记
-    let (_original_header, content) = trim_lang_header(&source_lang, &content);
+    let trimmed = trim_lang_header(source_lang, &content);
+    for warning in &trimmed.warnings {
+        log::warn!("{}: {}", name, warning);
+    }
+    let header = trimmed.header.map(|h| h.text).unwrap_or_default();
+    let content = trimmed.content;
     // 修复YAML文件中的格式问题
-    let content = fix_yaml_content(&content)?;
-    // 切片
-    let chunks = split_yaml_content(&target_filename, &content, max_chunk_tokens)?;
-    log::info!("File split into {} chunks", chunks.len());
-
-    // 翻译每个切片
-    let mut translated_chunks = Vec::new();
-    for (i, chunk) in chunks.iter().enumerate() {
-        log::trace!(
-            "\n======TRACE Translating chunk======\n{}\n======TRACE END======\n",
-            &chunk.content
-        );
+    let fixed = fix_yaml_content(&content)?;
+    log_fixes(name, &trimmed.lines, &fixed.fixes);
+    let fixed = fixed.content;
+    let pending = if known.is_empty() {
+        fixed.clone()
+    } else {
+        let pending = remove_entries(&fixed, |key| known.contains_key(key));
+        // 只剩注释与空行时无需请求大模型
+        if parse_entries(&pending).is_empty() {
+            String::new()
+        } else {
+            pending
+        }
+    };
+    let versions = parse_entries(&content)
+        .into_iter()
+        .filter_map(|e| e.version.map(|v| (e.key, v)))
+        .collect();
+    Ok(PreparedContent {
+        header,
+        fixed,
+        pending,
+        versions,
+    })
+}
 
-        let slice = translator
-            .translate_chunk(&chunk, &source_lang, target_lang)
-            .await?;
+/// 在日志中报告预处理对源文件的修复
+///
+/// 修复时语言头（以及其他语言的语言块）已被去除，行号按 `lines`（见 `TrimmedContent::lines`）换算回规范化后的源文件。
+fn log_fixes(name: &str, lines: &[usize], fixes: &[preprocess::YamlFix]) {
+    if fixes.is_empty() {
+        return;
+    }
+    log::info!("Preprocessing fixed {} lines in {}:", fixes.len(), name);
+    for fix in fixes {
+        let mut fix = fix.clone();
+        fix.line = lines.get(fix.line - 1).copied().unwrap_or(fix.line);
+        log::info!("  {}", fix);
+    }
+}
 
-        log::trace!(
-            "\n======TRACE Translated======\n{}\n======TRACE END======\n",
-            &slice.content
-        );
+/// 按源文件的结构重建译文，值取自模型的译文（键 -> 译文）与已有译文
+///
+/// 语言头沿用源文件的写法（包括追踪号与行尾注释），只替换其中的语言。
+fn finish_content(
+    mut translations: HashMap<String, String>,
+    source_lang: &str,
+    target_lang: &str,
+    prepared: &PreparedContent,
+    known: &HashMap<String, String>,
+) -> Result<String> {
+    use crate::postprocess::reconstruct_yaml_file;
+    use crate::preprocess::{parse_entries, rewrite_lang_header};
 
-        translated_chunks.push(slice);
-        log::info!("Translated chunk {}/{}", i + 1, chunks.len());
+    let header = rewrite_lang_header(&prepared.header, source_lang, target_lang);
+    if !known.is_empty() {
+        let reused = parse_entries(&prepared.fixed)
+            .iter()
+            .filter(|e| known.contains_key(&e.key))
+            .count();
+        // 已有译文（尤其是人工维护的译文）总是优先于模型返回的同名条目
+        for (key, value) in known {
+            translations.insert(key.clone(), value.clone());
+        }
+        log::info!("Reused {} existing translations", reused);
     }
-    let reconstructed = reconstruct_yaml_file(translated_chunks, &target_lang)?;
+    let rebuilt =
+        reconstruct_yaml_file(&prepared.fixed, &header, &translations, &prepared.versions);
+    if !rebuilt.missing.is_empty() {
+        log::warn!(
+            "{} entries have no translation, keeping source text: {}",
+            rebuilt.missing.len(),
+            rebuilt.missing.join(", ")
+        );
+    }
+    Ok(rebuilt.content)
+}
 
-    write_translated_file(&reconstructed, &output_path, true)?;
-    log::info!("Successfully translated: {:?}", output_path);
-    Ok(())
+/// 翻译一段完整的本地化文件内容，返回带有目标语言头的译文（不含 BOM）
+///
+/// `target_filename` 仅用于日志与切片标识，不会写入任何文件。
+/// `known` 中已有译文的条目（键 -> 译文）不发送给大模型，直接写入结果。
+pub async fn translate_content(
+    translator: &translate::Translator,
+    source_lang: &str,
+    target_lang: &str,
+    max_chunk_tokens: usize,
+    target_filename: &str,
+    content: &str,
+    known: &HashMap<String, String>,
+) -> Result<String> {
+    let prepared = prepare_content(target_filename, source_lang, content, known)?;
+    let translations = translate_pending(
+        translator,
+        source_lang,
+        target_lang,
+        max_chunk_tokens,
+        1,
+        target_filename,
+        &prepared.pending,
+    )
+    .await?;
+    finish_content(translations, source_lang, target_lang, &prepared, known)
+}
+
+/// 以流水线方式翻译待翻译的内容：切片 → 翻译（含验证）→ 合并，返回键 -> 译文
+///
+/// 各环节之间通过有界通道传递，切片按需生成，同时进行的请求不超过 `concurrency` 个，
+/// 每个译文切片按源文件顺序合并后即被释放，因此超大文件也不会同时在内存中保留全部切片与回复。
+/// 任一切片翻译失败时停止生成新的切片并返回该错误。
+async fn translate_pending(
+    translator: &translate::Translator,
+    source_lang: &str,
+    target_lang: &str,
+    max_chunk_tokens: usize,
+    concurrency: usize,
+    target_filename: &str,
+    pending: &str,
+) -> Result<HashMap<String, String>> {
+    use crate::postprocess::{TranslationSlice, merge_translations};
+    use crate::translate::YamlChunks;
+    use futures::StreamExt;
+    use tokio::sync::mpsc;
+
+    // 按键名前缀分组时切分分组后的内容，译文按键合并，不受顺序影响
+    let grouped;
+    let pending = if translator.groups_by_prefix() {
+        grouped = crate::translate::group_by_key_prefix(pending);
+        grouped.as_str()
+    } else {
+        pending
+    };
+    let total = YamlChunks::new(target_filename, pending, max_chunk_tokens).count();
+    log::info!("File split into {} chunks", total);
+    metrics::metrics().add_chunks_total(total);
+
+    // 按文件保持会话时切片必须依次翻译
+    let concurrency = if translator.keeps_conversations() {
+        1
+    } else {
+        concurrency.max(1)
+    };
+    let (chunk_tx, chunk_rx) = mpsc::channel::<FileChunk>(concurrency);
+    let (slice_tx, mut slice_rx) = mpsc::channel::<Result<TranslationSlice>>(concurrency);
+
+    // 切片：下游已停止时不再继续
+    let produce = async move {
+        let chunks = YamlChunks::new(target_filename, pending, max_chunk_tokens)
+            .with_overlap(translator.chunk_overlap());
+        for chunk in chunks {
+            if chunk_tx.send(chunk).await.is_err() {
+                break;
+            }
+        }
+    };
+    // 翻译：按切片顺序输出结果，出错后停止
+    let translate = async move {
+        let chunks = futures::stream::unfold(chunk_rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        });
+        let slices = chunks
+            .map(|chunk| async move {
+                log::trace!(
+                    "\n======TRACE Translating chunk======\n{}\n======TRACE END======\n",
+                    &chunk.content
+                );
+                translator
+                    .translate_chunk(&chunk, source_lang, target_lang)
+                    .await
+            })
+            .buffered(concurrency);
+        let mut slices = std::pin::pin!(slices);
+        while let Some(slice) = slices.next().await {
+            let failed = slice.is_err();
+            if slice_tx.send(slice).await.is_err() || failed {
+                break;
+            }
+        }
+    };
+    // 合并
+    let merge = async {
+        let mut translations = HashMap::new();
+        let mut translated = 0;
+        while let Some(slice) = slice_rx.recv().await {
+            let slice = slice?;
+            log::trace!(
+                "\n======TRACE Translated======\n{}\n======TRACE END======\n",
+                &slice.content
+            );
+            merge_translations(&mut translations, &slice);
+            translated += 1;
+            metrics::metrics().chunks_completed(1);
+            log::info!("Translated chunk {}/{}", translated, total);
+        }
+        Ok(translations)
+    };
+
+    let ((), (), translations) = tokio::join!(produce, translate, merge);
+    translator.end_conversation(target_filename, target_lang);
+    translations
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn translate_one_file_batch(
     translator: &translate::Translator,
     source_lang: &str,
     target_lang: &str,
     max_chunk_tokens: usize,
     batch_size: usize,
-    target_dir: &std::path::PathBuf,
-    source_file: &std::path::PathBuf,
+    output_path: &std::path::Path,
+    source: &dyn source::LocalisationSource,
+    source_file: &std::path::Path,
+    reuse: &translate::ReuseSources,
+    max_entries_per_file: Option<usize>,
 ) -> Result<()> {
-    use crate::postprocess::{reconstruct_yaml_file, write_translated_file};
-    use crate::preprocess::{fix_yaml_content, generate_target_filename, trim_lang_header};
-    use crate::translate::split_yaml_content;
-    use std::fs;
+    use crate::postprocess::write_translated_output;
 
-    // 算出输出文件路径
-    let filename = source_file
+    let target_filename = output_path
         .file_name()
         .and_then(|n| n.to_str())
         .ok_or_else(|| TranslationError::FileNotFound("Invalid filename".to_string()))?;
-    let target_filename = generate_target_filename(filename, &source_lang, target_lang);
-    let output_path = target_dir.join(&target_filename);
 
     // 读取源文件内容
-    let content = fs::read_to_string(source_file)?;
-    // 去除 BOM 头
-    let content = if content.starts_with("\u{FEFF}") {
-        content.trim_start_matches("\u{FEFF}")
-    } else {
-        &content
-    }
-    .to_string();
-    // 去除语言头标记
-    let (_original_header, content) = trim_lang_header(&source_lang, &content);
-    // 修复YAML文件中的格式问题
-    let content = fix_yaml_content(&content)?;
-    // 切片
-    let chunks = split_yaml_content(&target_filename, &content, max_chunk_tokens)?;
-    log::info!("File split into {} chunks", chunks.len());
-
-    // 翻译每个切片
-    let mut translated_slices = Vec::new();
-    let total = chunks.len();
-    let batches = chunks.chunks(batch_size);
-    let mut translated_count = 0;
-    for batch in batches {
-        let chunks: Vec<FileChunk> = batch.into_iter().map(|x| x.to_owned()).collect();
-        let will_translate = chunks.len();
-        let slices = translator
-            .translate_batch(chunks, &source_lang, target_lang)
-            .await?;
-        translated_count += will_translate;
-        log::info!("Translated chunk {}/{}", translated_count, total);
-        translated_slices.extend(slices);
-    }
-    let reconstructed = reconstruct_yaml_file(translated_slices, &target_lang)?;
+    let content = source.read_file(source_file)?;
+    let mut known = reuse.known_translations(&content, source_lang, target_lang);
+    let pinned = postprocess::pinned_in_output(output_path)?;
+    reuse.keep_pinned(&mut known, &pinned, target_lang);
+    let prepared = prepare_content(
+        &source_file.display().to_string(),
+        source_lang,
+        &content,
+        &known,
+    )?;
+    let translations = translate_pending(
+        translator,
+        source_lang,
+        target_lang,
+        max_chunk_tokens,
+        batch_size,
+        target_filename,
+        &prepared.pending,
+    )
+    .await?;
+    let reconstructed = finish_content(translations, source_lang, target_lang, &prepared, &known)?;
+    reuse.record(&content, &reconstructed, &known, source_lang, target_lang);
+    let reconstructed = postprocess::mark_pinned(&reconstructed, &pinned);
+    let reconstructed = annotate_provenance(
+        translator,
+        reuse,
+        &content,
+        reconstructed,
+        &known,
+        &pinned,
+        target_lang,
+    );
 
-    write_translated_file(&reconstructed, &output_path, true)?;
+    write_translated_output(&reconstructed, output_path, max_entries_per_file)?;
     log::info!("Successfully translated: {:?}", output_path);
     Ok(())
 }
 
 pub async fn validate_translation(task: config::TranslationTask) -> Result<()> {
-    use walkdir::WalkDir;
-
     log::info!("Starting translation validation");
     log::info!("Source language: {}", task.source_lang);
     log::info!("Target languages: {:?}", task.target_langs);
@@ -243,26 +1184,13 @@ pub async fn validate_translation(task: config::TranslationTask) -> Result<()> {
     let source_dir = task.source_dir();
     log::info!("Reading source files from: {:?}", source_dir);
 
-    let mut source_files = Vec::new();
-    for entry in WalkDir::new(&source_dir) {
-        let entry = entry.map_err(|e| {
-            TranslationError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("WalkDir error: {}", e),
-            ))
-        })?;
-        if entry.file_type().is_file() {
-            let path = entry.path();
-            if let Some(ext) = path.extension() {
-                if ext == "yml" || ext == "yaml" {
-                    source_files.push(path.to_path_buf());
-                }
-            }
-        }
-    }
+    let source = task.source()?;
+    let source_files = task.list_source_files(source.as_ref()).await?;
 
     log::info!("Found {} source files", source_files.len());
 
+    let validator = FormatValidator::for_task(&task);
+    let ignore = ValidationIgnore::for_task(&task)?;
     for target_lang in &task.target_langs {
         log::info!(
             "Validating translations for target language: {}",
@@ -273,21 +1201,41 @@ pub async fn validate_translation(task: config::TranslationTask) -> Result<()> {
         log::info!("Looking for translated files in: {:?}", target_dir);
 
         for source_file in &source_files {
-            let filename = source_file
-                .file_name()
-                .and_then(|n| n.to_str())
-                .ok_or_else(|| TranslationError::FileNotFound("Invalid filename".to_string()))?;
-            let target_filename =
-                preprocess::generate_target_filename(filename, &task.source_lang, target_lang);
-            let output_path = target_dir.join(&target_filename);
-
-            if output_path.exists() {
-                validate_one_file(&task.source_lang, target_lang, source_file, &output_path)
-                    .await?;
+            let source_lang = task.file_source_lang(source_file);
+            if source_lang == target_lang {
+                continue;
+            }
+            let output_path = task.target_file(source_file, target_lang);
+            if postprocess::output_exists(&output_path) {
+                validate_one_file(
+                    &validator,
+                    &ignore,
+                    source_lang,
+                    target_lang,
+                    source.as_ref(),
+                    source_file,
+                    &output_path,
+                )
+                .await?;
             } else {
                 log::warn!("Missing translated file: {:?}", output_path);
             }
         }
+
+        // 检查目标语言的整个目录：残留的其他语言文件，以及在游戏中只有一处生效的重复定义的键
+        let language_dir = task.language_dir(target_lang);
+        if language_dir.is_dir() {
+            for issue in
+                preprocess::find_leftover_files(&language_dir, &task.source_lang, target_lang)?
+            {
+                log::warn!("{}", issue);
+            }
+            let mut files = utils::find_localisation_files(&language_dir)?;
+            preprocess::sort_by_load_order(&mut files);
+            for issue in preprocess::find_duplicate_keys(&files)? {
+                log::warn!("{}", issue);
+            }
+        }
     }
 
     log::info!("Translation validation completed");
@@ -295,31 +1243,38 @@ pub async fn validate_translation(task: config::TranslationTask) -> Result<()> {
 }
 
 pub async fn validate_one_file(
+    validator: &FormatValidator,
+    ignore: &ValidationIgnore,
     source_lang: &str,
     target_lang: &str,
-    source_file: &std::path::PathBuf,
-    translated_file: &std::path::PathBuf,
+    source: &dyn source::LocalisationSource,
+    source_file: &std::path::Path,
+    translated_file: &std::path::Path,
 ) -> Result<()> {
-    use std::fs;
-
-    let source = fs::read_to_string(source_file)?;
-    let translated = fs::read_to_string(translated_file)?;
+    let source = source.read_file(source_file)?;
+    // 译文可能拆分为多个文件写入
+    let translated = postprocess::read_translated_output(translated_file)?
+        .ok_or_else(|| TranslationError::FileNotFound(translated_file.display().to_string()))?;
 
-    // 去除 BOM 头
-    let source = source.trim_start_matches("\u{FEFF}");
-    let translated = translated.trim_start_matches("\u{FEFF}");
-
-    // 去除语言头标记
-    let (_, source) = trim_lang_header(source_lang, source);
-    let (_, translated) = trim_lang_header(target_lang, translated);
+    // 规范化文本并去除语言头标记
+    let source = trim_lang_header(source_lang, &preprocess::normalize_text(&source)).content;
+    let translated =
+        trim_lang_header(target_lang, &preprocess::normalize_text(&translated)).content;
 
     // 修复YAML文件中的格式问题
-    let source = fix_yaml_content(&source)?;
-    let translated = fix_yaml_content(&translated)?;
+    let source = fix_yaml_content(&source)?.content;
+    let translated = fix_yaml_content(&translated)?.content;
 
-    let validator = FormatValidator::new();
     // 检查 key 的数量和名称是否一一对应
-    let issues = validator.validate(&source, &translated);
+    let mut issues = validator.validate(&source, &translated);
+    let ignored = ignore.retain(&[source_file, translated_file], &mut issues);
+    if ignored > 0 {
+        log::info!(
+            "{} issues in {} are ignored by the validation ignore list",
+            ignored,
+            translated_file.display()
+        );
+    }
     if issues.is_empty() {
         log::info!(
             "[x] Validation passed for file {}",
@@ -327,9 +1282,16 @@ pub async fn validate_one_file(
         );
         return Ok(());
     }
-    log::warn!("[ ] Issues in {}:", translated_file.display());
-    for (i, issue) in issues.iter().enumerate() {
-        log::warn!("  {}. {}", i + 1, issue);
+    log::warn!(
+        "[ ] {} issues in {}",
+        issues.len(),
+        translated_file.display()
+    );
+    let file = translated_file.display().to_string();
+    let report = translate::issue_report();
+    report.record_diff(&file, StructureDiff::new(validator, &source, &translated));
+    for issue in issues {
+        report.record(&file, issue);
     }
 
     Ok(())
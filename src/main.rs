@@ -2,13 +2,56 @@
 //!
 //! 命令行接口和主工作流程。
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use ftail::Ftail;
 use log::{LevelFilter, Log};
-use paradox_mod_translator::config::{TranslationTask, load_openai_api_key};
+use paradox_mod_translator::config::{
+    ClientSettings, NotificationEvent, ProjectConfig, TaskFileConfig, TranslationTask,
+    load_openai_api_key, load_paratranz_token,
+};
 use paradox_mod_translator::error::{Result, TranslationError};
-use paradox_mod_translator::{translate_task, validate_translation};
+use paradox_mod_translator::exchange::{
+    ParatranzClient, collect_exchange_files, export_grouped, from_po, from_xliff,
+    import_exchange_files, pull_from_paratranz, push_to_paratranz, to_po, to_xliff,
+};
+use paradox_mod_translator::i18n::{UiLang, set_ui_lang};
+use paradox_mod_translator::postprocess::{
+    Changelog, CleanupItem, Comparison, build_translation_mod, find_cleanup_items,
+    find_stale_outputs, remove_cleanup_items, snapshot_dir, snapshot_outputs, snapshot_outputs_at,
+    task_status, write_translated_file, zip_directory,
+};
+use paradox_mod_translator::preprocess::{
+    analyze_key_usage, detect_lang_header, generate_target_filename, lint_dir, normalize_input,
+    scan_scripts,
+};
+use paradox_mod_translator::review::{
+    collect_entries, collect_review_items, export_review_sheet, import_review_sheet,
+    read_review_sheet, run_interactive_review,
+};
+use paradox_mod_translator::steam::{
+    detect_source_lang, download_workshop_item, extract_localisation, find_game_install_dir,
+    find_workshop_item, read_descriptor_name, write_workshop_task,
+};
+use paradox_mod_translator::translate::{
+    DEFAULT_AB_PATH, DEFAULT_CHUNK_STORE_PATH, DEFAULT_MEMORY_PATH, DEFAULT_USAGE_PATH,
+    QualityScorer, RepairSummary, SharedMemory, TermCandidate, TranslationMemory, Translator,
+    VanillaLocalisation, check_task_file, chunk_store, issue_report, load_ab_runs, load_glossaries,
+    load_glossaries_from_task, render_quality_report, run_ab, run_preview, run_repair,
+    run_term_extraction, run_tune, score_ab_sheet, start_recording, start_replay, tally_ab,
+    task_quality, tune_grid, usage_ledger, write_candidate_glossary, write_preview_report,
+    write_tune_report,
+};
+use paradox_mod_translator::utils::{
+    Notifier, on_shutdown, read_file_with_bom, run_until_interrupted,
+};
+use paradox_mod_translator::{
+    prune_stale_outputs, tr, translate_content, translate_task, translate_task_keys,
+    translate_task_since, translate_task_with_memory, validate_translation,
+};
+use std::io::{IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// 命令行参数
 #[derive(Parser)]
@@ -18,6 +61,24 @@ use std::path::{Path, PathBuf};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// 将每次 API 请求与原始响应保存为目录中编号的 JSON 文件（认证信息已隐去），用于问题报告与离线回放
+    #[arg(long, global = true, value_name = "DIR")]
+    record: Option<PathBuf>,
+
+    /// 不访问网络，按请求内容从 --record 保存的记录中取得响应，用于确定地复现问题
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+
+    /// 运行结束时在问题摘要之后按文件列出全部验证问题（默认只输出摘要）
+    #[arg(long, global = true)]
+    show_all_warnings: bool,
+
+    /// 命令行消息的语言（en 或 zh），未指定时取 PMT_LANG 或系统的 LANG 环境变量，默认为英语
+    ///
+    /// 不叫 `--lang`：`pmt key-usage --lang` 指定的是本地化语言，与界面语言无关。
+    #[arg(long, global = true, value_name = "LANG")]
+    ui_lang: Option<String>,
 }
 
 /// 子命令
@@ -32,6 +93,83 @@ enum Commands {
         /// 是否适用并发方法
         #[arg(long, default_value_t = false)]
         concurrent: bool,
+
+        /// 只翻译自该 git 修订版本（提交、标签或分支）以来新增或修改的条目，并合并到已有的目标文件
+        #[arg(long, value_name = "GIT_REF")]
+        since: Option<String>,
+
+        /// 只翻译键名与正则表达式完全匹配的条目（例如 'tech_.*|ap_.*'），并合并到已有的目标文件
+        #[arg(long, value_name = "REGEX", conflicts_with = "since")]
+        keys: Option<String>,
+
+        /// 不保存也不复用切片译文（默认保存在配置文件所在目录的 .pmt/chunks/ 中，未改动的切片直接复用）
+        #[arg(long, default_value_t = false)]
+        no_chunk_store: bool,
+
+        /// 运行完成后将目标文件中新增、修改与删除的键写入该文件（BBCode 格式，可粘贴到创意工坊的更新说明）
+        #[arg(long, value_name = "FILE")]
+        changelog: Option<PathBuf>,
+
+        /// 在指定地址上暴露 OpenMetrics 格式的运行指标（例如 127.0.0.1:9898）
+        #[cfg(feature = "metrics")]
+        #[arg(long, value_name = "ADDR")]
+        metrics_addr: Option<std::net::SocketAddr>,
+    },
+    /// 不使用任务配置文件，直接由命令行参数执行一次性翻译
+    Quick {
+        /// 源语言目录（例如 localisation/english），也可以是整个 Mod：压缩包、.mod 描述文件或 Mod 目录
+        #[arg(long, value_name = "SOURCE_DIR")]
+        from: PathBuf,
+
+        /// 目标语言，可重复指定
+        #[arg(long = "to", value_name = "LANG", required = true)]
+        target_langs: Vec<String>,
+
+        /// 使用的术语表名称，可重复指定
+        #[arg(long = "glossary", value_name = "NAME")]
+        glossaries: Vec<String>,
+
+        /// 模型名称（默认使用客户端默认设置）
+        #[arg(long)]
+        model: Option<String>,
+
+        /// API基础URL（默认使用客户端默认设置）
+        #[arg(long)]
+        api_base: Option<String>,
+
+        /// 是否适用并发方法
+        #[arg(long, default_value_t = false)]
+        concurrent: bool,
+    },
+    /// 翻译单个文件，输入为 `-` 时从标准输入读取并将译文写到标准输出
+    File {
+        /// 输入文件路径，或 `-` 表示标准输入
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// 目标语言
+        #[arg(long = "target", value_name = "LANG")]
+        target_lang: String,
+
+        /// 源语言（默认从文件的语言头推断）
+        #[arg(long = "source", value_name = "LANG")]
+        source_lang: Option<String>,
+
+        /// 使用的术语表名称，可重复指定
+        #[arg(long = "glossary", value_name = "NAME")]
+        glossaries: Vec<String>,
+
+        /// 输出文件路径（默认与输入文件同目录；标准输入模式下默认输出到标准输出）
+        #[arg(long, short, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+
+        /// 模型名称（默认使用客户端默认设置）
+        #[arg(long)]
+        model: Option<String>,
+
+        /// API基础URL（默认使用客户端默认设置）
+        #[arg(long)]
+        api_base: Option<String>,
     },
     /// 在已经完成翻译的情况下，跳过翻译任务，只检查翻译结果是否符合要求
     Validate {
@@ -39,8 +177,382 @@ enum Commands {
         #[arg(value_name = "TASK_FILE")]
         task_file: PathBuf,
     },
+    /// 检查源语言本地化文件中的重复键、缺失的引号、有误的语言头与不成对的格式标记，无需翻译任务
+    Lint {
+        /// 要检查的目录，递归查找其中的本地化文件
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+    },
+    /// 扫描 Mod 脚本中 custom_tooltip、set_name 等字段直接写入的文本，这类文本无法通过本地化文件翻译
+    ScanScripts {
+        /// Mod 根目录
+        #[arg(value_name = "MOD_DIR")]
+        mod_dir: PathBuf,
+    },
+    /// 对照本地化键与 Mod 脚本中的引用，列出未被使用（可以不翻译）的键与引用了但未定义的键
+    KeyUsage {
+        /// Mod 根目录
+        #[arg(value_name = "MOD_DIR")]
+        mod_dir: PathBuf,
+
+        /// 本地化键所用的语言
+        #[arg(long, value_name = "LANG", default_value = "english")]
+        lang: String,
+
+        /// 游戏本体的本地化目录，其中定义的键不计为缺失（默认在本机 Steam 库中查找）
+        #[arg(long, value_name = "DIR")]
+        vanilla_dir: Option<PathBuf>,
+    },
+    /// 列出验证中被标记的条目，或使用 --interactive 逐条审校
+    Review {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 逐条交互审校，并将决定写回目标文件与翻译记忆
+        #[arg(long, default_value_t = false)]
+        interactive: bool,
+
+        /// 翻译记忆文件路径
+        #[arg(long, value_name = "FILE", default_value = DEFAULT_MEMORY_PATH)]
+        memory: PathBuf,
+    },
+    /// 导出双语审校表格（CSV），供校对人员编辑
+    ExportReview {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 输出的表格路径
+        #[arg(long, short, value_name = "FILE", default_value = "review.csv")]
+        output: PathBuf,
+
+        /// 只导出验证中被标记的条目
+        #[arg(long, default_value_t = false)]
+        flagged_only: bool,
+    },
+    /// 将编辑后的审校表格合并回目标文件
+    ImportReview {
+        /// 审校表格路径
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// 翻译记忆文件路径
+        #[arg(long, value_name = "FILE", default_value = DEFAULT_MEMORY_PATH)]
+        memory: PathBuf,
+    },
+    /// 导出 XLIFF 2.0 文档，供 CAT 工具（OmegaT、Trados 等）使用
+    ExportXliff {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 输出目录，每个语言对生成一个 .xlf 文件
+        #[arg(long, short, value_name = "DIR", default_value = "xliff")]
+        output: PathBuf,
+    },
+    /// 将完成的 XLIFF 文档导回本地化文件
+    ImportXliff {
+        /// XLIFF 文件路径，可指定多个
+        #[arg(value_name = "FILE", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// 翻译记忆文件路径
+        #[arg(long, value_name = "FILE", default_value = DEFAULT_MEMORY_PATH)]
+        memory: PathBuf,
+    },
+    /// 导出 gettext PO 文件，键名作为 msgctxt
+    ExportPo {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 输出目录，每个语言对生成一个 .po 文件
+        #[arg(long, short, value_name = "DIR", default_value = "po")]
+        output: PathBuf,
+    },
+    /// 将完成的 PO 文件导回本地化文件
+    ImportPo {
+        /// PO 文件路径，可指定多个
+        #[arg(value_name = "FILE", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// 翻译记忆文件路径
+        #[arg(long, value_name = "FILE", default_value = DEFAULT_MEMORY_PATH)]
+        memory: PathBuf,
+    },
+    /// 与 Paratranz 项目同步（需要任务配置文件中的 [paratranz] 设置和 PARATRANZ_TOKEN 环境变量）
+    Paratranz {
+        #[command(subcommand)]
+        action: ParatranzAction,
+    },
+    /// 获取创意工坊 Mod 的本地化文件到工作目录，并生成 task.toml
+    FetchWorkshop {
+        /// 创意工坊物品 ID
+        #[arg(value_name = "WORKSHOP_ID")]
+        workshop_id: String,
+
+        /// 工作目录（默认 `workshop_<WORKSHOP_ID>`）
+        #[arg(long, short, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        /// 目标语言，可重复指定
+        #[arg(long = "to", value_name = "LANG", default_value = "simp_chinese")]
+        target_langs: Vec<String>,
+
+        /// 使用的术语表名称，可重复指定
+        #[arg(long = "glossary", value_name = "NAME", default_value = "stellaris")]
+        glossaries: Vec<String>,
+
+        /// 使用 steamcmd 下载，而不是在本机 Steam 库中查找已订阅的 Mod
+        #[arg(long, value_name = "PATH")]
+        steamcmd: Option<PathBuf>,
+
+        /// steamcmd 登录使用的 Steam 用户名
+        #[arg(long, value_name = "USER", default_value = "anonymous")]
+        steam_user: String,
+    },
+    /// 检查任务配置文件（设置、路径、术语表、提示词模板与 API 连接），一次列出全部问题，不翻译
+    Check {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 不访问网络，跳过 API 连接检查
+        #[arg(long)]
+        offline: bool,
+    },
+    /// 以文件 × 目标语言的表格列出翻译进度（missing、partial、stale、complete）
+    Status {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 以 JSON 格式将所有任务的翻译进度输出到标准输出，便于生成进度页面
+        #[arg(long)]
+        json: bool,
+    },
+    /// 为已翻译的目标文件计算自动质量指标（chrF、术语一致率、标记完整率、长度比），按文件汇总
+    Quality {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 翻译记忆文件路径，其中人工审校的译文作为 chrF 的参考译文
+        #[arg(long, value_name = "FILE", default_value = DEFAULT_MEMORY_PATH)]
+        memory: PathBuf,
+
+        /// 以 JSON 格式将各文件的指标输出到标准输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 列出目标文件自 git 修订版本以来新增、修改与删除的键（BBCode 格式，可粘贴到创意工坊的更新说明）
+    Changelog {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 与目标文件在该 git 修订版本（提交、标签或分支）中的内容比较
+        #[arg(long, value_name = "GIT_REF")]
+        since: String,
+
+        /// 更新日志输出路径，未指定时输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 逐个键比较两个目录中的译文（例如上一版翻译子 Mod 与这次运行的输出），列出新增、删除与修改的键
+    Compare {
+        /// 旧译文所在的目录
+        #[arg(value_name = "OLD_DIR")]
+        old: PathBuf,
+
+        /// 新译文所在的目录
+        #[arg(value_name = "NEW_DIR")]
+        new: PathBuf,
+
+        /// 差异输出路径（不带颜色），未指定时输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 删除项目中未完成的运行留下的检查点与写入中断时残留的临时文件（翻译记忆不受影响）
+    Clean {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 只列出将要删除的文件，不实际删除
+        #[arg(long)]
+        dry_run: bool,
+
+        /// 同时删除源文件已不存在的目标文件（Mod 更新删除了源文件后残留的旧译文）
+        #[arg(long)]
+        prune: bool,
+    },
+    /// 重新翻译未通过验证的条目，并写回已有的目标文件
+    Repair {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+    },
+    /// 翻译每个文件中随机抽取的少量条目并生成预览报告，用于在完整运行前检查译文
+    Preview {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 每个文件抽取的条目数
+        #[arg(long, default_value_t = 20)]
+        sample: usize,
+
+        /// 目标语言（默认为第一个任务的第一个目标语言）
+        #[arg(long = "to", value_name = "LANG")]
+        target_lang: Option<String>,
+
+        /// 随机种子，指定后每次抽取相同的条目
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// 预览报告输出路径
+        #[arg(short, long, default_value = "preview.md")]
+        output: PathBuf,
+    },
+    /// 由模型通读源文本，提取 Mod 自创的专有名词并给出译法，生成待审核的候选术语表
+    ExtractTerms {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 目标语言（默认为第一个任务的第一个目标语言）
+        #[arg(long = "to", value_name = "LANG")]
+        target_lang: Option<String>,
+
+        /// 候选术语表输出路径
+        #[arg(short, long, default_value = "glossary_candidates.json")]
+        output: PathBuf,
+    },
+    /// 用不同的模型与温度翻译少量抽样条目，生成并排对比表格
+    Tune {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 要比较的模型，以逗号分隔（默认使用客户端设置中的模型）
+        #[arg(long, value_delimiter = ',')]
+        models: Vec<String>,
+
+        /// 要比较的温度，以逗号分隔
+        #[arg(long, value_delimiter = ',', default_value = "0.3,0.7,1.0")]
+        temperatures: Vec<f32>,
+
+        /// 抽样条目数
+        #[arg(long, default_value_t = 10)]
+        sample: usize,
+
+        /// 目标语言（默认为第一个任务的第一个目标语言）
+        #[arg(long = "to", value_name = "LANG")]
+        target_lang: Option<String>,
+
+        /// 对比表格输出路径
+        #[arg(short, long, default_value = "tune.csv")]
+        output: PathBuf,
+    },
+    /// 用多个模型翻译同一批抽样条目，生成隐去模型名称的并排对比表格供人工盲评
+    Ab {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 要比较的模型，以逗号分隔（至少两个）
+        #[arg(long, value_delimiter = ',', required_unless_present = "score")]
+        models: Vec<String>,
+
+        /// 抽样条目数
+        #[arg(long, default_value_t = 50)]
+        sample: usize,
+
+        /// 目标语言（默认为第一个任务的第一个目标语言）
+        #[arg(long = "to", value_name = "LANG")]
+        target_lang: Option<String>,
+
+        /// 随机种子，决定抽取的条目与各列的模型顺序
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// 盲评表格输出路径（CSV；扩展名为 .html 时另外生成便于阅读的 HTML）
+        #[arg(short, long, default_value = "ab.csv")]
+        output: PathBuf,
+
+        /// 读取已填写 preferred 列的表格，记录评审结果并汇总各模型的成绩，不翻译
+        #[arg(long, value_name = "JUDGED_FILE", conflicts_with = "models")]
+        score: Option<PathBuf>,
+    },
+    /// 将译文打包为独立的翻译子 Mod（需要任务配置文件中的 [package] 设置）
+    Package {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+    },
+    /// 多 Mod 项目（project.toml）
+    Project {
+        #[command(subcommand)]
+        action: ProjectAction,
+    },
     /// 检查API密钥
     CheckApi,
+    /// 输出 shell 补全脚本，例如 `pmt completions bash > /etc/bash_completion.d/pmt`
+    Completions {
+        /// 目标 shell
+        #[arg(value_name = "SHELL")]
+        shell: Shell,
+    },
+    /// 生成 man 手册页（roff 格式），未指定目录时将 pmt(1) 输出到标准输出
+    Man {
+        /// 为 pmt 及每个子命令各写入一个手册页（pmt.1、pmt-translate.1 等）的目录
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// 项目操作
+#[derive(Subcommand)]
+enum ProjectAction {
+    /// 依次翻译项目中的所有 Mod，共用客户端设置与翻译记忆
+    ///
+    /// 某个 Mod 失败时继续翻译其余 Mod，最后汇总失败的 Mod。
+    Translate {
+        /// 项目配置文件路径
+        #[arg(value_name = "PROJECT_FILE", default_value = "project.toml")]
+        project_file: PathBuf,
+
+        /// 是否适用并发方法
+        #[arg(long, default_value_t = false)]
+        concurrent: bool,
+
+        /// 不保存也不复用切片译文（默认保存在配置文件所在目录的 .pmt/chunks/ 中，未改动的切片直接复用）
+        #[arg(long, default_value_t = false)]
+        no_chunk_store: bool,
+    },
+}
+
+/// Paratranz 同步操作
+#[derive(Subcommand)]
+enum ParatranzAction {
+    /// 上传源文件条目（已有译文作为初始译文）
+    Push {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+    },
+    /// 拉取审核过的译文，覆盖 AI 译文写回目标文件
+    Pull {
+        /// 任务配置文件路径
+        #[arg(value_name = "TASK_FILE")]
+        task_file: PathBuf,
+
+        /// 翻译记忆文件路径
+        #[arg(long, value_name = "FILE", default_value = DEFAULT_MEMORY_PATH)]
+        memory: PathBuf,
+    },
 }
 
 /// 主函数
@@ -62,42 +574,257 @@ async fn main() -> Result<()> {
         .unwrap();
 
     let cli = Cli::parse();
+    set_ui_lang(UiLang::detect(cli.ui_lang.as_deref()));
+    if let Some(dir) = &cli.record {
+        start_recording(dir)?;
+    }
+    if let Some(dir) = &cli.replay {
+        start_replay(dir)?;
+    }
 
-    match cli.command {
+    // Ctrl-C 时保存已登记的状态，再丢弃正在执行的命令以取消进行中的请求后退出
+    let show_all_warnings = cli.show_all_warnings;
+    let interrupt = async {
+        let _ = tokio::signal::ctrl_c().await;
+        log::warn!("{}", tr!("cli.interrupted"));
+    };
+    match run_until_interrupted(run(cli.command), interrupt).await {
+        Some(result) => {
+            report_issues(show_all_warnings);
+            // 错误连同发生的位置（文件、切片、键）写入日志
+            result.inspect_err(|e| log::error!("{}", e))
+        }
+        None => {
+            let command: Vec<String> = std::env::args().collect();
+            log::info!("{}", tr!("cli.resume_hint", command.join(" ")));
+            std::process::exit(130);
+        }
+    }
+}
+
+/// 执行子命令
+async fn run(command: Commands) -> Result<()> {
+    match command {
         Commands::Translate {
             task_file,
             concurrent,
+            since,
+            keys,
+            no_chunk_store,
+            changelog,
+            #[cfg(feature = "metrics")]
+            metrics_addr,
         } => {
             // 检查API密钥
-            if !paradox_mod_translator::config::has_api_key() {
-                log::error!("OPENAI_API_KEY environment variable is not set");
-                log::info!("Please set OPENAI_API_KEY environment variable or create a .env file");
-                return Err(TranslationError::MissingEnvVar(
-                    "OPENAI_API_KEY environment variable is required".to_string(),
-                ));
+            ensure_api_key()?;
+            // 正则需要匹配完整的键名
+            let keys = keys
+                .map(|pattern| regex::Regex::new(&format!("^(?:{})$", pattern)))
+                .transpose()
+                .map_err(|e| {
+                    TranslationError::ValidationError(format!("Invalid --keys pattern: {}", e))
+                })?;
+
+            // 启动指标服务
+            #[cfg(feature = "metrics")]
+            if let Some(addr) = metrics_addr {
+                paradox_mod_translator::metrics::serve_metrics(addr).await?;
             }
 
             // 加载配置
             log::info!("Loading task configuration...");
-            let (client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let TaskFileConfig {
+                client_settings,
+                notifications,
+                task: tasks,
+                ..
+            } = TaskFileConfig::from_file(&task_file)?;
+            track_usage(&task_file)?;
+            if !no_chunk_store {
+                store_chunks(&task_file);
+            }
             log::info!("Use API: {}", &client_settings.api_base);
             log::info!("Use Model: {}", &client_settings.model);
             log::info!(
                 "Configuration loaded successfully, found {} task(s)",
                 tasks.len()
             );
+            // 在翻译任何任务之前确认所有任务都能按 git 修订版本比较
+            if since.is_some() {
+                for task in &tasks {
+                    task.check_git_source()?;
+                }
+            }
+
+            let notifier = notifications.map(|n| Arc::new(Notifier::new(n)));
+            let budget_watcher = notifier.as_ref().and_then(|n| n.watch_budget());
+            let mut changes = Changelog::default();
 
             for (i, task) in tasks.iter().enumerate() {
                 log::info!("Processing task {}/{}", i + 1, tasks.len());
+                let before = changelog
+                    .as_ref()
+                    .map(|_| snapshot_outputs(task))
+                    .transpose()?;
                 log::debug!("Source language: {}", task.source_lang);
                 log::debug!("Target languages: {:?}", task.target_langs);
                 log::debug!("Glossaries: {:?}", task.glossaries);
 
                 // 执行翻译任务
-                translate_task(task.clone(), client_settings.clone(), concurrent).await?;
+                let task_name = task.localisation_dir.display().to_string();
+                let result = match (&since, &keys) {
+                    (Some(since), _) => {
+                        translate_task_since(task.clone(), client_settings.clone(), since).await
+                    }
+                    (None, Some(keys)) => {
+                        translate_task_keys(task.clone(), client_settings.clone(), keys).await
+                    }
+                    (None, None) => {
+                        translate_task(task.clone(), client_settings.clone(), concurrent).await
+                    }
+                };
+                if let Some(notifier) = &notifier {
+                    match &result {
+                        Ok(()) => {
+                            let message = format!(
+                                "[pmt] Task {}/{} finished: {}",
+                                i + 1,
+                                tasks.len(),
+                                task_name
+                            );
+                            notifier
+                                .notify(NotificationEvent::Finished, &task_name, &message)
+                                .await;
+                        }
+                        Err(e) => {
+                            let message = format!(
+                                "[pmt] Task {}/{} failed: {}: {}",
+                                i + 1,
+                                tasks.len(),
+                                task_name,
+                                e
+                            );
+                            notifier
+                                .notify(NotificationEvent::Failed, &task_name, &message)
+                                .await;
+                        }
+                    }
+                }
+                result?;
+                if let Some(before) = before {
+                    changes.extend(Changelog::between(&before, &snapshot_outputs(task)?));
+                }
+            }
+
+            if since.is_none() && keys.is_none() {
+                prune_stale_outputs(&tasks)?;
             }
 
-            log::info!("All translation tasks completed!");
+            if let Some(watcher) = budget_watcher {
+                watcher.abort();
+            }
+
+            if let Some(path) = &changelog {
+                std::fs::write(path, changes.render())?;
+                log::info!("Wrote translation changelog to {:?}", path);
+            }
+            log::info!("{}", tr!("cli.tasks_completed"));
+            report_usage()
+        }
+        Commands::Quick {
+            from,
+            target_langs,
+            glossaries,
+            model,
+            api_base,
+            concurrent,
+        } => {
+            ensure_api_key()?;
+
+            let task = if paradox_mod_translator::source::is_mod_package(&from) {
+                TranslationTask::from_mod(&from, target_langs, glossaries)?
+            } else {
+                TranslationTask::from_source_dir(&from, target_langs, glossaries)?
+            };
+            let client_settings = client_settings_from_flags(model, api_base)?;
+
+            translate_task(task, client_settings, concurrent).await?;
+            log::info!("{}", tr!("cli.quick_completed"));
+            Ok(())
+        }
+        Commands::File {
+            input,
+            target_lang,
+            source_lang,
+            glossaries,
+            output,
+            model,
+            api_base,
+        } => {
+            ensure_api_key()?;
+
+            let from_stdin = input.as_os_str() == "-";
+            let content = if from_stdin {
+                let mut buf = String::new();
+                std::io::stdin().read_to_string(&mut buf)?;
+                normalize_input(&buf)
+            } else {
+                read_file_with_bom(&input)?
+            };
+
+            let source_lang = match source_lang.or_else(|| detect_lang_header(&content)) {
+                Some(lang) => lang,
+                None => {
+                    return Err(TranslationError::ValidationError(
+                        "Cannot detect source language from header, please specify --source"
+                            .to_string(),
+                    ));
+                }
+            };
+            let target_filename = if from_stdin {
+                format!("l_{}.yml", target_lang)
+            } else {
+                let filename = input.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
+                    TranslationError::FileNotFound("Invalid filename".to_string())
+                })?;
+                generate_target_filename(filename, &source_lang, &target_lang)
+            };
+
+            let client_settings = client_settings_from_flags(model, api_base)?;
+            let max_chunk_tokens = client_settings.max_chunk_tokens;
+            let translator =
+                Translator::from_settings(client_settings, load_glossaries(&glossaries)?)?;
+            let translated = translate_content(
+                &translator,
+                &source_lang,
+                &target_lang,
+                max_chunk_tokens,
+                &target_filename,
+                &content,
+                &Default::default(),
+            )
+            .await?;
+
+            let output = match output {
+                Some(path) => Some(path),
+                None if from_stdin => None,
+                None => Some(
+                    input
+                        .parent()
+                        .unwrap_or(Path::new("."))
+                        .join(&target_filename),
+                ),
+            };
+            match output {
+                Some(path) => {
+                    write_translated_file(&translated, &path, true)?;
+                    log::info!("Successfully translated: {:?}", path);
+                }
+                None => {
+                    let mut stdout = std::io::stdout().lock();
+                    writeln!(stdout, "{}", translated)?;
+                }
+            }
             Ok(())
         }
         Commands::Validate { task_file } => {
@@ -121,6 +848,767 @@ async fn main() -> Result<()> {
 
             Ok(())
         }
+        Commands::Lint { dir } => {
+            let issues = lint_dir(&dir)?;
+            for issue in &issues {
+                log::warn!("{}", issue);
+            }
+            if issues.is_empty() {
+                log::info!("No issues found in {:?}", dir);
+                Ok(())
+            } else {
+                Err(TranslationError::ValidationError(format!(
+                    "Found {} issues in {:?}",
+                    issues.len(),
+                    dir
+                )))
+            }
+        }
+        Commands::ScanScripts { mod_dir } => {
+            let literals = scan_scripts(&mod_dir)?;
+            for literal in &literals {
+                log::warn!("{}", literal);
+            }
+            log::info!(
+                "Found {} hard-coded strings in scripts under {:?}",
+                literals.len(),
+                mod_dir
+            );
+            Ok(())
+        }
+        Commands::KeyUsage {
+            mod_dir,
+            lang,
+            vanilla_dir,
+        } => {
+            let vanilla_dir =
+                vanilla_dir.or_else(|| find_game_install_dir().map(|d| d.join("localisation")));
+            let vanilla = match &vanilla_dir {
+                Some(dir) => VanillaLocalisation::load(dir, std::slice::from_ref(&lang))?,
+                None => {
+                    log::warn!(
+                        "Game localisation not found, keys defined by the game will be reported as missing"
+                    );
+                    VanillaLocalisation::default()
+                }
+            };
+            let usage =
+                analyze_key_usage(&mod_dir, &lang, |key| vanilla.get(&lang, key).is_some())?;
+            for orphan in &usage.orphans {
+                log::info!(
+                    "{}:{}: Key '{}' is never referenced",
+                    orphan.file.display(),
+                    orphan.line,
+                    orphan.key
+                );
+            }
+            for missing in &usage.missing {
+                log::warn!(
+                    "{}:{}: Key '{}' is referenced but never defined",
+                    missing.file.display(),
+                    missing.line,
+                    missing.key
+                );
+            }
+            log::info!(
+                "Found {} unreferenced keys and {} missing keys",
+                usage.orphans.len(),
+                usage.missing.len()
+            );
+            Ok(())
+        }
+        Commands::Review {
+            task_file,
+            interactive,
+            memory,
+        } => {
+            let TaskFileConfig {
+                client_settings,
+                task: tasks,
+                ..
+            } = TaskFileConfig::from_file(&task_file)?;
+
+            let mut items = Vec::new();
+            for task in &tasks {
+                items.extend(collect_review_items(task)?);
+            }
+            log::info!("Found {} flagged entries", items.len());
+
+            if !interactive {
+                for item in &items {
+                    log::warn!(
+                        "[{}] {} {}:",
+                        item.target_lang,
+                        item.target_file.display(),
+                        item.key
+                    );
+                    for problem in &item.problems {
+                        log::warn!("    {}", problem);
+                    }
+                }
+                return Ok(());
+            }
+
+            let mut memory = TranslationMemory::open(&memory)?;
+            // 重新翻译只使用第一个任务的术语表
+            let translator = if paradox_mod_translator::config::has_api_key() {
+                let glossary = match tasks.first() {
+                    Some(task) => load_glossaries_from_task(task)?,
+                    None => Default::default(),
+                };
+                Some(Translator::from_settings(client_settings, glossary)?)
+            } else {
+                log::warn!("OPENAI_API_KEY is not set, retranslation is disabled");
+                None
+            };
+
+            let stdin = std::io::stdin();
+            let summary = run_interactive_review(
+                &items,
+                &mut memory,
+                translator.as_ref(),
+                &mut stdin.lock(),
+                &mut std::io::stdout(),
+            )
+            .await?;
+            memory.save()?;
+            log::info!(
+                "Review finished: {} accepted, {} changed, {} skipped",
+                summary.accepted,
+                summary.changed,
+                summary.skipped
+            );
+            Ok(())
+        }
+        Commands::ExportReview {
+            task_file,
+            output,
+            flagged_only,
+        } => {
+            let (_client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let mut items = Vec::new();
+            for task in &tasks {
+                items.extend(collect_entries(task, flagged_only)?);
+            }
+            let count = export_review_sheet(&items, &output)?;
+            log::info!("Exported {} entries to {:?}", count, output);
+            Ok(())
+        }
+        Commands::ImportReview { input, memory } => {
+            let rows = read_review_sheet(&input)?;
+            let mut memory = TranslationMemory::open(&memory)?;
+            let changed = import_review_sheet(&rows, &mut memory)?;
+            memory.save()?;
+            log::info!(
+                "Imported {} rows from {:?}, {} entries changed",
+                rows.len(),
+                input,
+                changed
+            );
+            Ok(())
+        }
+        Commands::ExportXliff { task_file, output } => {
+            let (_client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let mut files = Vec::new();
+            for task in &tasks {
+                files.extend(collect_exchange_files(task)?);
+            }
+            for path in export_grouped(&files, &output, "xlf", to_xliff)? {
+                log::info!("Exported XLIFF: {:?}", path);
+            }
+            Ok(())
+        }
+        Commands::ImportXliff { inputs, memory } => {
+            let mut memory = TranslationMemory::open(&memory)?;
+            for input in &inputs {
+                let files = from_xliff(&std::fs::read_to_string(input)?)?;
+                let changed = import_exchange_files(&files, &mut memory)?;
+                log::info!("Imported {:?}: {} entries written", input, changed);
+            }
+            memory.save()?;
+            Ok(())
+        }
+        Commands::ExportPo { task_file, output } => {
+            let (_client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let mut files = Vec::new();
+            for task in &tasks {
+                files.extend(collect_exchange_files(task)?);
+            }
+            for path in export_grouped(&files, &output, "po", to_po)? {
+                log::info!("Exported PO: {:?}", path);
+            }
+            Ok(())
+        }
+        Commands::ImportPo { inputs, memory } => {
+            let mut memory = TranslationMemory::open(&memory)?;
+            for input in &inputs {
+                let files = from_po(&std::fs::read_to_string(input)?)?;
+                let changed = import_exchange_files(&files, &mut memory)?;
+                log::info!("Imported {:?}: {} entries written", input, changed);
+            }
+            memory.save()?;
+            Ok(())
+        }
+        Commands::Paratranz { action } => {
+            let task_file = match &action {
+                ParatranzAction::Push { task_file } | ParatranzAction::Pull { task_file, .. } => {
+                    task_file
+                }
+            };
+            let TaskFileConfig {
+                paratranz,
+                task: tasks,
+                ..
+            } = TaskFileConfig::from_file(task_file)?;
+            let settings = paratranz.ok_or_else(|| {
+                TranslationError::Config(paradox_mod_translator::error::ConfigError::MissingField(
+                    "[paratranz] section is required in the task file".to_string(),
+                ))
+            })?;
+            let client = ParatranzClient::new(settings, load_paratranz_token()?);
+
+            match action {
+                ParatranzAction::Push { .. } => {
+                    for task in &tasks {
+                        let count = push_to_paratranz(&client, task).await?;
+                        log::info!("Pushed {} file(s) to Paratranz", count);
+                    }
+                }
+                ParatranzAction::Pull { memory, .. } => {
+                    let mut memory = TranslationMemory::open(&memory)?;
+                    for task in &tasks {
+                        let changed = pull_from_paratranz(&client, task, &mut memory).await?;
+                        log::info!("Pulled from Paratranz: {} entries written", changed);
+                    }
+                    memory.save()?;
+                }
+            }
+            Ok(())
+        }
+        Commands::FetchWorkshop {
+            workshop_id,
+            output,
+            target_langs,
+            glossaries,
+            steamcmd,
+            steam_user,
+        } => {
+            let mod_dir = match &steamcmd {
+                Some(steamcmd) => download_workshop_item(steamcmd, &workshop_id, &steam_user)?,
+                None => find_workshop_item(&workshop_id).ok_or_else(|| {
+                    TranslationError::FileNotFound(format!(
+                        "Workshop item {} not found in local Steam libraries; subscribe to it or use --steamcmd",
+                        workshop_id
+                    ))
+                })?,
+            };
+            log::info!("Using workshop content: {:?}", mod_dir);
+
+            let output =
+                output.unwrap_or_else(|| PathBuf::from(format!("workshop_{}", workshop_id)));
+            std::fs::create_dir_all(&output)?;
+            let count = extract_localisation(&mod_dir, &output)?;
+            log::info!("Extracted {} localisation files to {:?}", count, output);
+
+            let localisation_dir = output.join("localisation");
+            let source_lang = detect_source_lang(&localisation_dir).ok_or_else(|| {
+                TranslationError::FileNotFound("No language directory in localisation".to_string())
+            })?;
+            let mod_name = read_descriptor_name(&mod_dir).unwrap_or_else(|| workshop_id.clone());
+            let task_file = write_workshop_task(
+                &output,
+                &localisation_dir,
+                &mod_name,
+                &source_lang,
+                &target_langs,
+                &glossaries,
+            )?;
+            log::info!(
+                "Generated {:?} for \"{}\" ({})",
+                task_file,
+                mod_name,
+                source_lang
+            );
+            Ok(())
+        }
+        Commands::Check { task_file, offline } => {
+            let mut check = check_task_file(&task_file);
+            if !offline {
+                check.check_api().await;
+            }
+            for line in check.render().lines() {
+                log::info!("{}", line);
+            }
+            match check.problem_count() {
+                0 => {
+                    log::info!("No problems found in {:?}", task_file);
+                    Ok(())
+                }
+                count => Err(TranslationError::ValidationError(format!(
+                    "{} problems found in {:?}",
+                    count, task_file
+                ))),
+            }
+        }
+        Commands::Status { task_file, json } => {
+            let (_client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            if json {
+                let matrices = tasks.iter().map(task_status).collect::<Result<Vec<_>>>()?;
+                let content =
+                    serde_json::to_string_pretty(&matrices).map_err(std::io::Error::other)?;
+                writeln!(std::io::stdout().lock(), "{}", content)?;
+                return Ok(());
+            }
+            for task in &tasks {
+                log::info!("Translation status of {:?}:", task.localisation_dir);
+                for line in task_status(task)?.render().lines() {
+                    log::info!("  {}", line);
+                }
+            }
+            Ok(())
+        }
+        Commands::Quality {
+            task_file,
+            memory,
+            json,
+        } => {
+            let (_client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let mut files = Vec::new();
+            for task in &tasks {
+                let memory = memory
+                    .is_file()
+                    .then(|| TranslationMemory::open(&memory))
+                    .transpose()?;
+                let mut scorer = QualityScorer::for_task(task, memory)?;
+                let quality = task_quality(task, &mut scorer)?;
+                if !json {
+                    log::info!("Translation quality of {:?}:", task.localisation_dir);
+                    for line in render_quality_report(&quality).lines() {
+                        log::info!("  {}", line);
+                    }
+                }
+                files.extend(quality);
+            }
+            if json {
+                let content =
+                    serde_json::to_string_pretty(&files).map_err(std::io::Error::other)?;
+                writeln!(std::io::stdout().lock(), "{}", content)?;
+            }
+            Ok(())
+        }
+        Commands::Changelog {
+            task_file,
+            since,
+            output,
+        } => {
+            let (_client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let mut changes = Changelog::default();
+            for task in &tasks {
+                changes.extend(Changelog::between(
+                    &snapshot_outputs_at(task, &since)?,
+                    &snapshot_outputs(task)?,
+                ));
+            }
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, changes.render())?;
+                    log::info!("Wrote translation changelog to {:?}", path);
+                }
+                None => write!(std::io::stdout().lock(), "{}", changes.render())?,
+            }
+            Ok(())
+        }
+        Commands::Compare { old, new, output } => {
+            let comparison = Comparison::between(&snapshot_dir(&old)?, &snapshot_dir(&new)?);
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, comparison.render(false))?;
+                    log::info!("Wrote comparison to {:?}", path);
+                }
+                None => {
+                    let color = std::io::stdout().is_terminal();
+                    write!(std::io::stdout().lock(), "{}", comparison.render(color))?;
+                }
+            }
+            Ok(())
+        }
+        Commands::Clean {
+            task_file,
+            dry_run,
+            prune,
+        } => {
+            let (_client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let state_dir = task_file.parent().unwrap_or(Path::new("."));
+            let mut items = find_cleanup_items(state_dir, &tasks);
+            if prune {
+                for task in &tasks {
+                    items.extend(find_stale_outputs(task, &tasks)?.into_iter().map(|path| {
+                        CleanupItem {
+                            path,
+                            kind: "stale",
+                        }
+                    }));
+                }
+            }
+            if items.is_empty() {
+                log::info!("{}", tr!("cli.nothing_to_clean"));
+                return Ok(());
+            }
+            for item in &items {
+                let kind = tr!(&format!("cleanup.{}", item.kind));
+                let path = item.path.display();
+                if dry_run {
+                    log::info!("{}", tr!("cli.would_remove", kind, path));
+                } else {
+                    log::info!("{}", tr!("cli.removing", kind, path));
+                }
+            }
+            if !dry_run {
+                remove_cleanup_items(&items)?;
+            }
+            if dry_run {
+                log::info!("{}", tr!("cli.would_remove_total", items.len()));
+            } else {
+                log::info!("{}", tr!("cli.removed_total", items.len()));
+            }
+            Ok(())
+        }
+        Commands::Repair { task_file } => {
+            ensure_api_key()?;
+            let (client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let mut summary = RepairSummary::default();
+            for task in &tasks {
+                let result = run_repair(task, client_settings.clone()).await?;
+                summary.files += result.files;
+                summary.failing += result.failing;
+                summary.repaired += result.repaired;
+            }
+            log::info!(
+                "Repaired {} of {} entries with issues in {} files",
+                summary.repaired,
+                summary.failing,
+                summary.files
+            );
+            Ok(())
+        }
+        Commands::Preview {
+            task_file,
+            sample,
+            target_lang,
+            seed,
+            output,
+        } => {
+            ensure_api_key()?;
+            let (client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let seed = seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or_default()
+            });
+            log::info!("Sampling {} entries per file with seed {}", sample, seed);
+
+            let lang = target_lang.unwrap_or_else(|| tasks[0].target_langs[0].clone());
+            let mut files = Vec::new();
+            for task in &tasks {
+                files
+                    .extend(run_preview(task, client_settings.clone(), &lang, sample, seed).await?);
+            }
+            write_preview_report(&files, &client_settings.model, &lang, &output)?;
+            log::info!("Wrote preview of {} files to {:?}", files.len(), output);
+            Ok(())
+        }
+        Commands::ExtractTerms {
+            task_file,
+            target_lang,
+            output,
+        } => {
+            ensure_api_key()?;
+            let (client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let lang = target_lang.unwrap_or_else(|| tasks[0].target_langs[0].clone());
+            let mut candidates: Vec<TermCandidate> = Vec::new();
+            for task in &tasks {
+                for candidate in run_term_extraction(task, client_settings.clone(), &lang).await? {
+                    if !candidates
+                        .iter()
+                        .any(|c| c.source.eq_ignore_ascii_case(&candidate.source))
+                    {
+                        candidates.push(candidate);
+                    }
+                }
+            }
+            for candidate in &candidates {
+                writeln!(
+                    std::io::stdout().lock(),
+                    "{}",
+                    tr!(
+                        "cli.term_candidate",
+                        candidate.source,
+                        candidate.target,
+                        candidate.occurrences,
+                        candidate.file
+                    )
+                )?;
+            }
+            write_candidate_glossary(&candidates, &tasks[0].source_lang, &lang, &output)?;
+            log::info!(
+                "{}",
+                tr!(
+                    "cli.term_candidates_written",
+                    candidates.len(),
+                    output.display()
+                )
+            );
+            Ok(())
+        }
+        Commands::Tune {
+            task_file,
+            models,
+            temperatures,
+            sample,
+            target_lang,
+            output,
+        } => {
+            ensure_api_key()?;
+            let (client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let task = &tasks[0];
+            let target_lang = target_lang.unwrap_or_else(|| task.target_langs[0].clone());
+            let models = if models.is_empty() {
+                vec![client_settings.model.clone()]
+            } else {
+                models
+            };
+            let combinations = tune_grid(&models, &temperatures);
+            for combination in &combinations {
+                let mut settings = client_settings.clone();
+                settings.temperature = combination.temperature;
+                settings.validate()?;
+            }
+
+            let report =
+                run_tune(task, &client_settings, &target_lang, &combinations, sample).await?;
+            write_tune_report(&report, &output)?;
+            log::info!(
+                "Wrote comparison of {} entries across {} combinations to {:?}",
+                report.entries.len(),
+                combinations.len(),
+                output
+            );
+            Ok(())
+        }
+        Commands::Ab {
+            task_file,
+            models,
+            sample,
+            target_lang,
+            seed,
+            output,
+            score,
+        } => {
+            let dir = task_file
+                .parent()
+                .unwrap_or(Path::new("."))
+                .join(DEFAULT_AB_PATH);
+            if let Some(judged) = score {
+                let runs = score_ab_sheet(&dir, &judged)?;
+                let judged_count: usize = runs.iter().map(|r| r.judgments.len()).sum();
+                log::info!(
+                    "Recorded {} judgments from {:?} in {} comparisons",
+                    judged_count,
+                    judged,
+                    runs.len()
+                );
+                for (model, score) in tally_ab(&load_ab_runs(&dir)?) {
+                    writeln!(
+                        std::io::stdout().lock(),
+                        "{}",
+                        tr!(
+                            "cli.ab_tally",
+                            model,
+                            score.wins,
+                            score.ties,
+                            score.judged,
+                            score.issues
+                        )
+                    )?;
+                }
+                return Ok(());
+            }
+
+            ensure_api_key()?;
+            let (client_settings, tasks) = TranslationTask::from_file(&task_file)?;
+            let task = &tasks[0];
+            let target_lang = target_lang.unwrap_or_else(|| task.target_langs[0].clone());
+            let seed = seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or_default()
+            });
+            let run = run_ab(task, &client_settings, &target_lang, &models, sample, seed).await?;
+            let record = run.save(&dir)?;
+            let sheet = run.write_sheet(&output)?;
+            log::info!(
+                "Wrote blind comparison of {} entries across {} models to {:?}, results saved to {:?}",
+                run.rows.len(),
+                models.len(),
+                output,
+                record
+            );
+            log::info!(
+                "Fill in the preferred column of {:?} (a column letter, or = for a tie) and run `pmt ab {} --score {}`",
+                sheet,
+                task_file.display(),
+                sheet.display()
+            );
+            Ok(())
+        }
+        Commands::Package { task_file } => {
+            let TaskFileConfig {
+                package,
+                task: tasks,
+                ..
+            } = TaskFileConfig::from_file(&task_file)?;
+            let settings = package.ok_or_else(|| {
+                TranslationError::Config(paradox_mod_translator::error::ConfigError::MissingField(
+                    "[package] section is required in the task file".to_string(),
+                ))
+            })?;
+
+            let (mod_dir, count) = build_translation_mod(&settings, &tasks)?;
+            log::info!("Assembled {} translated files in {:?}", count, mod_dir);
+            let zip_path = mod_dir.with_extension("zip");
+            zip_directory(&mod_dir, &zip_path)?;
+            log::info!("Packaged translation mod: {:?}", zip_path);
+            Ok(())
+        }
+        Commands::Project {
+            action:
+                ProjectAction::Translate {
+                    project_file,
+                    concurrent,
+                    no_chunk_store,
+                },
+        } => {
+            ensure_api_key()?;
+            let project = ProjectConfig::from_file(&project_file)?;
+            track_usage(&project_file)?;
+            if !no_chunk_store {
+                store_chunks(&project_file);
+            }
+            log::info!(
+                "Project loaded successfully, found {} mod(s)",
+                project.mods.len()
+            );
+            let memory = SharedMemory::new(TranslationMemory::open(&project.memory)?);
+            // 中断时保存已完成的 Mod 记入的译文
+            let _flush_memory = on_shutdown("translation memory", {
+                let memory = memory.clone();
+                move || memory.save()
+            });
+            let notifier = project
+                .notifications
+                .clone()
+                .map(|n| Arc::new(Notifier::new(n)));
+            let budget_watcher = notifier.as_ref().and_then(|n| n.watch_budget());
+
+            let mut failed = Vec::new();
+            for (i, m) in project.mods.iter().enumerate() {
+                log::info!(
+                    "Translating mod {}/{}: {}",
+                    i + 1,
+                    project.mods.len(),
+                    m.name
+                );
+                let mut result =
+                    TaskFileConfig::from_file(&m.task_file).map_err(TranslationError::from);
+                if let Ok(config) = &result {
+                    let mut failure = None;
+                    for task in &config.task {
+                        let outcome = translate_task_with_memory(
+                            task.clone(),
+                            project.client_settings.clone(),
+                            concurrent,
+                            Some(memory.clone()),
+                        )
+                        .await;
+                        if let Err(e) = outcome {
+                            failure = Some(e);
+                            break;
+                        }
+                    }
+                    if failure.is_none() {
+                        failure = prune_stale_outputs(&config.task).err();
+                    }
+                    if let Some(e) = failure {
+                        result = Err(e);
+                    }
+                }
+                // 每个 Mod 完成后保存翻译记忆，中途失败也不会丢失已完成的译文
+                memory.save()?;
+
+                let (event, message) = match &result {
+                    Ok(_) => (
+                        NotificationEvent::Finished,
+                        format!(
+                            "[pmt] Mod {}/{} finished: {}",
+                            i + 1,
+                            project.mods.len(),
+                            m.name
+                        ),
+                    ),
+                    Err(e) => {
+                        log::error!("Mod {} failed: {}", m.name, e);
+                        failed.push(m.name.clone());
+                        (
+                            NotificationEvent::Failed,
+                            format!(
+                                "[pmt] Mod {}/{} failed: {}: {}",
+                                i + 1,
+                                project.mods.len(),
+                                m.name,
+                                e
+                            ),
+                        )
+                    }
+                };
+                if let Some(notifier) = &notifier {
+                    notifier.notify(event, &m.name, &message).await;
+                }
+            }
+
+            if let Some(watcher) = budget_watcher {
+                watcher.abort();
+            }
+            log::info!("Translation memory now holds {} entries", memory.len());
+            if !failed.is_empty() {
+                return Err(TranslationError::ValidationError(format!(
+                    "{} mod(s) failed: {}",
+                    failed.len(),
+                    failed.join(", ")
+                )));
+            }
+            log::info!("{}", tr!("cli.mods_completed"));
+            report_usage()
+        }
+        Commands::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+            Ok(())
+        }
+        Commands::Man { output } => {
+            let command = Cli::command();
+            match output {
+                Some(dir) => {
+                    std::fs::create_dir_all(&dir)?;
+                    clap_mangen::generate_to(command, &dir)?;
+                    log::info!("Wrote man pages to {:?}", dir);
+                }
+                None => clap_mangen::Man::new(command).render(&mut std::io::stdout())?,
+            }
+            Ok(())
+        }
         Commands::CheckApi => {
             if paradox_mod_translator::config::has_api_key() {
                 log::info!("API key is configured");
@@ -140,13 +1628,93 @@ async fn main() -> Result<()> {
                 }
             } else {
                 log::error!("API key is not configured");
-                log::info!("Please set OPENAI_API_KEY environment variable");
-                return Err(TranslationError::MissingEnvVar(
-                    "OPENAI_API_KEY environment variable is required".to_string(),
-                ));
+                log::info!("{}", tr!("cli.api_key_hint"));
+                return Err(TranslationError::MissingEnvVar(tr!("cli.api_key_required")));
             }
 
             Ok(())
         }
     }
 }
+
+/// 检查API密钥是否已配置
+fn ensure_api_key() -> Result<()> {
+    if !paradox_mod_translator::config::has_api_key() {
+        log::error!("{}", tr!("cli.api_key_missing"));
+        log::info!("{}", tr!("cli.api_key_hint"));
+        return Err(TranslationError::MissingEnvVar(tr!("cli.api_key_required")));
+    }
+    Ok(())
+}
+
+/// 在配置文件所在目录保存切片译文，源文件改动后未改动的切片直接复用
+fn store_chunks(config_file: &Path) {
+    let dir = config_file.parent().unwrap_or(Path::new("."));
+    chunk_store().persist_to(&dir.join(DEFAULT_CHUNK_STORE_PATH));
+}
+
+/// 在配置文件所在目录保存 token 用量，上次运行未完成时从其中的累计值继续统计
+fn track_usage(config_file: &Path) -> Result<()> {
+    let dir = config_file.parent().unwrap_or(Path::new("."));
+    usage_ledger().persist_to(&dir.join(DEFAULT_USAGE_PATH))?;
+    let resumed = usage_ledger().total();
+    if resumed.requests > 0 {
+        log::info!(
+            "Continuing token usage totals from the previous run: {} requests, {} tokens",
+            resumed.requests,
+            resumed.total_tokens()
+        );
+    }
+    Ok(())
+}
+
+/// 运行全部完成后输出 token 用量明细，并删除保存的累计值
+fn report_usage() -> Result<()> {
+    if usage_ledger().total().requests > 0 {
+        log::info!("Token usage:");
+        for line in usage_ledger().render_table().lines() {
+            log::info!("  {}", line);
+        }
+    }
+    usage_ledger().finish()
+}
+
+/// 输出翻译与验证中发现的问题摘要，`show_all` 为 true 时再按文件列出全部问题
+fn report_issues(show_all: bool) {
+    let report = issue_report();
+    if report.is_empty() {
+        return;
+    }
+    log::warn!("{}", tr!("cli.issues_header"));
+    for line in report.render_summary().lines() {
+        log::warn!("  {}", line);
+    }
+    if show_all {
+        // 彩色的差异直接输出到控制台，日志文件中保存不带颜色的副本
+        let color = std::io::stderr().is_terminal();
+        eprint!("{}", report.render_details(color));
+        for line in report.render_details(false).lines() {
+            log::debug!("{}", line);
+        }
+    } else {
+        log::info!("{}", tr!("cli.show_all_hint"));
+    }
+}
+
+/// 由命令行参数覆盖默认客户端设置
+fn client_settings_from_flags(
+    model: Option<String>,
+    api_base: Option<String>,
+) -> Result<ClientSettings> {
+    let mut client_settings = ClientSettings::default();
+    if let Some(model) = model {
+        client_settings.model = model;
+    }
+    if let Some(api_base) = api_base {
+        client_settings.api_base = api_base;
+    }
+    client_settings.validate()?;
+    log::info!("Use API: {}", &client_settings.api_base);
+    log::info!("Use Model: {}", &client_settings.model);
+    Ok(client_settings)
+}
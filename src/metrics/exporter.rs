@@ -0,0 +1,55 @@
+//! 指标导出模块
+//!
+//! 一个极简的 HTTP 服务，在 `/metrics` 路径上以 OpenMetrics 格式返回全局运行指标。
+
+use super::metrics;
+use crate::error::Result;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// 在指定地址启动指标服务，服务在后台任务中运行直到进程退出
+pub async fn serve_metrics(addr: SocketAddr) -> Result<tokio::task::JoinHandle<()>> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("Metrics endpoint listening on http://{}/metrics", addr);
+
+    let handle = tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream).await {
+                            log::debug!("Metrics connection error: {}", e);
+                        }
+                    });
+                }
+                Err(e) => log::warn!("Failed to accept metrics connection: {}", e),
+            }
+        }
+    });
+    Ok(handle)
+}
+
+/// 处理单个 HTTP 请求，只读取请求行
+async fn handle_connection(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+    let response = if path == "/metrics" {
+        let body = metrics().render();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            CONTENT_TYPE,
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,210 @@
+//! 运行指标模块
+//!
+//! 统计翻译任务的运行进度（文件、切片、token 用量、错误数），
+//! 启用 `metrics` 特性后可通过 HTTP 以 OpenMetrics 文本格式对外暴露，便于远程监控长时间运行的任务。
+
+#[cfg(feature = "metrics")]
+mod exporter;
+
+#[cfg(feature = "metrics")]
+pub use exporter::*;
+
+use std::fmt::Write;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 运行指标
+///
+/// 所有计数器均为原子类型，可在并发翻译时安全更新。
+pub struct RunMetrics {
+    files_total: AtomicU64,
+    files_completed: AtomicU64,
+    chunks_total: AtomicU64,
+    chunks_completed: AtomicU64,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    errors: AtomicU64,
+    started_at: OnceLock<Instant>,
+}
+
+static METRICS: RunMetrics = RunMetrics::new();
+
+/// 获取全局运行指标
+pub fn metrics() -> &'static RunMetrics {
+    &METRICS
+}
+
+impl Default for RunMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunMetrics {
+    /// 创建空的运行指标
+    pub const fn new() -> Self {
+        Self {
+            files_total: AtomicU64::new(0),
+            files_completed: AtomicU64::new(0),
+            chunks_total: AtomicU64::new(0),
+            chunks_completed: AtomicU64::new(0),
+            prompt_tokens: AtomicU64::new(0),
+            completion_tokens: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            started_at: OnceLock::new(),
+        }
+    }
+
+    /// 登记待翻译的文件数，并在首次调用时记录开始时间
+    pub fn add_files_total(&self, count: usize) {
+        self.started_at.get_or_init(Instant::now);
+        self.files_total.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// 记录一个文件翻译完成
+    pub fn file_completed(&self) {
+        self.files_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 登记待翻译的切片数
+    pub fn add_chunks_total(&self, count: usize) {
+        self.chunks_total.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// 记录若干切片翻译完成
+    pub fn chunks_completed(&self, count: usize) {
+        self.chunks_completed
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// 记录一次请求的 token 用量
+    pub fn record_tokens(&self, prompt_tokens: u32, completion_tokens: u32) {
+        self.prompt_tokens
+            .fetch_add(prompt_tokens as u64, Ordering::Relaxed);
+        self.completion_tokens
+            .fetch_add(completion_tokens as u64, Ordering::Relaxed);
+    }
+
+    /// 目前为止使用的总 token 数
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens.load(Ordering::Relaxed) + self.completion_tokens.load(Ordering::Relaxed)
+    }
+
+    /// 已完成的文件数
+    pub fn files_completed_count(&self) -> u64 {
+        self.files_completed.load(Ordering::Relaxed)
+    }
+
+    /// 记录一次错误
+    pub fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 自开始以来经过的时间
+    pub fn elapsed(&self) -> Duration {
+        self.started_at
+            .get()
+            .map(|t| t.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// 按已完成文件的平均耗时估算剩余时间，尚无完成文件时返回 None
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.files_total.load(Ordering::Relaxed);
+        let completed = self.files_completed.load(Ordering::Relaxed);
+        if completed == 0 {
+            return None;
+        }
+        let remaining = total.saturating_sub(completed);
+        Some(self.elapsed().mul_f64(remaining as f64 / completed as f64))
+    }
+
+    /// 以 OpenMetrics 文本格式输出所有指标
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("pmt_files", "Files translated", &self.files_completed),
+            ("pmt_chunks", "Chunks translated", &self.chunks_completed),
+            (
+                "pmt_prompt_tokens",
+                "Prompt tokens used",
+                &self.prompt_tokens,
+            ),
+            (
+                "pmt_completion_tokens",
+                "Completion tokens used",
+                &self.completion_tokens,
+            ),
+            ("pmt_errors", "Failed translation requests", &self.errors),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "{}_total {}", name, value.load(Ordering::Relaxed));
+        }
+
+        let gauges = [
+            (
+                "pmt_files_planned",
+                "Files scheduled for translation",
+                self.files_total.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "pmt_chunks_planned",
+                "Chunks scheduled in files split so far",
+                self.chunks_total.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "pmt_elapsed_seconds",
+                "Seconds since the run started",
+                self.elapsed().as_secs_f64(),
+            ),
+            (
+                "pmt_eta_seconds",
+                "Estimated seconds until the run completes",
+                self.eta().map(|d| d.as_secs_f64()).unwrap_or(f64::NAN),
+            ),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out.push_str("# EOF\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_openmetrics() {
+        let m = RunMetrics::new();
+        m.add_files_total(4);
+        m.add_chunks_total(10);
+        m.chunks_completed(3);
+        m.record_tokens(100, 50);
+        m.record_error();
+
+        let text = m.render();
+        assert!(text.contains("pmt_chunks_total 3\n"));
+        assert!(text.contains("pmt_prompt_tokens_total 100\n"));
+        assert!(text.contains("pmt_completion_tokens_total 50\n"));
+        assert!(text.contains("pmt_errors_total 1\n"));
+        assert!(text.contains("pmt_files_planned 4\n"));
+        assert!(text.contains("pmt_eta_seconds NaN\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn test_eta_after_first_file() {
+        let m = RunMetrics::new();
+        m.add_files_total(3);
+        assert!(m.eta().is_none());
+        m.file_completed();
+        assert!(m.eta().is_some());
+    }
+}
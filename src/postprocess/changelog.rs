@@ -0,0 +1,217 @@
+//! 译文更新日志
+//!
+//! 比较两次运行前后（或 git 修订版本与当前）各目标语言的目标文件，列出新增、修改与删除的键，
+//! 以 BBCode 格式输出，可以直接粘贴到创意工坊的更新说明中。
+//! 按语言汇总全部目标文件中的键（同一个键按游戏的加载顺序取生效的译文），键在文件之间移动或拆分到其他部分不算修改。
+
+use crate::config::TranslationTask;
+use crate::error::Result;
+use crate::preprocess::{is_localisation_content, parse_entries, sort_by_load_order};
+use crate::utils::{
+    file_at_revision, files_at_revision, find_localisation_files, read_file_with_bom,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// 各目标语言的目标文件中的全部条目（语言 -> 键 -> 译文）
+pub type OutputSnapshot = BTreeMap<String, BTreeMap<String, String>>;
+
+/// 将内容中的条目加入 `entries`，已有的键以后出现的为准
+pub(crate) fn add_entries(entries: &mut BTreeMap<String, String>, content: &str) {
+    for entry in parse_entries(content) {
+        entries.insert(entry.key, entry.value);
+    }
+}
+
+/// 读取任务各目标语言当前的目标文件
+pub fn snapshot_outputs(task: &TranslationTask) -> Result<OutputSnapshot> {
+    let mut snapshot = OutputSnapshot::new();
+    for lang in &task.target_langs {
+        let entries = snapshot.entry(lang.clone()).or_default();
+        let dir = task.target_dir(lang);
+        if !dir.is_dir() {
+            continue;
+        }
+        let mut files = find_localisation_files(&dir)?;
+        sort_by_load_order(&mut files);
+        for file in files {
+            add_entries(entries, &read_file_with_bom(&file)?);
+        }
+    }
+    Ok(snapshot)
+}
+
+/// 读取任务各目标语言的目标文件在 git 修订版本 `revision` 中的内容
+pub fn snapshot_outputs_at(task: &TranslationTask, revision: &str) -> Result<OutputSnapshot> {
+    let mut snapshot = OutputSnapshot::new();
+    for lang in &task.target_langs {
+        let entries = snapshot.entry(lang.clone()).or_default();
+        let dir = task.target_dir(lang);
+        if !dir.is_dir() {
+            continue;
+        }
+        let mut files = files_at_revision(&dir, revision)?;
+        sort_by_load_order(&mut files);
+        for file in files {
+            if !file
+                .extension()
+                .is_some_and(|ext| ext == "yml" || ext == "yaml")
+            {
+                continue;
+            }
+            if let Some(content) = file_at_revision(&dir, revision, &file)?
+                && is_localisation_content(&content)
+            {
+                add_entries(entries, &content);
+            }
+        }
+    }
+    Ok(snapshot)
+}
+
+/// 一种目标语言中有变化的键
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageChanges {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl LanguageChanges {
+    /// 是否没有任何变化
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// 各目标语言的译文变化
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changelog {
+    pub languages: BTreeMap<String, LanguageChanges>,
+}
+
+impl Changelog {
+    /// 比较前后两次的目标文件，没有变化的语言不列出
+    pub fn between(before: &OutputSnapshot, after: &OutputSnapshot) -> Self {
+        let empty = BTreeMap::new();
+        let mut changelog = Self::default();
+        for lang in before.keys().chain(after.keys()) {
+            if changelog.languages.contains_key(lang) {
+                continue;
+            }
+            let old = before.get(lang).unwrap_or(&empty);
+            let new = after.get(lang).unwrap_or(&empty);
+            let mut changes = LanguageChanges::default();
+            for (key, value) in new {
+                match old.get(key) {
+                    None => changes.added.push(key.clone()),
+                    Some(previous) if previous != value => changes.changed.push(key.clone()),
+                    Some(_) => {}
+                }
+            }
+            changes.removed = old
+                .keys()
+                .filter(|key| !new.contains_key(*key))
+                .cloned()
+                .collect();
+            changelog.languages.insert(lang.clone(), changes);
+        }
+        changelog.languages.retain(|_, changes| !changes.is_empty());
+        changelog
+    }
+
+    /// 合并另一个任务的变化
+    pub fn extend(&mut self, other: Changelog) {
+        for (lang, changes) in other.languages {
+            let entry = self.languages.entry(lang).or_default();
+            entry.added.extend(changes.added);
+            entry.changed.extend(changes.changed);
+            entry.removed.extend(changes.removed);
+        }
+    }
+
+    /// 是否没有任何变化
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty()
+    }
+
+    /// 以 BBCode 输出，每种语言一节，分别列出新增、修改与删除的键
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if self.is_empty() {
+            out.push_str("No translation changes.\n");
+            return out;
+        }
+        for (lang, changes) in &self.languages {
+            let _ = writeln!(out, "[h2]{}[/h2]", lang);
+            let _ = writeln!(
+                out,
+                "{} added, {} changed, {} removed",
+                changes.added.len(),
+                changes.changed.len(),
+                changes.removed.len()
+            );
+            for (title, keys) in [
+                ("Added", &changes.added),
+                ("Changed", &changes.changed),
+                ("Removed", &changes.removed),
+            ] {
+                if keys.is_empty() {
+                    continue;
+                }
+                let _ = writeln!(out, "[b]{}[/b]\n[list]", title);
+                for key in keys {
+                    let _ = writeln!(out, "[*]{}", key);
+                }
+                out.push_str("[/list]\n");
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changelog() {
+        let entries = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let mut before = OutputSnapshot::new();
+        before.insert(
+            "simp_chinese".to_string(),
+            entries(&[("a", "甲"), ("b", "乙"), ("c", "丙")]),
+        );
+        before.insert("german".to_string(), entries(&[("a", "A")]));
+        let mut after = OutputSnapshot::new();
+        after.insert(
+            "simp_chinese".to_string(),
+            entries(&[("a", "甲"), ("b", "乙二"), ("d", "丁")]),
+        );
+        after.insert("german".to_string(), entries(&[("a", "A")]));
+
+        let changelog = Changelog::between(&before, &after);
+        assert_eq!(changelog.languages.len(), 1);
+        assert_eq!(
+            changelog.languages["simp_chinese"],
+            LanguageChanges {
+                added: vec!["d".to_string()],
+                changed: vec!["b".to_string()],
+                removed: vec!["c".to_string()],
+            }
+        );
+        assert_eq!(
+            changelog.render(),
+            "[h2]simp_chinese[/h2]\n1 added, 1 changed, 1 removed\n\
+             [b]Added[/b]\n[list]\n[*]d\n[/list]\n\
+             [b]Changed[/b]\n[list]\n[*]b\n[/list]\n\
+             [b]Removed[/b]\n[list]\n[*]c\n[/list]\n"
+        );
+        assert!(Changelog::between(&after, &after).is_empty());
+    }
+}
@@ -2,8 +2,21 @@
 //!
 //! 清理临时文件和中间文件。
 
+use crate::config::TranslationTask;
 use crate::error::Result;
-use std::path::Path;
+use crate::translate::DEFAULT_USAGE_PATH;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// 可以清理的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanupItem {
+    /// 文件路径
+    pub path: PathBuf,
+    /// 文件的类别，例如 `checkpoint`、`temp`
+    pub kind: &'static str,
+}
 
 /// 清理临时文件
 pub fn cleanup_temp_files(temp_dir: &Path) -> Result<()> {
@@ -23,14 +36,252 @@ pub fn cleanup_files_by_extension(dir: &Path, extension: &str) -> Result<()> {
         let entry = entry?;
         let path = entry.path();
 
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext == extension {
-                    std::fs::remove_file(&path)?;
+        if path.is_file()
+            && let Some(ext) = path.extension()
+            && ext == extension
+        {
+            std::fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 递归查找目录中文件名以 `suffix` 结尾的文件（例如 `.tmp`）
+pub fn find_files_with_suffix(dir: &Path, suffix: &str) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name().to_string_lossy().ends_with(suffix))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// 查找项目中可以清理的文件
+///
+/// `state_dir` 为任务配置文件所在目录。包括上次未完成的运行留下的 token 用量检查点（`.pmt/usage.json`），
+/// 以及写入中途被中断时残留在 `.pmt/` 与各目标语言目录中的 `.tmp` 文件。翻译记忆不属于可以清理的文件。
+pub fn find_cleanup_items(state_dir: &Path, tasks: &[TranslationTask]) -> Vec<CleanupItem> {
+    let mut items = Vec::new();
+    let usage = state_dir.join(DEFAULT_USAGE_PATH);
+    if usage.is_file() {
+        items.push(CleanupItem {
+            path: usage,
+            kind: "checkpoint",
+        });
+    }
+
+    let mut dirs = vec![state_dir.join(".pmt")];
+    for task in tasks {
+        dirs.extend(task.target_langs.iter().map(|lang| task.language_dir(lang)));
+    }
+    for dir in dirs {
+        for path in find_files_with_suffix(&dir, ".tmp") {
+            if !items.iter().any(|item| item.path == path) {
+                items.push(CleanupItem { path, kind: "temp" });
+            }
+        }
+    }
+    items
+}
+
+/// 查找源文件已被删除的目标文件（Mod 更新删除了源文件后残留的旧译文），这些文件可能覆盖已删除的键
+///
+/// 只检查各目标语言的目标目录中的 YAML 文件，不是任何源文件的目标文件（或其拆分写入的部分）即视为残留。
+/// 目标目录就是目标语言目录本身（`output_subdir` 为空且未设置 `output_dir`）时，其中可能有 Mod 自带的译文，
+/// 不做检查；源语言目录中没有任何文件时也不做检查，以免配置错误时删除全部译文。
+///
+/// `tasks` 为同一配置文件中的全部任务：多个任务写入同一目标目录时，其中任一任务的目标文件都不算残留。
+pub fn find_stale_outputs(
+    task: &TranslationTask,
+    tasks: &[TranslationTask],
+) -> Result<Vec<PathBuf>> {
+    let source_files = task.source_files()?;
+    if source_files.is_empty() {
+        log::warn!(
+            "No source files in {:?}, skipping stale output check",
+            task.source_dir()
+        );
+        return Ok(Vec::new());
+    }
+
+    let mut stale = Vec::new();
+    for target_lang in &task.target_langs {
+        let target_dir = task.target_dir(target_lang);
+        if target_dir == task.localisation_dir.join(target_lang) && task.output_dir.is_none() {
+            log::warn!(
+                "Outputs for {} are written directly into {:?}, skipping stale output check",
+                target_lang,
+                target_dir
+            );
+            continue;
+        }
+        if !target_dir.is_dir() {
+            continue;
+        }
+        let mut expected = HashSet::new();
+        for source_file in &source_files {
+            let target = task.target_file(source_file, target_lang);
+            expected.extend(super::output_parts(&target));
+            expected.insert(target);
+        }
+        // 写入同一目标目录的其他任务的目标文件
+        for other in tasks {
+            for lang in &other.target_langs {
+                if other.target_dir(lang) != target_dir {
+                    continue;
+                }
+                for source_file in &other.source_files()? {
+                    let target = other.target_file(source_file, lang);
+                    expected.extend(super::output_parts(&target));
+                    expected.insert(target);
                 }
             }
         }
+        for entry in std::fs::read_dir(&target_dir)? {
+            let path = entry?.path();
+            let is_yaml = path
+                .extension()
+                .is_some_and(|ext| ext == "yml" || ext == "yaml");
+            if path.is_file() && is_yaml && !expected.contains(&path) {
+                stale.push(path);
+            }
+        }
     }
+    stale.sort();
+    Ok(stale)
+}
 
+/// 删除可以清理的文件
+pub fn remove_cleanup_items(items: &[CleanupItem]) -> Result<()> {
+    for item in items {
+        std::fs::remove_file(&item.path)?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_cleanup_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".pmt")).unwrap();
+        std::fs::create_dir_all(root.join("loc/english")).unwrap();
+        std::fs::create_dir_all(root.join("loc/simp_chinese/replace")).unwrap();
+        for file in [
+            ".pmt/usage.json",
+            ".pmt/memory.json",
+            ".pmt/memory.json.tmp",
+            "loc/english/a_l_english.yml",
+            "loc/simp_chinese/replace/a_l_simp_chinese.yml",
+            "loc/simp_chinese/replace/b_l_simp_chinese.yml.tmp",
+        ] {
+            std::fs::write(root.join(file), "l_english:\n").unwrap();
+        }
+        let task = TranslationTask::from_source_dir(
+            root.join("loc/english"),
+            vec!["simp_chinese".to_string()],
+            Vec::new(),
+        )
+        .unwrap();
+
+        let items = find_cleanup_items(root, &[task]);
+        let found: Vec<(String, &str)> = items
+            .iter()
+            .map(|item| {
+                let path = item.path.strip_prefix(root).unwrap();
+                (path.to_string_lossy().replace('\\', "/"), item.kind)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (".pmt/usage.json".to_string(), "checkpoint"),
+                (".pmt/memory.json.tmp".to_string(), "temp"),
+                (
+                    "loc/simp_chinese/replace/b_l_simp_chinese.yml.tmp".to_string(),
+                    "temp"
+                ),
+            ]
+        );
+
+        remove_cleanup_items(&items).unwrap();
+        assert!(find_cleanup_items(root, &[]).is_empty());
+        assert!(root.join(".pmt/memory.json").exists());
+    }
+
+    #[test]
+    fn test_find_stale_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("english")).unwrap();
+        std::fs::create_dir_all(root.join("simp_chinese/replace")).unwrap();
+        for file in [
+            "english/a_l_english.yml",
+            "english/b_l_english.yml",
+            "simp_chinese/replace/a_l_simp_chinese.yml",
+            "simp_chinese/replace/b_part1_l_simp_chinese.yml",
+            "simp_chinese/replace/b_part2_l_simp_chinese.yml",
+            "simp_chinese/replace/removed_l_simp_chinese.yml",
+            "simp_chinese/mod_own_l_simp_chinese.yml",
+        ] {
+            std::fs::write(root.join(file), "l_english:\n").unwrap();
+        }
+        let mut task = TranslationTask::from_source_dir(
+            root.join("english"),
+            vec!["simp_chinese".to_string()],
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            find_stale_outputs(&task, &[]).unwrap(),
+            [root.join("simp_chinese/replace/removed_l_simp_chinese.yml")]
+        );
+
+        // 直接写入目标语言目录时不检查
+        task.output_subdir = PathBuf::new();
+        assert!(find_stale_outputs(&task, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_stale_outputs_shared_target_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "core/english/a_l_english.yml",
+            "addon/english/b_l_english.yml",
+            "out/simp_chinese/replace/a_l_simp_chinese.yml",
+            "out/simp_chinese/replace/b_l_simp_chinese.yml",
+            "out/simp_chinese/replace/removed_l_simp_chinese.yml",
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "l_english:\n").unwrap();
+        }
+        let tasks: Vec<TranslationTask> = ["core", "addon"]
+            .iter()
+            .map(|name| {
+                let mut task = TranslationTask::from_source_dir(
+                    root.join(name).join("english"),
+                    vec!["simp_chinese".to_string()],
+                    Vec::new(),
+                )
+                .unwrap();
+                task.output_dir = Some(root.join("out"));
+                task
+            })
+            .collect();
+
+        // 两个任务写入同一目标目录，另一个任务的目标文件不算残留
+        for task in &tasks {
+            assert_eq!(
+                find_stale_outputs(task, &tasks).unwrap(),
+                [root.join("out/simp_chinese/replace/removed_l_simp_chinese.yml")]
+            );
+        }
+        assert_eq!(find_stale_outputs(&tasks[0], &[]).unwrap().len(), 2);
+    }
+}
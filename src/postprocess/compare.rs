@@ -0,0 +1,332 @@
+//! 比较两份译文
+//!
+//! 逐个键比较两个目录（例如上一版翻译子 Mod 与这次运行的输出）中的本地化文件，列出新增、删除与
+//! 译文有变化的键，并标出译文中改动的部分，便于审校者核对更换模型或术语表实际改变了哪些译文。
+
+use super::{OutputSnapshot, add_entries};
+use crate::error::Result;
+use crate::preprocess::{detect_lang_header, sort_by_load_order};
+use crate::utils::{find_localisation_files, read_file_with_bom};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// 读取目录下的全部本地化文件，按语言头中的语言汇总条目，同一个键按游戏的加载顺序取生效的译文
+pub fn snapshot_dir(dir: &Path) -> Result<OutputSnapshot> {
+    let mut files = find_localisation_files(dir)?;
+    sort_by_load_order(&mut files);
+    let mut snapshot = OutputSnapshot::new();
+    for file in files {
+        let content = read_file_with_bom(&file)?;
+        let Some(lang) = detect_lang_header(&content) else {
+            continue;
+        };
+        add_entries(snapshot.entry(lang).or_default(), &content);
+    }
+    Ok(snapshot)
+}
+
+/// 一种语言中两份译文的差异
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageComparison {
+    /// 只有新译文中才有的键及其译文
+    pub added: Vec<(String, String)>,
+    /// 只有旧译文中才有的键及其译文
+    pub removed: Vec<(String, String)>,
+    /// 译文有变化的键：（键，旧译文，新译文）
+    pub changed: Vec<(String, String, String)>,
+}
+
+impl LanguageComparison {
+    /// 是否没有任何差异
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// 两份译文的差异，按语言分开
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comparison {
+    pub languages: BTreeMap<String, LanguageComparison>,
+}
+
+/// 将文本拆分为比较的单位：连续的 ASCII 字母与数字为一个词，连续的空白为一个单位，其余每个字符单独成为一个单位
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let same = |next: char| {
+            (c.is_ascii_alphanumeric() && next.is_ascii_alphanumeric())
+                || (c.is_whitespace() && next.is_whitespace())
+        };
+        match chars.peek() {
+            Some(&(_, next)) if same(next) => {}
+            Some(&(j, _)) => {
+                tokens.push(&text[start..j]);
+                start = j;
+            }
+            None => tokens.push(&text[start..i + c.len_utf8()]),
+        }
+    }
+    tokens
+}
+
+/// 比较单位数的乘积超过该值时不逐个单位比较，只去除共同的开头与结尾
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// 比较旧文本与新文本，返回两者的片段：（片段，是否为改动的部分）
+///
+/// 逐个单位求最长公共子序列，相邻的改动合并为一个片段。
+#[allow(clippy::type_complexity)]
+fn diff_segments<'a>(old: &'a str, new: &'a str) -> (Vec<(&'a str, bool)>, Vec<(&'a str, bool)>) {
+    let (a, b) = (tokens(old), tokens(new));
+    let (n, m) = (a.len(), b.len());
+    // 公共的单位：两者中的下标
+    let mut common: Vec<(usize, usize)> = Vec::new();
+    if n * m <= MAX_DIFF_CELLS {
+        // lcs[i][j]：a[i..] 与 b[j..] 的最长公共子序列长度
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if a[i] == b[j] {
+                common.push((i, j));
+                i += 1;
+                j += 1;
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    } else {
+        let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+        let suffix = a[prefix..]
+            .iter()
+            .rev()
+            .zip(b[prefix..].iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count();
+        common.extend((0..prefix).map(|i| (i, i)));
+        common.extend((0..suffix).map(|k| (n - suffix + k, m - suffix + k)));
+    }
+
+    // 各单位在原文中首尾相接，连续的未改动或改动的单位合并为原文中的一个片段
+    let segments = |text: &'a str, tokens: &[&str], kept: Vec<usize>| {
+        let mut kept = kept.into_iter().peekable();
+        let mut segments: Vec<(&'a str, bool)> = Vec::new();
+        let (mut start, mut offset) = (0, 0);
+        for (i, token) in tokens.iter().enumerate() {
+            let changed = kept.next_if_eq(&i).is_none();
+            match segments.last_mut() {
+                Some((segment, last)) if *last == changed => {
+                    *segment = &text[start..offset + token.len()];
+                }
+                _ => {
+                    start = offset;
+                    segments.push((&text[start..offset + token.len()], changed));
+                }
+            }
+            offset += token.len();
+        }
+        segments
+    };
+    (
+        segments(old, &a, common.iter().map(|(i, _)| *i).collect()),
+        segments(new, &b, common.iter().map(|(_, j)| *j).collect()),
+    )
+}
+
+impl Comparison {
+    /// 比较旧译文与新译文，没有差异的语言不列出
+    pub fn between(old: &OutputSnapshot, new: &OutputSnapshot) -> Self {
+        let empty = BTreeMap::new();
+        let mut comparison = Self::default();
+        for lang in old.keys().chain(new.keys()) {
+            if comparison.languages.contains_key(lang) {
+                continue;
+            }
+            let before = old.get(lang).unwrap_or(&empty);
+            let after = new.get(lang).unwrap_or(&empty);
+            let mut diff = LanguageComparison::default();
+            for (key, value) in after {
+                match before.get(key) {
+                    None => diff.added.push((key.clone(), value.clone())),
+                    Some(previous) if previous != value => {
+                        diff.changed
+                            .push((key.clone(), previous.clone(), value.clone()))
+                    }
+                    Some(_) => {}
+                }
+            }
+            for (key, value) in before {
+                if !after.contains_key(key) {
+                    diff.removed.push((key.clone(), value.clone()));
+                }
+            }
+            comparison.languages.insert(lang.clone(), diff);
+        }
+        comparison.languages.retain(|_, diff| !diff.is_empty());
+        comparison
+    }
+
+    /// 是否没有任何差异
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty()
+    }
+
+    /// 输出差异：新增（`+`）、删除（`-`）与修改（`~`）的键，修改的键分两行列出旧译文与新译文
+    ///
+    /// `color` 为 true 时以红色与绿色标出改动的部分，否则以 `[-旧-]` 与 `{+新+}` 标出。
+    pub fn render(&self, color: bool) -> String {
+        let paint = |text: &str, code: &str| {
+            if color {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+        let mark = |text: &str, code: &str, open: &str, close: &str| {
+            if color {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                format!("{}{}{}", open, text, close)
+            }
+        };
+
+        let mut out = String::new();
+        if self.is_empty() {
+            out.push_str("No differences.\n");
+            return out;
+        }
+        for (lang, diff) in &self.languages {
+            let _ = writeln!(
+                out,
+                "{}: {} added, {} removed, {} changed",
+                lang,
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len()
+            );
+            for (key, value) in &diff.added {
+                let _ = writeln!(
+                    out,
+                    "{}",
+                    paint(&format!("+ {}: \"{}\"", key, value), GREEN)
+                );
+            }
+            for (key, value) in &diff.removed {
+                let _ = writeln!(out, "{}", paint(&format!("- {}: \"{}\"", key, value), RED));
+            }
+            for (key, old, new) in &diff.changed {
+                let (old_segments, new_segments) = diff_segments(old, new);
+                let line = |segments: Vec<(&str, bool)>, code: &str, open: &str, close: &str| {
+                    segments
+                        .into_iter()
+                        .map(|(text, changed)| {
+                            if changed {
+                                mark(text, code, open, close)
+                            } else {
+                                text.to_string()
+                            }
+                        })
+                        .collect::<String>()
+                };
+                let _ = writeln!(out, "{}", paint(&format!("~ {}:", key), YELLOW));
+                let _ = writeln!(out, "    - \"{}\"", line(old_segments, RED, "[-", "-]"));
+                let _ = writeln!(out, "    + \"{}\"", line(new_segments, GREEN, "{+", "+}"));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_dirs() {
+        let old = tempfile::tempdir().unwrap();
+        let new = tempfile::tempdir().unwrap();
+        let write = |dir: &Path, path: &str, content: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(
+            old.path(),
+            "simp_chinese/replace/a_l_simp_chinese.yml",
+            "l_simp_chinese:\n a:0 \"研究速度提高\"\n b:0 \"乙\"\n c:0 \"丙\"\n",
+        );
+        // 键移动到其他文件不算差异
+        write(
+            new.path(),
+            "localisation/simp_chinese/replace/a_l_simp_chinese.yml",
+            "l_simp_chinese:\n a:0 \"研究速率提高\"\n",
+        );
+        write(
+            new.path(),
+            "localisation/simp_chinese/replace/b_l_simp_chinese.yml",
+            "l_simp_chinese:\n b:0 \"乙\"\n d:0 \"丁\"\n",
+        );
+
+        let comparison = Comparison::between(
+            &snapshot_dir(old.path()).unwrap(),
+            &snapshot_dir(new.path()).unwrap(),
+        );
+        assert_eq!(
+            comparison.render(false),
+            "simp_chinese: 1 added, 1 removed, 1 changed\n\
+             + d: \"丁\"\n\
+             - c: \"丙\"\n\
+             ~ a:\n    \
+             - \"研究速[-度-]提高\"\n    \
+             + \"研究速{+率+}提高\"\n"
+        );
+        assert!(
+            Comparison::between(
+                &snapshot_dir(old.path()).unwrap(),
+                &snapshot_dir(old.path()).unwrap()
+            )
+            .is_empty()
+        );
+        // 只标出改动的词，未改动的部分原样保留
+        let (old_segments, new_segments) =
+            diff_segments("Gain 10 energy per month", "Gain 12 energy each month");
+        assert_eq!(
+            old_segments,
+            [
+                ("Gain ", false),
+                ("10", true),
+                (" energy ", false),
+                ("per", true),
+                (" month", false)
+            ]
+        );
+        assert_eq!(
+            new_segments,
+            [
+                ("Gain ", false),
+                ("12", true),
+                (" energy ", false),
+                ("each", true),
+                (" month", false)
+            ]
+        );
+    }
+}
@@ -1,8 +1,14 @@
 //! 合并模块
 //!
-//! 合并翻译后的切片为完整的文件。
+//! 按键收集翻译后的切片，并按源文件的结构重建完整的文件。
 
-use crate::error::{Result, TranslationError};
+use super::output_parts;
+use crate::error::Result;
+use crate::preprocess::{parse_entries, parse_entry_line};
+use crate::translate::{CONTEXT_BEGIN, CONTEXT_END};
+use crate::utils::read_file_with_bom;
+use std::collections::HashMap;
+use std::path::Path;
 
 /// 翻译切片
 pub struct TranslationSlice {
@@ -14,48 +20,265 @@ pub struct TranslationSlice {
     pub end_line: usize,
 }
 
-/// 合并翻译切片并恢复缩进
-pub fn merge_slices(slices: Vec<TranslationSlice>) -> Result<String> {
-    if slices.is_empty() {
-        return Err(TranslationError::Postprocess(
-            crate::error::PostprocessError::InconsistentSlices,
-        ));
-    }
-
-    // 按起始行排序
-    let mut sorted_slices = slices;
-    sorted_slices.sort_by_key(|s| s.start_line);
-
-    // 检查连续性
-    for i in 1..sorted_slices.len() {
-        if sorted_slices[i].start_line != sorted_slices[i - 1].end_line + 1 {
-            return Err(TranslationError::Postprocess(
-                crate::error::PostprocessError::MergeFailed(format!(
-                    "Slices are not contiguous: {} != {} + 1",
-                    sorted_slices[i].start_line,
-                    sorted_slices[i - 1].end_line
-                )),
-            ));
+/// 去除模型回复中照抄的切片上下文块
+pub fn strip_context_header(content: &str) -> String {
+    let mut in_header = false;
+    let mut stripped = false;
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|line| {
+            let trimmed = line.trim().trim_matches('`');
+            if !stripped && trimmed == CONTEXT_BEGIN {
+                in_header = true;
+                return false;
+            }
+            if in_header {
+                if trimmed == CONTEXT_END {
+                    in_header = false;
+                    stripped = true;
+                }
+                return false;
+            }
+            true
+        })
+        .collect();
+    if in_header {
+        // 缺少结束行时不做处理，以免误删译文
+        return content.to_string();
+    }
+    lines.join("\n")
+}
+
+/// 按键对齐后的译文切片
+#[derive(Debug)]
+pub struct AlignedSlice {
+    /// 与源切片逐行对应的译文
+    pub content: String,
+    /// 译文中缺失、以原文填充的键
+    pub missing: Vec<String>,
+}
+
+/// 检查译文是否与源切片逐行对应：行数相同，且每个条目行的键与源切片同一行的键一致
+pub fn is_aligned(source: &str, translated: &str) -> bool {
+    let source_lines: Vec<&str> = source.lines().collect();
+    let translated_lines: Vec<&str> = translated.lines().collect();
+    source_lines.len() == translated_lines.len()
+        && source_lines
+            .iter()
+            .zip(&translated_lines)
+            .all(|(s, t)| match parse_entry_line(s) {
+                Some((key, _, _)) => parse_entry_line(t).is_some_and(|(k, _, _)| k == key),
+                None => parse_entry_line(t).is_none(),
+            })
+}
+
+/// 按键将译文重新对齐到源切片的结构
+///
+/// 模型可能合并、拆分或遗漏行，使译文的行数与源切片不同，按行合并会使之后的条目整体错位。
+/// 此函数按源切片逐行重建：条目行取译文中同键的值（追踪号沿用源文件），注释与空行照抄源文件，
+/// 译文中缺失的键以原文填充并记入 `missing`。
+pub fn align_to_source(source: &str, translated: &str) -> AlignedSlice {
+    let values: HashMap<String, String> = parse_entries(translated)
+        .into_iter()
+        .map(|e| (e.key, e.value))
+        .collect();
+    let mut missing = Vec::new();
+    let lines: Vec<String> = source
+        .lines()
+        .map(|line| match parse_entry_line(line) {
+            Some((key, version, value)) => {
+                let value = match values.get(&key) {
+                    Some(translated) => translated.clone(),
+                    None => {
+                        missing.push(key.clone());
+                        value
+                    }
+                };
+                format!("{}:{} \"{}\"", key, version.unwrap_or_default(), value)
+            }
+            None => line.to_string(),
+        })
+        .collect();
+    AlignedSlice {
+        content: lines.join("\n"),
+        missing,
+    }
+}
+
+/// 收集译文切片中的条目：键 -> 译文，同一个键出现多次时以第一次为准
+pub fn collect_translations(slices: &[TranslationSlice]) -> HashMap<String, String> {
+    let mut translations = HashMap::new();
+    for slice in slices {
+        merge_translations(&mut translations, slice);
+    }
+    translations
+}
+
+/// 将一个译文切片中的条目合并到 `translations`，已有的键保持不变
+pub fn merge_translations(translations: &mut HashMap<String, String>, slice: &TranslationSlice) {
+    for entry in parse_entries(&slice.content) {
+        translations.entry(entry.key).or_insert(entry.value);
+    }
+}
+
+/// 将拆分写入的多份目标文件内容（见 `split_output`）合并为一份，语言头只保留第一份的
+pub fn join_output_parts(parts: &[String]) -> String {
+    let mut joined = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        let part = part.trim_start_matches('\u{FEFF}');
+        let body = match part.split_once('\n') {
+            Some((first, rest))
+                if i > 0 && crate::preprocess::parse_lang_header(first).is_some() =>
+            {
+                rest
+            }
+            None if i > 0 && crate::preprocess::parse_lang_header(part).is_some() => "",
+            _ => part,
+        };
+        if !joined.is_empty() && !body.is_empty() {
+            joined.push('\n');
         }
+        joined.push_str(body.trim_end_matches('\n'));
     }
+    joined
+}
 
-    // 合并内容并增加两级缩进
-    let mut lines = Vec::new();
-    for slice in sorted_slices {
-        lines.extend(slice.content.lines().map(|s| format!("  {}", s)));
+/// 读取目标文件的完整内容，译文拆分为多个文件写入时合并各部分，目标文件不存在时返回 None
+pub fn read_translated_output(output_path: &Path) -> Result<Option<String>> {
+    let parts = output_parts(output_path);
+    if parts.is_empty() {
+        return Ok(None);
     }
+    let contents = parts
+        .iter()
+        .map(|path| read_file_with_bom(path))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(join_output_parts(&contents)))
+}
 
-    Ok(lines.join("\n"))
+/// 重建后的目标文件
+#[derive(Debug)]
+pub struct ReconstructedFile {
+    /// 带语言头的完整内容（不含 BOM）
+    pub content: String,
+    /// 没有译文、保留原文的键
+    pub missing: Vec<String>,
 }
 
-/// 从YAML内容重建完整文件
-pub fn reconstruct_yaml_file(slices: Vec<TranslationSlice>, target_lang: &str) -> Result<String> {
-    let merged = merge_slices(slices)?;
+/// 按源文件的结构重建目标文件
+///
+/// `source` 为源文件去除语言头后的内容，`header` 为目标文件的语言头行（见 `rewrite_lang_header`）。
+/// 条目的键与顺序、注释与空行都沿用源文件，只把值换成 `translations` 中的译文，
+/// 因此模型合并、拆分或遗漏行都不会使其他条目错位。源文件中没有追踪号的条目使用 `versions` 中的追踪号，
+/// 没有译文的键保留原文并记入 `missing`。
+pub fn reconstruct_yaml_file(
+    source: &str,
+    header: &str,
+    translations: &HashMap<String, String>,
+    versions: &HashMap<String, String>,
+) -> ReconstructedFile {
+    let mut missing = Vec::new();
+    let mut lines = vec![header.to_string()];
+    for line in source.lines() {
+        let trimmed = line.trim();
+        let line = match parse_entry_line(trimmed) {
+            Some((key, version, value)) => {
+                let value = match translations.get(&key) {
+                    Some(translated) => translated.clone(),
+                    None => {
+                        missing.push(key.clone());
+                        value
+                    }
+                };
+                let version = version
+                    .or_else(|| versions.get(&key).cloned())
+                    .unwrap_or_default();
+                format!("  {}:{} \"{}\"", key, version, value)
+            }
+            None if trimmed.is_empty() => String::new(),
+            None => format!("  {}", trimmed),
+        };
+        lines.push(line);
+    }
+    // 源文件末尾的空行不保留
+    while lines.len() > 1 && lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    ReconstructedFile {
+        content: lines.join("\n"),
+        missing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_to_source() {
+        let source = "# Techs\ntech_a:0 \"Alpha\"\ntech_b:1 \"Beta\"\n\ntech_c:0 \"Gamma\"";
+        // 模型丢掉了注释与空行，并且打乱了顺序
+        let translated = "tech_b: \"乙\"\ntech_a: \"甲\"\ntech_c: \"丙\"";
+        assert!(!is_aligned(source, translated));
+
+        let aligned = align_to_source(source, translated);
+        assert!(aligned.missing.is_empty());
+        assert_eq!(
+            aligned.content,
+            "# Techs\ntech_a:0 \"甲\"\ntech_b:1 \"乙\"\n\ntech_c:0 \"丙\""
+        );
+        assert!(is_aligned(source, &aligned.content));
 
-    let target_lang_header = format!("l_{}:\n", target_lang);
-    let mut result = String::new();
-    // 确保有文件头
-    result.push_str(&target_lang_header);
-    result.push_str(&merged);
-    Ok(result)
+        let aligned = align_to_source(source, "tech_a: \"甲\"");
+        assert_eq!(aligned.missing, ["tech_b", "tech_c"]);
+        assert_eq!(aligned.content.lines().count(), source.lines().count());
+    }
+
+    #[test]
+    fn test_reconstruct_yaml_file() {
+        let source = "# Techs\ntech_a: \"Alpha\"\ntech_b:1 \"Beta\"\n\ntech_c: \"Gamma\"\n";
+        // 模型把两个条目合并到了同一个切片的一行里，tech_c 丢失
+        let slices = vec![TranslationSlice {
+            content: "tech_b: \"乙\"\ntech_a: \"甲\"\ntech_a: \"重复\"".to_string(),
+            start_line: 1,
+            end_line: 3,
+        }];
+        let translations = collect_translations(&slices);
+        assert_eq!(translations["tech_a"], "甲");
+
+        let versions = HashMap::from([("tech_a".to_string(), "0".to_string())]);
+        let rebuilt = reconstruct_yaml_file(source, "l_simp_chinese:0", &translations, &versions);
+        assert_eq!(rebuilt.missing, ["tech_c"]);
+        assert_eq!(
+            rebuilt.content,
+            "l_simp_chinese:0\n  # Techs\n  tech_a:0 \"甲\"\n  tech_b:1 \"乙\"\n\n  tech_c: \"Gamma\""
+        );
+    }
+
+    #[test]
+    fn test_split_output_parts() {
+        let content = "l_simp_chinese:\n  a: \"甲\"\n  b: \"乙\"\n\n  # 第二节\n  c: \"丙\"";
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("pf_l_simp_chinese.yml");
+        std::fs::write(&output, "stale").unwrap();
+
+        let paths = crate::postprocess::write_translated_output(content, &output, Some(2)).unwrap();
+        assert_eq!(
+            paths,
+            [
+                dir.path().join("pf_part1_l_simp_chinese.yml"),
+                dir.path().join("pf_part2_l_simp_chinese.yml"),
+            ]
+        );
+        assert!(!output.exists());
+        assert_eq!(
+            read_file_with_bom(&paths[1]).unwrap(),
+            "l_simp_chinese:\n\n  # 第二节\n  c: \"丙\""
+        );
+        assert_eq!(read_translated_output(&output).unwrap().unwrap(), content);
+
+        // 条目数不再超出时恢复为一个文件，并删除各部分
+        crate::postprocess::write_translated_output(content, &output, Some(10)).unwrap();
+        assert!(output.exists() && !paths[0].exists() && !paths[1].exists());
+    }
 }
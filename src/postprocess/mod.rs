@@ -1,11 +1,23 @@
 //! 后处理模块
 //!
-//! 负责合并翻译后的切片，写入目标目录，并清理临时文件。
+//! 负责合并翻译后的切片，写入目标目录，清理临时文件，并将译文打包为翻译子 Mod。
 
+mod changelog;
 mod cleanup;
+mod compare;
 mod merger;
+mod package;
+mod pinned;
+mod provenance;
+mod status;
 mod writer;
 
+pub use changelog::*;
 pub use cleanup::*;
+pub use compare::*;
 pub use merger::*;
+pub use package::*;
+pub use pinned::*;
+pub use provenance::*;
+pub use status::*;
 pub use writer::*;
@@ -0,0 +1,324 @@
+//! 翻译子 Mod 打包
+//!
+//! 将各目标语言的译文整理为独立的翻译子 Mod：生成 descriptor.mod、复制缩略图，
+//! 按 `localisation/<语言>/<output_subdir>` 布局（默认 `replace`）放置译文，并打包为可上传创意工坊的 zip 文件。
+
+use crate::config::{PackageSettings, TranslationTask};
+use crate::error::{Result, TranslationError};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// 由子 Mod 名称生成目录名，非字母数字的字符替换为下划线
+///
+/// 名称中没有可用字符时使用 `translation`，以免目录名为空而指向输出目录本身。
+pub fn mod_dir_name(name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let slug = slug.trim_matches('_');
+    if slug.is_empty() {
+        "translation".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// 生成 descriptor.mod 内容
+pub fn render_descriptor(settings: &PackageSettings, has_thumbnail: bool) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace('"', "\\\""));
+    let mut out = String::new();
+    let _ = writeln!(out, "version={}", quote(&settings.version));
+    if !settings.tags.is_empty() {
+        out.push_str("tags={\n");
+        for tag in &settings.tags {
+            let _ = writeln!(out, "\t{}", quote(tag));
+        }
+        out.push_str("}\n");
+    }
+    let _ = writeln!(out, "name={}", quote(&settings.name));
+    if has_thumbnail {
+        out.push_str("picture=\"thumbnail.png\"\n");
+    }
+    if let Some(version) = &settings.supported_version {
+        let _ = writeln!(out, "supported_version={}", quote(version));
+    }
+    if !settings.dependencies.is_empty() {
+        out.push_str("dependencies={\n");
+        for dependency in &settings.dependencies {
+            let _ = writeln!(out, "\t{}", quote(dependency));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// 组装翻译子 Mod 目录，返回目录路径与复制的译文文件数
+pub fn build_translation_mod(
+    settings: &PackageSettings,
+    tasks: &[TranslationTask],
+) -> Result<(PathBuf, usize)> {
+    let mod_dir = settings.output_dir.join(mod_dir_name(&settings.name));
+    // 组装前会清空子 Mod 目录，其中不能有任务读取的源文件或写入的译文
+    let inside_mod_dir =
+        |path: &Path| lexical_absolute(path).starts_with(lexical_absolute(&mod_dir));
+    for task in tasks {
+        let overlapping = std::iter::once(task.localisation_dir.clone())
+            .chain(task.target_langs.iter().map(|lang| task.target_dir(lang)))
+            .find(|path| inside_mod_dir(path));
+        if let Some(path) = overlapping {
+            return Err(crate::error::ConfigError::InvalidValue(crate::tr!(
+                "config.package_dir_overlaps",
+                mod_dir.display(),
+                path.display()
+            ))
+            .into());
+        }
+    }
+    if mod_dir.exists() {
+        std::fs::remove_dir_all(&mod_dir)?;
+    }
+    std::fs::create_dir_all(&mod_dir)?;
+
+    let mut count = 0;
+    for task in tasks {
+        for target_lang in &task.target_langs {
+            let source = task.target_dir(target_lang);
+            if !source.is_dir() {
+                log::warn!("No translations for {} in {:?}", target_lang, source);
+                continue;
+            }
+            let dest = mod_dir
+                .join("localisation")
+                .join(target_lang)
+                .join(&task.output_subdir);
+            for entry in WalkDir::new(&source).into_iter().filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if !path.is_file() || !path.extension().is_some_and(|e| e == "yml" || e == "yaml") {
+                    continue;
+                }
+                let relative = path.strip_prefix(&source).unwrap_or(path);
+                let target = dest.join(relative);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::copy(path, &target)?;
+                count += 1;
+            }
+        }
+    }
+
+    if let Some(thumbnail) = &settings.thumbnail {
+        std::fs::copy(thumbnail, mod_dir.join("thumbnail.png"))?;
+    }
+    std::fs::write(
+        mod_dir.join("descriptor.mod"),
+        render_descriptor(settings, settings.thumbnail.is_some()),
+    )?;
+    Ok((mod_dir, count))
+}
+
+/// 不访问文件系统地将路径转为绝对路径，并去除其中的 `.` 与 `..`
+fn lexical_absolute(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// 将目录内容打包为 zip 文件（不包含目录本身这一层）
+pub fn zip_directory(dir: &Path, zip_path: &Path) -> Result<()> {
+    let zip_error = |e: zip::result::ZipError| {
+        TranslationError::Io(std::io::Error::other(format!(
+            "Failed to write {:?}: {}",
+            zip_path, e
+        )))
+    };
+    let file = std::fs::File::create(zip_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry.map_err(|e| {
+            TranslationError::Io(std::io::Error::other(format!("WalkDir error: {}", e)))
+        })?;
+        let path = entry.path();
+        let relative = path
+            .strip_prefix(dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if path.is_file() {
+            zip.start_file(relative, options).map_err(zip_error)?;
+            let mut f = std::fs::File::open(path)?;
+            std::io::copy(&mut f, &mut zip)?;
+        } else if !relative.is_empty() {
+            zip.add_directory(relative, options).map_err(zip_error)?;
+        }
+    }
+    zip.finish().map_err(zip_error)?;
+    Ok(())
+}
+
+/// 将任务各目标语言的目标目录打包为 zip 文件，返回打包的文件数
+///
+/// 文件在压缩包中的路径为 `localisation/<相对于输出根目录的路径>`，例如 `localisation/simp_chinese/replace/a_l_simp_chinese.yml`，
+/// 解压到 Mod 目录即可使用。
+pub fn zip_task_outputs(task: &TranslationTask, zip_path: &Path) -> Result<usize> {
+    let zip_error = |e: zip::result::ZipError| {
+        TranslationError::Io(std::io::Error::other(format!(
+            "Failed to write {:?}: {}",
+            zip_path, e
+        )))
+    };
+    if let Some(parent) = zip_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let root = task.output_dir.as_ref().unwrap_or(&task.localisation_dir);
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(zip_path)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let mut count = 0;
+    for target_lang in &task.target_langs {
+        let target_dir = task.target_dir(target_lang);
+        if !target_dir.is_dir() {
+            continue;
+        }
+        for entry in WalkDir::new(&target_dir).sort_by_file_name() {
+            let entry = entry.map_err(|e| {
+                TranslationError::Io(std::io::Error::other(format!("WalkDir error: {}", e)))
+            })?;
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            let name = Path::new("localisation")
+                .join(relative)
+                .to_string_lossy()
+                .replace('\\', "/");
+            zip.start_file(name, options).map_err(zip_error)?;
+            std::io::copy(&mut std::fs::File::open(path)?, &mut zip)?;
+            count += 1;
+        }
+    }
+    zip.finish().map_err(zip_error)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_translation_mod() {
+        let dir = tempfile::tempdir().unwrap();
+        let loc = dir.path().join("localisation");
+        std::fs::create_dir_all(loc.join("english")).unwrap();
+        std::fs::create_dir_all(loc.join("simp_chinese/replace")).unwrap();
+        std::fs::write(
+            loc.join("simp_chinese/replace/a_l_simp_chinese.yml"),
+            "l_simp_chinese:\n key: \"值\"",
+        )
+        .unwrap();
+
+        let task = TranslationTask {
+            source_lang: "english".to_string(),
+            source_langs: Vec::new(),
+            target_langs: vec!["simp_chinese".to_string()],
+            glossaries: vec![],
+            localisation_dir: loc,
+            vanilla_localisation_dir: None,
+            reuse_vanilla: false,
+            game_install_dir: None,
+            passes: 1,
+            draft_model: None,
+            refine_model: None,
+            ensemble_models: vec![],
+            judge_model: None,
+            pivot_lang: None,
+            pivot_dir: ".pmt/pivot".into(),
+            output_subdir: PathBuf::from("replace"),
+            output_dir: None,
+            output_archive: None,
+            filename_template: None,
+            provenance: false,
+            group_by_prefix: false,
+            micro_batch_tokens: None,
+            overrides_dir: "overrides".into(),
+            length_budgets: vec![],
+            max_entries_per_file: None,
+            custom_markers: Vec::new(),
+            marker_check: Default::default(),
+            validation_ignore: "validation_ignore.toml".into(),
+            prune_stale_outputs: false,
+            continue_on_error: false,
+            file_langs: Default::default(),
+            fallback_files: Default::default(),
+        };
+        let settings: PackageSettings = toml::from_str(&format!(
+            "name = \"Test Mod 简体中文\"\nsupported_version = \"v4.0.*\"\ndependencies = [\"Test Mod\"]\noutput_dir = {:?}",
+            dir.path().join("dist")
+        ))
+        .unwrap();
+
+        let (mod_dir, count) =
+            build_translation_mod(&settings, std::slice::from_ref(&task)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            mod_dir.file_name().unwrap().to_str().unwrap(),
+            "test_mod_简体中文"
+        );
+        assert!(
+            mod_dir
+                .join("localisation/simp_chinese/replace/a_l_simp_chinese.yml")
+                .is_file()
+        );
+        assert_eq!(
+            std::fs::read_to_string(mod_dir.join("descriptor.mod")).unwrap(),
+            "version=\"1.0\"\ntags={\n\t\"Translation\"\n}\nname=\"Test Mod 简体中文\"\nsupported_version=\"v4.0.*\"\ndependencies={\n\t\"Test Mod\"\n}\n"
+        );
+
+        // 译文写入子 Mod 目录时拒绝组装，以免清空目录时删除译文
+        let inside = TranslationTask {
+            output_dir: Some(mod_dir.join("out")),
+            ..task.clone()
+        };
+        assert!(matches!(
+            build_translation_mod(&settings, &[inside]),
+            Err(TranslationError::Config(_))
+        ));
+        assert!(mod_dir.join("descriptor.mod").is_file());
+
+        let zip_path = dir.path().join("dist/test.zip");
+        zip_directory(&mod_dir, &zip_path).unwrap();
+        let archive = zip::ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap();
+        assert!(archive.file_names().any(|n| n == "descriptor.mod"));
+
+        let outputs = dir.path().join("dist/outputs.zip");
+        assert_eq!(zip_task_outputs(&task, &outputs).unwrap(), 1);
+        let source = crate::source::open_source(&outputs).unwrap();
+        assert_eq!(
+            source.list_files("simp_chinese").unwrap(),
+            [PathBuf::from("simp_chinese/replace/a_l_simp_chinese.yml")]
+        );
+    }
+}
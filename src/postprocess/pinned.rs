@@ -0,0 +1,93 @@
+//! 固定的译文
+//!
+//! 审校者可以在目标文件中某个条目的上一行写 `# pmt:keep`，固定这条已认可的译文：
+//! 再次翻译时该条目直接沿用目标文件中的译文，不再交给大模型，重建的目标文件中也保留这一行标记。
+
+use super::read_translated_output;
+use crate::error::Result;
+use crate::preprocess::parse_entry_line;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 固定下一行条目的标记
+pub const KEEP_MARKER: &str = "# pmt:keep";
+
+fn is_keep_marker(line: &str) -> bool {
+    line.trim() == KEEP_MARKER
+}
+
+/// 内容中以 `# pmt:keep` 固定的条目，返回「键 -> 译文」
+///
+/// 标记与条目之间可以有空行，但不能隔着其他注释或条目。
+pub fn pinned_entries(content: &str) -> HashMap<String, String> {
+    let mut pinned = HashMap::new();
+    let mut marked = false;
+    for line in content.lines() {
+        if is_keep_marker(line) {
+            marked = true;
+        } else if line.trim().is_empty() {
+            continue;
+        } else {
+            if marked && let Some((key, _, value)) = parse_entry_line(line) {
+                pinned.insert(key, value);
+            }
+            marked = false;
+        }
+    }
+    pinned
+}
+
+/// 已有目标文件中固定的条目，目标文件不存在时为空
+pub fn pinned_in_output(output_path: &Path) -> Result<HashMap<String, String>> {
+    Ok(read_translated_output(output_path)?
+        .map(|content| pinned_entries(&content))
+        .unwrap_or_default())
+}
+
+/// 在重建的内容中为 `pinned` 中的条目重新加上 `# pmt:keep` 标记，缩进与条目相同
+pub fn mark_pinned(content: &str, pinned: &HashMap<String, String>) -> String {
+    if pinned.is_empty() {
+        return content.to_string();
+    }
+    let mut marked = String::with_capacity(content.len());
+    let mut previous_marker = false;
+    for line in content.split_inclusive('\n') {
+        if !previous_marker
+            && let Some((key, _, _)) = parse_entry_line(line)
+            && pinned.contains_key(&key)
+        {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            marked.push_str(indent);
+            marked.push_str(KEEP_MARKER);
+            marked.push('\n');
+        }
+        if !line.trim().is_empty() {
+            previous_marker = is_keep_marker(line);
+        }
+        marked.push_str(line);
+    }
+    marked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_entries() {
+        let existing = "l_simp_chinese:\n  # pmt:keep\n  a:0 \"甲（已审校）\"\n  b:0 \"乙\"\n  # pmt:keep\n\n  c: \"丙\"\n  # pmt:keep\n  # 其他注释\n  d: \"丁\"\n";
+        let pinned = pinned_entries(existing);
+        assert_eq!(pinned.len(), 2);
+        assert_eq!(pinned["a"], "甲（已审校）");
+        assert_eq!(pinned["c"], "丙");
+
+        let rebuilt =
+            "l_simp_chinese:\n  a:0 \"甲（已审校）\"\n  b:0 \"乙二\"\n  c: \"丙\"\n  d: \"丁\"";
+        assert_eq!(
+            mark_pinned(rebuilt, &pinned),
+            "l_simp_chinese:\n  # pmt:keep\n  a:0 \"甲（已审校）\"\n  b:0 \"乙二\"\n  # pmt:keep\n  c: \"丙\"\n  d: \"丁\""
+        );
+        // 已有标记的条目不重复添加
+        assert_eq!(mark_pinned(existing, &pinned), existing);
+    }
+}
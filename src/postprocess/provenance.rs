@@ -0,0 +1,208 @@
+//! 译文来源标注
+//!
+//! 启用后在生成的目标文件开头写一行注释，记录工具版本、模型、生成日期与源文件内容的哈希，
+//! 并在每个条目行尾标注译文的来源（AI 译文、翻译记忆、官方译文、人工译文或固定的译文）。
+//! 注释不影响游戏读取，需要干净文件的用户可以不启用。
+
+use super::{output_parts, write_translated_file};
+use crate::error::Result;
+use crate::utils::cached_regex;
+use crate::utils::{FNV_OFFSET, fnv1a, read_file_with_bom};
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// 来源文件头注释的开头
+pub const PROVENANCE_HEADER: &str = "# Generated by pmt";
+
+/// 条目译文的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryOrigin {
+    /// 大模型的译文
+    Ai,
+    /// 翻译记忆中的译文
+    Memory,
+    /// 游戏本体的官方译文
+    Vanilla,
+    /// `overrides/` 中人工维护的译文
+    Override,
+    /// 目标文件中以 `# pmt:keep` 固定的译文
+    Kept,
+}
+
+impl EntryOrigin {
+    /// 行尾标记中的名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ai => "ai",
+            Self::Memory => "tm",
+            Self::Vanilla => "vanilla",
+            Self::Override => "human",
+            Self::Kept => "kept",
+        }
+    }
+}
+
+/// Unix 时间戳（秒）对应的 UTC 日期，格式为 `YYYY-MM-DD`
+fn utc_date(secs: u64) -> String {
+    // 按公历 400 年的周期换算天数，见 http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 来源文件头注释：工具版本、模型、日期与源文件内容的哈希
+pub fn provenance_header(model: &str, source: &str, secs: u64) -> String {
+    let mut hash = FNV_OFFSET;
+    fnv1a(&mut hash, source.as_bytes());
+    format!(
+        "{} {}; model: {}; date: {}; source: {:016x}",
+        PROVENANCE_HEADER,
+        env!("CARGO_PKG_VERSION"),
+        model,
+        utc_date(secs),
+        hash
+    )
+}
+
+/// 为译文加上来源文件头注释与每个条目的来源标记，已有的文件头与标记先去除
+///
+/// `origin` 给出每个键的译文来源，`secs` 为生成时间的 Unix 时间戳。
+pub fn annotate_provenance(
+    content: &str,
+    model: &str,
+    source: &str,
+    secs: u64,
+    origin: impl Fn(&str) -> EntryOrigin,
+) -> String {
+    mark_origins(
+        content,
+        Some(&provenance_header(model, source, secs)),
+        |key| Some(origin(key)),
+    )
+}
+
+/// 合并部分条目后更新目标文件（及其拆分写入的各部分）的来源标注
+///
+/// 第一部分的文件头注释换为 `source` 对应的新文件头；`origin` 返回 `None` 的条目是合并前已有的，
+/// 保留原来的标记。
+pub fn annotate_merged_output(
+    output_path: &Path,
+    model: &str,
+    source: &str,
+    secs: u64,
+    origin: impl Fn(&str) -> Option<EntryOrigin>,
+) -> Result<()> {
+    let header = provenance_header(model, source, secs);
+    for (i, part) in output_parts(output_path).iter().enumerate() {
+        let content = read_file_with_bom(part)?;
+        let annotated = mark_origins(&content, (i == 0).then_some(header.as_str()), &origin);
+        if annotated != content {
+            write_translated_file(&annotated, part, false)?;
+        }
+    }
+    Ok(())
+}
+
+/// 去除已有的文件头注释，`header` 不为空时在开头写入新的文件头；`origin` 返回来源的条目换为新的标记
+fn mark_origins(
+    content: &str,
+    header: Option<&str>,
+    origin: impl Fn(&str) -> Option<EntryOrigin>,
+) -> String {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    let marker = cached_regex(&MARKER, || r"\s+# pmt:[a-z]+\s*$");
+    let mut annotated = String::new();
+    if let Some(header) = header {
+        annotated.push_str(header);
+        annotated.push('\n');
+    }
+    for line in content.lines() {
+        if line.trim_start().starts_with(PROVENANCE_HEADER) {
+            continue;
+        }
+        match crate::preprocess::parse_entry_line(line).and_then(|(key, _, _)| origin(&key)) {
+            Some(origin) => {
+                annotated.push_str(&marker.replace(line, ""));
+                annotated.push_str(" # pmt:");
+                annotated.push_str(origin.as_str());
+            }
+            None => annotated.push_str(line),
+        }
+        annotated.push('\n');
+    }
+    if !content.ends_with('\n') {
+        annotated.pop();
+    }
+    annotated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_provenance() {
+        assert_eq!(utc_date(0), "1970-01-01");
+        assert_eq!(utc_date(951_782_400), "2000-02-29");
+        assert_eq!(utc_date(1_791_936_000), "2026-10-14");
+
+        let source = "l_english:\n a:0 \"A\"\n b:0 \"B\"\n";
+        let translated = "l_simp_chinese:\n  # 注释\n  a:0 \"甲\"\n  b:0 \"乙\" # pmt:ai";
+        let origin = |key: &str| match key {
+            "a" => EntryOrigin::Override,
+            _ => EntryOrigin::Memory,
+        };
+        let annotated = annotate_provenance(translated, "m", source, 0, origin);
+        let (header, body) = annotated.split_once('\n').unwrap();
+        assert!(header.starts_with("# Generated by pmt "));
+        assert!(header.contains("; model: m; date: 1970-01-01; source: "));
+        assert_eq!(
+            body,
+            "l_simp_chinese:\n  # 注释\n  a:0 \"甲\" # pmt:human\n  b:0 \"乙\" # pmt:tm"
+        );
+        // 再次标注时替换已有的文件头与标记
+        assert_eq!(
+            annotate_provenance(&annotated, "m", source, 0, origin),
+            annotated
+        );
+    }
+
+    #[test]
+    fn test_annotate_merged_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("a_l_simp_chinese.yml");
+        let old_source = "l_english:\n a:0 \"A\"\n b:0 \"B\"\n";
+        let annotated = annotate_provenance(
+            "l_simp_chinese:\n  a:0 \"甲\"\n  b:0 \"乙\"",
+            "m",
+            old_source,
+            0,
+            |_| EntryOrigin::Memory,
+        );
+        write_translated_file(&annotated, &output, false).unwrap();
+
+        // 合并后的条目没有标记，重新标注时只更新它们，其余条目保留原有标记
+        crate::review::write_entries(&output, &[("b".to_string(), "新乙".to_string())]).unwrap();
+        let source = "l_english:\n a:0 \"A\"\n b:0 \"Beta\"\n";
+        annotate_merged_output(&output, "m", source, 0, |key| {
+            (key == "b").then_some(EntryOrigin::Ai)
+        })
+        .unwrap();
+        let content = read_file_with_bom(&output).unwrap();
+        let (header, body) = content.split_once('\n').unwrap();
+        assert_eq!(header, provenance_header("m", source, 0));
+        assert_eq!(
+            body,
+            "l_simp_chinese:\n  a:0 \"甲\" # pmt:tm\n  b:0 \"新乙\" # pmt:ai"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! 翻译进度
+//!
+//! 逐个键比较源文件与各目标语言的目标文件，得到每个文件在每种目标语言下的翻译状态，
+//! 以文件 × 目标语言的表格输出，供维护者了解哪些译文需要补齐或更新。
+
+use super::{output_parts, read_translated_output};
+use crate::config::TranslationTask;
+use crate::error::Result;
+use crate::preprocess::parse_entries;
+use crate::source::LocalisationSource;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// 一个文件在一种目标语言下的翻译状态
+///
+/// 序列化为 JSON 时以 `status` 字段区分，例如 `{"status": "partial", "translated": 98, "total": 140}`。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileStatus {
+    /// 目标文件不存在
+    Missing,
+    /// 部分源文件中的键没有译文
+    Partial { translated: usize, total: usize },
+    /// 所有键都有译文，但源文件在翻译之后被修改过，或目标文件中有源文件已删除的键
+    Stale { removed_keys: usize },
+    /// 所有键都有译文
+    Complete,
+}
+
+impl FileStatus {
+    /// 表格中显示的文本
+    pub fn label(&self) -> String {
+        match self {
+            FileStatus::Missing => "missing".to_string(),
+            FileStatus::Partial { translated, total } => {
+                format!("partial {}/{}", translated, total)
+            }
+            FileStatus::Stale { .. } => "stale".to_string(),
+            FileStatus::Complete => "complete".to_string(),
+        }
+    }
+}
+
+/// 一个源文件在各目标语言下的翻译状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusRow {
+    /// 源文件名
+    pub file: String,
+    /// 源文件中的键数
+    pub keys: usize,
+    /// 与 `StatusMatrix::languages` 一一对应
+    pub cells: Vec<FileStatus>,
+}
+
+/// 任务的翻译进度：文件 × 目标语言
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusMatrix {
+    /// 任务（本地化目录）
+    pub task: String,
+    /// 目标语言
+    pub languages: Vec<String>,
+    /// 每个源文件一行
+    pub rows: Vec<StatusRow>,
+}
+
+/// 文件的修改时间，无法获取时为 `None`
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 比较源文件与目标文件的键，得到翻译状态
+///
+/// 源文件在压缩包中时，以压缩包的修改时间作为源文件的修改时间。
+pub fn file_status(
+    source: &dyn LocalisationSource,
+    source_file: &Path,
+    target_file: &Path,
+) -> Result<FileStatus> {
+    let Some(target) = read_translated_output(target_file)? else {
+        return Ok(FileStatus::Missing);
+    };
+    let source_keys: Vec<String> = parse_entries(&source.read_file(source_file)?)
+        .into_iter()
+        .map(|e| e.key)
+        .collect();
+    let target_keys: HashSet<String> = parse_entries(&target).into_iter().map(|e| e.key).collect();
+
+    let source_set: HashSet<&String> = source_keys.iter().collect();
+    let total = source_set.len();
+    let translated = source_set
+        .iter()
+        .filter(|k| target_keys.contains(**k))
+        .count();
+    if translated < total {
+        return Ok(FileStatus::Partial { translated, total });
+    }
+
+    let removed_keys = target_keys
+        .iter()
+        .filter(|k| !source_set.contains(k))
+        .count();
+    // 拆分写入时以最早写入的部分为准
+    let translated_at = output_parts(target_file)
+        .iter()
+        .filter_map(|p| modified(p))
+        .min();
+    let source_changed = matches!(
+        (modified(source_file).or_else(|| modified(source.origin())), translated_at),
+        (Some(source), Some(target)) if source > target
+    );
+    if removed_keys > 0 || source_changed {
+        return Ok(FileStatus::Stale { removed_keys });
+    }
+    Ok(FileStatus::Complete)
+}
+
+/// 计算任务中每个源文件在每种目标语言下的翻译状态
+pub fn task_status(task: &TranslationTask) -> Result<StatusMatrix> {
+    let source = task.source()?;
+    let mut rows = Vec::new();
+    for source_file in task.source_files_in(source.as_ref())? {
+        let file = source_file
+            .strip_prefix(task.source_dir())
+            .or_else(|_| source_file.strip_prefix(task.file_source_lang(&source_file)))
+            .unwrap_or(&source_file)
+            .display()
+            .to_string();
+        let keys = parse_entries(&source.read_file(&source_file)?).len();
+        let cells = task
+            .target_langs
+            .iter()
+            .map(|lang| {
+                // 自动检测源语言时，已经是目标语言的源文件无需翻译
+                if task.file_source_lang(&source_file) == lang {
+                    return Ok(FileStatus::Complete);
+                }
+                file_status(
+                    source.as_ref(),
+                    &source_file,
+                    &task.target_file(&source_file, lang),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        rows.push(StatusRow { file, keys, cells });
+    }
+    rows.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(StatusMatrix {
+        task: task.localisation_dir.display().to_string(),
+        languages: task.target_langs.clone(),
+        rows,
+    })
+}
+
+impl StatusMatrix {
+    /// 以文本表格输出，最后一行为每种目标语言完成的文件数
+    pub fn render(&self) -> String {
+        let file_width = self
+            .rows
+            .iter()
+            .map(|r| r.file.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("File".len());
+        let widths: Vec<usize> = self
+            .languages
+            .iter()
+            .enumerate()
+            .map(|(i, lang)| {
+                self.rows
+                    .iter()
+                    .map(|r| r.cells[i].label().len())
+                    .max()
+                    .unwrap_or(0)
+                    .max(lang.len())
+            })
+            .collect();
+
+        let mut out = String::new();
+        let line = |out: &mut String, first: &str, second: &str, cells: Vec<String>| {
+            let mut text = format!("{:<file_width$} {:>6}", first, second);
+            for (cell, width) in cells.iter().zip(&widths) {
+                let _ = write!(text, "  {:<width$}", cell, width = *width);
+            }
+            let _ = writeln!(out, "{}", text.trim_end());
+        };
+        line(&mut out, "File", "keys", self.languages.clone());
+        for row in &self.rows {
+            let cells = row.cells.iter().map(FileStatus::label).collect();
+            line(&mut out, &row.file, &row.keys.to_string(), cells);
+        }
+        let complete = (0..self.languages.len())
+            .map(|i| {
+                let done = self
+                    .rows
+                    .iter()
+                    .filter(|r| r.cells[i] == FileStatus::Complete)
+                    .count();
+                format!("{}/{}", done, self.rows.len())
+            })
+            .collect();
+        line(&mut out, "Complete", "", complete);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("english")).unwrap();
+        std::fs::create_dir_all(root.join("simp_chinese/replace")).unwrap();
+        let write = |file: &str, content: &str| std::fs::write(root.join(file), content).unwrap();
+        write(
+            "english/a_l_english.yml",
+            "l_english:\n a:0 \"A\"\n b:0 \"B\"\n",
+        );
+        write("english/b_l_english.yml", "l_english:\n c:0 \"C\"\n");
+        write("english/c_l_english.yml", "l_english:\n d:0 \"D\"\n");
+        write("english/d_l_english.yml", "l_english:\n e:0 \"E\"\n");
+        write(
+            "simp_chinese/replace/a_l_simp_chinese.yml",
+            "l_simp_chinese:\n a:0 \"甲\"\n",
+        );
+        write(
+            "simp_chinese/replace/b_l_simp_chinese.yml",
+            "l_simp_chinese:\n c:0 \"丙\"\n",
+        );
+        write(
+            "simp_chinese/replace/c_l_simp_chinese.yml",
+            "l_simp_chinese:\n d:0 \"丁\"\n old:0 \"旧\"\n",
+        );
+        let task = TranslationTask::from_source_dir(
+            root.join("english"),
+            vec!["simp_chinese".to_string()],
+            Vec::new(),
+        )
+        .unwrap();
+
+        let matrix = task_status(&task).unwrap();
+        let cells: Vec<&FileStatus> = matrix.rows.iter().map(|r| &r.cells[0]).collect();
+        assert_eq!(
+            cells,
+            [
+                &FileStatus::Partial {
+                    translated: 1,
+                    total: 2
+                },
+                &FileStatus::Complete,
+                &FileStatus::Stale { removed_keys: 1 },
+                &FileStatus::Missing,
+            ]
+        );
+        let rendered = matrix.render();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            lines[0].split_whitespace().collect::<Vec<_>>(),
+            ["File", "keys", "simp_chinese"]
+        );
+        assert!(lines[1].ends_with("partial 1/2"));
+        assert!(lines[5].starts_with("Complete") && lines[5].contains("1/4"));
+
+        let json = serde_json::to_value(&matrix).unwrap();
+        assert_eq!(json["languages"], serde_json::json!(["simp_chinese"]));
+        assert_eq!(json["rows"][0]["file"], "a_l_english.yml");
+        assert_eq!(
+            json["rows"][0]["cells"][0],
+            serde_json::json!({"status": "partial", "translated": 1, "total": 2})
+        );
+        assert_eq!(json["rows"][3]["cells"][0]["status"], "missing");
+    }
+}
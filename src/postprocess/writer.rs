@@ -10,23 +10,102 @@ use std::path::{Path, PathBuf};
 /// 写入翻译后的文件
 /// 因为 Rust str 本身编码为 UTF-8，所以只需要提前写入 BOM 头即可
 pub fn write_translated_file(content: &str, output_path: &Path, create_dirs: bool) -> Result<()> {
-    if create_dirs {
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-    }
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(output_path)?;
-    if !content.starts_with("\u{FEFF}") {
-        file.write("\u{FEFF}".as_bytes())?;
+    if create_dirs && let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
     }
-    file.write(content.as_bytes())?;
+    // 先写入临时文件再替换目标文件，写入中途被中断时不会留下不完整的文件
+    let mut temp_name = output_path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+    let mut file = fs::File::create(&temp_path)?;
+    // 无论内容中有几个 BOM，都只写入一个
+    file.write_all("\u{FEFF}".as_bytes())?;
+    file.write_all(content.trim_start_matches('\u{FEFF}').as_bytes())?;
+    drop(file);
+    fs::rename(&temp_path, output_path)?;
     Ok(())
 }
 
+/// 拆分写入时第 `part` 份（从 1 开始）的文件路径：在文件名的 `_l_<语言>` 之前（没有时在扩展名之前）插入 `_part<N>`，
+/// 例如 `pf_misc_l_simp_chinese.yml` 的第 2 份为 `pf_misc_part2_l_simp_chinese.yml`
+pub fn part_path(output_path: &Path, part: usize) -> PathBuf {
+    let filename = output_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (filename.as_str(), String::new()),
+    };
+    let (name, lang) = match stem.rfind("_l_") {
+        Some(i) => stem.split_at(i),
+        None => (stem, ""),
+    };
+    output_path.with_file_name(format!("{}_part{}{}{}", name, part, lang, extension))
+}
+
+/// 目标文件实际写入的文件：目标文件存在时只有它本身，否则为按顺序排列的各部分，都不存在时为空
+pub fn output_parts(output_path: &Path) -> Vec<PathBuf> {
+    if output_path.exists() {
+        return vec![output_path.to_path_buf()];
+    }
+    (1..)
+        .map(|part| part_path(output_path, part))
+        .take_while(|path| path.exists())
+        .collect()
+}
+
+/// 目标文件（或其拆分写入的各部分）是否存在
+pub fn output_exists(output_path: &Path) -> bool {
+    !output_parts(output_path).is_empty()
+}
+
+/// 写入完整的目标文件内容，设置了 `max_entries` 且条目数超出时拆分为多个文件（见 `part_path`），
+/// 返回写入的文件路径
+///
+/// 先前写入的、本次不再需要的整份文件或多余的部分会被删除，保证目标文件只有一种写法。
+pub fn write_translated_output(
+    content: &str,
+    output_path: &Path,
+    max_entries: Option<usize>,
+) -> Result<Vec<PathBuf>> {
+    let parts = match max_entries {
+        Some(max_entries) => crate::translate::split_output(content, max_entries),
+        None => vec![content.to_string()],
+    };
+    let paths: Vec<PathBuf> = if parts.len() == 1 {
+        vec![output_path.to_path_buf()]
+    } else {
+        (1..=parts.len())
+            .map(|part| part_path(output_path, part))
+            .collect()
+    };
+    for (part, path) in parts.iter().zip(&paths) {
+        write_translated_file(part, path, true)?;
+    }
+
+    // 删除过期的写法
+    if paths.len() > 1 && output_path.exists() {
+        fs::remove_file(output_path)?;
+    }
+    let first_stale = if paths.len() > 1 { paths.len() + 1 } else { 1 };
+    for path in (first_stale..)
+        .map(|part| part_path(output_path, part))
+        .take_while(|path| path.exists())
+    {
+        fs::remove_file(path)?;
+    }
+    if paths.len() > 1 {
+        log::info!(
+            "Split {:?} into {} files of at most {} entries",
+            output_path,
+            paths.len(),
+            max_entries.unwrap_or_default()
+        );
+    }
+    Ok(paths)
+}
+
 /// 批量写入翻译文件
 pub fn write_translated_files(
     files: Vec<(PathBuf, String)>,
@@ -1,10 +1,74 @@
 /// 生成目标文件名（例如将 l_english 替换为目标语言），并确保文件后缀名为 .yml
+///
+/// 文件名中不含 `l_<源语言>` 时在文件名末尾追加 `_l_<目标语言>`，
+/// 否则译文会与源文件同名，不同目标语言的译文也会相互覆盖。
 pub fn generate_target_filename(
     source_filename: &str,
     source_lang: &str,
     target_lang: &str,
 ) -> String {
-    source_filename
-        .replace(&format!("l_{}", source_lang), &format!("l_{}", target_lang))
-        .replace(".yaml", ".yml") // 统一使用 .yml 扩展名
+    let marker = format!("l_{}", source_lang);
+    if source_filename.contains(&marker) {
+        return source_filename
+            .replace(&marker, &format!("l_{}", target_lang))
+            .replace(".yaml", ".yml"); // 统一使用 .yml 扩展名
+    }
+    format!(
+        "{}_l_{}.yml",
+        filename_stem(source_filename, source_lang),
+        target_lang
+    )
+}
+
+/// 源文件名去除扩展名与 `l_<源语言>` 标记后的部分，例如 `events_l_english.yml` 为 `events`
+pub fn filename_stem(source_filename: &str, source_lang: &str) -> String {
+    let stem = source_filename
+        .strip_suffix(".yml")
+        .or_else(|| source_filename.strip_suffix(".yaml"))
+        .unwrap_or(source_filename);
+    let stem = stem.replace(&format!("l_{}", source_lang), "");
+    let parts: Vec<&str> = stem.split('_').filter(|p| !p.is_empty()).collect();
+    parts.join("_")
+}
+
+/// 按模板生成目标文件名，模板中可以使用 `{stem}`、`{source}` 与 `{target}`，
+/// 例如 `{stem}_l_{target}.yml`
+pub fn render_filename_template(
+    template: &str,
+    source_filename: &str,
+    source_lang: &str,
+    target_lang: &str,
+) -> String {
+    template
+        .replace("{stem}", &filename_stem(source_filename, source_lang))
+        .replace("{source}", source_lang)
+        .replace("{target}", target_lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_filename() {
+        assert_eq!(
+            generate_target_filename("events_l_english.yaml", "english", "simp_chinese"),
+            "events_l_simp_chinese.yml"
+        );
+        // 文件名中没有源语言时追加目标语言，避免与源文件同名
+        assert_eq!(
+            generate_target_filename("events.yml", "english", "simp_chinese"),
+            "events_l_simp_chinese.yml"
+        );
+        assert_eq!(filename_stem("l_english_techs.yml", "english"), "techs");
+        assert_eq!(
+            render_filename_template(
+                "zz_{stem}_l_{target}.yml",
+                "l_english_techs.yml",
+                "english",
+                "russian"
+            ),
+            "zz_techs_l_russian.yml"
+        );
+    }
 }
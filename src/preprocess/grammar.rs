@@ -0,0 +1,88 @@
+//! 语法变体保护
+//!
+//! Victoria 3、Crusader Kings 3 等游戏的本地化文本可以调用本地化函数，例如
+//! `[GetPlayer.GetCustom('PlayerTitle')]`、`[ROOT.Char.Custom('HerHis')]`，
+//! 由游戏根据性别、单复数等条件选择不同的文本。这些结构必须原样保留，
+//! 因此在发送给模型前替换为占位符，收到译文后再还原。
+
+use crate::utils::cached_regex;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// 语法变体结构：包含函数调用的 `[...]` 指令
+fn grammar_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    cached_regex(&PATTERN, || r"\[[^\[\]]*\([^\[\]]*\)[^\[\]]*\]")
+}
+
+/// 占位符：`{G0}`、`{G1}`……
+fn placeholder_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    cached_regex(&PATTERN, || r"\{G(\d+)\}")
+}
+
+/// 找出文本中的语法变体结构
+pub fn find_grammar_constructs(text: &str) -> Vec<String> {
+    grammar_pattern()
+        .find_iter(text)
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// 将语法变体结构替换为占位符，返回替换后的文本与按编号排列的原始结构
+pub fn protect_grammar(text: &str) -> (String, Vec<String>) {
+    let mut constructs = Vec::new();
+    let protected = grammar_pattern()
+        .replace_all(text, |caps: &regex::Captures| {
+            constructs.push(caps[0].to_string());
+            format!("{{G{}}}", constructs.len() - 1)
+        })
+        .into_owned();
+    (protected, constructs)
+}
+
+/// 将占位符还原为原始结构，编号超出范围的占位符保持不变
+pub fn restore_grammar(text: &str, constructs: &[String]) -> String {
+    if constructs.is_empty() {
+        return text.to_string();
+    }
+    placeholder_pattern()
+        .replace_all(text, |caps: &regex::Captures| {
+            caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| constructs.get(i))
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protect_and_restore_grammar() {
+        let text = r#" title:0 "[GetPlayer.GetCustom('PlayerTitle')] of [ROOT.GetName]"
+ desc:0 "[ROOT.Char.Custom('HerHis')] army""#;
+        let (protected, constructs) = protect_grammar(text);
+        assert_eq!(
+            constructs,
+            [
+                "[GetPlayer.GetCustom('PlayerTitle')]",
+                "[ROOT.Char.Custom('HerHis')]"
+            ]
+        );
+        assert!(protected.contains(r#""{G0} of [ROOT.GetName]""#));
+        assert!(protected.contains(r#""{G1} army""#));
+
+        let translated = r#" title:0 "[ROOT.GetName]的{G0}"
+ desc:0 "{G1}军队""#;
+        let restored = restore_grammar(translated, &constructs);
+        assert_eq!(
+            find_grammar_constructs(&restored),
+            find_grammar_constructs(text)
+        );
+    }
+}
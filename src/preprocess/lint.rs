@@ -0,0 +1,399 @@
+//! 源文件检查模块
+//!
+//! 独立于翻译检查本地化文件中的常见错误：缺失的引号、有误的语言头、不成对的格式标记，
+//! 以及文件内或多个文件中重复定义的键（游戏按加载顺序以后加载的定义为准）。
+
+use super::{FixRule, detect_lang_header, fix_yaml_content, parse_entries, parse_lang_header};
+use crate::error::Result;
+use crate::utils::{find_localisation_files, find_yaml_files, read_file_with_bom};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// 检查发现的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// 值缺少引号
+    MissingQuote { rule: FixRule },
+    /// 缺少语言头、语言头重复，或语言头与文件名中的语言不符
+    BadHeader { message: String },
+    /// 格式标记不成对
+    InvalidMarker { key: String, message: String },
+    /// 目标语言目录中残留的其他语言文件（通常是组装翻译子 Mod 时误复制的源文件）
+    LeftoverFile {
+        target_lang: String,
+        message: String,
+    },
+    /// 重复定义的键，被同一文件中之后的定义或后加载的文件覆盖
+    OverriddenKey {
+        key: String,
+        file: PathBuf,
+        line: usize,
+    },
+}
+
+/// 检查发现的问题及其位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    /// 文件路径
+    pub file: PathBuf,
+    /// 行号（从 1 开始）
+    pub line: usize,
+    /// 问题
+    pub kind: LintKind,
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintKind::MissingQuote { rule } => write!(f, "Value has {}", rule),
+            LintKind::BadHeader { message } => write!(f, "{}", message),
+            LintKind::InvalidMarker { key, message } => {
+                write!(f, "Invalid marker in key '{}': {}", key, message)
+            }
+            LintKind::LeftoverFile {
+                target_lang,
+                message,
+            } => write!(
+                f,
+                "Leftover file in the {} directory: {}",
+                target_lang, message
+            ),
+            LintKind::OverriddenKey { key, file, line } => write!(
+                f,
+                "Key '{}' is overridden by {}:{}",
+                key,
+                file.display(),
+                line
+            ),
+        }
+    }
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.kind)
+    }
+}
+
+/// 检查单个文件的内容，返回问题所在的行号与问题，重复的键由 `find_duplicate_keys` 检查
+///
+/// 提供 `file_name` 时检查其中是否包含语言头对应的 `l_<语言>` 标记（游戏据此识别文件的语言）。
+pub fn lint_content(content: &str, file_name: Option<&str>) -> Vec<(usize, LintKind)> {
+    let mut issues = Vec::new();
+
+    // 语言头
+    let mut header_lang: Option<String> = None;
+    let mut seen_entry = false;
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let Some((lang, standard)) = parse_lang_header(trimmed) else {
+            if header_lang.is_none() && !seen_entry {
+                issues.push((
+                    i + 1,
+                    LintKind::BadHeader {
+                        message: "Missing language header before the first entry".to_string(),
+                    },
+                ));
+            }
+            seen_entry = true;
+            continue;
+        };
+        if !standard {
+            issues.push((
+                i + 1,
+                LintKind::BadHeader {
+                    message: format!(
+                        "Non-standard language header '{}', expected 'l_{}:'",
+                        trimmed, lang
+                    ),
+                },
+            ));
+        }
+        match &header_lang {
+            Some(first) if *first == lang => issues.push((
+                i + 1,
+                LintKind::BadHeader {
+                    message: format!("Duplicate language header 'l_{}:'", lang),
+                },
+            )),
+            Some(_) => {}
+            None => {
+                if let Some(name) = file_name
+                    && !name.contains(&format!("l_{}", lang))
+                {
+                    issues.push((
+                        i + 1,
+                        LintKind::BadHeader {
+                            message: format!(
+                                "Language header 'l_{}:' does not match the file name {}",
+                                lang, name
+                            ),
+                        },
+                    ));
+                }
+                header_lang = Some(lang);
+            }
+        }
+    }
+
+    // 缺失的引号
+    if let Ok(fixed) = fix_yaml_content(content) {
+        issues.extend(
+            fixed
+                .fixes
+                .into_iter()
+                .filter(|fix| fix.rule != FixRule::Indentation)
+                .map(|fix| (fix.line, LintKind::MissingQuote { rule: fix.rule })),
+        );
+    }
+
+    // 不成对的标记
+    for entry in parse_entries(content) {
+        for message in marker_errors(&entry.value) {
+            issues.push((
+                entry.line,
+                LintKind::InvalidMarker {
+                    key: entry.key.clone(),
+                    message,
+                },
+            ));
+        }
+    }
+
+    issues.sort_by_key(|(line, _)| *line);
+    issues
+}
+
+/// 检查值中的格式标记是否成对
+fn marker_errors(value: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (marker, name) in [('$', "variable"), ('£', "icon")] {
+        if value.matches(marker).count() % 2 != 0 {
+            errors.push(format!("unpaired {} marker '{}'", name, marker));
+        }
+    }
+    let mut depth = 0usize;
+    for c in value.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 0 => {
+                errors.push("']' without matching '['".to_string());
+                break;
+            }
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        errors.push("unclosed '[' command".to_string());
+    }
+    if value.ends_with('§') {
+        errors.push("color code '§' at the end of the value".to_string());
+    }
+    errors
+}
+
+/// 按游戏的加载顺序排列文件
+///
+/// 文件按路径排序依次加载，`replace` 目录中的文件在其他文件之后加载，覆盖其中的同名键。
+pub fn sort_by_load_order(files: &mut [PathBuf]) {
+    files.sort_by_cached_key(|file| {
+        let replace = file.components().any(|c| c.as_os_str() == "replace");
+        (replace, file.clone())
+    });
+}
+
+/// 查找重复定义的键，同一文件中多次定义或同一语言的多个文件中定义的键都计入
+///
+/// `files` 按加载顺序排列，以最后加载的定义为准，返回每个被覆盖的定义及生效的定义所在的位置。
+pub fn find_duplicate_keys(files: &[PathBuf]) -> Result<Vec<LintIssue>> {
+    // 语言 -> 键 -> 按加载顺序排列的定义（文件、行号）
+    let mut definitions: HashMap<String, HashMap<String, Vec<(PathBuf, usize)>>> = HashMap::new();
+    for file in files {
+        let content = read_file_with_bom(file)?;
+        let lang = detect_lang_header(&content).unwrap_or_default();
+        let keys = definitions.entry(lang).or_default();
+        for entry in parse_entries(&content) {
+            keys.entry(entry.key)
+                .or_default()
+                .push((file.clone(), entry.line));
+        }
+    }
+
+    let mut issues = Vec::new();
+    for keys in definitions.values() {
+        for (key, defs) in keys {
+            let Some((winner, overridden)) = defs.split_last() else {
+                continue;
+            };
+            for (file, line) in overridden {
+                issues.push(LintIssue {
+                    file: file.clone(),
+                    line: *line,
+                    kind: LintKind::OverriddenKey {
+                        key: key.clone(),
+                        file: winner.0.clone(),
+                        line: winner.1,
+                    },
+                });
+            }
+        }
+    }
+    issues.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    Ok(issues)
+}
+
+/// 查找目标语言目录中残留的其他语言文件
+///
+/// 文件名中带有源语言标记 `l_<源语言>`，或语言头不是目标语言的 YAML 文件都会被报告。
+pub fn find_leftover_files(
+    dir: &Path,
+    source_lang: &str,
+    target_lang: &str,
+) -> Result<Vec<LintIssue>> {
+    let marker = format!("l_{}", source_lang);
+    let mut issues = Vec::new();
+    for file in find_yaml_files(dir)? {
+        let leftover = |line, message| LintIssue {
+            file: file.clone(),
+            line,
+            kind: LintKind::LeftoverFile {
+                target_lang: target_lang.to_string(),
+                message,
+            },
+        };
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if name.contains(&marker) {
+            issues.push(leftover(1, format!("file name contains '{}'", marker)));
+        }
+        let content = read_file_with_bom(&file)?;
+        for (i, line) in content.lines().enumerate() {
+            if let Some(lang) = detect_lang_header(line)
+                && lang != target_lang
+            {
+                issues.push(leftover(i + 1, format!("language header is 'l_{}:'", lang)));
+            }
+        }
+    }
+    issues.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    Ok(issues)
+}
+
+/// 检查目录下的所有本地化文件
+///
+/// 除逐个文件的检查外，报告文件内以及同一语言的多个文件之间重复定义的键。
+pub fn lint_dir(dir: &Path) -> Result<Vec<LintIssue>> {
+    let mut files = find_localisation_files(dir)?;
+    sort_by_load_order(&mut files);
+
+    let mut issues = find_duplicate_keys(&files)?;
+    for file in &files {
+        let content = read_file_with_bom(file)?;
+        let file_name = file.file_name().and_then(|n| n.to_str());
+        issues.extend(
+            lint_content(&content, file_name)
+                .into_iter()
+                .map(|(line, kind)| LintIssue {
+                    file: file.clone(),
+                    line,
+                    kind,
+                }),
+        );
+    }
+
+    issues.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicate_and_leftover_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let replace = dir.path().join("replace");
+        std::fs::create_dir_all(&replace).unwrap();
+        let a = dir.path().join("a_l_english.yml");
+        let b = dir.path().join("b_l_english.yml");
+        let r = replace.join("r_l_english.yml");
+        std::fs::write(&a, "l_english:\n  x:0 \"A\"\n  x:0 \"A2\"\n  y:0 \"Y\"").unwrap();
+        std::fs::write(&b, "l_english:\n  x:0 \"B\"").unwrap();
+        std::fs::write(&r, "l_english:\n  y:0 \"R\"").unwrap();
+
+        // replace 目录中的文件最后加载
+        let mut files = vec![r.clone(), b.clone(), a.clone()];
+        sort_by_load_order(&mut files);
+        assert_eq!(files, [a.clone(), b.clone(), r.clone()]);
+
+        let issues: Vec<(usize, LintKind)> = find_duplicate_keys(&files)
+            .unwrap()
+            .into_iter()
+            .map(|issue| (issue.line, issue.kind))
+            .collect();
+        let overridden = |key: &str, file: &PathBuf, line| LintKind::OverriddenKey {
+            key: key.to_string(),
+            file: file.clone(),
+            line,
+        };
+        assert_eq!(
+            issues,
+            [
+                (2, overridden("x", &b, 2)),
+                (3, overridden("x", &b, 2)),
+                (4, overridden("y", &r, 2)),
+            ]
+        );
+
+        // 在目标语言目录中，这些文件都是残留的源语言文件
+        let leftovers = find_leftover_files(dir.path(), "english", "simp_chinese").unwrap();
+        assert_eq!(leftovers.len(), 6);
+        assert!(leftovers[0].to_string().ends_with(
+            "Leftover file in the simp_chinese directory: file name contains 'l_english'"
+        ));
+    }
+
+    #[test]
+    fn test_lint_content() {
+        let content = [
+            "l_english:",
+            "  a:0 \"A $var$\"",
+            "  b:0 unquoted",
+            "  a:1 \"Again [Root.GetName\"",
+            "  c:0 \"£energy coins\"",
+            "l_english:",
+        ]
+        .join("\n");
+        let issues: Vec<String> = lint_content(&content, Some("l_french_test.yml"))
+            .iter()
+            .map(|(line, kind)| format!("{}: {}", line, kind))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "1: Language header 'l_english:' does not match the file name l_french_test.yml",
+                "3: Value has missing quotes",
+                "4: Invalid marker in key 'a': unclosed '[' command",
+                "5: Invalid marker in key 'c': unpaired icon marker '£'",
+                "6: Duplicate language header 'l_english:'",
+            ]
+        );
+
+        assert_eq!(
+            lint_content("a: \"A\"", None),
+            [(
+                1,
+                LintKind::BadHeader {
+                    message: "Missing language header before the first entry".to_string()
+                }
+            )]
+        );
+    }
+}
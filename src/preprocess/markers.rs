@@ -0,0 +1,106 @@
+//! 自定义标记保护
+//!
+//! 部分 Mod 定义了自己的标记写法，例如 `@custom_icon!`、`<K_key>`，由 Mod 的脚本或界面替换为图标与文本。
+//! 任务的 `custom_markers` 以正则表达式描述这些标记，发送给模型前将条目值中匹配的标记替换为占位符，
+//! 收到译文后再还原，验证时也检查这些标记是否与原文一致。
+
+use crate::utils::cached_regex;
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::parse_entry_line;
+
+/// 占位符：`{M0}`、`{M1}`……
+fn placeholder_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    cached_regex(&PATTERN, || r"\{M(\d+)\}")
+}
+
+/// 编译自定义标记的正则表达式，无效的表达式给出警告并忽略
+pub fn compile_markers(markers: &[String]) -> Vec<Regex> {
+    markers
+        .iter()
+        .filter_map(|marker| match Regex::new(marker) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                log::warn!("Ignoring custom marker {:?}: {}", marker, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// 将条目值中的自定义标记替换为占位符，返回替换后的内容与按编号排列的原始标记
+///
+/// 只处理条目值，键名保持不变；一处文本同时匹配多条表达式时使用先匹配的一条。
+pub fn protect_markers(content: &str, patterns: &[Regex]) -> (String, Vec<String>) {
+    if patterns.is_empty() {
+        return (content.to_string(), Vec::new());
+    }
+    let mut markers = Vec::new();
+    let mut out = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        let value_start = match (parse_entry_line(line), line.find('"')) {
+            (Some(_), Some(quote)) => quote,
+            _ => {
+                out.push_str(line);
+                continue;
+            }
+        };
+        out.push_str(&line[..value_start]);
+        let mut value = line[value_start..].to_string();
+        for pattern in patterns {
+            value = pattern
+                .replace_all(&value, |caps: &regex::Captures| {
+                    markers.push(caps[0].to_string());
+                    format!("{{M{}}}", markers.len() - 1)
+                })
+                .into_owned();
+        }
+        out.push_str(&value);
+    }
+    (out, markers)
+}
+
+/// 将占位符还原为原始标记，编号超出范围的占位符保持不变
+pub fn restore_markers(text: &str, markers: &[String]) -> String {
+    if markers.is_empty() {
+        return text.to_string();
+    }
+    placeholder_pattern()
+        .replace_all(text, |caps: &regex::Captures| {
+            caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| markers.get(i))
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protect_and_restore_markers() {
+        let patterns = compile_markers(&[
+            r"@[a-z_]+!".to_string(),
+            r"<K_[A-Za-z_]+>".to_string(),
+            "(".to_string(),
+        ]);
+        assert_eq!(patterns.len(), 2);
+
+        let text = " icon_key:0 \"@custom_icon! Gain <K_energy>\"\n # @not_a_value!\n";
+        let (protected, markers) = protect_markers(text, &patterns);
+        assert_eq!(markers, ["@custom_icon!", "<K_energy>"]);
+        assert_eq!(
+            protected,
+            " icon_key:0 \"{M0} Gain {M1}\"\n # @not_a_value!\n"
+        );
+
+        let restored = restore_markers(" icon_key:0 \"获得 {M1} {M0}\"", &markers);
+        assert_eq!(restored, " icon_key:0 \"获得 <K_energy> @custom_icon!\"");
+    }
+}
@@ -3,9 +3,21 @@
 //! 负责清洗和整理原始本地化文件，修复YAML格式问题，并将大文件切片。
 
 mod file_prepare;
+mod grammar;
+mod lint;
+mod markers;
 mod normalizer;
+mod numbers;
+mod parser;
+mod scripts;
 mod yaml_fixer;
 
 pub use file_prepare::*;
+pub use grammar::*;
+pub use lint::*;
+pub use markers::*;
 pub use normalizer::*;
+pub use numbers::*;
+pub use parser::*;
+pub use scripts::*;
 pub use yaml_fixer::*;
@@ -1,11 +1,75 @@
 //! 文本规范化模块
 //!
-//! 统一换行符、编码和空白字符。
+//! 统一换行符、BOM、缩进和行尾空白字符。所有读入的本地化文本都先经过 `normalize_input`，
+//! 翻译前再由 `normalize_text` 统一缩进并合并重复的语言头，之后的处理无需再考虑这些差异。
 
-use crate::error::Result;
+/// 规范化读入的文本内容
+///
+/// - 去除文件开头的 BOM（可能重复出现），以及拼接文件时混入行首的 BOM
+/// - 将 `\r\n` 与单独的 `\r` 统一为 `\n`
+/// - 去除每行末尾的空白字符
+///
+/// 写入时由 `write_translated_file` 重新加上唯一的 BOM。
+pub fn normalize_input(content: &str) -> String {
+    let content = content.replace("\r\n", "\n").replace('\r', "\n");
+    let lines: Vec<&str> = content
+        .split('\n')
+        .map(|line| line.trim_start_matches('\u{FEFF}').trim_end())
+        .collect();
+    lines.join("\n")
+}
+
+/// 翻译前规范化文本内容，在 `fix_yaml_content` 之前调用
+///
+/// 在 `normalize_input` 的基础上，将行首缩进中的制表符替换为两个空格，
+/// 并去除与所在语言块语言相同的重复语言头（拼接多个文件时常见）。
+pub fn normalize_text(content: &str) -> String {
+    let content = normalize_input(content);
+    let mut current_header = None;
+    let mut lines = Vec::new();
+    for line in content.split('\n') {
+        let body = line.trim_start_matches([' ', '\t']);
+        let indent = line[..line.len() - body.len()].replace('\t', "  ");
+        if let Some(lang) = super::detect_lang_header(body) {
+            if current_header.as_ref() == Some(&lang) {
+                log::debug!("Removed duplicate language header: {}", body);
+                continue;
+            }
+            current_header = Some(lang);
+        }
+        lines.push(format!("{}{}", indent, body));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocess::parse_entries;
+
+    #[test]
+    fn test_normalize_input() {
+        for original in [
+            include_str!("../../tests/localisation/english/l_english_pf_misc.yml"),
+            include_str!("../../tests/localisation/english/l_english_test_fix.yml"),
+        ] {
+            let expected = normalize_input(original);
+            assert!(!expected.starts_with('\u{FEFF}'));
+            assert!(!expected.contains('\r'));
+
+            // Windows 换行、重复的 BOM 与行尾空白都不影响结果
+            let mangled = format!("\u{FEFF}\u{FEFF}{}", original.replace('\n', " \t\r\n"));
+            assert_eq!(normalize_input(&mangled), expected);
+            assert_eq!(
+                parse_entries(&normalize_input(&mangled)).len(),
+                parse_entries(original).len()
+            );
+        }
+        assert_eq!(normalize_input("a\rb\r\n\u{FEFF}c  "), "a\nb\nc");
 
-/// 规范化文本内容
-pub fn normalize_text(content: &str) -> Result<String> {
-    // TODO: 实现规范化逻辑
-    Ok(content.to_string())
+        assert_eq!(
+            normalize_text("l_english:\n\ta:0 \"A\"\r\n\u{FEFF}l_english:\n\t\tb:0 \"B\"\t"),
+            "l_english:\n  a:0 \"A\"\n    b:0 \"B\""
+        );
+    }
 }
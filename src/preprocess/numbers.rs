@@ -0,0 +1,121 @@
+//! 数字保护
+//!
+//! 文本中的数值、百分比以及 `%d` 一类的格式化标记必须与原文一致，
+//! 否则会出现「+10% 射速」被译为「+15% 射速」这样改变游戏数值描述的错误。
+//! 因此在发送给模型前将条目值中的数字替换为占位符，收到译文后再还原。
+
+use crate::utils::cached_regex;
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::parse_entry_line;
+
+/// 数字（可带正负号、小数、千位分隔符与百分号）以及 printf 风格的格式化标记
+///
+/// 游戏标记（`£...£`、`$...$`、`[...]`、`§x`）与占位符 `{...}` 中的数字属于标识符，
+/// 先由前面的分支整体匹配，不会被视为数字。
+fn number_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    cached_regex(
+        &PATTERN,
+        || r#"£[^£]+£|\$[^$]+\$|\[[^\]]+\]|\{[^}]*\}|§.|(?P<num>%[-+]?\d*(?:\.\d+)?[dfis]|[+-]?\b\d+(?:[.,]\d+)*%?)"#,
+    )
+}
+
+/// 占位符：`{N0}`、`{N1}`……
+fn placeholder_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    cached_regex(&PATTERN, || r"\{N(\d+)\}")
+}
+
+/// 正负号只在前面不是字母或数字时计入，以免把 `T-1000` 中的连字符当作负号
+fn number_at(text: &str, start: usize, end: usize) -> (usize, &str) {
+    let matched = &text[start..end];
+    if matched.starts_with(['+', '-'])
+        && text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric())
+    {
+        (start + 1, &matched[1..])
+    } else {
+        (start, matched)
+    }
+}
+
+/// 找出文本中的数字
+pub fn find_numbers(text: &str) -> Vec<String> {
+    number_pattern()
+        .captures_iter(text)
+        .filter_map(|caps| caps.name("num"))
+        .map(|m| number_at(text, m.start(), m.end()).1.to_string())
+        .collect()
+}
+
+/// 将条目值中的数字替换为占位符，返回替换后的内容与按编号排列的原始数字
+///
+/// 只处理条目值，键名与追踪号中的数字保持不变。
+pub fn protect_numbers(content: &str) -> (String, Vec<String>) {
+    let pattern = number_pattern();
+    let mut numbers = Vec::new();
+    let mut out = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        let value_start = match (parse_entry_line(line), line.find('"')) {
+            (Some(_), Some(quote)) => quote,
+            _ => {
+                out.push_str(line);
+                continue;
+            }
+        };
+        out.push_str(&line[..value_start]);
+        let value = &line[value_start..];
+        let mut last = 0;
+        for caps in pattern.captures_iter(value) {
+            let Some(m) = caps.name("num") else {
+                continue;
+            };
+            let (start, number) = number_at(value, m.start(), m.end());
+            out.push_str(&value[last..start]);
+            out.push_str(&format!("{{N{}}}", numbers.len()));
+            numbers.push(number.to_string());
+            last = m.end();
+        }
+        out.push_str(&value[last..]);
+    }
+    (out, numbers)
+}
+
+/// 将占位符还原为原始数字，编号超出范围的占位符保持不变
+pub fn restore_numbers(text: &str, numbers: &[String]) -> String {
+    if numbers.is_empty() {
+        return text.to_string();
+    }
+    placeholder_pattern()
+        .replace_all(text, |caps: &regex::Captures| {
+            caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| numbers.get(i))
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protect_and_restore_numbers() {
+        let content = "l_english:\n weapon_2_desc:0 \"+10% fire rate, T-1000 deals %d damage to $job_2$ over 1,500.5 days\"\n";
+        let (protected, numbers) = protect_numbers(content);
+        assert_eq!(numbers, ["+10%", "1000", "%d", "1,500.5"]);
+        assert_eq!(
+            protected,
+            "l_english:\n weapon_2_desc:0 \"{N0} fire rate, T-{N1} deals {N2} damage to $job_2$ over {N3} days\"\n"
+        );
+        assert_eq!(restore_numbers(&protected, &numbers), content);
+        assert_eq!(find_numbers("[Root.GetName] {G0} 射速 +10%"), ["+10%"]);
+    }
+}
@@ -0,0 +1,314 @@
+//! 本地化条目解析模块
+//!
+//! 将本地化文件内容解析为 `key:<version> "value"` 条目，并支持按键改写条目的值。
+
+/// 本地化条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocEntry {
+    /// 键名
+    pub key: String,
+    /// 冒号后的追踪号（可选）
+    pub version: Option<String>,
+    /// 引号内的文本
+    pub value: String,
+    /// 在文件中的行号（从 1 开始）
+    pub line: usize,
+}
+
+/// 键名中允许出现的字符：任意文字的字母与数字，以及 `_`、`.`、`-`、`'`
+///
+/// 例如 `pf.100.desc`、`tech_ion-cannon`、`NAME_Gor'tak` 与 `schädel_event.2.a`。
+pub fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '\'')
+}
+
+/// 匹配一个键名的正则表达式，字符集与 [`is_key_char`] 相同
+pub const KEY_PATTERN: &str = r"[\p{Alphabetic}\p{N}_.'\-]+";
+
+/// 解析单行条目，语言头、注释、空行以及无法识别的行返回 None
+pub fn parse_entry_line(line: &str) -> Option<(String, Option<String>, String)> {
+    let trimmed = line.trim().trim_start_matches('\u{FEFF}');
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+    let (key, rest) = trimmed.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    // 追踪号
+    let version_len = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (version, rest) = rest.split_at(version_len);
+    let version = (!version.is_empty()).then(|| version.to_string());
+
+    let rest = rest.trim();
+    // 语言头 `l_english:` 没有值
+    if rest.is_empty() {
+        return None;
+    }
+    // 值为第一个引号与最后一个引号之间的内容，其后可跟注释
+    let value = match (rest.find('"'), rest.rfind('"')) {
+        (Some(start), Some(end)) if start == 0 && end > start => &rest[1..end],
+        (Some(0), _) => &rest[1..],
+        _ => rest,
+    };
+    Some((key.to_string(), version, value.to_string()))
+}
+
+/// 解析文件内容中的所有条目，按出现顺序返回
+pub fn parse_entries(content: &str) -> Vec<LocEntry> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            parse_entry_line(line).map(|(key, version, value)| LocEntry {
+                key,
+                version,
+                value,
+                line: i + 1,
+            })
+        })
+        .collect()
+}
+
+/// 将内容中指定键的值替换为新值，保留原有缩进与追踪号
+///
+/// 若键不存在则返回 None。
+pub fn replace_entry_value(content: &str, key: &str, new_value: &str) -> Option<String> {
+    let values = std::collections::HashMap::from([(key.to_string(), new_value.to_string())]);
+    let (updated, replaced) = replace_entry_values(content, &values);
+    (!replaced.is_empty()).then_some(updated)
+}
+
+/// 一次替换内容中多个键的值（键 -> 新值），保留原有缩进与追踪号，返回替换后的内容与被替换的键
+///
+/// 同一个键出现多次时只替换第一次出现的条目。
+pub fn replace_entry_values(
+    content: &str,
+    values: &std::collections::HashMap<String, String>,
+) -> (String, std::collections::HashSet<String>) {
+    let mut replaced = std::collections::HashSet::new();
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| match parse_entry_line(line) {
+            Some((k, version, _)) if !replaced.contains(&k) => match values.get(&k) {
+                Some(new_value) => {
+                    replaced.insert(k.clone());
+                    let indent_len = line.len() - line.trim_start().len();
+                    format!(
+                        "{}{}:{} \"{}\"",
+                        &line[..indent_len],
+                        k,
+                        version.unwrap_or_default(),
+                        new_value
+                    )
+                }
+                None => line.to_string(),
+            },
+            _ => line.to_string(),
+        })
+        .collect();
+    (lines.join("\n"), replaced)
+}
+
+/// 比较两个版本的内容，返回新版本中新增或值有变化的键，按出现顺序排列
+pub fn changed_keys(old_content: &str, new_content: &str) -> Vec<String> {
+    let old: std::collections::HashMap<String, String> = parse_entries(old_content)
+        .into_iter()
+        .map(|e| (e.key, e.value))
+        .collect();
+    parse_entries(new_content)
+        .into_iter()
+        .filter(|e| old.get(&e.key) != Some(&e.value))
+        .map(|e| e.key)
+        .collect()
+}
+
+/// 检测两个版本之间被重命名的键：旧版本中删除的键与新版本中新增的键文本完全相同时，视为重命名
+///
+/// 按源文本哈希配对，同一文本对应多个删除或新增的键时无法确定对应关系，不视为重命名。
+/// 返回 `(旧键, 新键)`，按新版本中的顺序排列。
+pub fn detect_renamed_keys(old_content: &str, new_content: &str) -> Vec<(String, String)> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::{HashMap, HashSet};
+    use std::hash::{Hash, Hasher};
+
+    let hash = |text: &str| {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        hasher.finish()
+    };
+    let old_entries = parse_entries(old_content);
+    let new_entries = parse_entries(new_content);
+    let old_keys: HashSet<&str> = old_entries.iter().map(|e| e.key.as_str()).collect();
+    let new_keys: HashSet<&str> = new_entries.iter().map(|e| e.key.as_str()).collect();
+
+    let mut removed: HashMap<u64, Vec<&str>> = HashMap::new();
+    for e in old_entries
+        .iter()
+        .filter(|e| !new_keys.contains(e.key.as_str()))
+    {
+        removed.entry(hash(&e.value)).or_default().push(&e.key);
+    }
+    let mut added: HashMap<u64, Vec<&str>> = HashMap::new();
+    for e in new_entries
+        .iter()
+        .filter(|e| !old_keys.contains(e.key.as_str()))
+    {
+        added.entry(hash(&e.value)).or_default().push(&e.key);
+    }
+
+    new_entries
+        .iter()
+        .filter(|e| !e.value.trim().is_empty())
+        .filter_map(|e| {
+            let h = hash(&e.value);
+            match (removed.get(&h), added.get(&h)) {
+                (Some(old), Some(new)) if old.len() == 1 && new.len() == 1 && new[0] == e.key => {
+                    Some((old[0].to_string(), e.key.clone()))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// 只保留键满足条件的条目行，语言头、注释等其余行全部去除
+pub fn filter_entries(content: &str, keep: impl Fn(&str) -> bool) -> String {
+    content
+        .lines()
+        .filter(|line| parse_entry_line(line).is_some_and(|(key, _, _)| keep(&key)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 去除键满足条件的条目行，保留其余所有行
+pub fn remove_entries(content: &str, remove: impl Fn(&str) -> bool) -> String {
+    content
+        .lines()
+        .filter(|line| !parse_entry_line(line).is_some_and(|(key, _, _)| remove(&key)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 判断文件内容是否为本地化文件
+///
+/// 有语言头（如 `l_english:`）时直接认定；否则要求至少一半的非空、非注释行是 `key: "value"` 形式的条目，
+/// 以排除放在本地化目录中的其他 YAML 文件。
+pub fn is_localisation_content(content: &str) -> bool {
+    if super::detect_lang_header(content).is_some() {
+        return true;
+    }
+    let lines: Vec<&str> = content
+        .trim_start_matches('\u{FEFF}')
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let quoted = lines
+        .iter()
+        .filter(|line| {
+            parse_entry_line(line).is_some()
+                && line.split_once(':').is_some_and(|(_, rest)| {
+                    rest.trim_start_matches(|c: char| c.is_ascii_digit())
+                        .trim_start()
+                        .starts_with('"')
+                })
+        })
+        .count();
+    quoted > 0 && quoted * 2 >= lines.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries() {
+        let content = "\u{FEFF}l_english:\n # comment\n key_a:0 \"Hello: \"world\"\" # note\n key_b: \"Plain\"\n";
+        let entries = parse_entries(content);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key, "key_a");
+        assert_eq!(entries[0].version.as_deref(), Some("0"));
+        assert_eq!(entries[0].value, "Hello: \"world\"");
+        assert_eq!(entries[0].line, 3);
+        assert_eq!(entries[1].key, "key_b");
+        assert_eq!(entries[1].version, None);
+        assert_eq!(entries[1].value, "Plain");
+    }
+
+    #[test]
+    fn test_replace_entry_value() {
+        let content = "l_simp_chinese:\n  key_a:0 \"旧\"\n  key_b: \"保持\"";
+        let replaced = replace_entry_value(content, "key_a", "新").unwrap();
+        assert_eq!(
+            replaced,
+            "l_simp_chinese:\n  key_a:0 \"新\"\n  key_b: \"保持\""
+        );
+        assert!(replace_entry_value(content, "missing", "x").is_none());
+    }
+
+    #[test]
+    fn test_detect_renamed_keys() {
+        let old =
+            " old_name: \"Fungal Sanctuary\"\n dup_a: \"Same\"\n dup_b: \"Same\"\n kept: \"Kept\"";
+        let new = " new_name: \"Fungal Sanctuary\"\n dup_c: \"Same\"\n kept: \"Kept\"";
+        assert_eq!(
+            detect_renamed_keys(old, new),
+            [("old_name".to_string(), "new_name".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_changed_keys() {
+        let old = "l_english:\n key_a: \"Same\"\n key_b: \"Old\"\n key_c: \"Removed\"";
+        let new = "l_english:\n key_a: \"Same\"\n key_b:1 \"New\"\n key_d: \"Added\"";
+        let keys = changed_keys(old, new);
+        assert_eq!(keys, ["key_b", "key_d"]);
+        assert_eq!(
+            filter_entries(new, |k| keys.iter().any(|c| c == k)),
+            " key_b:1 \"New\"\n key_d: \"Added\""
+        );
+    }
+
+    #[test]
+    fn test_is_localisation_content() {
+        assert!(is_localisation_content("\u{FEFF}l_english:\n"));
+        assert!(is_localisation_content(
+            " key:0 \"Value\"\n other: \"Text\""
+        ));
+        // 普通的 YAML 配置文件
+        assert!(!is_localisation_content(
+            "settings:\n  enabled: true\n  name: \"x\"\n  size: 3\n  color: red"
+        ));
+        assert!(!is_localisation_content(""));
+    }
+
+    #[test]
+    fn test_tricky_keys() {
+        let keys = [
+            "pf.100.desc",
+            "pf.1.10.a",
+            "tech_ion-cannon",
+            "NAME_Gor'tak",
+            "schädel_event.2.a",
+            "事件_1",
+        ];
+        let content: String = keys
+            .iter()
+            .map(|key| format!(" {}:0 Text of {}\n", key, key))
+            .collect();
+        let fixed = crate::preprocess::fix_yaml_content(&content).unwrap();
+        assert_eq!(fixed.fixes.len(), keys.len());
+        let parsed: Vec<String> = parse_entries(&fixed.content)
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        assert_eq!(parsed, keys);
+
+        let pattern = regex::Regex::new(&format!("^{}$", KEY_PATTERN)).unwrap();
+        assert!(keys.iter().all(|key| pattern.is_match(key)));
+        assert!(keys.iter().all(|key| key.chars().all(is_key_char)));
+        assert!(!pattern.is_match("key with spaces") && !pattern.is_match("$var$"));
+    }
+}
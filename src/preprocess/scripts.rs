@@ -0,0 +1,323 @@
+//! 脚本检查模块
+//!
+//! 扫描 Mod 脚本（`common/`、`events/` 等目录中的 `.txt` 文件）：找出 `custom_tooltip`、`set_name`
+//! 等字段中直接写入的文本（这类文本没有对应的本地化键，无法通过本地化文件翻译，需要 Mod 作者改为引用本地化键），
+//! 以及将本地化键与脚本中的引用对照，找出未被使用的键与引用了但未定义的键。
+
+use super::{KEY_PATTERN, detect_lang_header, is_key_char, parse_entries};
+use crate::error::Result;
+use crate::utils::{cached_regex, find_localisation_files, read_file_with_bom};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+/// 取值会显示给玩家的脚本字段
+const TEXT_FIELDS: &[&str] = &[
+    "custom_tooltip",
+    "custom_tooltip_fail",
+    "custom_tooltip_success",
+    "set_name",
+];
+
+/// 取值为本地化键的脚本字段
+const KEY_FIELDS: &[&str] = &[
+    "title",
+    "desc",
+    "name",
+    "text",
+    "tooltip",
+    "custom_tooltip",
+    "custom_tooltip_fail",
+    "custom_tooltip_success",
+    "set_name",
+];
+
+/// 游戏按对象 ID 加后缀查找的本地化键，例如科技 `tech_x` 的说明为 `tech_x_desc`
+const IMPLICIT_SUFFIXES: &[&str] = &[
+    "_desc",
+    "_name",
+    "_plural",
+    "_adj",
+    "_title",
+    "_tooltip",
+    "_effect",
+    "_effect_desc",
+];
+
+/// 脚本中硬编码的文本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptLiteral {
+    /// 脚本文件路径
+    pub file: PathBuf,
+    /// 行号（从 1 开始）
+    pub line: usize,
+    /// 字段名
+    pub field: String,
+    /// 引号内的文本
+    pub text: String,
+}
+
+impl Display for ScriptLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} uses literal text \"{}\" instead of a localisation key",
+            self.file.display(),
+            self.line,
+            self.field,
+            self.text
+        )
+    }
+}
+
+/// 扫描一段脚本内容，返回硬编码文本所在的行号、字段名与文本
+///
+/// 引号内只含键名字符（字母、数字、`_`、`.`、`-`）的值视为本地化键的引用，不会报告。
+pub fn scan_script_content(content: &str) -> Vec<(usize, String, String)> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = cached_regex(&PATTERN, || {
+        format!(r#"\b({})\s*=\s*"([^"]*)""#, TEXT_FIELDS.join("|"))
+    });
+
+    let mut found = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let code = strip_comment(line);
+        for caps in pattern.captures_iter(code) {
+            let text = &caps[2];
+            if text.is_empty() || is_key_like(text) {
+                continue;
+            }
+            found.push((i + 1, caps[1].to_string(), text.to_string()));
+        }
+    }
+    found
+}
+
+/// 去除引号之外的 `#` 注释
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 值是否像本地化键名
+fn is_key_like(text: &str) -> bool {
+    text.chars().all(is_key_char)
+}
+
+/// Mod 目录下的所有脚本文件，`localisation` 目录除外
+fn script_files(mod_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(mod_dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "localisation")
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// 扫描 Mod 目录下的所有脚本文件，`localisation` 目录除外
+pub fn scan_scripts(mod_dir: &Path) -> Result<Vec<ScriptLiteral>> {
+    let mut literals = Vec::new();
+    for file in script_files(mod_dir) {
+        let content = read_file_with_bom(&file)?;
+        for (line, field, text) in scan_script_content(&content) {
+            literals.push(ScriptLiteral {
+                file: file.clone(),
+                line,
+                field,
+                text,
+            });
+        }
+    }
+    Ok(literals)
+}
+
+/// 本地化键及其所在位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyLocation {
+    /// 键名
+    pub key: String,
+    /// 定义或引用所在的文件
+    pub file: PathBuf,
+    /// 行号（从 1 开始）
+    pub line: usize,
+}
+
+/// 本地化键的使用情况
+#[derive(Debug, Default)]
+pub struct KeyUsage {
+    /// 定义了但脚本与其他条目中都没有引用的键（位置为定义处），可以不翻译
+    pub orphans: Vec<KeyLocation>,
+    /// 脚本中引用了但没有定义的键（位置为第一次引用处）
+    pub missing: Vec<KeyLocation>,
+}
+
+/// 对照 Mod 的本地化键与脚本中的引用
+///
+/// 本地化键取自 `<mod_dir>/localisation` 中源语言的文件。脚本中作为标识符出现的键、去除
+/// `_desc` 等后缀后作为标识符出现的键，以及在其他条目中以 `$key$` 引用的键都视为已使用。
+/// `title`、`desc`、`custom_tooltip` 等字段引用的键既没有在 Mod 中定义、`is_external` 也不认识时
+/// （通常用于查询游戏本体的本地化）视为缺失。结果基于文本匹配，只能作为参考。
+pub fn analyze_key_usage(
+    mod_dir: &Path,
+    source_lang: &str,
+    is_external: impl Fn(&str) -> bool,
+) -> Result<KeyUsage> {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    static VARIABLE: OnceLock<Regex> = OnceLock::new();
+    let token_pattern = cached_regex(&TOKEN, || KEY_PATTERN);
+    let field_pattern = cached_regex(&FIELD, || {
+        format!(
+            r#"\b({})\s*=\s*(?:"({key})"|({key}))"#,
+            KEY_FIELDS.join("|"),
+            key = KEY_PATTERN
+        )
+    });
+    let variable_pattern =
+        cached_regex(&VARIABLE, || format!(r"\$({})(?:\|[^$]*)?\$", KEY_PATTERN));
+
+    // 定义的键及其位置，以及在条目中引用的键
+    let mut defined: HashMap<String, KeyLocation> = HashMap::new();
+    let mut used: HashSet<String> = HashSet::new();
+    let localisation_dir = mod_dir.join("localisation");
+    if localisation_dir.is_dir() {
+        for file in find_localisation_files(&localisation_dir)? {
+            let content = read_file_with_bom(&file)?;
+            if detect_lang_header(&content).as_deref() != Some(source_lang) {
+                continue;
+            }
+            for entry in parse_entries(&content) {
+                for caps in variable_pattern.captures_iter(&entry.value) {
+                    used.insert(caps[1].to_string());
+                }
+                defined.entry(entry.key.clone()).or_insert(KeyLocation {
+                    key: entry.key,
+                    file: file.clone(),
+                    line: entry.line,
+                });
+            }
+        }
+    }
+
+    // 脚本中的标识符与字段引用的键
+    let mut missing: Vec<KeyLocation> = Vec::new();
+    for file in script_files(mod_dir) {
+        let content = read_file_with_bom(&file)?;
+        for (i, line) in content.lines().enumerate() {
+            let code = strip_comment(line);
+            used.extend(
+                token_pattern
+                    .find_iter(code)
+                    .map(|m| m.as_str().to_string()),
+            );
+            for caps in field_pattern.captures_iter(code) {
+                let Some(key) = caps.get(2).or_else(|| caps.get(3)).map(|m| m.as_str()) else {
+                    continue;
+                };
+                let is_value =
+                    matches!(key, "yes" | "no") || !key.chars().any(|c| c.is_ascii_alphabetic());
+                if is_value
+                    || defined.contains_key(key)
+                    || is_external(key)
+                    || missing.iter().any(|m| m.key == key)
+                {
+                    continue;
+                }
+                missing.push(KeyLocation {
+                    key: key.to_string(),
+                    file: file.clone(),
+                    line: i + 1,
+                });
+            }
+        }
+    }
+
+    let mut orphans: Vec<KeyLocation> = defined
+        .into_values()
+        .filter(|location| {
+            let key = &location.key;
+            !used.contains(key)
+                && !IMPLICIT_SUFFIXES.iter().any(|suffix| {
+                    key.strip_suffix(suffix)
+                        .is_some_and(|base| used.contains(base))
+                })
+        })
+        .collect();
+    orphans.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    Ok(KeyUsage { orphans, missing })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_script_content() {
+        let content = r#"
+country_event = {
+    immediate = {
+        custom_tooltip = "Unlocks the Gaia terraforming project" # shown to players
+        custom_tooltip = pf_unlock_gaia_tooltip
+        custom_tooltip = "pf_unlock_gaia_tooltip"
+        set_name = "New Eden"
+        # custom_tooltip = "Commented out"
+        name = "Ignored field"
+    }
+}
+"#;
+        assert_eq!(
+            scan_script_content(content),
+            [
+                (
+                    4,
+                    "custom_tooltip".to_string(),
+                    "Unlocks the Gaia terraforming project".to_string()
+                ),
+                (7, "set_name".to_string(), "New Eden".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze_key_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let events = dir.path().join("events");
+        let english = dir.path().join("localisation/english");
+        std::fs::create_dir_all(&events).unwrap();
+        std::fs::create_dir_all(&english).unwrap();
+        std::fs::write(
+            events.join("events.txt"),
+            "country_event = {\n  id = pf.1\n  title = pf.1.name\n  desc = pf.1.desc\n  option = { name = OK }\n  custom_tooltip = pf_gone_tooltip\n  title = \"Literal title\"\n}\ntech_pf_gaia = { cost = 10 }\nship_event = { desc = schädel.1.desc desc = NAME_Gor'tak }",
+        )
+        .unwrap();
+        std::fs::write(
+            english.join("pf_l_english.yml"),
+            "l_english:\n  pf.1.name:0 \"Event $pf_var$\"\n  pf_var:0 \"var\"\n  tech_pf_gaia_desc:0 \"Gaia\"\n  pf_unused:0 \"Unused\"\n  schädel.1.desc:0 \"Skull\"\n  NAME_Gor'tak:0 \"Gor'tak\"",
+        )
+        .unwrap();
+
+        let usage = analyze_key_usage(dir.path(), "english", |key| key == "OK").unwrap();
+        let orphans: Vec<&str> = usage.orphans.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(orphans, ["pf_unused"]);
+        let missing: Vec<(&str, usize)> = usage
+            .missing
+            .iter()
+            .map(|m| (m.key.as_str(), m.line))
+            .collect();
+        assert_eq!(missing, [("pf.1.desc", 4), ("pf_gone_tooltip", 6)]);
+    }
+}
@@ -2,75 +2,498 @@
 //!
 //! 修复Stellaris本地化文件的YAML格式问题。
 
+use super::is_key_char;
 use crate::error::Result;
-use regex::Regex;
+
+/// 预处理修复规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixRule {
+    /// 值没有引号
+    MissingQuotes,
+    /// 值缺少开始引号
+    MissingOpeningQuote,
+    /// 值缺少结束引号
+    MissingClosingQuote,
+    /// 缩进不是 2 的倍数
+    Indentation,
+}
+
+impl std::fmt::Display for FixRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FixRule::MissingQuotes => "missing quotes",
+            FixRule::MissingOpeningQuote => "missing opening quote",
+            FixRule::MissingClosingQuote => "missing closing quote",
+            FixRule::Indentation => "indentation",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 一处预处理修复
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YamlFix {
+    /// 行号（从 1 开始）
+    pub line: usize,
+    /// 修复规则，同一行同时缺少引号与缩进有误时记为引号问题
+    pub rule: FixRule,
+    /// 修复前的行
+    pub before: String,
+    /// 修复后的行
+    pub after: String,
+}
+
+impl std::fmt::Display for YamlFix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}: {}: {:?} -> {:?}",
+            self.line, self.rule, self.before, self.after
+        )
+    }
+}
+
+/// 修复后的YAML内容
+#[derive(Debug)]
+pub struct FixedYaml {
+    /// 修复后的内容
+    pub content: String,
+    /// 实际应用的修复，追踪号的去除不计入（写入译文时会恢复）
+    pub fixes: Vec<YamlFix>,
+}
 
 /// 修复YAML内容
-pub fn fix_yaml_content(content: &str) -> Result<String> {
-    let fixed = content.to_string();
-    // 都是单行处理
-    // 处理如 key:0 value 的形式，不假定 value 存在或有完整的引号
-    let re_key_zero = Regex::new(r#"^(\w+):\d+\s+(.*)$"#).unwrap();
-    // 处理 key: value 的形式，value 可能有两个引号，或只有一侧有引号，或没有引号
-    let re_unquoted_value = Regex::new(r#"^(\w+):\s+"?([^"]*)"?$"#).unwrap();
-
-    let lines = fixed.lines().into_iter().map(|line| {
-        // 0. 跳过空行和注释行
-        if line.trim().is_empty() {
-            return "".into();
-        }
-        if line.trim_start().starts_with('#') {
-            return line.into();
+///
+/// 逐行判断行的类别（空行、注释、语言头、条目），只修复确实有问题的条目行：
+/// 去除追踪号（`key:0 "value"` 改为 `key: "value"`）、补全缺失的引号，并将缩进统一为 2 的倍数。
+/// 引号完整的值原样保留，其中的冒号、`#` 与引号不会被改动；无法识别的行与结束引号之后还有其他文本的行也原样保留。
+pub fn fix_yaml_content(content: &str) -> Result<FixedYaml> {
+    let mut fixes = Vec::new();
+    let lines: Vec<String> = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let (fixed, rule) = fix_line(line);
+            if let Some(rule) = rule {
+                let fix = YamlFix {
+                    line: i + 1,
+                    rule,
+                    before: line.to_string(),
+                    after: fixed.clone(),
+                };
+                log::debug!("Fixed {}", fix);
+                fixes.push(fix);
+            }
+            fixed
+        })
+        .collect();
+    Ok(FixedYaml {
+        content: lines.join("\n"),
+        fixes,
+    })
+}
+
+/// 修复单行内容，返回修复后的行与应用的修复规则
+fn fix_line(line: &str) -> (String, Option<FixRule>) {
+    let unchanged = (line.to_string(), None);
+    let trimmed = line.trim();
+    // 空行与注释
+    if trimmed.is_empty() {
+        return (String::new(), None);
+    }
+    if trimmed.starts_with('#') {
+        return unchanged;
+    }
+    // 语言头
+    if detect_lang_header(trimmed).is_some() {
+        return unchanged;
+    }
+    let Some((key, rest)) = trimmed.split_once(':') else {
+        return unchanged;
+    };
+    if key.is_empty() || !key.chars().all(is_key_char) {
+        return unchanged;
+    }
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit()).trim();
+    if rest.is_empty() {
+        return unchanged;
+    }
+    let (value, rule) = match rest.strip_prefix('"') {
+        Some(inner) => match inner.rfind('"') {
+            // 引号完整，其后可跟行尾注释
+            Some(end)
+                if inner[end + 1..].trim().is_empty()
+                    || inner[end + 1..].trim().starts_with('#') =>
+            {
+                (rest.to_string(), None)
+            }
+            // 结束引号之后还有其他文本（`key:0 "a" b`），无法判断引号应当加在哪里，原样保留
+            Some(_) => {
+                log::warn!(
+                    "Text after the closing quote, leaving the line unchanged: {:?}",
+                    line
+                );
+                return unchanged;
+            }
+            None => (format!("\"{}\"", inner), Some(FixRule::MissingClosingQuote)),
+        },
+        None => match rest.strip_suffix('"') {
+            Some(inner) => (format!("\"{}\"", inner), Some(FixRule::MissingOpeningQuote)),
+            None => (format!("\"{}\"", rest), Some(FixRule::MissingQuotes)),
+        },
+    };
+
+    // 缩进统一为 2 的倍数
+    let indent = line.len() - line.trim_start().len();
+    let rule = rule.or((!indent.is_multiple_of(2)).then_some(FixRule::Indentation));
+    let fixed = format!("{}{}: {}", " ".repeat(indent / 2 * 2), key, value);
+    (fixed, rule)
+}
+
+/// 源文件的语言头
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LangHeader {
+    /// 所在行号（从 1 开始）
+    pub line: usize,
+    /// 语言代码（小写）
+    pub lang: String,
+    /// 语言头所在行的原文
+    pub text: String,
+}
+
+/// 语言头的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderWarning {
+    /// 文件中没有语言头
+    Missing,
+    /// 语言头的写法不规范（大写字母、冒号前有空白），已按 `l_<语言>:` 处理
+    Variant { line: usize, text: String },
+    /// 语言头的语言与期望的语言不符
+    WrongLanguage {
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    /// 第一个语言头之后的其他语言头，已从内容中去除
+    Extra { line: usize, lang: String },
+    /// 文件中其他语言的语言块，已连同其中的条目一并去除
+    OtherBlock { line: usize, lang: String },
+}
+
+impl std::fmt::Display for HeaderWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderWarning::Missing => write!(f, "no language header"),
+            HeaderWarning::Variant { line, text } => {
+                write!(f, "line {}: non-standard language header {:?}", line, text)
+            }
+            HeaderWarning::WrongLanguage {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: language header is l_{}, expected l_{}",
+                line, found, expected
+            ),
+            HeaderWarning::Extra { line, lang } => {
+                write!(f, "line {}: removed extra language header l_{}", line, lang)
+            }
+            HeaderWarning::OtherBlock { line, lang } => {
+                write!(f, "line {}: skipped the l_{} block", line, lang)
+            }
         }
-        // 1. 修复 `key:0 "value"` 格式
-        let fixed = re_key_zero.replace(line, r#"$1: $2"#);
-        // 2. 确保所有值都有引号
-        let fixed = re_unquoted_value.replace(&fixed, r#"$1: "$2""#);
-        // 3. 标准化缩进（2空格）
-        let trimmed = fixed.trim_start();
-        let indent_level = fixed.len() - trimmed.len();
-        let spaces = indent_level / 2 * 2; // 确保是2的倍数
-        let fixed = format!("{}{}", " ".repeat(spaces), trimmed);
-        fixed
-    });
-    let lines: Vec<String> = lines.collect();
-    Ok(lines.join("\n"))
-}
-
-/// 移除YAML内容中的语言头(如 l_english:)，并返回原始头和去除头后的内容
+    }
+}
+
+/// 去除语言头后的内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrimmedContent {
+    /// 第一个语言头，没有语言头时为 None
+    pub header: Option<LangHeader>,
+    /// 去除所有语言头与缩进后的内容
+    pub content: String,
+    /// `content` 中每一行在原内容中的行号（从 1 开始）
+    pub lines: Vec<usize>,
+    /// 语言头的问题
+    pub warnings: Vec<HeaderWarning>,
+}
+
+/// 移除YAML内容中的语言头(如 l_english:)，并返回第一个语言头、去除语言头后的内容与语言头的问题
+///
+/// 语言头之前可以有空行与注释；大小写或空白不规范的语言头（如 `L_English :`）同样识别。
+/// 文件包含多种语言的语言块且其中有 `lang` 的语言块时，只保留 `lang` 的语言块（以及第一个语言头之前的注释），
+/// 其他语言块连同其中的条目一并去除；没有 `lang` 的语言块时，语言不符的语言头和多余的语言头会被去除。
+/// 两种情况都会在 `warnings` 中报告。
 /// 去除头后的内容会去掉所有缩进
-pub fn trim_lang_header(lang: &str, fixed_content: &str) -> (String, String) {
-    let mut lines: Vec<String> = fixed_content.lines().map(String::from).collect();
-    let mut original_header = String::new();
-
-    // 查找第一个非注释行
-    let mut header_index = None;
-    for (i, line) in lines.iter().enumerate() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue; // 跳过空行
+pub fn trim_lang_header(lang: &str, fixed_content: &str) -> TrimmedContent {
+    let header_langs: Vec<String> = fixed_content
+        .lines()
+        .filter_map(|line| parse_lang_header(line).map(|(found, _)| found))
+        .collect();
+    let select_block = header_langs.iter().any(|found| found == lang)
+        && header_langs.iter().any(|found| found != lang);
+
+    let mut header: Option<LangHeader> = None;
+    let mut warnings = Vec::new();
+    let mut lines = Vec::new();
+    let mut line_numbers = Vec::new();
+    // 当前行所在的语言块是否保留
+    let mut keep = true;
+    for (i, line) in fixed_content.lines().enumerate() {
+        let line_number = i + 1;
+        let Some((found, standard)) = parse_lang_header(line) else {
+            if keep {
+                lines.push(line.trim_start());
+                line_numbers.push(line_number);
+            }
+            continue;
+        };
+        if select_block {
+            keep = found == lang;
+            if !keep {
+                warnings.push(HeaderWarning::OtherBlock {
+                    line: line_number,
+                    lang: found,
+                });
+                continue;
+            }
         }
-        if trimmed.starts_with('#') {
-            continue; // 跳过注释行
+        if header.is_some() {
+            warnings.push(HeaderWarning::Extra {
+                line: line_number,
+                lang: found,
+            });
+            continue;
         }
-        // 检查是否是语言头
-        if trimmed.starts_with(&format!("l_{}:", lang)) {
-            header_index = Some(i);
-            break;
+        if !standard {
+            warnings.push(HeaderWarning::Variant {
+                line: line_number,
+                text: line.trim().to_string(),
+            });
         }
-        // 如果不是语言头，则停止查找
-        break;
+        if found != lang {
+            warnings.push(HeaderWarning::WrongLanguage {
+                line: line_number,
+                expected: lang.to_string(),
+                found: found.clone(),
+            });
+        }
+        header = Some(LangHeader {
+            line: line_number,
+            lang: found,
+            text: line.to_string(),
+        });
+    }
+    if header.is_none() {
+        warnings.push(HeaderWarning::Missing);
     }
 
-    if let Some(index) = header_index {
-        original_header = lines.remove(index);
+    TrimmedContent {
+        header,
+        content: lines.join("\n"),
+        lines: line_numbers,
+        warnings,
     }
+}
+
+/// 将源文件的语言头改写为目标语言，保留其后的追踪号与行尾注释
+///
+/// 例如 `l_english:0 # v1.2` 改写为 `l_simp_chinese:0 # v1.2`。缩进与 BOM 会被去除，
+/// 语言头的语言与源语言不符或为空时使用标准形式 `l_<目标语言>:`。
+pub fn rewrite_lang_header(original_header: &str, source_lang: &str, target_lang: &str) -> String {
+    match parse_lang_header(original_header) {
+        Some((lang, _)) if lang == source_lang => {
+            let suffix = original_header
+                .split_once(':')
+                .map(|(_, suffix)| suffix.trim_end())
+                .unwrap_or_default();
+            format!("l_{}:{}", target_lang, suffix)
+        }
+        _ => format!("l_{}:", target_lang),
+    }
+}
+
+/// 解析一行语言头，返回小写的语言代码与写法是否规范
+///
+/// 语言头后可以跟追踪号与行尾注释，例如 `l_english:0 # v1.2`。
+/// `L_English:`、`l_english :` 等写法也视为语言头，但不规范。
+pub(crate) fn parse_lang_header(line: &str) -> Option<(String, bool)> {
+    let line = line.trim_start_matches('\u{FEFF}').trim();
+    let (name, suffix) = line.split_once(':')?;
+    let suffix = suffix
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim();
+    if !suffix.is_empty() && !suffix.starts_with('#') {
+        return None;
+    }
+    let trimmed_name = name.trim_end();
+    let lang = trimmed_name
+        .strip_prefix("l_")
+        .or_else(|| trimmed_name.strip_prefix("L_"))?;
+    if lang.is_empty() || !lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let standard = trimmed_name == name
+        && name.starts_with("l_")
+        && !lang.chars().any(|c| c.is_ascii_uppercase());
+    Some((lang.to_ascii_lowercase(), standard))
+}
 
-    // 去除所有缩进
-    let content_without_header = lines
-        .into_iter()
-        .map(|line| line.trim_start().to_string())
-        .collect::<Vec<String>>()
+/// 从内容中找到第一个语言头（如 `l_english:`）并返回语言代码，忽略开头的 BOM、空行与注释
+pub fn detect_lang_header(content: &str) -> Option<String> {
+    let line = content
+        .trim_start_matches('\u{FEFF}')
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+    parse_lang_header(line).map(|(lang, _)| lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_lang_header() {
+        // 跳过开头的 BOM、空行与注释，语言头可以带追踪号与行尾注释
+        assert_eq!(
+            detect_lang_header("\u{FEFF}\n# comment\nl_english:0 # v1.2\n key:0 \"value\"")
+                .as_deref(),
+            Some("english")
+        );
+        // 不规范的写法也能识别，语言代码转为小写
+        assert_eq!(
+            detect_lang_header("L_Simp_Chinese :\n").as_deref(),
+            Some("simp_chinese")
+        );
+        // 第一个有内容的行不是语言头时返回 None
+        assert_eq!(detect_lang_header(" key:0 \"value\"\nl_english:"), None);
+        assert_eq!(detect_lang_header("l_english: \"value\""), None);
+        assert_eq!(detect_lang_header("# only a comment\n"), None);
+    }
+
+    #[test]
+    fn test_rewrite_lang_header() {
+        let content = "\u{FEFF}# comment\nl_english:0 # v1.2\n key:0 \"value\"";
+        let trimmed = trim_lang_header("english", content.trim_start_matches('\u{FEFF}'));
+        let header = trimmed.header.unwrap().text;
+        assert_eq!(header, "l_english:0 # v1.2");
+        assert_eq!(trimmed.content, "# comment\nkey:0 \"value\"");
+        assert!(trimmed.warnings.is_empty());
+        assert_eq!(
+            rewrite_lang_header(&header, "english", "simp_chinese"),
+            "l_simp_chinese:0 # v1.2"
+        );
+        assert_eq!(
+            rewrite_lang_header("\u{FEFF}  l_english:", "english", "simp_chinese"),
+            "l_simp_chinese:"
+        );
+        assert_eq!(
+            rewrite_lang_header("", "english", "simp_chinese"),
+            "l_simp_chinese:"
+        );
+        assert_eq!(detect_lang_header(content).as_deref(), Some("english"));
+    }
+
+    #[test]
+    fn test_header_warnings() {
+        let content = "# comment\nL_English :\n key:0 \"value\"\nl_english:\n other:0 \"x\"";
+        let trimmed = trim_lang_header("english", content);
+        assert_eq!(trimmed.header.as_ref().map(|h| h.line), Some(2));
+        assert_eq!(trimmed.content, "# comment\nkey:0 \"value\"\nother:0 \"x\"");
+        assert_eq!(
+            trimmed.warnings,
+            [
+                HeaderWarning::Variant {
+                    line: 2,
+                    text: "L_English :".to_string()
+                },
+                HeaderWarning::Extra {
+                    line: 4,
+                    lang: "english".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            rewrite_lang_header("L_English :", "english", "simp_chinese"),
+            "l_simp_chinese:"
+        );
+
+        let trimmed = trim_lang_header("english", "l_french:\n key:0 \"value\"");
+        assert_eq!(
+            trimmed.warnings,
+            [HeaderWarning::WrongLanguage {
+                line: 1,
+                expected: "english".to_string(),
+                found: "french".to_string()
+            }]
+        );
+        let trimmed = trim_lang_header("english", "key:0 \"value\"");
+        assert_eq!(trimmed.warnings, [HeaderWarning::Missing]);
+        assert!(trimmed.header.is_none());
+    }
+
+    #[test]
+    fn test_language_blocks() {
+        let content = "# shared\nl_german:\n key_a:0 \"Hallo\"\nl_english:\n key_a:0 \"Hello\"\n key_b:0 \"Bye\"";
+        let trimmed = trim_lang_header("english", content);
+        assert_eq!(trimmed.header.map(|h| h.line), Some(4));
+        assert_eq!(
+            trimmed.content,
+            "# shared\nkey_a:0 \"Hello\"\nkey_b:0 \"Bye\""
+        );
+        assert_eq!(trimmed.lines, [1, 5, 6]);
+        assert_eq!(
+            trimmed.warnings,
+            [HeaderWarning::OtherBlock {
+                line: 2,
+                lang: "german".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_fix_yaml_content() {
+        let content = [
+            "# comment: \"kept\"",
+            "l_english:",
+            "key_a:0 \"Value: with colon # and hash\" # note",
+            "key_b: \"Say \"hi\" now\"",
+            "key_c: unquoted value",
+            "key_d:1 \"missing end",
+            "key_e: missing start\"",
+            "   key.f: \"odd indent\"",
+            "not an entry",
+            "key_g:0 \"a\" b",
+        ]
+        .join("\n");
+        let expected = [
+            "# comment: \"kept\"",
+            "l_english:",
+            "key_a: \"Value: with colon # and hash\" # note",
+            "key_b: \"Say \"hi\" now\"",
+            "key_c: \"unquoted value\"",
+            "key_d: \"missing end\"",
+            "key_e: \"missing start\"",
+            "  key.f: \"odd indent\"",
+            "not an entry",
+            // 结束引号之后还有其他文本，原样保留而不是再补一个引号
+            "key_g:0 \"a\" b",
+        ]
         .join("\n");
-    (original_header, content_without_header)
+        let fixed = fix_yaml_content(&content).unwrap();
+        assert_eq!(fixed.content, expected);
+        let rules: Vec<(usize, FixRule)> = fixed.fixes.iter().map(|f| (f.line, f.rule)).collect();
+        assert_eq!(
+            rules,
+            [
+                (5, FixRule::MissingQuotes),
+                (6, FixRule::MissingClosingQuote),
+                (7, FixRule::MissingOpeningQuote),
+                (8, FixRule::Indentation),
+            ]
+        );
+        assert_eq!(fixed.fixes[0].before, "key_c: unquoted value");
+    }
 }
@@ -0,0 +1,149 @@
+//! 交互式审校
+//!
+//! 在终端中逐条展示被标记的条目，由用户选择接受、编辑、重新翻译或跳过。
+
+use super::{ReviewItem, write_entry};
+use crate::error::Result;
+use crate::preprocess::parse_entries;
+use crate::translate::{FileChunk, FormatValidator, MemoryOrigin, TranslationMemory, Translator};
+use std::io::{BufRead, Write};
+
+/// 审校统计
+#[derive(Debug, Default)]
+pub struct ReviewSummary {
+    /// 接受的条目数
+    pub accepted: usize,
+    /// 修改的条目数（编辑或重新翻译后接受）
+    pub changed: usize,
+    /// 跳过的条目数
+    pub skipped: usize,
+}
+
+/// 逐条交互审校
+///
+/// `translator` 为 None 时不提供重新翻译选项（例如未配置 API 密钥）。
+/// 每个接受的决定都会立即写回目标文件，并以人工来源记入翻译记忆。
+pub async fn run_interactive_review<R: BufRead, W: Write>(
+    items: &[ReviewItem],
+    memory: &mut TranslationMemory,
+    translator: Option<&Translator>,
+    input: &mut R,
+    output: &mut W,
+) -> Result<ReviewSummary> {
+    let validator = FormatValidator::new();
+    let mut summary = ReviewSummary::default();
+
+    'items: for (i, item) in items.iter().enumerate() {
+        let mut candidate = item.target.clone();
+        let mut problems: Vec<String> = item.problems.iter().map(|p| p.to_string()).collect();
+
+        loop {
+            writeln!(output)?;
+            writeln!(
+                output,
+                "[{}/{}] {} | {} | {}",
+                i + 1,
+                items.len(),
+                item.target_lang,
+                item.target_file.display(),
+                item.key
+            )?;
+            for problem in &problems {
+                writeln!(output, "  ! {}", problem)?;
+            }
+            writeln!(output, "  Source: {}", item.source)?;
+            writeln!(output, "  Target: {}", candidate)?;
+            if translator.is_some() {
+                write!(output, "[a]ccept  [e]dit  [r]etranslate  [s]kip  [q]uit > ")?;
+            } else {
+                write!(output, "[a]ccept  [e]dit  [s]kip  [q]uit > ")?;
+            }
+            output.flush()?;
+
+            let Some(command) = read_line(input)? else {
+                break 'items;
+            };
+            match command.trim() {
+                "a" => {
+                    if candidate != item.target {
+                        write_entry(&item.target_file, &item.key, &candidate)?;
+                        summary.changed += 1;
+                    } else {
+                        summary.accepted += 1;
+                    }
+                    memory.record(
+                        &item.source_lang,
+                        &item.target_lang,
+                        &item.source,
+                        &candidate,
+                        MemoryOrigin::Human,
+                    );
+                    continue 'items;
+                }
+                "e" => {
+                    write!(output, "New translation > ")?;
+                    output.flush()?;
+                    let Some(edited) = read_line(input)? else {
+                        break 'items;
+                    };
+                    candidate = edited.trim().to_string();
+                }
+                "r" if let Some(translator) = translator => {
+                    match retranslate(translator, item).await {
+                        Ok(value) => candidate = value,
+                        Err(e) => {
+                            writeln!(output, "  Retranslation failed: {}", e)?;
+                            continue;
+                        }
+                    }
+                }
+                "s" => {
+                    summary.skipped += 1;
+                    continue 'items;
+                }
+                "q" => break 'items,
+                _ => {
+                    writeln!(output, "  Unknown command")?;
+                    continue;
+                }
+            }
+            problems = validator
+                .validate_entry(&item.key, &item.source, &candidate)
+                .iter()
+                .map(|p| p.to_string())
+                .collect();
+        }
+    }
+
+    Ok(summary)
+}
+
+/// 读取一行输入，输入结束时返回 None
+fn read_line<R: BufRead>(input: &mut R) -> Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line))
+}
+
+/// 单独重新翻译一个条目
+async fn retranslate(translator: &Translator, item: &ReviewItem) -> Result<String> {
+    let chunk = FileChunk {
+        content: format!("{}: \"{}\"", item.key, item.source),
+        start_line: 1,
+        end_line: 1,
+        target_filename: item.target_file.display().to_string(),
+        section: None,
+        preceding: Vec::new(),
+        following: Vec::new(),
+    };
+    let slice = translator
+        .translate_chunk(&chunk, &item.source_lang, &item.target_lang)
+        .await?;
+    Ok(parse_entries(&slice.content)
+        .into_iter()
+        .find(|e| e.key == item.key)
+        .map(|e| e.value)
+        .unwrap_or_else(|| slice.content.trim().to_string()))
+}
@@ -0,0 +1,232 @@
+//! 审校模块
+//!
+//! 收集翻译结果中被验证器标记的条目（标记不一致、长度异常、缺失），供人工审校，
+//! 并将审校决定写回目标文件与翻译记忆。
+
+mod interactive;
+mod spreadsheet;
+
+pub use interactive::*;
+pub use spreadsheet::*;
+
+use crate::config::TranslationTask;
+use crate::error::{Result, ResultExt};
+use crate::postprocess::{output_parts, read_translated_output, write_translated_file};
+use crate::preprocess::{parse_entries, remove_entries, replace_entry_value, replace_entry_values};
+use crate::translate::{FormatValidator, Problem, ValidationIgnore};
+use crate::utils::read_file_with_bom;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 待审校的条目
+#[derive(Debug)]
+pub struct ReviewItem {
+    /// 源语言
+    pub source_lang: String,
+    /// 目标语言
+    pub target_lang: String,
+    /// 目标文件路径
+    pub target_file: PathBuf,
+    /// 键名
+    pub key: String,
+    /// 原文
+    pub source: String,
+    /// 当前译文（缺失时为空）
+    pub target: String,
+    /// 验证发现的问题
+    pub problems: Vec<Problem>,
+}
+
+/// 遍历任务的所有目标文件，收集存在问题的条目
+pub fn collect_review_items(task: &TranslationTask) -> Result<Vec<ReviewItem>> {
+    collect_entries(task, true)
+}
+
+/// 遍历任务的所有目标文件，收集条目及其验证问题
+///
+/// `flagged_only` 为 true 时只返回存在问题的条目。
+pub fn collect_entries(task: &TranslationTask, flagged_only: bool) -> Result<Vec<ReviewItem>> {
+    let validator = FormatValidator::for_task(task);
+    let ignore = ValidationIgnore::for_task(task)?;
+    let source = task.source()?;
+    let source_files = task.source_files_in(source.as_ref())?;
+    let mut items = Vec::new();
+
+    for target_lang in &task.target_langs {
+        for source_file in &source_files {
+            let source_lang = task.file_source_lang(source_file);
+            if source_lang == target_lang {
+                continue;
+            }
+            let target_file = task.target_file(source_file, target_lang);
+            let Some(target) = read_translated_output(&target_file)? else {
+                continue;
+            };
+
+            let source = source.read_file(source_file)?;
+            let translated: HashMap<String, String> = parse_entries(&target)
+                .into_iter()
+                .map(|e| (e.key, e.value))
+                .collect();
+
+            for entry in parse_entries(&source) {
+                let (target_value, mut problems) = match translated.get(&entry.key) {
+                    Some(value) => (
+                        value.clone(),
+                        validator.validate_entry(&entry.key, &entry.value, value),
+                    ),
+                    None => (
+                        String::new(),
+                        vec![Problem::MissingKey {
+                            key: entry.key.clone(),
+                        }],
+                    ),
+                };
+                ignore.retain(&[source_file, &target_file], &mut problems);
+                if flagged_only && problems.is_empty() {
+                    continue;
+                }
+                items.push(ReviewItem {
+                    source_lang: source_lang.to_string(),
+                    target_lang: target_lang.clone(),
+                    target_file: target_file.clone(),
+                    key: entry.key,
+                    source: entry.value,
+                    target: target_value,
+                    problems,
+                });
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// 将条目的新译文写回目标文件，键不存在时追加到文件末尾
+///
+/// 译文拆分为多个文件写入时，写入包含该键的部分，键不存在时追加到最后一部分。
+pub fn write_entry(target_file: &Path, key: &str, value: &str) -> Result<()> {
+    write_entry_to_parts(target_file, key, value)
+        .in_file(target_file)
+        .for_key(key)
+}
+
+fn write_entry_to_parts(target_file: &Path, key: &str, value: &str) -> Result<()> {
+    let mut parts = output_parts(target_file);
+    if parts.is_empty() {
+        parts.push(target_file.to_path_buf());
+    }
+    for part in &parts {
+        let content = read_file_with_bom(part)?;
+        if let Some(updated) = replace_entry_value(&content, key, value) {
+            return write_translated_file(&updated, part, false);
+        }
+    }
+    let last = &parts[parts.len() - 1];
+    let content = read_file_with_bom(last)?;
+    let updated = format!("{}\n  {}: \"{}\"", content.trim_end(), key, value);
+    write_translated_file(&updated, last, false)
+}
+
+/// 将多个条目（键，译文）的新译文一次写回目标文件，效果与依次调用 [`write_entry`] 相同
+///
+/// 每个部分只读写一次：合并大量条目时不必为每个条目重新读写整个文件。
+pub fn write_entries(target_file: &Path, entries: &[(String, String)]) -> Result<()> {
+    write_entries_to_parts(target_file, entries).in_file(target_file)
+}
+
+fn write_entries_to_parts(target_file: &Path, entries: &[(String, String)]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut parts = output_parts(target_file);
+    if parts.is_empty() {
+        parts.push(target_file.to_path_buf());
+    }
+    // 同一个键出现多次时以最后一次为准
+    let mut pending: HashMap<String, String> = entries.iter().cloned().collect();
+    // 各部分的内容与是否有改动
+    let mut contents = Vec::new();
+    for part in &parts {
+        let (updated, replaced) = replace_entry_values(&read_file_with_bom(part)?, &pending);
+        pending.retain(|key, _| !replaced.contains(key));
+        contents.push((updated, !replaced.is_empty()));
+    }
+    // 不存在的键追加到最后一部分
+    if !pending.is_empty() {
+        let (content, changed) = contents.last_mut().expect("at least one part");
+        *content = content.trim_end().to_string();
+        for (key, _) in entries {
+            if let Some(value) = pending.remove(key) {
+                content.push_str(&format!("\n  {}: \"{}\"", key, value));
+            }
+        }
+        *changed = true;
+    }
+    for (part, (content, changed)) in parts.iter().zip(contents) {
+        if changed {
+            write_translated_file(&content, part, false)?;
+        }
+    }
+    Ok(())
+}
+
+/// 从目标文件（及其拆分写入的各部分）中删除键在 `keys` 中的条目，只写回有改动的部分
+pub fn remove_keys(target_file: &Path, keys: &[String]) -> Result<()> {
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let mut parts = output_parts(target_file);
+    if parts.is_empty() {
+        parts.push(target_file.to_path_buf());
+    }
+    for part in &parts {
+        let content = read_file_with_bom(part).in_file(part)?;
+        let updated = remove_entries(&content, |key| keys.iter().any(|k| k == key));
+        if updated.trim_end() != content.trim_end() {
+            write_translated_file(&updated, part, false)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("a_l_simp_chinese.yml");
+        std::fs::write(
+            &target,
+            "\u{feff}l_simp_chinese:\n a:0 \"旧 A\"\n # 注释\n b:1 \"旧 B\"\n",
+        )
+        .unwrap();
+        let entries = [("b", "新 B"), ("c", "新 C"), ("a", "新 A")]
+            .map(|(k, v)| (k.to_string(), v.to_string()));
+        write_entries(&target, &entries).unwrap();
+        assert_eq!(
+            read_file_with_bom(&target).unwrap(),
+            "l_simp_chinese:\n a:0 \"新 A\"\n # 注释\n b:1 \"新 B\"\n  c: \"新 C\""
+        );
+    }
+
+    #[test]
+    fn test_remove_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("a_l_simp_chinese.yml");
+        std::fs::write(
+            &target,
+            "\u{feff}l_simp_chinese:\n old_key:0 \"译文\"\n # 注释\n b:0 \"B\"\n",
+        )
+        .unwrap();
+        // 译文转移到重命名后的键，旧键从目标文件中删除
+        write_entries(&target, &[("new_key".to_string(), "译文".to_string())]).unwrap();
+        remove_keys(&target, &["old_key".to_string()]).unwrap();
+        assert_eq!(
+            read_file_with_bom(&target).unwrap(),
+            "l_simp_chinese:\n # 注释\n b:0 \"B\"\n  new_key: \"译文\""
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! 双语审校表格
+//!
+//! 将条目导出为 CSV 表格（语言、文件、键、原文、译文、问题）供校对人员编辑，
+//! 并将编辑后的译文合并回目标文件。
+
+use super::{ReviewItem, write_entry};
+use crate::error::{PostprocessError, Result, TranslationError};
+use crate::preprocess::parse_entries;
+use crate::translate::{MemoryOrigin, TranslationMemory};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 审校表格中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRow {
+    pub source_lang: String,
+    pub target_lang: String,
+    pub file: PathBuf,
+    pub key: String,
+    pub source: String,
+    pub target: String,
+    /// 验证问题，多个问题以换行分隔；导入时忽略
+    #[serde(default)]
+    pub issues: String,
+}
+
+impl From<&ReviewItem> for ReviewRow {
+    fn from(item: &ReviewItem) -> Self {
+        Self {
+            source_lang: item.source_lang.clone(),
+            target_lang: item.target_lang.clone(),
+            file: item.target_file.clone(),
+            key: item.key.clone(),
+            source: item.source.clone(),
+            target: item.target.clone(),
+            issues: item
+                .problems
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+fn sheet_error(e: impl std::fmt::Display) -> TranslationError {
+    TranslationError::Postprocess(PostprocessError::ReviewSheet(e.to_string()))
+}
+
+/// 导出审校表格，文件以 UTF-8 with BOM 编码以便电子表格软件正确识别中文
+pub fn export_review_sheet(items: &[ReviewItem], output: &Path) -> Result<usize> {
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::File::create(output)?;
+    std::io::Write::write_all(&mut file, "\u{FEFF}".as_bytes())?;
+    let mut writer = csv::Writer::from_writer(file);
+    for item in items {
+        writer
+            .serialize(ReviewRow::from(item))
+            .map_err(sheet_error)?;
+    }
+    writer.flush()?;
+    Ok(items.len())
+}
+
+/// 读取审校表格
+pub fn read_review_sheet(input: &Path) -> Result<Vec<ReviewRow>> {
+    let content = std::fs::read_to_string(input)?;
+    let mut reader = csv::Reader::from_reader(content.trim_start_matches('\u{FEFF}').as_bytes());
+    reader
+        .deserialize()
+        .collect::<std::result::Result<Vec<ReviewRow>, _>>()
+        .map_err(sheet_error)
+}
+
+/// 将审校表格中修改过的译文写回目标文件，并以人工来源记入翻译记忆
+///
+/// 返回实际修改的条目数。译文未变化的行只记入翻译记忆。
+pub fn import_review_sheet(rows: &[ReviewRow], memory: &mut TranslationMemory) -> Result<usize> {
+    let mut by_file: HashMap<&Path, Vec<&ReviewRow>> = HashMap::new();
+    for row in rows {
+        by_file.entry(row.file.as_path()).or_default().push(row);
+    }
+
+    let mut changed = 0;
+    for (file, rows) in by_file {
+        if !file.exists() {
+            log::warn!("Target file in review sheet does not exist: {:?}", file);
+            continue;
+        }
+        let content = std::fs::read_to_string(file)?;
+        let current: HashMap<String, String> = parse_entries(&content)
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+
+        for row in rows {
+            if row.target.trim().is_empty() {
+                continue;
+            }
+            if current.get(&row.key) != Some(&row.target) {
+                write_entry(file, &row.key, &row.target)?;
+                changed += 1;
+            }
+            memory.record(
+                &row.source_lang,
+                &row.target_lang,
+                &row.source,
+                &row.target,
+                MemoryOrigin::Human,
+            );
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_sheet_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("l_simp_chinese_a.yml");
+        std::fs::write(
+            &target,
+            "\u{FEFF}l_simp_chinese:\n  key_a:0 \"旧译文\"\n  key_b: \"保持\"",
+        )
+        .unwrap();
+
+        let items = vec![ReviewItem {
+            source_lang: "english".to_string(),
+            target_lang: "simp_chinese".to_string(),
+            target_file: target.clone(),
+            key: "key_a".to_string(),
+            source: "Old, \"quoted\"\ntext".to_string(),
+            target: "旧译文".to_string(),
+            problems: vec![],
+        }];
+        let sheet = dir.path().join("review.csv");
+        export_review_sheet(&items, &sheet).unwrap();
+
+        let mut rows = read_review_sheet(&sheet).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].source, "Old, \"quoted\"\ntext");
+        rows[0].target = "新译文".to_string();
+
+        let mut memory = TranslationMemory::default();
+        assert_eq!(import_review_sheet(&rows, &mut memory).unwrap(), 1);
+        let content = std::fs::read_to_string(&target).unwrap();
+        assert_eq!(
+            content,
+            "\u{FEFF}l_simp_chinese:\n  key_a:0 \"新译文\"\n  key_b: \"保持\""
+        );
+        assert_eq!(memory.len(), 1);
+    }
+}
@@ -0,0 +1,159 @@
+//! Mod 压缩包
+
+use super::LocalisationSource;
+use crate::error::{Result, TranslationError};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// zip 压缩包中的本地化文件
+///
+/// 打开时只解压本地化目录下的 YAML 文件并读入内存（本地化文件通常只有几 MB），贴图、音乐等其余文件不会被读取，
+/// 之后不再访问压缩包。同一进程中再次打开未改动的压缩包时直接复用已读取的内容。
+#[derive(Debug, Clone)]
+pub struct ArchiveSource {
+    archive: PathBuf,
+    /// 相对于本地化根目录的路径 -> 文件内容
+    files: Arc<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+/// 已打开的压缩包：（路径，大小，修改时间） -> 读取的文件
+type OpenedArchives = BTreeMap<(PathBuf, u64, Option<SystemTime>), Arc<BTreeMap<PathBuf, Vec<u8>>>>;
+
+/// 本进程中已打开的压缩包，任务的检测、验证与翻译各自打开来源时不必重复解压
+static OPENED: Mutex<OpenedArchives> = Mutex::new(BTreeMap::new());
+
+/// 压缩包中的路径相对于本地化根目录的部分：第一层名为 `localisation` 的目录之下，没有该目录时为 None
+fn localisation_relative(path: &Path) -> Option<PathBuf> {
+    path.components()
+        .position(|c| c == Component::Normal("localisation".as_ref()))
+        .map(|i| path.components().skip(i + 1).collect::<PathBuf>())
+}
+
+impl ArchiveSource {
+    /// 打开压缩包
+    ///
+    /// 本地化根目录为第一层名为 `localisation` 的目录（可以位于 Mod 名称的目录之下），
+    /// 压缩包中没有该目录时以压缩包根目录为本地化根目录。
+    pub fn open(archive: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(archive)?;
+        let key = (
+            archive.to_path_buf(),
+            metadata.len(),
+            metadata.modified().ok(),
+        );
+        let mut opened = OPENED.lock().unwrap_or_else(|e| e.into_inner());
+        let files = match opened.get(&key) {
+            Some(files) => files.clone(),
+            None => {
+                let files = Arc::new(read_localisation(archive)?);
+                opened.insert(key, files.clone());
+                files
+            }
+        };
+        Ok(Self {
+            archive: archive.to_path_buf(),
+            files,
+        })
+    }
+}
+
+/// 只解压压缩包中本地化根目录下的 YAML 文件
+fn read_localisation(archive: &Path) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+    let zip_error = |e: zip::result::ZipError| {
+        TranslationError::Io(std::io::Error::other(format!(
+            "Failed to read {:?}: {}",
+            archive, e
+        )))
+    };
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(archive)?).map_err(zip_error)?;
+    // 先只读取文件名，确定本地化根目录与需要解压的文件
+    let mut entries = Vec::new();
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i).map_err(zip_error)?;
+        if file.is_dir() {
+            continue;
+        }
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+        if path
+            .extension()
+            .is_some_and(|ext| ext == "yml" || ext == "yaml")
+        {
+            entries.push((i, path));
+        }
+    }
+    let nested = entries
+        .iter()
+        .any(|(_, path)| localisation_relative(path).is_some());
+
+    let mut files = BTreeMap::new();
+    for (i, path) in entries {
+        let relative = if nested {
+            match localisation_relative(&path) {
+                Some(relative) => relative,
+                None => continue,
+            }
+        } else {
+            path
+        };
+        let mut content = Vec::new();
+        zip.by_index(i)
+            .map_err(zip_error)?
+            .read_to_end(&mut content)?;
+        files.insert(relative, content);
+    }
+    log::debug!("Read {} localisation files from {:?}", files.len(), archive);
+    Ok(files)
+}
+
+impl LocalisationSource for ArchiveSource {
+    fn origin(&self) -> &Path {
+        &self.archive
+    }
+
+    fn languages(&self) -> Result<Vec<String>> {
+        let mut langs: Vec<String> = self
+            .files
+            .keys()
+            .filter(|p| p.components().count() > 1)
+            .filter_map(|p| {
+                p.components()
+                    .next()?
+                    .as_os_str()
+                    .to_str()
+                    .map(String::from)
+            })
+            .filter(|name| name != "replace")
+            .collect();
+        langs.dedup();
+        Ok(langs)
+    }
+
+    fn list_files(&self, lang: &str) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for path in self.files.keys().filter(|p| p.starts_with(lang)) {
+            if !path
+                .extension()
+                .is_some_and(|ext| ext == "yml" || ext == "yaml")
+            {
+                continue;
+            }
+            if crate::preprocess::is_localisation_content(&self.read_file(path)?) {
+                files.push(path.clone());
+            } else {
+                log::warn!("Skipping {:?}: not a localisation file", path);
+            }
+        }
+        Ok(files)
+    }
+
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            TranslationError::FileNotFound(format!("{:?} not found in {:?}", path, self.archive))
+        })
+    }
+}
@@ -0,0 +1,50 @@
+//! 磁盘上的本地化目录
+
+use super::LocalisationSource;
+use crate::error::Result;
+use futures::future::BoxFuture;
+use std::path::{Path, PathBuf};
+
+/// 磁盘上的本地化目录，其下每种语言一个子目录
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    /// 以本地化目录（例如 `<Mod>/localisation`）创建来源
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl LocalisationSource for DirectorySource {
+    fn origin(&self) -> &Path {
+        &self.root
+    }
+
+    fn languages(&self) -> Result<Vec<String>> {
+        let mut langs: Vec<String> = std::fs::read_dir(&self.root)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(String::from))
+            .filter(|name| name != "replace")
+            .collect();
+        langs.sort();
+        Ok(langs)
+    }
+
+    fn list_files(&self, lang: &str) -> Result<Vec<PathBuf>> {
+        crate::utils::find_localisation_files(&self.root.join(lang))
+    }
+
+    fn list_files_parallel<'a>(&'a self, lang: &'a str) -> BoxFuture<'a, Result<Vec<PathBuf>>> {
+        Box::pin(
+            async move { crate::utils::find_localisation_files_async(&self.root.join(lang)).await },
+        )
+    }
+
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        Ok(std::fs::read(path)?)
+    }
+}
@@ -0,0 +1,158 @@
+//! 补充缺少的键的本地化来源
+
+use super::LocalisationSource;
+use crate::error::Result;
+use crate::preprocess::{parse_entries, parse_entry_line};
+use futures::future::BoxFuture;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// 在另一个来源之上，读取源文件时从其他语言版本中补充缺少的键
+///
+/// 例如以中文编写、只有部分英文的 Mod：中文文件中没有的键取自英文文件，追加在文件末尾。
+pub struct FallbackSource {
+    inner: Box<dyn LocalisationSource>,
+    /// 源文件 -> 按优先级排列的其他语言版本
+    fallbacks: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl FallbackSource {
+    /// 以 `fallbacks`（源文件 -> 按优先级排列的其他语言版本）包装来源
+    pub fn new(
+        inner: Box<dyn LocalisationSource>,
+        fallbacks: BTreeMap<PathBuf, Vec<PathBuf>>,
+    ) -> Self {
+        Self { inner, fallbacks }
+    }
+}
+
+/// 将 `fallbacks` 中 `primary` 没有的条目按原样追加到 `primary` 末尾，同一个键只取第一个出现的版本
+pub fn merge_missing_entries(primary: &str, fallbacks: &[String]) -> String {
+    let mut keys: HashSet<String> = parse_entries(primary).into_iter().map(|e| e.key).collect();
+    let mut merged = primary.to_string();
+    for fallback in fallbacks {
+        for line in fallback.lines() {
+            let Some((key, _, _)) = parse_entry_line(line) else {
+                continue;
+            };
+            if keys.insert(key) {
+                if !merged.is_empty() && !merged.ends_with('\n') {
+                    merged.push('\n');
+                }
+                merged.push_str(line.trim_end());
+                merged.push('\n');
+            }
+        }
+    }
+    merged
+}
+
+impl LocalisationSource for FallbackSource {
+    fn origin(&self) -> &Path {
+        self.inner.origin()
+    }
+
+    fn languages(&self) -> Result<Vec<String>> {
+        self.inner.languages()
+    }
+
+    fn list_files(&self, lang: &str) -> Result<Vec<PathBuf>> {
+        self.inner.list_files(lang)
+    }
+
+    fn list_files_parallel<'a>(&'a self, lang: &'a str) -> BoxFuture<'a, Result<Vec<PathBuf>>> {
+        self.inner.list_files_parallel(lang)
+    }
+
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        self.inner.read_bytes(path)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<String> {
+        let content = self.inner.read_file(path)?;
+        let Some(files) = self.fallbacks.get(path) else {
+            return Ok(content);
+        };
+        let fallbacks = files
+            .iter()
+            .map(|file| self.inner.read_file(file))
+            .collect::<Result<Vec<_>>>()?;
+        let merged = merge_missing_entries(&content, &fallbacks);
+        if merged.len() != content.len() {
+            log::debug!("Filled missing keys of {:?} from {:?}", path, files);
+        }
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TranslationTask;
+
+    #[test]
+    fn test_fallback_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(
+            "simp_chinese/a_l_simp_chinese.yml",
+            "l_simp_chinese:\n a:0 \"甲\"\n",
+        );
+        write(
+            "english/a_l_english.yml",
+            "l_english:\n a:0 \"A\"\n b:0 \"B\" # note\n",
+        );
+        write("english/c_l_english.yml", "l_english:\n c:0 \"C\"\n");
+        // 上次运行的输出不是源文件
+        write(
+            "english/replace/a_l_english.yml",
+            "l_english:\n a:0 \"A\"\n z:0 \"Z\"\n",
+        );
+
+        let mut task: TranslationTask = toml::from_str(&format!(
+            "source_langs = [\"simp_chinese\", \"english\"]\ntarget_langs = [\"english\"]\nglossaries = []\nlocalisation_dir = {:?}",
+            dir.path()
+        ))
+        .unwrap();
+        task.resolve_fallback_langs().unwrap();
+        task.detect_source_langs().unwrap();
+        assert!(task.validate().is_ok());
+        assert_eq!(task.source_lang, "simp_chinese");
+
+        let primary = dir.path().join("simp_chinese/a_l_simp_chinese.yml");
+        let english_only = dir.path().join("english/c_l_english.yml");
+        assert_eq!(
+            task.source_files().unwrap(),
+            [english_only.clone(), primary.clone()]
+        );
+        assert_eq!(task.file_source_lang(&english_only), "english");
+        let source = task.source().unwrap();
+        assert_eq!(
+            source.read_file(&primary).unwrap(),
+            "l_simp_chinese:\n a:0 \"甲\"\n b:0 \"B\" # note\n"
+        );
+        assert_eq!(
+            source.read_file(&english_only).unwrap(),
+            "l_english:\n c:0 \"C\"\n"
+        );
+
+        // 同一个键只取优先级最高的版本
+        assert_eq!(
+            merge_missing_entries(
+                "l_german:\n a:0 \"A\"",
+                &[
+                    "l_french:\n b:0 \"Fb\"\n".to_string(),
+                    " b:0 \"Eb\"\n c:0 \"C\"\n".to_string()
+                ]
+            ),
+            "l_german:\n a:0 \"A\"\n b:0 \"Fb\"\n c:0 \"C\"\n"
+        );
+
+        task.source_lang = "english".to_string();
+        assert!(task.validate().is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! 本地化来源
+//!
+//! 翻译流程通过 [`LocalisationSource`] 查找与读取源语言文件，不关心文件实际存放在哪里：
+//! 可以是磁盘上的本地化目录，也可以是下载的 Mod 压缩包，无需事先手动解压。
+//! [`open_source`] 根据路径选择合适的来源。
+
+mod archive;
+mod directory;
+mod fallback;
+
+pub use archive::*;
+pub use directory::*;
+pub use fallback::*;
+
+use crate::error::{Result, TranslationError};
+use crate::steam::descriptor_value;
+use futures::future::BoxFuture;
+use std::path::{Path, PathBuf};
+
+/// 本地化文件的来源
+///
+/// 文件以来源内部的路径标识：目录来源中是磁盘上的路径，压缩包来源中是相对于本地化根目录的路径
+/// （例如 `english/a_l_english.yml`）。目标文件只取其中的文件名，两者可以同样使用。
+pub trait LocalisationSource: Send + Sync {
+    /// 来源在磁盘上的位置（目录或压缩包），用于日志
+    fn origin(&self) -> &Path;
+
+    /// 来源中的语言目录，按名称排序，不包含 `replace`
+    fn languages(&self) -> Result<Vec<String>>;
+
+    /// 一种语言的所有本地化文件，不是本地化文件的 YAML 文件会被跳过
+    fn list_files(&self, lang: &str) -> Result<Vec<PathBuf>>;
+
+    /// 与 [`list_files`](Self::list_files) 相同，供异步的翻译流程使用，目录来源并行读取各目录与文件
+    fn list_files_parallel<'a>(&'a self, lang: &'a str) -> BoxFuture<'a, Result<Vec<PathBuf>>> {
+        Box::pin(async move { self.list_files(lang) })
+    }
+
+    /// 读取文件的原始内容
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// 读取文件内容，与 [`crate::utils::read_file_with_bom`] 一样解码并规范化
+    fn read_file(&self, path: &Path) -> Result<String> {
+        Ok(crate::utils::decode_file_content(
+            &self.read_bytes(path)?,
+            path,
+        ))
+    }
+
+    /// 推测源语言：优先 english，否则取第一个语言目录
+    fn detect_source_lang(&self) -> Result<Option<String>> {
+        let langs = self.languages()?;
+        if langs.iter().any(|l| l == "english") {
+            return Ok(Some("english".to_string()));
+        }
+        Ok(langs.into_iter().next())
+    }
+}
+
+/// 路径是否指向整个 Mod（压缩包、`.mod` 描述文件或 Mod 目录），而不是某种语言的本地化目录
+pub fn is_mod_package(path: &Path) -> bool {
+    if path.is_file() {
+        return true;
+    }
+    path.join("localisation").is_dir()
+        || path.join("descriptor.mod").is_file()
+        || packed_archive(path).ok().flatten().is_some()
+}
+
+/// 根据路径打开本地化来源
+///
+/// - `.zip` 或 `.bin` 压缩包：读取其中 `localisation/` 下的文件，没有该目录时以压缩包根目录为本地化目录
+/// - `.mod` 描述文件：按其中的 `archive` 或 `path` 打开 Mod
+/// - 含 `localisation/` 的 Mod 目录：使用该目录
+/// - 只含压缩包的创意工坊物品目录（旧式物品的 `.bin` 与 `descriptor.mod`）：使用目录中的压缩包
+/// - 其他目录：视为本地化目录本身
+pub fn open_source(path: &Path) -> Result<Box<dyn LocalisationSource>> {
+    if path.is_file() {
+        return match path.extension().and_then(|e| e.to_str()) {
+            Some("zip" | "bin") => Ok(Box::new(ArchiveSource::open(path)?)),
+            Some("mod") => open_source(&resolve_descriptor(path)?),
+            _ => Err(TranslationError::ValidationError(format!(
+                "Unsupported localisation source {:?}: expected a directory, .zip, .bin or .mod file",
+                path
+            ))),
+        };
+    }
+    if !path.is_dir() {
+        return Err(TranslationError::FileNotFound(format!(
+            "Localisation source does not exist: {:?}",
+            path
+        )));
+    }
+
+    let localisation = path.join("localisation");
+    if localisation.is_dir() {
+        return Ok(Box::new(DirectorySource::new(localisation)));
+    }
+    if let Some(archive) = packed_archive(path)? {
+        return Ok(Box::new(ArchiveSource::open(&archive)?));
+    }
+    Ok(Box::new(DirectorySource::new(path)))
+}
+
+/// 创意工坊物品目录中打包的 Mod：目录中有 `descriptor.mod` 或没有任何子目录时，取其中的第一个压缩包
+///
+/// 本地化目录中不会有压缩包，但以防万一，有语言子目录且没有 `descriptor.mod` 时不视为物品目录。
+fn packed_archive(dir: &Path) -> Result<Option<PathBuf>> {
+    let has_subdirs = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .any(|e| e.path().is_dir());
+    if has_subdirs && !dir.join("descriptor.mod").is_file() {
+        return Ok(None);
+    }
+    find_archive(dir)
+}
+
+/// 目录中的第一个压缩包（`.zip`，或旧式创意工坊物品的 `.bin`，两者都是 zip 格式）
+fn find_archive(dir: &Path) -> Result<Option<PathBuf>> {
+    let mut archives: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .is_some_and(|ext| ext == "zip" || ext == "bin")
+        })
+        .collect();
+    archives.sort();
+    Ok(archives.into_iter().next())
+}
+
+/// `.mod` 描述文件指向的压缩包或 Mod 目录
+///
+/// 启动器生成的描述文件位于用户数据目录的 `mod/` 之下，其中的相对路径相对于用户数据目录，
+/// 找不到时再相对于描述文件所在的目录。
+fn resolve_descriptor(descriptor: &Path) -> Result<PathBuf> {
+    let content = crate::utils::read_file_with_bom(descriptor)?;
+    let target = descriptor_value(&content, "archive")
+        .or_else(|| descriptor_value(&content, "path"))
+        .ok_or_else(|| {
+            TranslationError::ValidationError(format!(
+                "{:?} has neither `archive` nor `path`",
+                descriptor
+            ))
+        })?;
+    let target = PathBuf::from(target);
+    if target.is_absolute() {
+        return Ok(target);
+    }
+    let dir = descriptor.parent().unwrap_or(Path::new("."));
+    [dir.parent(), Some(dir)]
+        .into_iter()
+        .flatten()
+        .map(|base| base.join(&target))
+        .find(|p| p.exists())
+        .ok_or_else(|| {
+            TranslationError::FileNotFound(format!(
+                "Mod {:?} referenced by {:?} does not exist",
+                target, descriptor
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_open_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let item = dir.path().join("workshop/123");
+        std::fs::create_dir_all(&item).unwrap();
+        std::fs::write(item.join("descriptor.mod"), "name=\"Zipped\"\n").unwrap();
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(item.join("mod.zip")).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, content) in [
+            ("descriptor.mod", "name=\"Zipped\"\n"),
+            (
+                "localisation/english/a_l_english.yml",
+                "\u{feff}l_english:\n a:0 \"A\"\n",
+            ),
+            ("localisation/english/notes.yml", "not: localisation\n"),
+            (
+                "localisation/german/a_l_german.yml",
+                "l_german:\n a:0 \"A\"\n",
+            ),
+            (
+                "localisation/replace/r_l_english.yml",
+                "l_english:\n r:0 \"R\"\n",
+            ),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let source = open_source(&item).unwrap();
+        assert_eq!(source.origin(), item.join("mod.zip"));
+        assert_eq!(source.languages().unwrap(), ["english", "german"]);
+        assert_eq!(source.detect_source_lang().unwrap().unwrap(), "english");
+        let files = source.list_files("english").unwrap();
+        assert_eq!(files, [PathBuf::from("english/a_l_english.yml")]);
+        assert_eq!(
+            source.read_file(&files[0]).unwrap(),
+            "l_english:\n a:0 \"A\"\n"
+        );
+        assert!(source.read_file(Path::new("english/missing.yml")).is_err());
+
+        // 启动器生成的描述文件，路径相对于用户数据目录
+        std::fs::create_dir_all(dir.path().join("mod")).unwrap();
+        let descriptor = dir.path().join("mod/ugc_123.mod");
+        std::fs::write(
+            &descriptor,
+            "name=\"Zipped\"\narchive=\"workshop/123/mod.zip\"\n",
+        )
+        .unwrap();
+        let source = open_source(&descriptor).unwrap();
+        assert_eq!(source.list_files("german").unwrap().len(), 1);
+
+        // 解压后的 Mod 目录与本地化目录
+        let loc = dir.path().join("unpacked/localisation/english");
+        std::fs::create_dir_all(&loc).unwrap();
+        std::fs::write(loc.join("b_l_english.yml"), "l_english:\n b:0 \"B\"\n").unwrap();
+        let source = open_source(&dir.path().join("unpacked")).unwrap();
+        assert_eq!(
+            source.list_files("english").unwrap(),
+            [loc.join("b_l_english.yml")]
+        );
+        assert!(is_mod_package(&dir.path().join("unpacked")));
+        assert!(!is_mod_package(&loc));
+    }
+}
@@ -0,0 +1,132 @@
+//! Steam 集成模块
+//!
+//! 查找本机的 Steam 库目录与游戏安装目录，并从创意工坊获取 Mod 内容。
+
+mod workshop;
+
+pub use workshop::*;
+
+use std::path::{Path, PathBuf};
+
+/// Stellaris 的 Steam 应用 ID
+pub const STELLARIS_APP_ID: u32 = 281990;
+
+/// 本机可能的 Steam 安装目录，`STEAM_DIR` 环境变量优先
+pub fn steam_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Ok(dir) = std::env::var("STEAM_DIR") {
+        roots.push(PathBuf::from(dir));
+    }
+    if cfg!(target_os = "windows") {
+        roots.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+        roots.push(PathBuf::from(r"C:\Program Files\Steam"));
+    } else if let Ok(home) = std::env::var("HOME") {
+        let home = PathBuf::from(home);
+        if cfg!(target_os = "macos") {
+            roots.push(home.join("Library/Application Support/Steam"));
+        } else {
+            roots.push(home.join(".steam/steam"));
+            roots.push(home.join(".local/share/Steam"));
+            roots.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+        }
+    }
+    roots.into_iter().filter(|p| p.is_dir()).collect()
+}
+
+/// 从 `libraryfolders.vdf` 的内容中解析所有库目录
+pub fn parse_library_folders(vdf: &str) -> Vec<PathBuf> {
+    vdf.lines()
+        .filter_map(|line| {
+            let mut parts = line.split('"').filter(|s| !s.trim().is_empty());
+            match (parts.next(), parts.next()) {
+                (Some("path"), Some(path)) => Some(PathBuf::from(path.replace("\\\\", "\\"))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// 本机的全部 Steam 库目录（包含各个 Steam 安装目录本身）
+pub fn steam_library_folders() -> Vec<PathBuf> {
+    let mut libraries: Vec<PathBuf> = Vec::new();
+    for root in steam_roots() {
+        let vdf = root.join("steamapps").join("libraryfolders.vdf");
+        let mut found = std::fs::read_to_string(&vdf)
+            .map(|content| parse_library_folders(&content))
+            .unwrap_or_default();
+        found.insert(0, root);
+        for library in found {
+            if library.is_dir() && !libraries.iter().any(|l| same_dir(l, &library)) {
+                libraries.push(library);
+            }
+        }
+    }
+    libraries
+}
+
+/// 从 `appmanifest_<appid>.acf` 的内容中读取安装目录名
+fn parse_install_dir(acf: &str) -> Option<String> {
+    acf.lines().find_map(|line| {
+        let mut parts = line.split('"').filter(|s| !s.trim().is_empty());
+        match (parts.next(), parts.next()) {
+            (Some("installdir"), Some(dir)) => Some(dir.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// 在本机 Steam 库中查找 Stellaris 的安装目录
+pub fn find_game_install_dir() -> Option<PathBuf> {
+    let manifest = format!("appmanifest_{}.acf", STELLARIS_APP_ID);
+    steam_library_folders().into_iter().find_map(|library| {
+        let steamapps = library.join("steamapps");
+        let install_dir = std::fs::read_to_string(steamapps.join(&manifest))
+            .ok()
+            .and_then(|acf| parse_install_dir(&acf))
+            .unwrap_or_else(|| "Stellaris".to_string());
+        let dir = steamapps.join("common").join(install_dir);
+        dir.join("localisation").is_dir().then_some(dir)
+    })
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_library_folders() {
+        let vdf = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"label"		""
+		"apps"
+		{
+			"281990"		"12345"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+	}
+}"#;
+        assert_eq!(
+            parse_library_folders(vdf),
+            [
+                PathBuf::from(r"C:\Program Files (x86)\Steam"),
+                PathBuf::from("/mnt/games/SteamLibrary"),
+            ]
+        );
+        let acf =
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"281990\"\n\t\"installdir\"\t\t\"Stellaris\"\n}";
+        assert_eq!(parse_install_dir(acf).as_deref(), Some("Stellaris"));
+    }
+}
@@ -0,0 +1,243 @@
+//! 创意工坊内容获取
+//!
+//! 在本机 Steam 库中查找已订阅的 Mod，或通过 steamcmd 下载，并提取其中的本地化文件。
+
+use super::{STELLARIS_APP_ID, steam_library_folders};
+use crate::error::{Result, TranslationError};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// 在本机 Steam 库中查找已订阅（已下载）的创意工坊物品目录
+pub fn find_workshop_item(workshop_id: &str) -> Option<PathBuf> {
+    steam_library_folders()
+        .into_iter()
+        .map(|library| {
+            library
+                .join("steamapps")
+                .join("workshop")
+                .join("content")
+                .join(STELLARIS_APP_ID.to_string())
+                .join(workshop_id)
+        })
+        .find(|dir| dir.is_dir())
+}
+
+/// 通过 steamcmd 下载创意工坊物品，返回下载目录
+///
+/// `login` 为 Steam 用户名，`anonymous` 表示匿名登录（并非所有物品都允许匿名下载）。
+pub fn download_workshop_item(steamcmd: &Path, workshop_id: &str, login: &str) -> Result<PathBuf> {
+    log::info!("Downloading workshop item {} with steamcmd...", workshop_id);
+    let output = Command::new(steamcmd)
+        .args(["+login", login])
+        .args([
+            "+workshop_download_item",
+            &STELLARIS_APP_ID.to_string(),
+            workshop_id,
+        ])
+        .arg("+quit")
+        .output()
+        .map_err(|e| {
+            TranslationError::Io(std::io::Error::other(format!(
+                "Failed to run steamcmd {:?}: {}",
+                steamcmd, e
+            )))
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    log::debug!("steamcmd output:\n{}", stdout);
+
+    // 成功时输出 `Success. Downloaded item <id> to "<path>" (<n> bytes)`
+    stdout
+        .lines()
+        .find(|line| line.contains("Downloaded item"))
+        .and_then(|line| line.split('"').nth(1))
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .ok_or_else(|| {
+            let reason = stdout
+                .lines()
+                .rfind(|line| line.contains("ERROR") || line.contains("FAILED"))
+                .unwrap_or("no download path in steamcmd output");
+            TranslationError::ApiError(format!(
+                "steamcmd failed to download workshop item {}: {}",
+                workshop_id,
+                reason.trim()
+            ))
+        })
+}
+
+/// 读取 descriptor.mod 中的 Mod 名称
+pub fn read_descriptor_name(mod_dir: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(mod_dir.join("descriptor.mod")).ok()?;
+    descriptor_value(&content, "name")
+}
+
+/// Mod 描述文件（`descriptor.mod` 或启动器生成的 `<名称>.mod`）中某个键的值
+pub fn descriptor_value(content: &str, key: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let (k, value) = line.split_once('=')?;
+        (k.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// 将 Mod 中的 `localisation` 目录复制到 `output_dir/localisation`，返回复制的文件数
+///
+/// 旧式的创意工坊物品只包含一个 zip 压缩包，此时从压缩包中提取。
+pub fn extract_localisation(mod_dir: &Path, output_dir: &Path) -> Result<usize> {
+    let target = output_dir.join("localisation");
+    let source = mod_dir.join("localisation");
+    if source.is_dir() {
+        let mut count = 0;
+        for entry in WalkDir::new(&source).into_iter().filter_map(|e| e.ok()) {
+            let relative = entry.path().strip_prefix(&source).unwrap_or(entry.path());
+            let dest = target.join(relative);
+            if entry.file_type().is_dir() {
+                std::fs::create_dir_all(&dest)?;
+            } else {
+                std::fs::copy(entry.path(), &dest)?;
+                count += 1;
+            }
+        }
+        return Ok(count);
+    }
+
+    let archive = std::fs::read_dir(mod_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.extension().is_some_and(|ext| ext == "zip"))
+        .ok_or_else(|| {
+            TranslationError::FileNotFound(format!("No localisation directory in {:?}", mod_dir))
+        })?;
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&archive)?).map_err(|e| {
+        TranslationError::Io(std::io::Error::other(format!(
+            "Failed to open {:?}: {}",
+            archive, e
+        )))
+    })?;
+    let mut count = 0;
+    for i in 0..zip.len() {
+        let mut file = zip
+            .by_index(i)
+            .map_err(|e| TranslationError::Io(std::io::Error::other(e.to_string())))?;
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix("localisation") else {
+            continue;
+        };
+        if file.is_dir() {
+            continue;
+        }
+        let dest = target.join(relative);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        std::fs::write(&dest, content)?;
+        count += 1;
+    }
+    if count == 0 {
+        return Err(TranslationError::FileNotFound(format!(
+            "No localisation files in {:?}",
+            archive
+        )));
+    }
+    Ok(count)
+}
+
+/// 推测本地化目录中的源语言：优先 english，否则取第一个语言目录
+pub fn detect_source_lang(localisation_dir: &Path) -> Option<String> {
+    if localisation_dir.join("english").is_dir() {
+        return Some("english".to_string());
+    }
+    let mut langs: Vec<String> = std::fs::read_dir(localisation_dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().to_str().map(String::from))
+        .filter(|name| name != "replace")
+        .collect();
+    langs.sort();
+    langs.into_iter().next()
+}
+
+/// 在工作目录中生成 task.toml，返回其路径
+///
+/// 与其他任务配置文件一样，`localisation_dir` 相对于运行 pmt 时的当前目录。
+pub fn write_workshop_task(
+    output_dir: &Path,
+    localisation_dir: &Path,
+    mod_name: &str,
+    source_lang: &str,
+    target_langs: &[String],
+    glossaries: &[String],
+) -> Result<PathBuf> {
+    let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+    let list = |items: &[String]| {
+        items
+            .iter()
+            .map(|s| quote(s))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let content = format!(
+        "# {}\n# 由 pmt fetch-workshop 生成，客户端设置见 task.template.toml\n\n[[task]]\nsource_lang = {}\ntarget_langs = [{}]\nglossaries = [{}]\nlocalisation_dir = {}\n",
+        mod_name,
+        quote(source_lang),
+        list(target_langs),
+        list(glossaries),
+        quote(&localisation_dir.to_string_lossy()),
+    );
+    let path = output_dir.join("task.toml");
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TaskFileConfig;
+
+    #[test]
+    fn test_extract_and_generate_task() {
+        let mod_dir = tempfile::tempdir().unwrap();
+        let loc = mod_dir.path().join("localisation/english");
+        std::fs::create_dir_all(&loc).unwrap();
+        std::fs::write(loc.join("a_l_english.yml"), "l_english:\n key: \"v\"").unwrap();
+        std::fs::write(
+            mod_dir.path().join("descriptor.mod"),
+            "version=\"1.0\"\nname=\"My Mod\"\n",
+        )
+        .unwrap();
+
+        let work = tempfile::tempdir().unwrap();
+        assert_eq!(
+            extract_localisation(mod_dir.path(), work.path()).unwrap(),
+            1
+        );
+        let lang = detect_source_lang(&work.path().join("localisation")).unwrap();
+        assert_eq!(lang, "english");
+
+        let name = read_descriptor_name(mod_dir.path()).unwrap();
+        assert_eq!(name, "My Mod");
+        let path = write_workshop_task(
+            work.path(),
+            &work.path().join("localisation"),
+            &name,
+            &lang,
+            &["simp_chinese".to_string()],
+            &["stellaris".to_string()],
+        )
+        .unwrap();
+        let config: TaskFileConfig =
+            toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(config.task[0].source_lang, "english");
+        assert_eq!(config.task[0].target_langs, ["simp_chinese"]);
+        assert_eq!(
+            config.task[0].localisation_dir,
+            work.path().join("localisation")
+        );
+    }
+}
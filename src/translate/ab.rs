@@ -0,0 +1,500 @@
+//! 模型盲评
+//!
+//! 用多个模型翻译同一批抽样条目，生成隐去模型名称的并排对比表格：每行各列的模型顺序随机，
+//! 评审者只看到 A、B、C… 的译文，在 `preferred` 列填写更好的一列。对应关系与译文保存在
+//! `.pmt/ab/` 中，读取填写后的表格即可统计各模型胜出的次数，多次评审的结果一并汇总。
+
+use super::preview::xorshift;
+use super::tune::task_entries;
+use super::{
+    FormatValidator, Translator, entries_to_content, load_glossaries_from_task, sample_random,
+};
+use crate::config::{ClientSettings, TranslationTask};
+use crate::error::{Result, TranslationError};
+use crate::preprocess::parse_entries;
+use crate::translate_content;
+use crate::utils::{FNV_OFFSET, fnv1a};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// 默认的盲评记录目录（相对于任务配置文件所在目录）
+pub const DEFAULT_AB_PATH: &str = ".pmt/ab";
+
+/// 填写时表示各列不分高下
+pub const AB_TIE: &str = "=";
+
+/// 盲评表格中的一行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbRow {
+    pub key: String,
+    pub source: String,
+    /// 各列（A、B…）的模型，顺序随机
+    pub models: Vec<String>,
+    /// 各列的译文
+    pub translations: Vec<String>,
+}
+
+/// 一次盲评：抽样条目、各模型的译文与评审结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbRun {
+    pub id: String,
+    pub source_lang: String,
+    pub target_lang: String,
+    pub models: Vec<String>,
+    pub seed: u64,
+    pub rows: Vec<AbRow>,
+    /// 各模型译文中的验证问题数
+    #[serde(default)]
+    pub issues: BTreeMap<String, usize>,
+    /// 键 -> 胜出的模型（不分高下时为 [`AB_TIE`]）
+    #[serde(default)]
+    pub judgments: BTreeMap<String, String>,
+}
+
+/// 第 `i` 列的列名：A、B、C…
+pub fn column_label(i: usize) -> String {
+    char::from(b'A' + (i % 26) as u8).to_string()
+}
+
+/// 一行中各列对应的模型下标，由随机种子与键决定，同一次盲评中每行的顺序不同
+fn shuffled_columns(n: usize, seed: u64, key: &str) -> Vec<usize> {
+    let mut state = FNV_OFFSET ^ seed;
+    fnv1a(&mut state, key.as_bytes());
+    let mut next = xorshift(state);
+    let mut columns: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        columns.swap(i, (next() % (i as u64 + 1)) as usize);
+    }
+    columns
+}
+
+/// 用每个模型翻译任务中随机抽取的 `sample` 个条目
+pub async fn run_ab(
+    task: &TranslationTask,
+    client_settings: &ClientSettings,
+    target_lang: &str,
+    models: &[String],
+    sample: usize,
+    seed: u64,
+) -> Result<AbRun> {
+    if models.len() < 2 {
+        return Err(TranslationError::ValidationError(
+            "At least two models are required for a blind comparison".to_string(),
+        ));
+    }
+    let entries = sample_random(&task_entries(task)?, sample, seed);
+    if entries.is_empty() {
+        return Err(TranslationError::ValidationError(format!(
+            "No entries to sample in {:?}",
+            task.source_dir()
+        )));
+    }
+    let content = entries_to_content(&task.source_lang, &entries);
+    log::info!(
+        "Sampled {} entries, comparing {} models",
+        entries.len(),
+        models.len()
+    );
+
+    let glossary = load_glossaries_from_task(task)?;
+    let validator = FormatValidator::for_task(task);
+    let mut issues = BTreeMap::new();
+    let mut translations: Vec<HashMap<String, String>> = Vec::new();
+    for model in models {
+        let mut settings = client_settings.clone();
+        settings.model = model.clone();
+        let translator = Translator::for_task(settings, glossary.clone(), task)?;
+        log::info!("Translating sample with {}", model);
+        let translated = translate_content(
+            &translator,
+            &task.source_lang,
+            target_lang,
+            client_settings.max_chunk_tokens,
+            &format!("ab:{}", model),
+            &content,
+            &Default::default(),
+        )
+        .await?;
+        let values: HashMap<String, String> = parse_entries(&translated)
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+        let count = entries
+            .iter()
+            .map(|e| {
+                let target = values.get(&e.key).map(String::as_str).unwrap_or_default();
+                validator.validate_entry(&e.key, &e.value, target).len()
+            })
+            .sum();
+        issues.insert(model.clone(), count);
+        translations.push(values);
+    }
+
+    let rows = entries
+        .into_iter()
+        .map(|entry| {
+            let columns = shuffled_columns(models.len(), seed, &entry.key);
+            AbRow {
+                models: columns.iter().map(|&i| models[i].clone()).collect(),
+                translations: columns
+                    .iter()
+                    .map(|&i| translations[i].get(&entry.key).cloned().unwrap_or_default())
+                    .collect(),
+                key: entry.key,
+                source: entry.value,
+            }
+        })
+        .collect();
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    Ok(AbRun {
+        id: format!("{}-{:08x}", secs, seed as u32),
+        source_lang: task.source_lang.clone(),
+        target_lang: target_lang.to_string(),
+        models: models.to_vec(),
+        seed,
+        rows,
+        issues,
+        judgments: BTreeMap::new(),
+    })
+}
+
+impl AbRun {
+    /// 盲评的列数
+    fn columns(&self) -> usize {
+        self.models.len()
+    }
+
+    /// 记录文件的路径
+    pub fn path(dir: &Path, id: &str) -> PathBuf {
+        dir.join(format!("{}.json", id))
+    }
+
+    /// 读取保存的盲评记录
+    pub fn load(dir: &Path, id: &str) -> Result<Self> {
+        let path = Self::path(dir, id);
+        if !path.is_file() {
+            return Err(TranslationError::FileNotFound(format!(
+                "Blind comparison {:?} does not exist",
+                path
+            )));
+        }
+        serde_json::from_str(&std::fs::read_to_string(&path)?).map_err(|e| {
+            TranslationError::ValidationError(format!("Invalid blind comparison {:?}: {}", path, e))
+        })
+    }
+
+    /// 保存盲评记录，返回记录文件的路径
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = Self::path(dir, &self.id);
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    fn header(&self) -> Vec<String> {
+        let mut header = vec!["run".to_string(), "key".to_string(), "source".to_string()];
+        header.extend((0..self.columns()).map(column_label));
+        header.push("preferred".to_string());
+        header
+    }
+
+    /// 生成 HTML 表格，便于在浏览器中阅读；评审结果仍填写在 CSV 表格中
+    pub fn render_html(&self) -> String {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+            escape(&self.id)
+        );
+        let _ = writeln!(
+            out,
+            "<style>table{{border-collapse:collapse}}td,th{{border:1px solid #ccc;padding:4px;vertical-align:top}}</style>\n</head>\n<body>"
+        );
+        let _ = writeln!(
+            out,
+            "<p>{} → {}, {} entries, run <code>{}</code></p>\n<table>",
+            escape(&self.source_lang),
+            escape(&self.target_lang),
+            self.rows.len(),
+            escape(&self.id)
+        );
+        let cells: String = self.header()[1..]
+            .iter()
+            .map(|h| format!("<th>{}</th>", escape(h)))
+            .collect();
+        let _ = writeln!(out, "<tr>{}</tr>", cells);
+        for row in &self.rows {
+            let mut cells = format!(
+                "<td><code>{}</code></td><td>{}</td>",
+                escape(&row.key),
+                escape(&row.source)
+            );
+            for translation in &row.translations {
+                let _ = write!(cells, "<td>{}</td>", escape(translation));
+            }
+            cells.push_str("<td></td>");
+            let _ = writeln!(out, "<tr>{}</tr>", cells);
+        }
+        let _ = writeln!(out, "</table>\n</body>\n</html>");
+        out
+    }
+
+    /// 写出盲评表格，返回供填写评审结果的 CSV 表格路径
+    ///
+    /// 扩展名为 `.html` 时写为 HTML，并在旁边写出同名的 CSV 表格。
+    pub fn write_sheet(&self, output: &Path) -> Result<PathBuf> {
+        if let Some(parent) = output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if output
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("html"))
+        {
+            std::fs::write(output, self.render_html())?;
+            return self.write_sheet(&output.with_extension("csv"));
+        }
+
+        let csv_error = |e: csv::Error| {
+            TranslationError::Io(std::io::Error::other(format!(
+                "Failed to write {:?}: {}",
+                output, e
+            )))
+        };
+        let mut file = std::fs::File::create(output)?;
+        std::io::Write::write_all(&mut file, "\u{FEFF}".as_bytes())?;
+        let mut writer = csv::Writer::from_writer(file);
+        writer.write_record(self.header()).map_err(csv_error)?;
+        for row in &self.rows {
+            let mut record = vec![self.id.clone(), row.key.clone(), row.source.clone()];
+            record.extend(row.translations.iter().cloned());
+            record.push(String::new());
+            writer.write_record(&record).map_err(csv_error)?;
+        }
+        writer.flush()?;
+        Ok(output.to_path_buf())
+    }
+
+    /// 记录一行的评审结果：列名（不区分大小写）或 [`AB_TIE`]，空白表示尚未评审
+    pub fn judge(&mut self, key: &str, preferred: &str) -> Result<()> {
+        let preferred = preferred.trim();
+        if preferred.is_empty() {
+            return Ok(());
+        }
+        let row = self.rows.iter().find(|r| r.key == key).ok_or_else(|| {
+            TranslationError::ValidationError(format!(
+                "Key {} is not part of blind comparison {}",
+                key, self.id
+            ))
+        })?;
+        let winner = if preferred == AB_TIE {
+            AB_TIE.to_string()
+        } else {
+            (0..row.models.len())
+                .find(|&i| column_label(i).eq_ignore_ascii_case(preferred))
+                .map(|i| row.models[i].clone())
+                .ok_or_else(|| {
+                    TranslationError::ValidationError(format!(
+                        "Invalid preference {:?} for {}: expected a column name or {:?}",
+                        preferred, key, AB_TIE
+                    ))
+                })?
+        };
+        self.judgments.insert(key.to_string(), winner);
+        Ok(())
+    }
+}
+
+/// 读取填写后的 CSV 表格，将评审结果记入对应的盲评记录并保存，返回更新后的记录
+pub fn score_ab_sheet(dir: &Path, judged: &Path) -> Result<Vec<AbRun>> {
+    let csv_error = |e: csv::Error| {
+        TranslationError::ValidationError(format!("Failed to read {:?}: {}", judged, e))
+    };
+    let content = crate::utils::read_file_with_bom(judged)?;
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let column = |name: &str| {
+        headers.iter().position(|h| h == name).ok_or_else(|| {
+            TranslationError::ValidationError(format!("{:?} has no `{}` column", judged, name))
+        })
+    };
+    let (run_column, key_column, preferred_column) =
+        (column("run")?, column("key")?, column("preferred")?);
+
+    let mut runs: BTreeMap<String, AbRun> = BTreeMap::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let field = |i: usize| record.get(i).unwrap_or_default();
+        let id = field(run_column);
+        if !runs.contains_key(id) {
+            runs.insert(id.to_string(), AbRun::load(dir, id)?);
+        }
+        if let Some(run) = runs.get_mut(id) {
+            run.judge(field(key_column), field(preferred_column))?;
+        }
+    }
+    for run in runs.values() {
+        run.save(dir)?;
+    }
+    Ok(runs.into_values().collect())
+}
+
+/// 读取目录中保存的全部盲评记录，按编号排序
+pub fn load_ab_runs(dir: &Path) -> Result<Vec<AbRun>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut ids: Vec<String> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|p| p.file_stem().and_then(|s| s.to_str()).map(String::from))
+        .collect();
+    ids.sort();
+    ids.iter().map(|id| AbRun::load(dir, id)).collect()
+}
+
+/// 一个模型在盲评中的成绩
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AbScore {
+    /// 参与评审的条目数（包括不分高下的条目）
+    pub judged: usize,
+    /// 胜出的条目数
+    pub wins: usize,
+    /// 不分高下的条目数
+    pub ties: usize,
+    /// 译文中的验证问题数
+    pub issues: usize,
+}
+
+/// 汇总多次盲评中各模型的成绩，按胜出次数从多到少排列
+pub fn tally_ab(runs: &[AbRun]) -> Vec<(String, AbScore)> {
+    let mut scores: BTreeMap<String, AbScore> = BTreeMap::new();
+    for run in runs {
+        for (model, issues) in &run.issues {
+            scores.entry(model.clone()).or_default().issues += issues;
+        }
+        for row in &run.rows {
+            let Some(winner) = run.judgments.get(&row.key) else {
+                continue;
+            };
+            for model in &row.models {
+                let score = scores.entry(model.clone()).or_default();
+                score.judged += 1;
+                if winner == AB_TIE {
+                    score.ties += 1;
+                } else if winner == model {
+                    score.wins += 1;
+                }
+            }
+        }
+    }
+    let mut scores: Vec<(String, AbScore)> = scores.into_iter().collect();
+    scores.sort_by(|a, b| b.1.wins.cmp(&a.1.wins).then_with(|| a.0.cmp(&b.0)));
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ab_judging() {
+        let models = ["m1".to_string(), "m2".to_string()];
+        let rows: Vec<AbRow> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|key| {
+                let columns = shuffled_columns(2, 7, key);
+                AbRow {
+                    key: key.to_string(),
+                    source: format!("Source {}", key),
+                    models: columns.iter().map(|&i| models[i].clone()).collect(),
+                    translations: columns
+                        .iter()
+                        .map(|&i| format!("{} {}", models[i], key))
+                        .collect(),
+                }
+            })
+            .collect();
+        // 每行的顺序由种子与键决定，且是模型的一个排列
+        assert_eq!(shuffled_columns(2, 7, "a"), shuffled_columns(2, 7, "a"));
+        let mut sorted = shuffled_columns(5, 3, "x");
+        sorted.sort_unstable();
+        assert_eq!(sorted, [0, 1, 2, 3, 4]);
+
+        let run = AbRun {
+            id: "1-00000007".to_string(),
+            source_lang: "english".to_string(),
+            target_lang: "simp_chinese".to_string(),
+            models: models.to_vec(),
+            seed: 7,
+            rows,
+            issues: BTreeMap::from([("m1".to_string(), 0), ("m2".to_string(), 3)]),
+            judgments: BTreeMap::new(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        run.save(dir.path()).unwrap();
+
+        // 评审者为 a、b 选择 m1 所在的列，c 不分高下，d 未评审
+        let column_of = |key: &str, model: &str| {
+            let row = run.rows.iter().find(|r| r.key == key).unwrap();
+            column_label(row.models.iter().position(|m| m == model).unwrap())
+        };
+        let sheet = dir.path().join("judged.csv");
+        run.write_sheet(&sheet).unwrap();
+        let written = crate::utils::read_file_with_bom(&sheet).unwrap();
+        assert!(written.starts_with("run,key,source,A,B,preferred\n"));
+        let judged: String = written
+            .lines()
+            .map(|line| match line.split(',').nth(1) {
+                Some(key @ ("a" | "b")) => {
+                    format!("{}{}\n", line, column_of(key, "m1").to_lowercase())
+                }
+                Some("c") => format!("{}=\n", line),
+                _ => format!("{}\n", line),
+            })
+            .collect();
+        std::fs::write(&sheet, judged).unwrap();
+
+        let runs = score_ab_sheet(dir.path(), &sheet).unwrap();
+        assert_eq!(runs[0].judgments.len(), 3);
+        assert_eq!(load_ab_runs(dir.path()).unwrap(), runs);
+        let scores = tally_ab(&runs);
+        assert_eq!(scores[0].0, "m1");
+        assert_eq!(
+            scores[0].1,
+            AbScore {
+                judged: 3,
+                wins: 2,
+                ties: 1,
+                issues: 0
+            }
+        );
+        assert_eq!(scores[1].1.wins, 0);
+        assert_eq!(scores[1].1.issues, 3);
+
+        let html = dir.path().join("sheet.html");
+        assert_eq!(
+            run.write_sheet(&html).unwrap(),
+            dir.path().join("sheet.csv")
+        );
+        let mut run = runs[0].clone();
+        assert!(run.judge("a", "C").is_err());
+        assert!(run.judge("missing", "A").is_err());
+        assert!(
+            run.render_html()
+                .contains("<td><code>a</code></td><td>Source a</td>")
+        );
+    }
+}
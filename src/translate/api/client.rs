@@ -3,8 +3,10 @@
 //! OpenAI兼容API的HTTP客户端封装。
 
 use super::models::*;
-use crate::config::ClientSettings;
+use super::recorder::{record_exchange, replay_response};
+use crate::config::{ClientSettings, Provider};
 use crate::error::{Result, TranslationError};
+use crate::utils::{FNV_OFFSET, fnv1a};
 use reqwest::Client;
 
 /// API客户端
@@ -14,15 +16,91 @@ pub struct ApiClient {
     api_key: String,
 }
 
+/// 按客户端设置创建 HTTP 客户端：超时、额外的 HTTP 头、代理与额外信任的 CA 证书
+pub fn build_http_client(settings: &ClientSettings) -> Result<Client> {
+    let request_error =
+        |e| TranslationError::Translate(crate::error::TranslateError::ApiRequest(e));
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (name, value) in &settings.headers {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| TranslationError::ApiError(format!("Invalid header {}: {}", name, e)))?;
+        let value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|e| TranslationError::ApiError(format!("Invalid header {}: {}", name, e)))?;
+        headers.insert(name, value);
+    }
+    let mut builder = Client::builder()
+        .timeout(std::time::Duration::from_secs(settings.timeout_secs))
+        .default_headers(headers);
+
+    if let Some(proxy_url) = &settings.proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(request_error)?
+            .no_proxy(
+                settings
+                    .no_proxy
+                    .as_deref()
+                    .and_then(reqwest::NoProxy::from_string),
+            );
+        log::info!("Using proxy: {}", mask_proxy_credentials(proxy_url));
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ca_bundle) = &settings.ca_bundle {
+        let pem = std::fs::read(ca_bundle)?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem).map_err(request_error)?;
+        log::info!(
+            "Trusting {} extra CA certificates from {:?}",
+            certificates.len(),
+            ca_bundle
+        );
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder.build().map_err(request_error)
+}
+
+/// 隐去代理地址中的用户名与密码（`user:password@`），用于日志输出
+///
+/// 代理地址可以省略协议（例如 `user:password@host:8080`），因此按字符串处理而不解析为 URL。
+fn mask_proxy_credentials(proxy_url: &str) -> String {
+    let authority_start = proxy_url.find("://").map_or(0, |i| i + 3);
+    let authority_end = proxy_url[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(proxy_url.len(), |i| authority_start + i);
+    match proxy_url[authority_start..authority_end].rfind('@') {
+        Some(at) => format!(
+            "{}***{}",
+            &proxy_url[..authority_start],
+            &proxy_url[authority_start + at..]
+        ),
+        None => proxy_url.to_string(),
+    }
+}
+
+/// 由设置的种子、重试次数与消息内容得到请求的种子：内容相同的切片在每次运行时使用相同的种子，
+/// 重试时使用不同的种子，以免得到与上次相同的回复
+///
+/// 使用 FNV-1a 哈希以保证不同版本、不同平台的结果一致，结果限制在 `i64` 范围内以兼容各家 API。
+fn chunk_seed(seed: u64, attempt: u32, messages: &[ChatMessage]) -> u64 {
+    let mut hash = FNV_OFFSET ^ seed;
+    if attempt > 0 {
+        fnv1a(&mut hash, &attempt.to_le_bytes());
+    }
+    for message in messages {
+        fnv1a(&mut hash, message.role.as_bytes());
+        fnv1a(&mut hash, &[0]);
+        fnv1a(&mut hash, message.content.as_bytes());
+        fnv1a(&mut hash, &[0]);
+    }
+    hash & i64::MAX as u64
+}
+
 impl ApiClient {
     /// 创建新的API客户端
     pub fn new(settings: ClientSettings, api_key: String) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(settings.timeout_secs))
-            .build()
-            .map_err(|e| {
-                TranslationError::Translate(crate::error::TranslateError::ApiRequest(e))
-            })?;
+        let client = build_http_client(&settings)?;
 
         Ok(Self {
             client,
@@ -31,43 +109,64 @@ impl ApiClient {
         })
     }
 
-    /// 发送聊天补全请求
+    /// 请求使用的模型名称
+    pub fn model(&self) -> &str {
+        &self.settings.model
+    }
+
+    /// 模型的上下文长度（token）
+    pub fn context_window(&self) -> usize {
+        self.settings.context_window() as usize
+    }
+
+    /// 请求设置的最大输出 token 数
+    pub fn max_tokens(&self) -> Option<u32> {
+        self.settings.max_tokens
+    }
+
+    /// 发送聊天补全请求，`attempt` 为同一内容的第几次重试（首次请求为 0）
     pub async fn chat_completions(
         &self,
         messages: Vec<ChatMessage>,
+        attempt: u32,
     ) -> Result<ChatCompletionResponse> {
+        let seed = self
+            .settings
+            .seed
+            .map(|seed| chunk_seed(seed, attempt, &messages));
         let request = ChatCompletionRequest {
             model: self.settings.model.clone(),
             messages,
             temperature: Some(self.settings.temperature),
+            top_p: self.settings.top_p,
+            frequency_penalty: self.settings.frequency_penalty,
+            presence_penalty: self.settings.presence_penalty,
+            stop: self.settings.stop.clone(),
             max_tokens: self.settings.max_tokens,
             stream: Some(self.settings.stream),
+            seed,
+            extra: self.settings.extra_body.clone(),
         };
 
-        let response = self
-            .client
-            .post(&self.settings.chat_completions_url())
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| {
-                TranslationError::Translate(crate::error::TranslateError::ApiRequest(e))
-            })?;
+        let (status, body) = match replay_response(&request) {
+            Some(replayed) => replayed?,
+            None => self.send(&request).await?,
+        };
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
+        if status == 429 {
+            log::debug!("Rate limited by the API: {}", body);
+            return Err(TranslationError::Translate(
+                crate::error::TranslateError::RateLimited,
+            ));
+        }
+        if !(200..300).contains(&status) {
             return Err(TranslationError::ApiError(format!(
                 "API request failed with status {}: {}",
-                status, error_text
+                status, body
             )));
         }
 
-        let completion = response.json().await;
-        // dbg!(&completion);
-        let completion = completion.map_err(|e| {
+        let completion = serde_json::from_str(&body).map_err(|e| {
             TranslationError::Translate(crate::error::TranslateError::InvalidResponse(
                 e.to_string(),
             ))
@@ -75,4 +174,88 @@ impl ApiClient {
 
         Ok(completion)
     }
+
+    /// 发送请求，返回状态码与原始响应体，启用记录时一并记录
+    async fn send(&self, request: &ChatCompletionRequest) -> Result<(u16, String)> {
+        let request_builder = self
+            .client
+            .post(self.settings.chat_completions_url())
+            .query(&self.settings.request_query());
+        let request_builder = match self.settings.provider {
+            Provider::Openai => {
+                request_builder.header("Authorization", format!("Bearer {}", self.api_key))
+            }
+            Provider::Azure => request_builder.header("api-key", &self.api_key),
+        };
+        let http_request = request_builder
+            .header("Content-Type", "application/json")
+            .json(request)
+            .build()
+            .map_err(|e| {
+                TranslationError::Translate(crate::error::TranslateError::ApiRequest(e))
+            })?;
+        let url = http_request.url().to_string();
+        // 记录的请求头包括客户端的默认请求头
+        let mut headers = http_request.headers().clone();
+        for (name, value) in &self.settings.headers {
+            if let (Ok(name), Ok(value)) = (
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+                reqwest::header::HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+
+        let response = self.client.execute(http_request).await.map_err(|e| {
+            TranslationError::Translate(crate::error::TranslateError::ApiRequest(e))
+        })?;
+
+        let status = response.status();
+        let body = response.text().await.map_err(|e| {
+            TranslationError::Translate(crate::error::TranslateError::ApiRequest(e))
+        })?;
+        record_exchange(&url, &headers, request, status.as_u16(), &body);
+        Ok((status.as_u16(), body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_proxy_credentials() {
+        assert_eq!(
+            mask_proxy_credentials("http://user:p@ss@proxy.example.com:8080/"),
+            "http://***@proxy.example.com:8080/"
+        );
+        assert_eq!(
+            mask_proxy_credentials("user:secret@127.0.0.1:1080"),
+            "***@127.0.0.1:1080"
+        );
+        assert_eq!(
+            mask_proxy_credentials("socks5://127.0.0.1:1080"),
+            "socks5://127.0.0.1:1080"
+        );
+    }
+
+    #[test]
+    fn test_chunk_seed() {
+        let messages = |content: &str| {
+            vec![ChatMessage {
+                role: "user".to_string(),
+                content: content.to_string(),
+            }]
+        };
+        let seed = chunk_seed(42, 0, &messages("KEY:0 \"Text\""));
+        assert_eq!(seed, chunk_seed(42, 0, &messages("KEY:0 \"Text\"")));
+        assert_ne!(seed, chunk_seed(42, 0, &messages("KEY:0 \"Other\"")));
+        assert_ne!(seed, chunk_seed(7, 0, &messages("KEY:0 \"Text\"")));
+        // 重试时种子不同，每次重试的种子也各不相同
+        let retry = chunk_seed(42, 1, &messages("KEY:0 \"Text\""));
+        assert_ne!(seed, retry);
+        assert_ne!(retry, chunk_seed(42, 2, &messages("KEY:0 \"Text\"")));
+        assert_eq!(retry, chunk_seed(42, 1, &messages("KEY:0 \"Text\"")));
+        assert!(seed <= i64::MAX as u64);
+    }
 }
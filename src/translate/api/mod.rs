@@ -4,6 +4,8 @@
 
 mod client;
 mod models;
+mod recorder;
 
 pub use client::*;
 pub use models::*;
+pub use recorder::*;
@@ -1,6 +1,7 @@
 //! API请求和响应数据结构
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// 聊天消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,12 +22,30 @@ pub struct ChatCompletionRequest {
     /// 温度参数
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+    /// 核采样概率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// 频率惩罚
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// 存在惩罚
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// 停止序列
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
     /// 最大输出token数
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
     /// 是否流式响应
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    /// 随机种子，模型支持时相同的请求与种子产生相同的输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// 服务商特有的其他参数，原样合并到请求体中
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// 聊天补全响应
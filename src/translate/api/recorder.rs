@@ -0,0 +1,233 @@
+//! 请求记录与回放
+//!
+//! 启用记录后将每次 API 请求与原始响应按顺序保存为编号的 JSON 文件（`0001.json`、`0002.json`……），
+//! 便于提交问题报告。认证信息不会写入记录。
+//!
+//! 启用回放后不再访问网络，按请求内容（模型与消息）从记录中查找响应，
+//! 使切片、合并、验证等流程的问题可以确定地复现。
+
+use super::ChatCompletionRequest;
+use crate::error::Result;
+use crate::error::TranslationError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// 替换敏感请求头的占位文本
+const REDACTED: &str = "[REDACTED]";
+
+/// 一次请求与响应的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    /// 请求地址（含查询参数）
+    pub url: String,
+    /// 请求头，认证信息已替换为占位文本
+    pub headers: BTreeMap<String, String>,
+    /// 请求体
+    pub request: ChatCompletionRequest,
+    /// HTTP 状态码
+    pub status: u16,
+    /// 原始响应体，是合法 JSON 时按 JSON 保存，否则保存为字符串
+    pub response: serde_json::Value,
+}
+
+struct Recorder {
+    dir: PathBuf,
+    count: AtomicUsize,
+}
+
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+/// 回放用的记录：请求内容 -> 按记录顺序排列的响应（状态码、响应体）
+type ReplayStore = HashMap<String, VecDeque<(u16, String)>>;
+
+static REPLAY: OnceLock<Mutex<ReplayStore>> = OnceLock::new();
+
+/// 开始将之后的所有 API 请求记录到目录中
+pub fn start_recording(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let recorder = Recorder {
+        dir: dir.to_path_buf(),
+        count: AtomicUsize::new(0),
+    };
+    if RECORDER.set(recorder).is_err() {
+        log::warn!(
+            "API requests are already being recorded, ignoring {:?}",
+            dir
+        );
+    } else {
+        log::info!("Recording API requests to {:?}", dir);
+    }
+    Ok(())
+}
+
+/// 是否需要隐去请求头（或查询参数）的值
+///
+/// 各家服务商的认证头名称不一（`x-api-key`、`Ocp-Apim-Subscription-Key`、`x-goog-api-key` 等），
+/// 因此按名称中的关键词判断。
+fn is_sensitive_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["key", "auth", "token", "secret"]
+        .iter()
+        .any(|word| name.contains(word))
+}
+
+/// 隐去地址中可能是认证信息的查询参数（例如 `?key=...`）
+fn redact_url(url: &str) -> String {
+    let Ok(mut parsed) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    if parsed.query().is_none() {
+        return url.to_string();
+    }
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = if is_sensitive_header(&name) {
+                REDACTED.to_string()
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+
+/// 记录一次请求与响应（未启用记录时不做任何事），写入失败只给出警告
+pub(crate) fn record_exchange(
+    url: &str,
+    headers: &reqwest::header::HeaderMap,
+    request: &ChatCompletionRequest,
+    status: u16,
+    response: &str,
+) {
+    let Some(recorder) = RECORDER.get() else {
+        return;
+    };
+    let headers = headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive_header(name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                value.to_str().unwrap_or_default().to_string()
+            };
+            (name.to_string(), value)
+        })
+        .collect();
+    let exchange = Exchange {
+        url: redact_url(url),
+        headers,
+        request: request.clone(),
+        status,
+        response: serde_json::from_str(response)
+            .unwrap_or_else(|_| serde_json::Value::String(response.to_string())),
+    };
+
+    let index = recorder.count.fetch_add(1, Ordering::Relaxed) + 1;
+    let path = recorder.dir.join(format!("{:04}.json", index));
+    let written = serde_json::to_string_pretty(&exchange)
+        .map_err(std::io::Error::other)
+        .and_then(|content| std::fs::write(&path, content));
+    if let Err(e) = written {
+        log::warn!("Failed to record API request to {:?}: {}", path, e);
+    }
+}
+
+/// 回放时用于匹配请求的内容：模型与消息
+fn replay_key(request: &ChatCompletionRequest) -> String {
+    serde_json::to_string(&(&request.model, &request.messages)).unwrap_or_default()
+}
+
+/// 加载目录中的记录，之后的 API 请求都从记录中取得响应
+pub fn start_replay(dir: &Path) -> Result<()> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    let mut responses = ReplayStore::new();
+    for file in &files {
+        let exchange: Exchange =
+            serde_json::from_str(&std::fs::read_to_string(file)?).map_err(|e| {
+                TranslationError::ValidationError(format!(
+                    "Invalid transcript {}: {}",
+                    file.display(),
+                    e
+                ))
+            })?;
+        let body = match exchange.response {
+            serde_json::Value::String(text) => text,
+            value => value.to_string(),
+        };
+        responses
+            .entry(replay_key(&exchange.request))
+            .or_default()
+            .push_back((exchange.status, body));
+    }
+    if REPLAY.set(Mutex::new(responses)).is_err() {
+        log::warn!(
+            "API responses are already being replayed, ignoring {:?}",
+            dir
+        );
+    } else {
+        log::info!(
+            "Replaying {} recorded API responses from {:?}",
+            files.len(),
+            dir
+        );
+    }
+    Ok(())
+}
+
+/// 回放模式下取得请求对应的记录响应（状态码、响应体），未启用回放时返回 None
+///
+/// 相同的请求出现多次时按记录顺序依次返回，找不到记录时返回错误。
+pub(crate) fn replay_response(request: &ChatCompletionRequest) -> Option<Result<(u16, String)>> {
+    let replay = REPLAY.get()?;
+    let mut responses = replay.lock().unwrap_or_else(|e| e.into_inner());
+    Some(
+        responses
+            .get_mut(&replay_key(request))
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| {
+                TranslationError::ApiError(
+                    "No recorded response matches this request in the replay transcripts"
+                        .to_string(),
+                )
+            }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sensitive_headers() {
+        for name in [
+            "Authorization",
+            "api-key",
+            "X-Auth-Token",
+            "x-client-secret",
+            "x-api-key",
+            "Ocp-Apim-Subscription-Key",
+            "x-goog-api-key",
+        ] {
+            assert!(is_sensitive_header(name), "{}", name);
+        }
+        for name in ["Content-Type", "X-Title", "HTTP-Referer"] {
+            assert!(!is_sensitive_header(name), "{}", name);
+        }
+        assert_eq!(
+            redact_url("https://api.example.com/v1/chat/completions?key=abc&api-version=1"),
+            "https://api.example.com/v1/chat/completions?key=%5BREDACTED%5D&api-version=1"
+        );
+    }
+}
@@ -1,10 +1,14 @@
 //! 批处理模块
 //!
 //! 管理翻译任务的批处理和并发控制。
+//!
+//! [`AdaptiveConcurrency`] 按服务商的响应调整同时进行的请求数：请求顺利且延迟没有上升时逐步提高并发，
+//! 遇到限流（429）或超时时减半，延迟明显上升时减一，用户无需猜测合适的 `concurrency`。
 
 use crate::error::Result;
-use std::sync::Arc;
-use tokio::sync::Semaphore;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 /// 批处理管理器
 pub struct TranslationBatcher {
@@ -68,3 +72,178 @@ impl TranslationBatcher {
         Ok(results)
     }
 }
+
+/// 一次请求的结果，用于调整并发数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequestOutcome {
+    /// 请求成功，`latency` 为耗时，`tokens` 为生成的 token 数
+    Success { latency: Duration, tokens: u32 },
+    /// 服务商限流或请求超时
+    Overloaded,
+    /// 其他错误，不影响并发数
+    Failed,
+}
+
+/// 并发控制器的状态
+#[derive(Debug, Clone)]
+struct ControllerState {
+    /// 当前的并发上限
+    limit: usize,
+    /// 降低上限后尚未收回的许可数，请求结束时不再归还
+    surplus: usize,
+    /// 平滑后每个生成 token 的耗时（秒）
+    latency: Option<f64>,
+    /// 自上次调整以来顺利完成的请求数
+    successes: usize,
+}
+
+impl ControllerState {
+    /// 按一次请求的结果调整并发上限，并发数在 1 与 `max` 之间
+    ///
+    /// 加性增、乘性减：每顺利完成与当前上限相同数量的请求后上限加一；过载时减半；
+    /// 单个 token 的耗时超过平滑值的两倍时视为服务商开始排队，上限减一。
+    fn adjust(&mut self, outcome: RequestOutcome, max: usize) {
+        match outcome {
+            RequestOutcome::Success { latency, tokens } => {
+                let per_token = latency.as_secs_f64() / f64::from(tokens.max(1));
+                let slow = self
+                    .latency
+                    .is_some_and(|average| per_token > average * 2.0);
+                self.latency = Some(match self.latency {
+                    Some(average) => average * 0.8 + per_token * 0.2,
+                    None => per_token,
+                });
+                if slow {
+                    self.limit = (self.limit - 1).max(1);
+                    self.successes = 0;
+                } else {
+                    self.successes += 1;
+                    if self.successes >= self.limit && self.limit < max {
+                        self.limit += 1;
+                        self.successes = 0;
+                    }
+                }
+            }
+            RequestOutcome::Overloaded => {
+                self.limit = (self.limit / 2).max(1);
+                self.successes = 0;
+            }
+            RequestOutcome::Failed => {}
+        }
+    }
+}
+
+/// 根据服务商的延迟与错误自动调整的并发上限
+///
+/// 每个请求发送前以 [`acquire`](Self::acquire) 取得许可，完成后以 [`release`](Self::release)
+/// 报告结果并归还许可。降低上限时不收回正在进行的请求的许可，而是在它们结束时不再归还。
+pub struct AdaptiveConcurrency {
+    semaphore: Arc<Semaphore>,
+    state: Mutex<ControllerState>,
+    max: usize,
+}
+
+impl AdaptiveConcurrency {
+    /// 从 `initial` 个并发开始，最多 `max` 个
+    pub fn new(initial: usize, max: usize) -> Self {
+        let max = max.max(1);
+        let initial = initial.clamp(1, max);
+        Self {
+            semaphore: Arc::new(Semaphore::new(initial)),
+            state: Mutex::new(ControllerState {
+                limit: initial,
+                surplus: 0,
+                latency: None,
+                successes: 0,
+            }),
+            max,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ControllerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 当前的并发上限
+    pub fn limit(&self) -> usize {
+        self.lock().limit
+    }
+
+    /// 等待一个请求许可
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("concurrency semaphore is never closed")
+    }
+
+    /// 报告请求的结果并归还许可
+    pub fn release(&self, permit: OwnedSemaphorePermit, outcome: RequestOutcome) {
+        let mut state = self.lock();
+        let before = state.limit;
+        state.adjust(outcome, self.max);
+        let after = state.limit;
+        if after > before {
+            // 先抵消尚未收回的许可，其余的新增许可
+            let added = after - before;
+            let offset = added.min(state.surplus);
+            state.surplus -= offset;
+            self.semaphore.add_permits(added - offset);
+            log::debug!("Raised concurrency to {}", after);
+        } else if after < before {
+            state.surplus += before - after;
+            log::warn!(
+                "Provider is {}, reducing concurrency to {}",
+                if outcome == RequestOutcome::Overloaded {
+                    "overloaded"
+                } else {
+                    "slowing down"
+                },
+                after
+            );
+        }
+        if state.surplus > 0 {
+            state.surplus -= 1;
+            permit.forget();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_adaptive_concurrency() {
+        let success = |millis: u64| RequestOutcome::Success {
+            latency: Duration::from_millis(millis),
+            tokens: 100,
+        };
+        let controller = AdaptiveConcurrency::new(2, 4);
+        // 每顺利完成与上限相同数量的请求后上限加一，不超过最大值
+        for _ in 0..20 {
+            let permit = controller.acquire().await;
+            controller.release(permit, success(1000));
+        }
+        assert_eq!(controller.limit(), 4);
+        assert_eq!(controller.semaphore.available_permits(), 4);
+
+        // 过载时减半，正在进行的请求结束后不再归还多出的许可
+        let permits = [controller.acquire().await, controller.acquire().await];
+        let [first, second] = permits;
+        controller.release(first, RequestOutcome::Overloaded);
+        assert_eq!(controller.limit(), 2);
+        controller.release(second, RequestOutcome::Failed);
+        assert_eq!(controller.semaphore.available_permits(), 2);
+
+        // 延迟明显上升时减一，不低于 1
+        let permit = controller.acquire().await;
+        controller.release(permit, success(5000));
+        assert_eq!(controller.limit(), 1);
+        let permit = controller.acquire().await;
+        controller.release(permit, RequestOutcome::Overloaded);
+        assert_eq!(controller.limit(), 1);
+        assert_eq!(controller.semaphore.available_permits(), 1);
+    }
+}
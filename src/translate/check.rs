@@ -0,0 +1,296 @@
+//! 配置检查
+//!
+//! 在翻译之前检查任务配置文件：解析 TOML，验证各项设置与路径，加载术语表与忽略规则，检查提示词模板中的占位符，
+//! 最后发送一个极小的请求确认 API 可以访问。与翻译时不同，检查不在第一个错误处停止，而是列出全部问题。
+
+use super::{
+    ApiClient, Glossary, PROMPT_PLACEHOLDERS, PROMPT_TEMPLATES, ValidationIgnore, user_message,
+};
+use crate::config::{ClientSettings, TaskFileConfig, load_openai_api_key};
+use crate::error::ConfigError;
+use crate::utils::{cached_regex, find_data_file};
+use regex::Regex;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// 一项检查及其发现的问题
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckItem {
+    /// 检查的内容
+    pub name: String,
+    /// 发现的问题，为空时表示通过
+    pub problems: Vec<String>,
+}
+
+/// 配置检查的结果
+#[derive(Debug, Clone, Default)]
+pub struct ConfigCheck {
+    pub items: Vec<CheckItem>,
+    /// 配置文件可以解析时其中的客户端设置，用于检查 API
+    client_settings: Option<ClientSettings>,
+}
+
+impl ConfigCheck {
+    fn push(&mut self, name: impl Into<String>, problems: Vec<String>) {
+        self.items.push(CheckItem {
+            name: name.into(),
+            problems,
+        });
+    }
+
+    /// 发送只要求一个 token 的请求，确认 API 地址、密钥与模型可用；配置文件无法解析时跳过
+    pub async fn check_api(&mut self) {
+        let Some(settings) = self.client_settings.take() else {
+            return;
+        };
+        let name = format!("API {} (model {})", settings.api_base, settings.model);
+        let problems = api_problems(settings).await;
+        self.push(name, problems);
+    }
+
+    /// 问题总数
+    pub fn problem_count(&self) -> usize {
+        self.items.iter().map(|i| i.problems.len()).sum()
+    }
+
+    /// 每项检查一行（`ok` 或 `FAIL`），其后列出该项的问题
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for item in &self.items {
+            let status = if item.problems.is_empty() {
+                "ok"
+            } else {
+                "FAIL"
+            };
+            let _ = writeln!(out, "[{:>4}] {}", status, item.name);
+            for problem in &item.problems {
+                let _ = writeln!(out, "       - {}", problem);
+            }
+        }
+        out
+    }
+}
+
+/// 将配置错误展开为逐条的问题
+fn config_problems(result: Result<(), ConfigError>) -> Vec<String> {
+    fn flatten(error: ConfigError, out: &mut Vec<String>) {
+        match error {
+            ConfigError::MultipleErrors(errors) => {
+                for error in errors {
+                    flatten(error, out);
+                }
+            }
+            error => out.push(error.to_string()),
+        }
+    }
+    let mut out = Vec::new();
+    if let Err(error) = result {
+        flatten(error, &mut out);
+    }
+    out
+}
+
+/// 模板中不认识的 `{{占位符}}`，按出现顺序去重
+pub fn unknown_placeholders(template: &str) -> Vec<String> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = cached_regex(&PATTERN, || r"\{\{\s*([^{}]*?)\s*\}\}");
+    let mut unknown: Vec<String> = Vec::new();
+    for capture in pattern.captures_iter(template) {
+        let name = &capture[1];
+        if !PROMPT_PLACEHOLDERS.contains(&name) && !unknown.iter().any(|u| u == name) {
+            unknown.push(name.to_string());
+        }
+    }
+    unknown
+}
+
+/// 检查术语表：每个名称都能找到文件，且文件可以解析
+fn glossary_problems(names: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for name in names {
+        let paths = [
+            format!("glossary_custom/{}.json", name),
+            format!("glossary/{}.json", name),
+        ];
+        let mut found = false;
+        for relative in &paths {
+            match find_data_file(relative) {
+                Ok(Some(path)) => {
+                    found = true;
+                    if let Err(e) = Glossary::from_json_file(&path) {
+                        problems.push(format!("{}: {}", path.display(), e));
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+        if !found {
+            problems.push(format!(
+                "Glossary '{}' not found in data/{} or data/{}",
+                name, paths[0], paths[1]
+            ));
+        }
+    }
+    problems
+}
+
+/// 检查提示词模板：文件存在，且只使用已知的占位符
+fn prompt_problems() -> Vec<String> {
+    let mut problems = Vec::new();
+    for template in PROMPT_TEMPLATES {
+        let path = match find_data_file(template) {
+            Ok(Some(path)) => path,
+            Ok(None) => {
+                problems.push(format!("Prompt template data/{} not found", template));
+                continue;
+            }
+            Err(e) => {
+                problems.push(e.to_string());
+                continue;
+            }
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                for name in unknown_placeholders(&content) {
+                    problems.push(format!(
+                        "{} uses unknown placeholder {{{{{}}}}} (known: {})",
+                        path.display(),
+                        name,
+                        PROMPT_PLACEHOLDERS.join(", ")
+                    ));
+                }
+            }
+            Err(e) => problems.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    problems
+}
+
+/// 发送只要求一个 token 的请求，返回请求失败的原因
+async fn api_problems(settings: ClientSettings) -> Vec<String> {
+    let api_key = match load_openai_api_key() {
+        Ok(key) => key,
+        Err(e) => return vec![e.to_string()],
+    };
+    let settings = ClientSettings {
+        max_tokens: Some(1),
+        ..settings
+    };
+    let client = match ApiClient::new(settings, api_key) {
+        Ok(client) => client,
+        Err(e) => return vec![e.to_string()],
+    };
+    match client
+        .chat_completions(vec![user_message("ping".to_string())], 0)
+        .await
+    {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+/// 检查任务配置文件中除 API 以外的一切，API 由 [`ConfigCheck::check_api`] 另行检查
+pub fn check_task_file(task_file: &Path) -> ConfigCheck {
+    let mut check = ConfigCheck::default();
+    let name = format!("Parse {}", task_file.display());
+    let config: TaskFileConfig = match std::fs::read_to_string(task_file) {
+        Ok(content) => match toml::from_str(&content) {
+            Ok(config) => config,
+            Err(e) => {
+                check.push(name, config_problems(Err(ConfigError::TomlParse(e))));
+                return check;
+            }
+        },
+        Err(e) => {
+            check.push(name, vec![e.to_string()]);
+            return check;
+        }
+    };
+    check.push(
+        name,
+        if config.task.is_empty() {
+            vec![crate::tr!("config.no_tasks")]
+        } else {
+            Vec::new()
+        },
+    );
+
+    check.push(
+        "Client settings",
+        config_problems(config.client_settings.validate()),
+    );
+    if let Some(notifications) = &config.notifications {
+        check.push("Notifications", config_problems(notifications.validate()));
+    }
+    if let Some(paratranz) = &config.paratranz {
+        check.push("Paratranz", config_problems(paratranz.validate()));
+    }
+    if let Some(package) = &config.package {
+        check.push("Package", config_problems(package.validate()));
+    }
+
+    for task in &config.task {
+        let name = task.localisation_dir.display();
+        check.push(format!("Task {}", name), config_problems(task.validate()));
+        check.push(
+            format!("Glossaries of {}", name),
+            glossary_problems(&task.glossaries),
+        );
+        check.push(
+            format!("Validation ignore rules of {}", name),
+            config_problems(ValidationIgnore::for_task(task).map(|_| ())),
+        );
+    }
+
+    check.push("Prompt templates", prompt_problems());
+    check.client_settings = Some(config.client_settings);
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_task_file() {
+        assert_eq!(
+            unknown_placeholders("{{glossary_csv}} {{ target }} {{target}} {G0}"),
+            ["target"]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let task_file = dir.path().join("task.toml");
+        std::fs::write(
+            &task_file,
+            format!(
+                "[client_settings]\ntemperature = 5.0\ntimeout_secs = 0\n\n[[task]]\nsource_lang = \"english\"\ntarget_langs = [\"simp_chinese\"]\nglossaries = [\"no_such_glossary\"]\nlocalisation_dir = {:?}\npasses = 3\n",
+                dir.path().join("missing")
+            ),
+        )
+        .unwrap();
+
+        let check = check_task_file(&task_file);
+        let problems = |prefix: &str| {
+            check
+                .items
+                .iter()
+                .find(|i| i.name.starts_with(prefix))
+                .map(|i| i.problems.len())
+                .unwrap()
+        };
+        assert_eq!(problems("Parse"), 0);
+        assert_eq!(problems("Client settings"), 2);
+        assert_eq!(problems("Task"), 2);
+        assert_eq!(problems("Glossaries"), 1);
+        assert_eq!(problems("Prompt templates"), 0);
+        assert_eq!(check.problem_count(), 5);
+        assert!(check.render().contains("[FAIL] Client settings\n"));
+
+        std::fs::write(&task_file, "[[task]\n").unwrap();
+        let check = check_task_file(&task_file);
+        assert_eq!(check.items.len(), 1);
+        assert_eq!(check.problem_count(), 1);
+    }
+}
@@ -0,0 +1,235 @@
+//! 切片译文存储
+//!
+//! 以源切片内容的哈希（连同语言对、模型与发送的提示词）为键保存每个切片的译文。源文件只有少量改动时，
+//! 未改动的切片与上次切分的结果完全相同，可以直接复用保存的译文而不必再次请求。
+//! 提示词包括系统提示词中的术语表与切片的上下文块，更新术语表或提示词模板后相应的切片会重新翻译。
+//!
+//! 每个切片保存为存储目录中的一个 JSON 文件，文件中同时记录源切片内容，读取时核对以排除哈希冲突。
+//! 文件先写入临时文件再替换，多个进程或并发的翻译同时写入也不会写出损坏的文件。
+
+use crate::error::Result;
+use crate::utils::{FNV_OFFSET, fnv1a};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 默认的切片译文存储目录（相对于任务配置文件所在目录）
+pub const DEFAULT_CHUNK_STORE_PATH: &str = ".pmt/chunks";
+
+/// 一个切片的译文
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredChunk {
+    source_lang: String,
+    target_lang: String,
+    model: String,
+    /// 源切片内容
+    source: String,
+    /// 译文
+    translation: String,
+}
+
+/// 切片译文存储
+///
+/// 未设置存储目录时不保存也不复用任何译文。
+#[derive(Debug, Default)]
+pub struct ChunkStore {
+    dir: OnceLock<PathBuf>,
+}
+
+static STORE: ChunkStore = ChunkStore::new();
+
+/// 获取全局切片译文存储，翻译器在翻译每个切片前查找、翻译完成后写入
+pub fn chunk_store() -> &'static ChunkStore {
+    &STORE
+}
+
+/// 临时文件名的序号，避免同一进程中并发写入同一切片时共用临时文件
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn chunk_hash(
+    source_lang: &str,
+    target_lang: &str,
+    model: &str,
+    prompt: &str,
+    source: &str,
+) -> u64 {
+    let mut hash = FNV_OFFSET;
+    for part in [source_lang, target_lang, model, prompt, source] {
+        fnv1a(&mut hash, part.as_bytes());
+        // 分隔各部分，避免 ("ab", "c") 与 ("a", "bc") 得到相同的哈希
+        fnv1a(&mut hash, &[0]);
+    }
+    hash
+}
+
+impl ChunkStore {
+    /// 创建未设置存储目录的切片译文存储
+    pub const fn new() -> Self {
+        Self {
+            dir: OnceLock::new(),
+        }
+    }
+
+    /// 创建保存到 `dir` 的切片译文存储
+    pub fn at(dir: &Path) -> Self {
+        let store = Self::new();
+        store.persist_to(dir);
+        store
+    }
+
+    /// 将切片译文保存到 `dir`，目录中已有的译文可以复用
+    pub fn persist_to(&self, dir: &Path) {
+        if self.dir.set(dir.to_path_buf()).is_err() {
+            log::warn!(
+                "Chunk translations are already stored elsewhere, ignoring {:?}",
+                dir
+            );
+        }
+    }
+
+    fn chunk_path(
+        &self,
+        source_lang: &str,
+        target_lang: &str,
+        model: &str,
+        prompt: &str,
+        source: &str,
+    ) -> Option<PathBuf> {
+        let hash = chunk_hash(source_lang, target_lang, model, prompt, source);
+        Some(self.dir.get()?.join(format!("{:016x}.json", hash)))
+    }
+
+    /// 查找源切片在该语言对、模型与提示词下保存的译文
+    ///
+    /// `prompt` 是请求中除源切片外决定译文的内容（系统提示词与上下文块），只参与哈希，不写入文件。
+    pub fn get(
+        &self,
+        source_lang: &str,
+        target_lang: &str,
+        model: &str,
+        prompt: &str,
+        source: &str,
+    ) -> Option<String> {
+        let path = self.chunk_path(source_lang, target_lang, model, prompt, source)?;
+        let content = std::fs::read_to_string(&path).ok()?;
+        let stored: StoredChunk = match serde_json::from_str(&content) {
+            Ok(stored) => stored,
+            Err(e) => {
+                log::warn!("Ignoring corrupted chunk translation {:?}: {}", path, e);
+                return None;
+            }
+        };
+        (stored.source_lang == source_lang
+            && stored.target_lang == target_lang
+            && stored.model == model
+            && stored.source == source)
+            .then_some(stored.translation)
+    }
+
+    /// 保存源切片的译文，未设置存储目录时不做任何事，写入失败只给出警告
+    pub fn put(
+        &self,
+        source_lang: &str,
+        target_lang: &str,
+        model: &str,
+        prompt: &str,
+        source: &str,
+        translation: &str,
+    ) {
+        let Some(path) = self.chunk_path(source_lang, target_lang, model, prompt, source) else {
+            return;
+        };
+        let stored = StoredChunk {
+            source_lang: source_lang.to_string(),
+            target_lang: target_lang.to_string(),
+            model: model.to_string(),
+            source: source.to_string(),
+            translation: translation.to_string(),
+        };
+        if let Err(e) = write_chunk(&path, &stored) {
+            log::warn!("Failed to store chunk translation to {:?}: {}", path, e);
+        }
+    }
+}
+
+fn write_chunk(path: &Path, stored: &StoredChunk) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(stored).map_err(std::io::Error::other)?;
+    let temp = path.with_extension(format!(
+        "{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&temp, content)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = "l_english:\n a:0 \"Alpha\"\n";
+        let translated = "l_simp_chinese:\n a:0 \"阿尔法\"\n";
+
+        assert!(
+            ChunkStore::new()
+                .get("english", "simp_chinese", "m", "p", source)
+                .is_none()
+        );
+
+        let store = ChunkStore::at(dir.path());
+        assert!(
+            store
+                .get("english", "simp_chinese", "m", "p", source)
+                .is_none()
+        );
+        store.put("english", "simp_chinese", "m", "p", source, translated);
+        assert_eq!(
+            store
+                .get("english", "simp_chinese", "m", "p", source)
+                .unwrap(),
+            translated
+        );
+        // 语言对、模型、提示词（例如术语表更新后）或源切片不同时不复用
+        assert!(store.get("english", "german", "m", "p", source).is_none());
+        assert!(
+            store
+                .get("english", "simp_chinese", "m", "p2", source)
+                .is_none()
+        );
+        assert!(
+            store
+                .get("english", "simp_chinese", "other", "p", source)
+                .is_none()
+        );
+        assert!(
+            store
+                .get(
+                    "english",
+                    "simp_chinese",
+                    "m",
+                    "p",
+                    "l_english:\n a:0 \"Beta\"\n"
+                )
+                .is_none()
+        );
+
+        // 另一个进程打开同一目录时可以复用
+        let reopened = ChunkStore::at(dir.path());
+        assert_eq!(
+            reopened
+                .get("english", "simp_chinese", "m", "p", source)
+                .unwrap(),
+            translated
+        );
+        let files: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+}
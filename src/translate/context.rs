@@ -0,0 +1,97 @@
+//! 切片上下文
+//!
+//! 在每个切片前附加一段带分隔符的元数据（文件名、所在分节的注释、键名模式），
+//! 让模型知道正在翻译的是科技名称、事件文本还是提示信息。模型回复中若带有这段内容，
+//! 会在后处理阶段去除。
+//!
+//! 设置了切片重叠时，上下文块中还附带相邻切片边界处的几行原文（`before`、`after`），
+//! 使引用前后条目的句子在切片边界处也能译得一致。这些行只供参考，模型即使翻译了它们，
+//! 译文也会在按键对齐到源切片时被丢弃。
+
+use super::FileChunk;
+use crate::preprocess::parse_entries;
+
+/// 上下文块的起始行
+pub const CONTEXT_BEGIN: &str = "<<<PMT_CONTEXT";
+/// 上下文块的结束行
+pub const CONTEXT_END: &str = "PMT_CONTEXT>>>";
+
+/// 切片中键名的公共前缀，截断到最后一个下划线，例如 `tech_*`
+pub fn key_pattern(content: &str) -> Option<String> {
+    let keys: Vec<String> = parse_entries(content).into_iter().map(|e| e.key).collect();
+    let first = keys.first()?;
+    let mut prefix_len = first.len();
+    for key in &keys[1..] {
+        prefix_len = first
+            .chars()
+            .zip(key.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum::<usize>()
+            .min(prefix_len);
+    }
+    let prefix = &first[..prefix_len];
+    let prefix = &prefix[..prefix.rfind('_')? + 1];
+    Some(format!("{}*", prefix))
+}
+
+/// 生成切片的上下文块
+pub fn render_context_header(chunk: &FileChunk) -> String {
+    let mut header = format!("{}\nfile: {}\n", CONTEXT_BEGIN, chunk.target_filename);
+    if let Some(section) = &chunk.section {
+        header.push_str(&format!("section: {}\n", section));
+    }
+    if let Some(pattern) = key_pattern(&chunk.content) {
+        header.push_str(&format!("keys: {}\n", pattern));
+    }
+    for (label, lines) in [("before", &chunk.preceding), ("after", &chunk.following)] {
+        if !lines.is_empty() {
+            header.push_str(&format!("{}:\n", label));
+            for line in lines {
+                header.push_str(&format!("{}\n", line));
+            }
+        }
+    }
+    header.push_str(CONTEXT_END);
+    header.push('\n');
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_context_header() {
+        let chunk = FileChunk {
+            content: " tech_laser_1:0 \"Red Laser\"\n tech_lasers_2:0 \"Blue Laser\"".to_string(),
+            start_line: 10,
+            end_line: 11,
+            target_filename: "l_simp_chinese_techs.yml".to_string(),
+            section: Some("Weapons".to_string()),
+            preceding: Vec::new(),
+            following: Vec::new(),
+        };
+        assert_eq!(
+            render_context_header(&chunk),
+            "<<<PMT_CONTEXT\nfile: l_simp_chinese_techs.yml\nsection: Weapons\nkeys: tech_*\nPMT_CONTEXT>>>\n"
+        );
+
+        let mut overlapped = chunk.clone();
+        overlapped.preceding = vec![" tech_laser_0:0 \"Laser\"".to_string()];
+        assert!(
+            render_context_header(&overlapped)
+                .ends_with("keys: tech_*\nbefore:\n tech_laser_0:0 \"Laser\"\nPMT_CONTEXT>>>\n")
+        );
+
+        // 模型照抄的上下文块在后处理中去除
+        let response = format!(
+            "{} tech_laser_1:0 \"红色激光\"",
+            render_context_header(&chunk)
+        );
+        assert_eq!(
+            crate::postprocess::strip_context_header(&response),
+            " tech_laser_1:0 \"红色激光\""
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! 按文件保持的会话
+//!
+//! 启用后同一文件的切片在一个会话中依次翻译：系统提示词只随第一个切片发送，之后的切片作为后续的用户消息，
+//! 附带之前各轮的原文与译文。模型可以参照前文保持译法一致，支持提示词缓存的服务商还能命中相同的前缀。
+//! 会话超出模型的上下文长度时丢弃最早的几轮对话，系统提示词始终保留。
+
+use crate::translate::api::ChatMessage;
+use crate::utils::estimate_mixed_tokens;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// 一个文件的会话
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    /// 系统提示词与之前的各轮对话
+    pub messages: Vec<ChatMessage>,
+    /// 系统提示词中已包含的术语（`原文,译文`），之后的切片只需在用户消息中附带其余的术语
+    pub system_terms: HashSet<String>,
+}
+
+/// 各文件的会话，以（目标文件名，目标语言）区分
+#[derive(Debug, Default)]
+pub struct Conversations {
+    sessions: Mutex<HashMap<(String, String), Conversation>>,
+}
+
+impl Conversations {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), Conversation>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 文件当前的会话，尚未开始时返回 None
+    pub fn get(&self, file: &str, target_lang: &str) -> Option<Conversation> {
+        self.lock()
+            .get(&(file.to_string(), target_lang.to_string()))
+            .cloned()
+    }
+
+    /// 保存一轮对话完成后的会话
+    pub fn record(&self, file: &str, target_lang: &str, conversation: Conversation) {
+        self.lock()
+            .insert((file.to_string(), target_lang.to_string()), conversation);
+    }
+
+    /// 文件翻译完成后结束会话，释放其中的消息
+    pub fn end(&self, file: &str, target_lang: &str) {
+        self.lock()
+            .remove(&(file.to_string(), target_lang.to_string()));
+    }
+}
+
+/// 从最早的一轮对话开始丢弃，直到全部消息估算的 token 数不超过 `budget`，返回丢弃的轮数
+///
+/// 消息依次为系统提示词、若干轮（用户消息，回复）与最后一条待发送的用户消息。
+/// 系统提示词与最后一条用户消息不会被丢弃。
+pub fn truncate_conversation(messages: &mut Vec<ChatMessage>, budget: usize) -> usize {
+    let tokens = |messages: &[ChatMessage]| -> usize {
+        messages
+            .iter()
+            .map(|m| estimate_mixed_tokens(&m.content))
+            .sum()
+    };
+    let mut dropped = 0;
+    while messages.len() > 2 && tokens(messages) > budget {
+        messages.drain(1..3);
+        dropped += 1;
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::api::{assistant_message, system_message, user_message};
+
+    #[test]
+    fn test_truncate_conversation() {
+        let turn = "a:0 \"Some fairly long source text\"".repeat(10);
+        let mut messages = vec![system_message("system prompt".to_string())];
+        for i in 0..4 {
+            messages.push(user_message(format!("{} {}", turn, i)));
+            messages.push(assistant_message(format!("{} {}", turn, i)));
+        }
+        messages.push(user_message("latest".to_string()));
+
+        let mut unlimited = messages.clone();
+        assert_eq!(truncate_conversation(&mut unlimited, usize::MAX), 0);
+        assert_eq!(unlimited.len(), 10);
+
+        let turn_tokens = estimate_mixed_tokens(&messages[1].content) * 2;
+        let mut truncated = messages.clone();
+        assert_eq!(
+            truncate_conversation(&mut truncated, turn_tokens * 2 + 10),
+            2
+        );
+        assert_eq!(truncated.len(), 6);
+        assert_eq!(truncated[0].content, "system prompt");
+        assert!(truncated[1].content.ends_with(" 2"));
+        assert_eq!(truncated.last().unwrap().content, "latest");
+
+        // 系统提示词与最后一条用户消息总是保留
+        assert_eq!(truncate_conversation(&mut messages, 0), 4);
+        assert_eq!(messages.len(), 2);
+    }
+}
@@ -0,0 +1,183 @@
+//! 结构差异
+//!
+//! 验证发现问题时，逐条比较原文与译文的结构：键是否存在、各条目中的特殊标记是否一致，
+//! 只列出有差异的条目，在控制台中以颜色标出缺失（红色）与多出（绿色）的键和标记，便于快速定位问题。
+
+use super::FormatValidator;
+use crate::preprocess::parse_entries;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// 一个有差异的条目
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    /// 译文中缺失的键及其原文中的标记
+    Missing { key: String, markers: Vec<String> },
+    /// 译文中多出的键及其标记
+    Extra { key: String, markers: Vec<String> },
+    /// 标记不一致的键
+    Changed {
+        key: String,
+        source: Vec<String>,
+        translated: Vec<String>,
+    },
+}
+
+/// 原文与译文的结构差异
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StructureDiff {
+    pub lines: Vec<DiffLine>,
+}
+
+impl StructureDiff {
+    /// 比较原文与译文（均为若干条目行）的结构
+    pub fn new(validator: &FormatValidator, source: &str, translated: &str) -> Self {
+        let source_entries = parse_entries(source);
+        let translated_entries = parse_entries(translated);
+        let mut translated_values: HashMap<&str, &str> = HashMap::new();
+        for entry in &translated_entries {
+            translated_values
+                .entry(entry.key.as_str())
+                .or_insert(entry.value.as_str());
+        }
+
+        let mut lines = Vec::new();
+        for entry in &source_entries {
+            let markers = validator.extract_markers(&entry.value);
+            match translated_values.get(entry.key.as_str()) {
+                None => lines.push(DiffLine::Missing {
+                    key: entry.key.clone(),
+                    markers,
+                }),
+                Some(value) => {
+                    let translated = validator.extract_markers(value);
+                    if translated != markers {
+                        lines.push(DiffLine::Changed {
+                            key: entry.key.clone(),
+                            source: markers,
+                            translated,
+                        });
+                    }
+                }
+            }
+        }
+        let source_keys: HashSet<&str> = source_entries.iter().map(|e| e.key.as_str()).collect();
+        let mut seen = HashSet::new();
+        for entry in &translated_entries {
+            if !source_keys.contains(entry.key.as_str()) && seen.insert(entry.key.as_str()) {
+                lines.push(DiffLine::Extra {
+                    key: entry.key.clone(),
+                    markers: validator.extract_markers(&entry.value),
+                });
+            }
+        }
+        Self { lines }
+    }
+
+    /// 是否没有差异
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// 每个有差异的条目输出一行：`-` 缺失的键，`+` 多出的键，`~` 标记不一致的键（`原文标记 => 译文标记`）
+    ///
+    /// `color` 为 true 时使用 ANSI 颜色标出缺失与多出的部分。
+    pub fn render(&self, color: bool) -> String {
+        let paint = |text: &str, code: &str| {
+            if color {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+        let list = |markers: &[String], highlighted: &[String], code: &str| {
+            if markers.is_empty() {
+                return "(none)".to_string();
+            }
+            let mut highlighted = highlighted.to_vec();
+            markers
+                .iter()
+                .map(|m| match highlighted.iter().position(|h| h == m) {
+                    Some(i) => {
+                        highlighted.remove(i);
+                        paint(m, code)
+                    }
+                    None => m.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut out = String::new();
+        for line in &self.lines {
+            let _ = match line {
+                DiffLine::Missing { key, markers } => writeln!(
+                    out,
+                    "{} {}",
+                    paint(&format!("- {}:", key), RED),
+                    list(markers, &[], RED)
+                ),
+                DiffLine::Extra { key, markers } => writeln!(
+                    out,
+                    "{} {}",
+                    paint(&format!("+ {}:", key), GREEN),
+                    list(markers, &[], GREEN)
+                ),
+                DiffLine::Changed {
+                    key,
+                    source,
+                    translated,
+                } => {
+                    let missing = remove_all(source, translated);
+                    let extra = remove_all(translated, source);
+                    writeln!(
+                        out,
+                        "{} {} => {}",
+                        paint(&format!("~ {}:", key), YELLOW),
+                        list(source, &missing, RED),
+                        list(translated, &extra, GREEN)
+                    )
+                }
+            };
+        }
+        out
+    }
+}
+
+/// `items` 中去除 `other` 中的元素后剩下的元素（每个只去除一次）
+fn remove_all(items: &[String], other: &[String]) -> Vec<String> {
+    let mut rest = items.to_vec();
+    for item in other {
+        if let Some(i) = rest.iter().position(|v| v == item) {
+            rest.remove(i);
+        }
+    }
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structure_diff() {
+        let validator = FormatValidator::new();
+        let source =
+            " a:0 \"£energy£ $X$ per month\"\n b:0 \"Plain\"\n c:0 \"[Root.GetName] wins\"\n";
+        let translated = " a:0 \"每月 $X$ §Y多§!\"\n c:0 \"[Root.GetName] 获胜\"\n d:0 \"额外\"\n";
+        let diff = StructureDiff::new(&validator, source, translated);
+        assert_eq!(
+            diff.render(false),
+            "~ a: £energy£ $X$ => $X$ §Y §!\n- b: (none)\n+ d: (none)\n"
+        );
+        let colored = diff.render(true);
+        assert!(colored.contains("\x1b[31m£energy£\x1b[0m $X$ => $X$ \x1b[32m§Y\x1b[0m"));
+
+        assert!(StructureDiff::new(&validator, source, source).is_empty());
+    }
+}
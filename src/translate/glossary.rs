@@ -110,6 +110,24 @@ impl GlossaryItem {
         }
     }
 
+    /// 语言在术语表文件中的数字键名（`"1"` 为 english，依此类推），不支持的语言返回 None
+    pub fn field_key(lang: &str) -> Option<&'static str> {
+        let key = match lang {
+            "english" => "1",
+            "simp_chinese" => "2",
+            "spanish" => "3",
+            "french" => "4",
+            "braz_por" => "5",
+            "russian" => "6",
+            "german" => "7",
+            "japanese" => "8",
+            "korean" => "9",
+            "polish" => "10",
+            _ => return None,
+        };
+        Some(key)
+    }
+
     /// 检查是否包含指定语言的术语
     pub fn has_language(&self, lang: &str) -> bool {
         self.get(lang).is_some()
@@ -189,16 +207,14 @@ impl Glossary {
                         }
                         Err(e) => {
                             // 无法解析的值，记录警告并跳过
-                            log::warn!("无法解析术语表条目: key={}, error={}", key, e);
+                            log::warn!("{}", crate::tr!("glossary.bad_entry", key, e));
                         }
                     }
                 }
             }
             _ => {
                 return Err(TranslationError::Translate(
-                    crate::error::TranslateError::GlossaryError(
-                        "术语表文件必须是JSON对象".to_string(),
-                    ),
+                    crate::error::TranslateError::GlossaryError(crate::tr!("glossary.not_object")),
                 ));
             }
         }
@@ -215,11 +231,11 @@ impl Glossary {
         target_lang: &str,
     ) -> HashMap<String, String> {
         let mut map = HashMap::new();
-        for (_key, item) in &self.entries {
-            if let Some(source_term) = item.get(source_lang) {
-                if let Some(target_term) = item.get(target_lang) {
-                    map.insert(source_term.to_string(), target_term.to_string());
-                }
+        for item in self.entries.values() {
+            if let Some(source_term) = item.get(source_lang)
+                && let Some(target_term) = item.get(target_lang)
+            {
+                map.insert(source_term.to_string(), target_term.to_string());
             }
         }
         map
@@ -273,12 +289,11 @@ impl Glossary {
         wtr.push_str(&format!("{},{}\n", source_lang, target_lang));
 
         for word in source_words {
-            if let Some(item) = self.entries.get(*word) {
-                if let Some(source_term) = item.get(source_lang) {
-                    if let Some(target_term) = item.get(target_lang) {
-                        wtr.push_str(&format!("{},{}\n", source_term, target_term));
-                    }
-                }
+            if let Some(item) = self.entries.get(*word)
+                && let Some(source_term) = item.get(source_lang)
+                && let Some(target_term) = item.get(target_lang)
+            {
+                wtr.push_str(&format!("{},{}\n", source_term, target_term));
             }
         }
 
@@ -289,11 +304,11 @@ impl Glossary {
     pub fn find_terms_in_text(&self, text: &str, source_lang: &str) -> Vec<String> {
         let mut found_terms = Vec::new();
         let text = text.to_lowercase();
-        for (_key, item) in &self.entries {
-            if let Some(source_term) = item.get(source_lang) {
-                if text.contains(source_term) {
-                    found_terms.push(source_term.to_string());
-                }
+        for item in self.entries.values() {
+            if let Some(source_term) = item.get(source_lang)
+                && text.contains(source_term)
+            {
+                found_terms.push(source_term.to_string());
             }
         }
         found_terms
@@ -313,14 +328,41 @@ impl Glossary {
     }
 }
 
+/// 按对文本的影响排序术语：在文本中出现次数（不区分大小写）多的在前；
+/// 次数相同时更具体的在前，即词数多的在前，词数也相同时更长的在前。
+///
+/// 提示词放不下全部术语时从末尾删减，排序保证最重要的术语总能保留。
+pub fn rank_terms<T>(items: &mut [T], text: &str, term: impl Fn(&T) -> &str) {
+    let text = text.to_lowercase();
+    items.sort_by_cached_key(|item| {
+        let term = term(item).to_lowercase();
+        let occurrences = if term.is_empty() {
+            0
+        } else {
+            text.matches(term.as_str()).count()
+        };
+        (
+            std::cmp::Reverse(occurrences),
+            std::cmp::Reverse(term.split_whitespace().count()),
+            std::cmp::Reverse(term.chars().count()),
+            term,
+        )
+    });
+}
+
 /// 从 TranslationTask.glossaries 配置中加载所有涉及的术语表，并将其合并为一个 Glossary 对象
 pub fn load_glossaries_from_task(
     task: &crate::config::TranslationTask,
 ) -> Result<crate::translate::Glossary> {
+    load_glossaries(&task.glossaries)
+}
+
+/// 按名称加载术语表并合并，同名的自定义术语表覆盖默认术语表
+pub fn load_glossaries(names: &[String]) -> Result<crate::translate::Glossary> {
     use crate::translate::Glossary;
     use crate::utils::find_data_file;
     let mut glossaries = Vec::new();
-    for glossary_name in &task.glossaries {
+    for glossary_name in names {
         // 先尝试 glossary_custom 目录
         let custom_path = format!("glossary_custom/{}.json", glossary_name);
         let default_path = format!("glossary/{}.json", glossary_name);
@@ -378,6 +420,18 @@ pub fn load_glossaries_from_task(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_rank_terms() {
+        let text =
+            "The Fleet Admiral leads the fleet. Fleet power grows, and the Jump Drive charges.";
+        let mut terms = vec!["jump drive", "admiral", "fleet", "fleet admiral", "psionic"];
+        rank_terms(&mut terms, text, |t| t);
+        assert_eq!(
+            terms,
+            ["fleet", "fleet admiral", "jump drive", "admiral", "psionic"]
+        );
+    }
+
     #[test]
     fn test_glossary_item_deserialize_new_format() {
         let json = r#"{"1": "energy", "2": "能量", "3": "energía"}"#;
@@ -471,13 +525,13 @@ mod tests {
                     }
                     Err(e) => {
                         // 无法解析的值，记录警告并跳过
-                        log::warn!("无法解析术语表条目: key={}, error={}", key, e);
+                        log::warn!("{}", crate::tr!("glossary.bad_entry", key, e));
                     }
                 }
             }
         } else {
             return Err(TranslationError::Translate(
-                crate::error::TranslateError::GlossaryError("术语表文件必须是JSON对象".to_string()),
+                crate::error::TranslateError::GlossaryError(crate::tr!("glossary.not_object")),
             ));
         }
 
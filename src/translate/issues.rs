@@ -0,0 +1,166 @@
+//! 验证问题汇总
+//!
+//! 翻译与验证过程中发现的问题先记入全局的问题汇总，不在控制台逐条输出（完整内容仍写入日志文件），
+//! 运行结束时按检查规则与文件统计后输出摘要，需要时再展开全部问题。
+
+use super::{Problem, StructureDiff};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// 摘要中列出的问题最多的文件数
+const TOP_FILES: usize = 5;
+
+/// 验证问题汇总
+///
+/// 内部加锁，可在并发翻译时通过共享引用记录。
+#[derive(Debug, Default)]
+pub struct IssueReport {
+    /// (文件名, 问题)，按记录的顺序排列
+    issues: Mutex<Vec<(String, Problem)>>,
+    /// (文件名, 有问题的切片的结构差异)
+    diffs: Mutex<Vec<(String, StructureDiff)>>,
+}
+
+static REPORT: IssueReport = IssueReport::new();
+
+/// 获取全局问题汇总，翻译与验证发现的问题都记入其中
+pub fn issue_report() -> &'static IssueReport {
+    &REPORT
+}
+
+impl IssueReport {
+    /// 创建空的问题汇总
+    pub const fn new() -> Self {
+        Self {
+            issues: Mutex::new(Vec::new()),
+            diffs: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(String, Problem)>> {
+        self.issues.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记录文件中的一个问题
+    pub fn record(&self, file: &str, problem: Problem) {
+        log::debug!("Found issue in {}: {}", file, problem);
+        self.lock().push((file.to_string(), problem));
+    }
+
+    /// 记录文件中有问题的切片的结构差异，展开全部问题时一并输出
+    pub fn record_diff(&self, file: &str, diff: StructureDiff) {
+        if diff.is_empty() {
+            return;
+        }
+        self.diffs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((file.to_string(), diff));
+    }
+
+    /// 已记录的问题数
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// 是否没有记录任何问题
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// 输出摘要：问题总数、各检查规则的问题数与问题最多的几个文件
+    pub fn render_summary(&self) -> String {
+        let issues = self.lock();
+        let mut by_rule: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut by_file: BTreeMap<&str, usize> = BTreeMap::new();
+        for (file, problem) in issues.iter() {
+            *by_rule.entry(problem.rule()).or_default() += 1;
+            *by_file.entry(file).or_default() += 1;
+        }
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{} issues in {} files", issues.len(), by_file.len());
+        let mut rules: Vec<(&str, usize)> = by_rule.into_iter().collect();
+        rules.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let _ = writeln!(out, "{:<48} {:>8}", "Rule", "issues");
+        for (rule, count) in rules {
+            let _ = writeln!(out, "{:<48} {:>8}", rule, count);
+        }
+        let mut files: Vec<(&str, usize)> = by_file.into_iter().collect();
+        files.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let _ = writeln!(out, "{:<48} {:>8}", "File", "issues");
+        for (file, count) in files.iter().take(TOP_FILES) {
+            let _ = writeln!(out, "{:<48} {:>8}", file, count);
+        }
+        if files.len() > TOP_FILES {
+            let _ = writeln!(out, "... and {} more files", files.len() - TOP_FILES);
+        }
+        out
+    }
+
+    /// 按文件分组输出全部问题，每个文件的问题之后是其结构差异，`color` 为 true 时以颜色标出差异
+    pub fn render_details(&self, color: bool) -> String {
+        let issues = self.lock();
+        let diffs = self.diffs.lock().unwrap_or_else(|e| e.into_inner());
+        let mut by_file: BTreeMap<&str, Vec<&Problem>> = BTreeMap::new();
+        for (file, problem) in issues.iter() {
+            by_file.entry(file).or_default().push(problem);
+        }
+        let mut out = String::new();
+        for (file, problems) in by_file {
+            let _ = writeln!(out, "{}:", file);
+            for (i, problem) in problems.iter().enumerate() {
+                let _ = writeln!(out, "  {}. {}", i + 1, problem);
+            }
+            for (_, diff) in diffs.iter().filter(|(f, _)| f == file) {
+                for line in diff.render(color).lines() {
+                    let _ = writeln!(out, "    {}", line);
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_summary() {
+        let report = IssueReport::new();
+        let missing = |key: &str| Problem::MissingKey {
+            key: key.to_string(),
+        };
+        report.record("a_l_simp_chinese.yml", missing("k1"));
+        report.record("b_l_simp_chinese.yml", missing("k2"));
+        report.record(
+            "b_l_simp_chinese.yml",
+            Problem::PatternNotFound {
+                key: "k3".to_string(),
+                original: "$x$".to_string(),
+            },
+        );
+
+        let summary = report.render_summary();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[0], "3 issues in 2 files");
+        assert!(lines[2].starts_with("missing_key") && lines[2].ends_with(" 2"));
+        assert!(lines[5].starts_with("b_l_simp_chinese.yml") && lines[5].ends_with(" 2"));
+
+        report.record_diff(
+            "b_l_simp_chinese.yml",
+            StructureDiff::new(
+                &crate::translate::FormatValidator::new(),
+                " k2:0 \"x\"\n",
+                "",
+            ),
+        );
+        let details = report.render_details(false);
+        assert!(
+            details.ends_with("  2. Pattern not found for key 'k3' in '$x$'\n    - k2: (none)\n")
+        );
+        assert!(details.starts_with("a_l_simp_chinese.yml:\n  1. Missing key 'k1'\n"));
+    }
+}
@@ -0,0 +1,337 @@
+//! 翻译记忆模块
+//!
+//! 以 JSON 文件保存「源文本 => 译文」的对照，记录每条译文的来源（AI 或人工审校），
+//! 供后续运行复用已确认的译文。
+//!
+//! 同一进程中并发的翻译通过 `SharedMemory` 共享翻译记忆；多个进程同时写入同一文件时，
+//! 保存前先获取文件锁并合并其他进程已写入的条目，再以原子替换的方式写回，避免互相覆盖或写出损坏的文件。
+
+use crate::error::{Result, TranslateError, TranslationError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// 默认的翻译记忆文件路径
+pub const DEFAULT_MEMORY_PATH: &str = ".pmt/memory.json";
+/// 等待其他进程释放文件锁的最长时间
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+/// 超过该时间仍未释放的文件锁视为进程异常退出后的残留
+const STALE_LOCK_AGE: Duration = Duration::from_secs(120);
+
+/// 译文来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryOrigin {
+    /// 由大模型翻译
+    Ai,
+    /// 经人工审校确认或修改
+    Human,
+}
+
+/// 翻译记忆条目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    /// 译文
+    pub target: String,
+    /// 译文来源
+    pub origin: MemoryOrigin,
+}
+
+/// 翻译记忆
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TranslationMemory {
+    /// 语言对（`english>simp_chinese`） -> 源文本 -> 条目
+    entries: HashMap<String, HashMap<String, MemoryEntry>>,
+    #[serde(skip)]
+    path: PathBuf,
+    /// 打开后新记录的条目（语言对、源文本、条目），保存时合并到文件中的最新内容上
+    #[serde(skip)]
+    pending: Vec<(String, String, MemoryEntry)>,
+}
+
+fn memory_error(message: String) -> TranslationError {
+    TranslationError::Translate(TranslateError::MemoryError(message))
+}
+
+fn pair_key(source_lang: &str, target_lang: &str) -> String {
+    format!("{}>{}", source_lang, target_lang)
+}
+
+impl TranslationMemory {
+    /// 从文件加载翻译记忆，文件不存在时返回空的翻译记忆
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut memory = Self::read(path)?;
+        memory.path = path.to_path_buf();
+        Ok(memory)
+    }
+
+    /// 读取文件中的翻译记忆，文件不存在时返回空的翻译记忆
+    fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(TranslationMemory::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str::<TranslationMemory>(&content).map_err(|e| {
+            memory_error(format!(
+                "Failed to parse translation memory {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// 查找源文本的译文
+    pub fn lookup(
+        &self,
+        source_lang: &str,
+        target_lang: &str,
+        source: &str,
+    ) -> Option<&MemoryEntry> {
+        self.entries
+            .get(&pair_key(source_lang, target_lang))?
+            .get(source)
+    }
+
+    /// 记录译文，人工审校的译文不会被 AI 译文覆盖
+    pub fn record(
+        &mut self,
+        source_lang: &str,
+        target_lang: &str,
+        source: &str,
+        target: &str,
+        origin: MemoryOrigin,
+    ) {
+        let pair = pair_key(source_lang, target_lang);
+        let entry = MemoryEntry {
+            target: target.to_string(),
+            origin,
+        };
+        if self.insert(&pair, source, entry.clone()) {
+            self.pending.push((pair, source.to_string(), entry));
+        }
+    }
+
+    /// 插入条目，返回是否写入（人工审校的译文不会被 AI 译文覆盖）
+    fn insert(&mut self, pair: &str, source: &str, entry: MemoryEntry) -> bool {
+        let pair = self.entries.entry(pair.to_string()).or_default();
+        if entry.origin == MemoryOrigin::Ai
+            && pair
+                .get(source)
+                .is_some_and(|e| e.origin == MemoryOrigin::Human)
+        {
+            return false;
+        }
+        pair.insert(source.to_string(), entry);
+        true
+    }
+
+    /// 条目总数
+    pub fn len(&self) -> usize {
+        self.entries.values().map(|m| m.len()).sum()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 将翻译记忆写回文件
+    ///
+    /// 持有文件锁期间重新读取文件，将打开后新记录的条目合并到其他进程已写入的内容上，
+    /// 先写入临时文件再替换原文件，写入中断时原文件保持完整。
+    pub fn save(&mut self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let _lock = FileLock::acquire(&self.path)?;
+
+        let mut merged = Self::read(&self.path)?;
+        for (pair, source, entry) in &self.pending {
+            merged.insert(pair, source, entry.clone());
+        }
+        let content =
+            serde_json::to_string_pretty(&merged).map_err(|e| memory_error(e.to_string()))?;
+        let temp = self.path.with_extension("json.tmp");
+        std::fs::write(&temp, content)?;
+        std::fs::rename(&temp, &self.path)?;
+
+        self.entries = merged.entries;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// 翻译记忆文件的锁，以 `<文件名>.lock` 文件表示，释放时删除
+struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// 获取文件锁，其他进程持有锁时等待，超时返回错误
+    fn acquire(memory_path: &Path) -> Result<Self> {
+        let path = memory_path.with_extension("json.lock");
+        let start = Instant::now();
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|t| t.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK_AGE);
+                    if stale {
+                        log::warn!("Removing stale translation memory lock {:?}", path);
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    if start.elapsed() > LOCK_TIMEOUT {
+                        return Err(memory_error(format!(
+                            "Timed out waiting for translation memory lock {}",
+                            path.display()
+                        )));
+                    }
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// 可在并发的翻译之间共享的翻译记忆
+///
+/// 只在查找与记录的瞬间持有锁，不会跨越 `.await`，可以放心地在异步任务间克隆使用。
+#[derive(Debug, Clone, Default)]
+pub struct SharedMemory(Arc<RwLock<TranslationMemory>>);
+
+impl SharedMemory {
+    pub fn new(memory: TranslationMemory) -> Self {
+        Self(Arc::new(RwLock::new(memory)))
+    }
+
+    /// 查找源文本的译文
+    pub fn lookup(
+        &self,
+        source_lang: &str,
+        target_lang: &str,
+        source: &str,
+    ) -> Option<MemoryEntry> {
+        let memory = self.0.read().unwrap_or_else(|e| e.into_inner());
+        memory.lookup(source_lang, target_lang, source).cloned()
+    }
+
+    /// 记录译文，人工审校的译文不会被 AI 译文覆盖
+    pub fn record(
+        &self,
+        source_lang: &str,
+        target_lang: &str,
+        source: &str,
+        target: &str,
+        origin: MemoryOrigin,
+    ) {
+        let mut memory = self.0.write().unwrap_or_else(|e| e.into_inner());
+        memory.record(source_lang, target_lang, source, target, origin);
+    }
+
+    /// 条目总数
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 将翻译记忆写回文件
+    pub fn save(&self) -> Result<()> {
+        self.0.write().unwrap_or_else(|e| e.into_inner()).save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_entries_win_over_ai() {
+        let mut memory = TranslationMemory::default();
+        memory.record(
+            "english",
+            "simp_chinese",
+            "Energy",
+            "能源",
+            MemoryOrigin::Human,
+        );
+        memory.record(
+            "english",
+            "simp_chinese",
+            "Energy",
+            "能量",
+            MemoryOrigin::Ai,
+        );
+        let entry = memory.lookup("english", "simp_chinese", "Energy").unwrap();
+        assert_eq!(entry.target, "能源");
+        assert_eq!(entry.origin, MemoryOrigin::Human);
+        assert!(memory.lookup("english", "french", "Energy").is_none());
+    }
+
+    #[test]
+    fn test_concurrent_saves_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memory.json");
+
+        // 两个进程各自打开翻译记忆并记录不同的条目，后保存的不会覆盖先保存的
+        let mut first = TranslationMemory::open(&path).unwrap();
+        let second = SharedMemory::new(TranslationMemory::open(&path).unwrap());
+        first.record(
+            "english",
+            "simp_chinese",
+            "Energy",
+            "能源",
+            MemoryOrigin::Human,
+        );
+        std::thread::scope(|scope| {
+            for (source, target) in [("Minerals", "矿物"), ("Food", "食物")] {
+                let second = second.clone();
+                scope.spawn(move || {
+                    second.record("english", "simp_chinese", source, target, MemoryOrigin::Ai)
+                });
+            }
+        });
+        second.record(
+            "english",
+            "simp_chinese",
+            "Energy",
+            "能量",
+            MemoryOrigin::Ai,
+        );
+        first.save().unwrap();
+        second.save().unwrap();
+
+        let memory = TranslationMemory::open(&path).unwrap();
+        assert_eq!(memory.len(), 3);
+        assert_eq!(
+            memory
+                .lookup("english", "simp_chinese", "Energy")
+                .unwrap()
+                .target,
+            "能源"
+        );
+        assert!(!dir.path().join("memory.json.lock").exists());
+    }
+}
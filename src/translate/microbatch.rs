@@ -0,0 +1,116 @@
+//! 小文件合并翻译
+//!
+//! Mod 中常有许多只有几个条目的小文件，逐个翻译时每个文件都要单独发送一次系统提示词与术语表。
+//! 合并翻译时若干小文件的待翻译内容拼接为一个请求，各文件之前加一行分隔注释，
+//! 译文按键拆回各文件，因此同一批次中的文件不能有重复的键。
+
+use crate::preprocess::parse_entries;
+use crate::utils::estimate_mixed_tokens;
+use std::collections::HashSet;
+
+/// 合并翻译时各文件之前的分隔注释，后接目标文件名
+pub const FILE_DELIMITER: &str = "# ===== file:";
+
+/// 合并翻译的一个小文件
+#[derive(Debug, Clone)]
+pub struct BatchFile {
+    /// 目标文件名，写入分隔注释
+    pub name: String,
+    /// 待翻译的内容
+    pub pending: String,
+}
+
+impl BatchFile {
+    /// 连同分隔注释估算的 token 数
+    pub fn tokens(&self) -> usize {
+        estimate_mixed_tokens(&self.pending) + estimate_mixed_tokens(&self.delimiter())
+    }
+
+    fn delimiter(&self) -> String {
+        format!("{} {} =====", FILE_DELIMITER, self.name)
+    }
+}
+
+/// 按顺序将文件装入批次，返回各批次中文件的下标
+///
+/// 每个批次估算的 token 数不超过 `max_tokens`（单个文件超出时独占一个批次），
+/// 与批次中已有文件的键重复的文件另起一批。没有待翻译内容的文件不计入任何批次。
+pub fn micro_batches(files: &[BatchFile], max_tokens: usize) -> Vec<Vec<usize>> {
+    let mut batches = Vec::new();
+    let mut current = Vec::new();
+    let mut tokens = 0;
+    let mut keys = HashSet::new();
+    for (index, file) in files.iter().enumerate() {
+        let file_keys: Vec<String> = parse_entries(&file.pending)
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        if file_keys.is_empty() {
+            continue;
+        }
+        let file_tokens = file.tokens();
+        let clashes = file_keys.iter().any(|k| keys.contains(k));
+        if !current.is_empty() && (tokens + file_tokens > max_tokens || clashes) {
+            batches.push(std::mem::take(&mut current));
+            tokens = 0;
+            keys.clear();
+        }
+        current.push(index);
+        tokens += file_tokens;
+        keys.extend(file_keys);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+/// 将一个批次中各文件的待翻译内容拼接为一段内容，每个文件之前是一行分隔注释
+pub fn combine_batch(files: &[&BatchFile]) -> String {
+    let mut combined = String::new();
+    for file in files {
+        combined.push_str(&file.delimiter());
+        combined.push('\n');
+        combined.push_str(file.pending.trim_end_matches('\n'));
+        combined.push('\n');
+    }
+    combined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_micro_batches() {
+        let file = |name: &str, pending: &str| BatchFile {
+            name: name.to_string(),
+            pending: pending.to_string(),
+        };
+        let files = [
+            file(
+                "a_l_simp_chinese.yml",
+                " a_1:0 \"Alpha\"\n a_2:0 \"Beta\"\n",
+            ),
+            file("b_l_simp_chinese.yml", " b_1:0 \"Gamma\"\n"),
+            file("empty_l_simp_chinese.yml", ""),
+            file("dup_l_simp_chinese.yml", " a_1:0 \"Alpha again\"\n"),
+            file("c_l_simp_chinese.yml", " c_1:0 \"Delta\"\n"),
+        ];
+        // 重复的键另起一批，没有待翻译内容的文件被跳过
+        assert_eq!(micro_batches(&files, 10_000), [vec![0, 1], vec![3, 4]]);
+        // 超出预算时另起一批，单个文件超出预算时独占一个批次
+        assert_eq!(
+            micro_batches(&files, 1),
+            [vec![0], vec![1], vec![3], vec![4]]
+        );
+
+        let combined = combine_batch(&[&files[0], &files[1]]);
+        assert_eq!(
+            combined,
+            "# ===== file: a_l_simp_chinese.yml =====\n a_1:0 \"Alpha\"\n a_2:0 \"Beta\"\n\
+             # ===== file: b_l_simp_chinese.yml =====\n b_1:0 \"Gamma\"\n"
+        );
+        assert_eq!(parse_entries(&combined).len(), 3);
+    }
+}
@@ -2,16 +2,62 @@
 //!
 //! 负责与大模型API交互，加载术语表，执行翻译并验证结果。
 
+mod ab;
 mod api;
 mod batcher;
+mod check;
+mod chunk_store;
+mod context;
+mod conversation;
+mod diff;
 mod glossary;
+mod issues;
+mod memory;
+mod microbatch;
+mod overrides;
+mod pivot;
+mod preview;
+mod quality;
+mod references;
+mod repair;
+mod reuse;
+mod session_glossary;
 mod splitter;
+mod terms;
 mod translator;
+mod tune;
+mod usage;
+mod validation_ignore;
 mod validator;
+mod vanilla;
+mod vanilla_index;
 
+pub use ab::*;
 pub use api::*;
 pub use batcher::*;
+pub use check::*;
+pub use chunk_store::*;
+pub use context::*;
+pub use conversation::*;
+pub use diff::*;
 pub use glossary::*;
+pub use issues::*;
+pub use memory::*;
+pub use microbatch::*;
+pub use overrides::*;
+pub use pivot::*;
+pub use preview::*;
+pub use quality::*;
+pub use references::*;
+pub use repair::*;
+pub use reuse::*;
+pub use session_glossary::*;
 pub use splitter::*;
+pub use terms::*;
 pub use translator::*;
+pub use tune::*;
+pub use usage::*;
+pub use validation_ignore::*;
 pub use validator::*;
+pub use vanilla::*;
+pub use vanilla_index::*;
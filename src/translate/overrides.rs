@@ -0,0 +1,107 @@
+//! 人工维护的译文
+//!
+//! `overrides/<目标语言>/` 中手工维护的 YAML 文件里的条目总是优先于 AI 译文：重建目标文件时直接写入，
+//! 这些条目也不再交给大模型翻译，人工修正的译文不会在再次运行时被覆盖。
+
+use crate::error::Result;
+use crate::preprocess::parse_entries;
+use crate::utils::{find_localisation_files, read_file_with_bom};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 人工维护的译文（目标语言 -> 键 -> 译文）
+#[derive(Debug, Default)]
+pub struct HumanOverrides {
+    entries: HashMap<String, HashMap<String, String>>,
+}
+
+impl HumanOverrides {
+    /// 从 `dir/<目标语言>/` 下的本地化文件加载各目标语言的译文，没有该目录的语言没有人工译文
+    ///
+    /// 同一个键在多个文件中出现时，按路径排序后靠后的文件优先，与游戏的加载顺序一致。
+    pub fn load(dir: &Path, target_langs: &[String]) -> Result<Self> {
+        let mut overrides = Self::default();
+        for lang in target_langs {
+            let lang_dir = dir.join(lang);
+            if !lang_dir.is_dir() {
+                continue;
+            }
+            let mut files = find_localisation_files(&lang_dir)?;
+            crate::preprocess::sort_by_load_order(&mut files);
+            let entries = overrides.entries.entry(lang.clone()).or_default();
+            for file in files {
+                for entry in parse_entries(&read_file_with_bom(&file)?) {
+                    entries.insert(entry.key, entry.value);
+                }
+            }
+            log::info!(
+                "Loaded {} human {} translations from {:?}",
+                entries.len(),
+                lang,
+                lang_dir
+            );
+        }
+        Ok(overrides)
+    }
+
+    /// 任务的人工译文目录存在时加载其中的译文
+    pub fn for_task(task: &crate::config::TranslationTask) -> Result<Option<Self>> {
+        if !task.overrides_dir.is_dir() {
+            return Ok(None);
+        }
+        Self::load(&task.overrides_dir, &task.target_langs).map(Some)
+    }
+
+    /// 键在目标语言下的人工译文
+    pub fn get(&self, target_lang: &str, key: &str) -> Option<&str> {
+        self.entries.get(target_lang)?.get(key).map(String::as_str)
+    }
+
+    /// 找出内容中有人工译文的条目，返回「键 -> 译文」
+    pub fn translations(&self, content: &str, target_lang: &str) -> HashMap<String, String> {
+        parse_entries(content)
+            .into_iter()
+            .filter_map(|e| {
+                self.get(target_lang, &e.key)
+                    .map(|value| (e.key, value.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let lang_dir = dir.path().join("simp_chinese");
+        std::fs::create_dir_all(lang_dir.join("replace")).unwrap();
+        std::fs::write(
+            lang_dir.join("a_l_simp_chinese.yml"),
+            "l_simp_chinese:\n a:0 \"甲\"\n b:0 \"乙\"\n",
+        )
+        .unwrap();
+        // replace 中的文件后加载，覆盖同名键
+        std::fs::write(
+            lang_dir.join("replace/z_l_simp_chinese.yml"),
+            "l_simp_chinese:\n b:0 \"乙（修正）\"\n",
+        )
+        .unwrap();
+
+        let overrides = HumanOverrides::load(
+            dir.path(),
+            &["simp_chinese".to_string(), "german".to_string()],
+        )
+        .unwrap();
+        let known = overrides.translations(
+            "l_english:\n a:0 \"A\"\n b:0 \"B\"\n c:0 \"C\"\n",
+            "simp_chinese",
+        );
+        assert_eq!(known.len(), 2);
+        assert_eq!(known["a"], "甲");
+        assert_eq!(known["b"], "乙（修正）");
+        assert!(overrides.get("german", "a").is_none());
+    }
+}
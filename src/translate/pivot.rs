@@ -0,0 +1,57 @@
+//! 经中转语言翻译
+//!
+//! 缺少语料的语言对先译为中转语言（通常是 english），再由中转语言译为目标语言。
+//! 中转译文旁保存源文件内容的哈希，源文件未改动时直接复用中转译文；
+//! 翻译到多个目标语言时中转语言也只需翻译一次。
+
+use crate::error::Result;
+use crate::utils::{FNV_OFFSET, fnv1a};
+use std::path::{Path, PathBuf};
+
+/// 中转译文对应的源文件哈希的保存路径：`<中转译文>.source`
+fn source_hash_path(pivot_file: &Path) -> PathBuf {
+    let mut name = pivot_file.as_os_str().to_os_string();
+    name.push(".source");
+    PathBuf::from(name)
+}
+
+fn source_hash(source: &str) -> String {
+    let mut hash = FNV_OFFSET;
+    fnv1a(&mut hash, source.as_bytes());
+    format!("{:016x}", hash)
+}
+
+/// 中转译文是否存在且由内容为 `source` 的源文件译出，是则可以直接复用
+pub fn pivot_is_current(pivot_file: &Path, source: &str) -> bool {
+    pivot_file.is_file()
+        && std::fs::read_to_string(source_hash_path(pivot_file))
+            .is_ok_and(|hash| hash.trim() == source_hash(source))
+}
+
+/// 中转译文写入后记录其源文件内容的哈希
+pub fn record_pivot_source(pivot_file: &Path, source: &str) -> Result<()> {
+    std::fs::write(source_hash_path(pivot_file), source_hash(source))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pivot_is_current() {
+        let dir = tempfile::tempdir().unwrap();
+        let pivot = dir.path().join("english/a_l_english.yml");
+        let source = "l_polish:\n a:0 \"Tak\"\n";
+        assert!(!pivot_is_current(&pivot, source));
+
+        std::fs::create_dir_all(pivot.parent().unwrap()).unwrap();
+        std::fs::write(&pivot, "l_english:\n a:0 \"Yes\"\n").unwrap();
+        // 没有记录源文件哈希的中转译文不复用
+        assert!(!pivot_is_current(&pivot, source));
+        record_pivot_source(&pivot, source).unwrap();
+        assert!(pivot_is_current(&pivot, source));
+        assert!(dir.path().join("english/a_l_english.yml.source").is_file());
+        assert!(!pivot_is_current(&pivot, "l_polish:\n a:0 \"Nie\"\n"));
+    }
+}
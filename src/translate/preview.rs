@@ -0,0 +1,217 @@
+//! 抽样预览
+//!
+//! 完整翻译前，从每个源文件中随机抽取少量条目翻译并生成预览报告，
+//! 以较低的成本检查译文风格与术语表的效果。
+
+use super::{FormatValidator, Translator, entries_to_content, load_glossaries_from_task};
+use crate::config::{ClientSettings, TranslationTask};
+use crate::error::Result;
+use crate::preprocess::{LocEntry, parse_entries};
+use crate::translate_content;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// 预览中的一个条目
+#[derive(Debug, Clone)]
+pub struct PreviewEntry {
+    pub key: String,
+    pub source: String,
+    pub target: String,
+    pub issues: Vec<String>,
+}
+
+/// 一个源文件的预览结果
+#[derive(Debug, Clone)]
+pub struct PreviewFile {
+    pub file: String,
+    pub total_entries: usize,
+    pub entries: Vec<PreviewEntry>,
+}
+
+/// 由种子生成的伪随机数序列（xorshift64*），足以打乱顺序，不需要密码学强度
+pub(crate) fn xorshift(seed: u64) -> impl FnMut() -> u64 {
+    let mut state = seed | 1;
+    move || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+/// 从条目中随机抽取 `n` 条，保持原有顺序；相同的 `seed` 得到相同的样本
+pub fn sample_random<T: Clone>(items: &[T], n: usize, seed: u64) -> Vec<T> {
+    if items.len() <= n {
+        return items.to_vec();
+    }
+    let mut next = xorshift(seed);
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+    for i in 0..n {
+        let j = i + (next() % (items.len() - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    let mut chosen = indices[..n].to_vec();
+    chosen.sort_unstable();
+    chosen.into_iter().map(|i| items[i].clone()).collect()
+}
+
+/// 翻译每个源文件中随机抽取的 `sample` 个条目
+pub async fn run_preview(
+    task: &TranslationTask,
+    client_settings: ClientSettings,
+    target_lang: &str,
+    sample: usize,
+    seed: u64,
+) -> Result<Vec<PreviewFile>> {
+    let max_chunk_tokens = client_settings.max_chunk_tokens;
+    let translator = Translator::for_task(client_settings, load_glossaries_from_task(task)?, task)?;
+    let validator = FormatValidator::for_task(task);
+
+    let mut files = Vec::new();
+    let source = task.source()?;
+    for path in source.list_files(&task.source_lang)? {
+        let all: Vec<LocEntry> = parse_entries(&source.read_file(&path)?);
+        let entries = sample_random(&all, sample, seed);
+        if entries.is_empty() {
+            continue;
+        }
+        let filename = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        log::info!(
+            "Previewing {} of {} entries in {}",
+            entries.len(),
+            all.len(),
+            filename
+        );
+        let translated = translate_content(
+            &translator,
+            &task.source_lang,
+            target_lang,
+            max_chunk_tokens,
+            &task.target_filename(&filename, target_lang),
+            &entries_to_content(&task.source_lang, &entries),
+            &Default::default(),
+        )
+        .await?;
+        let values: HashMap<String, String> = parse_entries(&translated)
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+        let entries = entries
+            .into_iter()
+            .map(|e| {
+                let target = values.get(&e.key).cloned().unwrap_or_default();
+                let issues = validator
+                    .validate_entry(&e.key, &e.value, &target)
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect();
+                PreviewEntry {
+                    key: e.key,
+                    source: e.value,
+                    target,
+                    issues,
+                }
+            })
+            .collect();
+        files.push(PreviewFile {
+            file: filename,
+            total_entries: all.len(),
+            entries,
+        });
+    }
+    Ok(files)
+}
+
+/// 表格单元格中的 `|` 与换行需要转义
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// 生成 Markdown 预览报告
+pub fn render_preview_report(files: &[PreviewFile], model: &str, target_lang: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# 翻译预览\n");
+    let _ = writeln!(out, "- 模型：{}", model);
+    let _ = writeln!(out, "- 目标语言：{}", target_lang);
+    let sampled: usize = files.iter().map(|f| f.entries.len()).sum();
+    let total: usize = files.iter().map(|f| f.total_entries).sum();
+    let flagged = files
+        .iter()
+        .flat_map(|f| &f.entries)
+        .filter(|e| !e.issues.is_empty())
+        .count();
+    let _ = writeln!(
+        out,
+        "- 抽样条目：{} / {}，其中 {} 条存在问题",
+        sampled, total, flagged
+    );
+    for file in files {
+        let _ = writeln!(
+            out,
+            "\n## {}（{} / {}）\n",
+            file.file,
+            file.entries.len(),
+            file.total_entries
+        );
+        let _ = writeln!(out, "| 键 | 原文 | 译文 | 问题 |");
+        let _ = writeln!(out, "| --- | --- | --- | --- |");
+        for entry in &file.entries {
+            let _ = writeln!(
+                out,
+                "| `{}` | {} | {} | {} |",
+                entry.key,
+                cell(&entry.source),
+                cell(&entry.target),
+                cell(&entry.issues.join("; "))
+            );
+        }
+    }
+    out
+}
+
+/// 写入预览报告
+pub fn write_preview_report(
+    files: &[PreviewFile],
+    model: &str,
+    target_lang: &str,
+    output: &Path,
+) -> Result<()> {
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(output, render_preview_report(files, model, target_lang))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_random_and_report() {
+        let items: Vec<usize> = (0..100).collect();
+        let sample = sample_random(&items, 5, 42);
+        assert_eq!(sample.len(), 5);
+        assert!(sample.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(sample, sample_random(&items, 5, 42));
+        assert_eq!(sample_random(&items[..3], 5, 42), [0, 1, 2]);
+
+        let files = vec![PreviewFile {
+            file: "l_english_a.yml".to_string(),
+            total_entries: 10,
+            entries: vec![PreviewEntry {
+                key: "a".to_string(),
+                source: "A | B".to_string(),
+                target: "甲".to_string(),
+                issues: vec!["Missing key 'a'".to_string()],
+            }],
+        }];
+        let report = render_preview_report(&files, "deepseek-chat", "simp_chinese");
+        assert!(report.contains("- 抽样条目：1 / 10，其中 1 条存在问题"));
+        assert!(report.contains("| `a` | A \\| B | 甲 | Missing key 'a' |"));
+    }
+}
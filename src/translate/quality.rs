@@ -0,0 +1,452 @@
+//! 自动质量指标
+//!
+//! 不调用模型，为每个条目计算几项廉价的指标，并按文件汇总：
+//!
+//! - chrF：与已有的可靠译文（人工译文、官方译文、人工审校的翻译记忆）的字符 n-gram F 值，没有参考译文时不计
+//! - 术语一致率：原文中出现的术语，其译法在译文中出现的比例
+//! - 标记完整率：`$变量$`、`£图标£` 等标记与原文一致的条目比例
+//! - 长度比：译文与原文的字符数之比
+//!
+//! 翻译记忆中由 AI 生成的译文多半就是目标文件中的译文本身，不作为参考译文。
+
+use super::{
+    FormatValidator, Glossary, HumanOverrides, MemoryOrigin, TranslationMemory,
+    VanillaLocalisation, load_glossaries_from_task,
+};
+use crate::config::TranslationTask;
+use crate::error::Result;
+use crate::postprocess::read_translated_output;
+use crate::preprocess::parse_entries;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// chrF 使用的最长字符 n-gram
+const CHRF_ORDER: usize = 6;
+
+/// chrF 中召回率相对于精确率的权重
+const CHRF_BETA: f64 = 2.0;
+
+/// 视为标记损坏的检查规则
+const MARKER_RULES: &[&str] = &[
+    "pattern_not_found",
+    "pattern_mismatch",
+    "extra_pattern",
+    "variant_mismatch",
+    "glyph_mismatch",
+];
+
+/// 译文与参考译文的 chrF 分数（0 到 100），空白不参与比较
+pub fn chrf(hypothesis: &str, reference: &str) -> f64 {
+    let chars = |text: &str| -> Vec<char> { text.chars().filter(|c| !c.is_whitespace()).collect() };
+    let (hypothesis, reference) = (chars(hypothesis), chars(reference));
+    if hypothesis == reference {
+        return 100.0;
+    }
+    fn ngrams(chars: &[char], n: usize) -> HashMap<&[char], usize> {
+        let mut counts: HashMap<&[char], usize> = HashMap::new();
+        for gram in chars.windows(n) {
+            *counts.entry(gram).or_default() += 1;
+        }
+        counts
+    }
+
+    let (mut precision, mut recall, mut orders) = (0.0, 0.0, 0);
+    for n in 1..=CHRF_ORDER {
+        if hypothesis.len() < n || reference.len() < n {
+            break;
+        }
+        let (hyp, refs) = (ngrams(&hypothesis, n), ngrams(&reference, n));
+        let matches: usize = hyp
+            .iter()
+            .map(|(gram, count)| (*count).min(refs.get(gram).copied().unwrap_or_default()))
+            .sum();
+        precision += matches as f64 / (hypothesis.len() + 1 - n) as f64;
+        recall += matches as f64 / (reference.len() + 1 - n) as f64;
+        orders += 1;
+    }
+    if orders == 0 {
+        return 0.0;
+    }
+    let (precision, recall) = (precision / orders as f64, recall / orders as f64);
+    if precision + recall == 0.0 {
+        return 0.0;
+    }
+    let beta2 = CHRF_BETA * CHRF_BETA;
+    100.0 * (1.0 + beta2) * precision * recall / (beta2 * precision + recall)
+}
+
+/// 小写的文本中是否出现了术语：术语的首尾为字母或数字时，要求前后不是字母或数字，避免 `ion` 匹配 `station`
+fn contains_term(text: &str, term: &str) -> bool {
+    if term.is_empty() {
+        return false;
+    }
+    text.match_indices(term).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + term.len()..].chars().next();
+        let boundary = |edge: Option<char>, inner: Option<char>| {
+            !inner.is_some_and(|c| c.is_ascii_alphanumeric())
+                || !edge.is_some_and(|c| c.is_ascii_alphanumeric())
+        };
+        boundary(before, term.chars().next()) && boundary(after, term.chars().next_back())
+    })
+}
+
+/// 一个条目的质量指标
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntryQuality {
+    pub key: String,
+    /// 与参考译文的 chrF，没有参考译文时为 None
+    pub chrf: Option<f64>,
+    /// 原文中出现的术语数
+    pub glossary_terms: usize,
+    /// 其中译法出现在译文中的术语数
+    pub glossary_matched: usize,
+    /// 标记是否与原文一致
+    pub markers_intact: bool,
+    /// 译文与原文的字符数之比，原文为空时为 1
+    pub length_ratio: f64,
+}
+
+/// 一个目标文件的质量指标汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FileQuality {
+    /// 源文件名
+    pub file: String,
+    pub target_lang: String,
+    /// 有译文的条目数
+    pub entries: usize,
+    /// 有参考译文的条目数
+    pub references: usize,
+    /// 有参考译文的条目的平均 chrF
+    pub chrf: Option<f64>,
+    pub glossary_terms: usize,
+    pub glossary_matched: usize,
+    /// 标记与原文一致的条目数
+    pub markers_intact: usize,
+    /// 平均长度比
+    pub length_ratio: f64,
+}
+
+impl FileQuality {
+    /// 汇总条目的指标
+    pub fn aggregate(file: &str, target_lang: &str, entries: &[EntryQuality]) -> Self {
+        let scores: Vec<f64> = entries.iter().filter_map(|e| e.chrf).collect();
+        let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len().max(1) as f64;
+        let ratios: Vec<f64> = entries.iter().map(|e| e.length_ratio).collect();
+        Self {
+            file: file.to_string(),
+            target_lang: target_lang.to_string(),
+            entries: entries.len(),
+            references: scores.len(),
+            chrf: (!scores.is_empty()).then(|| mean(&scores)),
+            glossary_terms: entries.iter().map(|e| e.glossary_terms).sum(),
+            glossary_matched: entries.iter().map(|e| e.glossary_matched).sum(),
+            markers_intact: entries.iter().filter(|e| e.markers_intact).count(),
+            length_ratio: mean(&ratios),
+        }
+    }
+
+    /// 术语一致率（0 到 1），原文中没有术语时为 None
+    pub fn glossary_rate(&self) -> Option<f64> {
+        (self.glossary_terms > 0).then(|| self.glossary_matched as f64 / self.glossary_terms as f64)
+    }
+
+    /// 标记完整率（0 到 1）
+    pub fn marker_rate(&self) -> f64 {
+        if self.entries == 0 {
+            return 1.0;
+        }
+        self.markers_intact as f64 / self.entries as f64
+    }
+}
+
+/// 质量指标的计算：格式检查、术语表与参考译文
+pub struct QualityScorer {
+    validator: FormatValidator,
+    glossary: Glossary,
+    overrides: Option<HumanOverrides>,
+    vanilla: Option<VanillaLocalisation>,
+    memory: Option<TranslationMemory>,
+    /// 按语言对缓存的小写术语对照
+    terms: HashMap<(String, String), Vec<(String, String)>>,
+}
+
+impl QualityScorer {
+    /// 按任务的格式检查、术语表、人工译文与官方译文设置创建，`memory` 中人工审校的译文也作为参考译文
+    pub fn for_task(task: &TranslationTask, memory: Option<TranslationMemory>) -> Result<Self> {
+        let vanilla = task
+            .vanilla_dir()
+            .map(|dir| VanillaLocalisation::load(dir, &task.target_langs))
+            .transpose()?;
+        Ok(Self {
+            validator: FormatValidator::for_task(task),
+            glossary: load_glossaries_from_task(task)?,
+            overrides: HumanOverrides::for_task(task)?,
+            vanilla,
+            memory,
+            terms: HashMap::new(),
+        })
+    }
+
+    /// 条目可靠的参考译文：人工译文优先，其次为官方译文与人工审校的翻译记忆
+    fn reference(
+        &self,
+        key: &str,
+        source_lang: &str,
+        target_lang: &str,
+        source: &str,
+    ) -> Option<String> {
+        if let Some(value) = self
+            .overrides
+            .as_ref()
+            .and_then(|o| o.get(target_lang, key))
+        {
+            return Some(value.to_string());
+        }
+        if let Some(value) = self
+            .vanilla
+            .as_ref()
+            .and_then(|v| v.official_translation(key, source_lang, target_lang, source))
+        {
+            return Some(value.to_string());
+        }
+        self.memory
+            .as_ref()?
+            .lookup(source_lang, target_lang, source)
+            .filter(|entry| entry.origin == MemoryOrigin::Human)
+            .map(|entry| entry.target.clone())
+    }
+
+    fn terms(&mut self, source_lang: &str, target_lang: &str) -> &[(String, String)] {
+        self.terms
+            .entry((source_lang.to_string(), target_lang.to_string()))
+            .or_insert_with(|| {
+                let mut terms: Vec<(String, String)> = self
+                    .glossary
+                    .get_translation_map(source_lang, target_lang)
+                    .into_iter()
+                    .map(|(source, target)| (source.to_lowercase(), target.to_lowercase()))
+                    .collect();
+                terms.sort();
+                terms
+            })
+    }
+
+    /// 计算一个条目的质量指标
+    pub fn score_entry(
+        &mut self,
+        key: &str,
+        source_lang: &str,
+        target_lang: &str,
+        source: &str,
+        translated: &str,
+    ) -> EntryQuality {
+        let chrf = self
+            .reference(key, source_lang, target_lang, source)
+            .map(|reference| chrf(translated, &reference));
+        let (source_lower, translated_lower) = (source.to_lowercase(), translated.to_lowercase());
+        let (mut glossary_terms, mut glossary_matched) = (0, 0);
+        for (source_term, target_term) in self.terms(source_lang, target_lang) {
+            if contains_term(&source_lower, source_term) {
+                glossary_terms += 1;
+                if translated_lower.contains(target_term.as_str()) {
+                    glossary_matched += 1;
+                }
+            }
+        }
+        let markers_intact = !self
+            .validator
+            .validate_entry(key, source, translated)
+            .iter()
+            .any(|p| MARKER_RULES.contains(&p.rule()));
+        let source_len = source.chars().count();
+        let length_ratio = if source_len == 0 {
+            1.0
+        } else {
+            translated.chars().count() as f64 / source_len as f64
+        };
+        EntryQuality {
+            key: key.to_string(),
+            chrf,
+            glossary_terms,
+            glossary_matched,
+            markers_intact,
+            length_ratio,
+        }
+    }
+
+    /// 计算目标文件中每个有译文的条目的质量指标
+    pub fn score_content(
+        &mut self,
+        source_lang: &str,
+        target_lang: &str,
+        source: &str,
+        translated: &str,
+    ) -> Vec<EntryQuality> {
+        let translated: HashMap<String, String> = parse_entries(translated)
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+        parse_entries(source)
+            .into_iter()
+            .filter_map(|entry| {
+                let target = translated.get(&entry.key)?;
+                Some(self.score_entry(&entry.key, source_lang, target_lang, &entry.value, target))
+            })
+            .collect()
+    }
+}
+
+/// 计算任务中每个已翻译的目标文件的质量指标，按源文件名与目标语言排序
+pub fn task_quality(
+    task: &TranslationTask,
+    scorer: &mut QualityScorer,
+) -> Result<Vec<FileQuality>> {
+    let source = task.source()?;
+    let mut files = Vec::new();
+    for source_file in task.source_files_in(source.as_ref())? {
+        let source_lang = task.file_source_lang(&source_file).to_string();
+        let name = source_file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let content = source.read_file(&source_file)?;
+        for target_lang in &task.target_langs {
+            if *target_lang == source_lang {
+                continue;
+            }
+            let Some(translated) =
+                read_translated_output(&task.target_file(&source_file, target_lang))?
+            else {
+                continue;
+            };
+            let entries = scorer.score_content(&source_lang, target_lang, &content, &translated);
+            files.push(FileQuality::aggregate(&name, target_lang, &entries));
+        }
+    }
+    files.sort_by(|a, b| (&a.file, &a.target_lang).cmp(&(&b.file, &b.target_lang)));
+    Ok(files)
+}
+
+/// 以文本表格输出各文件的质量指标，最后一行为全部文件的汇总
+pub fn render_quality_report(files: &[FileQuality]) -> String {
+    let percent =
+        |rate: Option<f64>| rate.map_or("-".to_string(), |r| format!("{:.0}%", r * 100.0));
+    let row = |quality: &FileQuality| {
+        [
+            quality.file.clone(),
+            quality.target_lang.clone(),
+            quality.entries.to_string(),
+            quality.chrf.map_or("-".to_string(), |score| {
+                format!("{:.1} ({})", score, quality.references)
+            }),
+            percent(quality.glossary_rate()),
+            percent(Some(quality.marker_rate())),
+            format!("{:.2}", quality.length_ratio),
+        ]
+    };
+
+    let mut rows = vec![
+        [
+            "File", "Language", "Entries", "chrF", "Glossary", "Markers", "Length",
+        ]
+        .map(String::from),
+    ];
+    rows.extend(files.iter().map(row));
+    if files.len() > 1 {
+        let mut total = FileQuality {
+            file: "Total".to_string(),
+            ..Default::default()
+        };
+        let (mut chrf_sum, mut ratio_sum) = (0.0, 0.0);
+        for file in files {
+            total.entries += file.entries;
+            total.references += file.references;
+            total.glossary_terms += file.glossary_terms;
+            total.glossary_matched += file.glossary_matched;
+            total.markers_intact += file.markers_intact;
+            chrf_sum += file.chrf.unwrap_or_default() * file.references as f64;
+            ratio_sum += file.length_ratio * file.entries as f64;
+        }
+        total.chrf = (total.references > 0).then(|| chrf_sum / total.references as f64);
+        total.length_ratio = ratio_sum / total.entries.max(1) as f64;
+        rows.push(row(&total));
+    }
+
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in &rows {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(widths).enumerate() {
+            let padding = width - cell.chars().count();
+            if i < 2 {
+                let _ = write!(line, "{}{}  ", cell, " ".repeat(padding));
+            } else {
+                let _ = write!(line, "{}{}  ", " ".repeat(padding), cell);
+            }
+        }
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_metrics() {
+        assert_eq!(chrf("研究速度提高", "研究 速度 提高"), 100.0);
+        assert_eq!(chrf("abc", "xyz"), 0.0);
+        let partial = chrf("研究速率提高", "研究速度提高");
+        assert!(partial > 20.0 && partial < 80.0, "{}", partial);
+
+        assert!(contains_term("build a space station", "station"));
+        assert!(!contains_term("build a space station", "ion"));
+        assert!(contains_term("研究能量", "能量"));
+
+        let mut scorer = QualityScorer {
+            validator: FormatValidator::new(),
+            glossary: Glossary::default(),
+            overrides: None,
+            vanilla: None,
+            memory: None,
+            terms: HashMap::new(),
+        };
+        scorer.terms.insert(
+            ("english".to_string(), "simp_chinese".to_string()),
+            vec![
+                ("energy".to_string(), "能量".to_string()),
+                ("minerals".to_string(), "矿物".to_string()),
+            ],
+        );
+        let entries = scorer.score_content(
+            "english",
+            "simp_chinese",
+            "l_english:\n a:0 \"Energy and Minerals\"\n b:0 \"$X$ energy\"\n c:0 \"Untranslated\"\n",
+            "l_simp_chinese:\n a:0 \"能量与矿产\"\n b:0 \"X 能量\"\n",
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            (entries[0].glossary_terms, entries[0].glossary_matched),
+            (2, 1)
+        );
+        assert!(entries[0].markers_intact);
+        assert!(!entries[1].markers_intact);
+        assert_eq!(entries[0].chrf, None);
+
+        let quality = FileQuality::aggregate("a_l_english.yml", "simp_chinese", &entries);
+        assert_eq!(quality.glossary_rate(), Some(2.0 / 3.0));
+        assert_eq!(quality.marker_rate(), 0.5);
+        let report = render_quality_report(&[quality]);
+        assert!(report.starts_with("File"));
+        assert!(report.contains("a_l_english.yml  simp_chinese"));
+        assert!(report.contains("67%"));
+    }
+}
@@ -0,0 +1,152 @@
+//! 概念与图标引用
+//!
+//! 本地化文本中的 `['concept_key', 文本]` 引用游戏概念（其名称是一个本地化键），`£icon£` 引用图标。
+//! 引用的键或图标不存在时游戏中显示为原样的文本。原版与 Mod 中定义的键、出现过的图标构成已知引用，
+//! 译文中出现了原文没有且不在已知引用中的引用时，多半是翻译时改动了引用（例如把概念键或图标名也翻译了）。
+
+use super::VanillaIndex;
+use crate::config::TranslationTask;
+use crate::error::Result;
+use crate::preprocess::parse_entries;
+use crate::utils::cached_regex;
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock};
+
+/// 文本中引用的概念键，例如 `['concept_pops', 人口]` 中的 `concept_pops`
+pub fn concept_references(text: &str) -> Vec<&str> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    cached_regex(&PATTERN, || r"\[\s*'([^'\]\s]+)'")
+        .captures_iter(text)
+        .filter_map(|caps| caps.get(1).map(|m| m.as_str()))
+        .collect()
+}
+
+/// 文本中引用的图标名，例如 `£energy£` 中的 `energy`（`£icon|1£` 一类带帧号的写法只取图标名）
+pub fn icon_references(text: &str) -> Vec<&str> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    cached_regex(&PATTERN, || r"£([^£|\s]+)(?:\|[^£]*)?£")
+        .captures_iter(text)
+        .filter_map(|caps| caps.get(1).map(|m| m.as_str()))
+        .collect()
+}
+
+/// 已知的本地化键与图标
+#[derive(Debug, Default)]
+pub struct KnownReferences {
+    keys: HashSet<String>,
+    icons: HashSet<String>,
+}
+
+impl KnownReferences {
+    /// 记录一个条目：键本身，以及文本中出现的图标
+    pub fn add_entry(&mut self, key: &str, text: &str) {
+        self.keys.insert(key.to_string());
+        for icon in icon_references(text) {
+            self.icons.insert(icon.to_string());
+        }
+    }
+
+    /// 由原版索引（源语言的全部条目）与 Mod 的源文件建立已知引用
+    pub fn load(index: &VanillaIndex, task: &TranslationTask) -> Result<Self> {
+        let mut known = Self::default();
+        for (key, text) in index.entries(&task.source_lang) {
+            known.add_entry(key, text);
+        }
+        let source = task.source()?;
+        for lang in source.languages()? {
+            for file in source.list_files(&lang)? {
+                for entry in parse_entries(&source.read_file(&file)?) {
+                    known.add_entry(&entry.key, &entry.value);
+                }
+            }
+        }
+        log::debug!(
+            "Known references: {} keys, {} icons",
+            known.keys.len(),
+            known.icons.len()
+        );
+        Ok(known)
+    }
+
+    /// 任务设置了原版本地化时建立已知引用；没有原版本地化无法判断原版的概念与图标，不做检查
+    pub fn for_task(task: &TranslationTask) -> Option<Arc<Self>> {
+        let dir = task.vanilla_dir()?;
+        match VanillaIndex::open(&dir).and_then(|index| Self::load(&index, task)) {
+            Ok(known) => Some(Arc::new(known)),
+            Err(e) => {
+                log::warn!("Skipping concept and icon reference checks: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 概念键是否已定义
+    pub fn has_key(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// 图标是否在原版或 Mod 中出现过
+    pub fn has_icon(&self, icon: &str) -> bool {
+        self.icons.contains(icon)
+    }
+
+    /// 译文中新出现的、指向未定义的键或图标的引用，以原样的标记形式返回（`['key']`、`£icon£`）
+    ///
+    /// 原文中已有的引用即使无法解析也不报告，那是 Mod 本身的问题，不是翻译引入的。
+    pub fn broken_references(&self, original: &str, translated: &str) -> Vec<String> {
+        let original_concepts = concept_references(original);
+        let original_icons = icon_references(original);
+        let mut broken = Vec::new();
+        for key in concept_references(translated) {
+            if !original_concepts.contains(&key) && !self.has_key(key) {
+                broken.push(format!("['{}']", key));
+            }
+        }
+        for icon in icon_references(translated) {
+            if !original_icons.contains(&icon) && !self.has_icon(icon) {
+                broken.push(format!("£{}£", icon));
+            }
+        }
+        broken.dedup();
+        broken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broken_references() {
+        assert_eq!(
+            concept_references("['concept_pops', $POPS$] and [ 'concept_jobs']"),
+            ["concept_pops", "concept_jobs"]
+        );
+        assert_eq!(
+            icon_references("£energy£ 5 £unity|1£ £"),
+            ["energy", "unity"]
+        );
+
+        let mut known = KnownReferences::default();
+        known.add_entry("concept_pops", "Pops £pop£");
+        known.add_entry("concept_jobs", "Jobs");
+
+        let original = "Needs £energy£ and ['concept_pops', pops] and ['concept_missing']";
+        assert!(
+            known
+                .broken_references(
+                    original,
+                    "需要 £energy£ ['concept_pops', 人口] ['concept_missing']"
+                )
+                .is_empty()
+        );
+        assert_eq!(
+            known.broken_references(
+                original,
+                "需要 £能量£ ['概念_人口', 人口] ['concept_jobs'] £pop£"
+            ),
+            ["['概念_人口']", "£能量£"]
+        );
+    }
+}
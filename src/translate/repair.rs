@@ -0,0 +1,194 @@
+//! 修复验证问题
+//!
+//! 对已有的译文运行验证，收集每个文件中未通过验证的键，只将这些条目连同问题说明交给模型重新翻译，
+//! 并将问题减少的译文写回原有的目标文件，其余条目保持不变。
+
+use super::{
+    FormatValidator, Problem, REPAIR_PROMPT, Translator, ValidationIgnore,
+    load_glossaries_from_task, split_yaml_content,
+};
+use crate::config::{ClientSettings, TranslationTask};
+use crate::error::Result;
+use crate::postprocess::{pinned_entries, read_translated_output};
+use crate::preprocess::{
+    filter_entries, fix_yaml_content, normalize_text, parse_entries, trim_lang_header,
+};
+use crate::review::write_entries;
+use crate::utils::require_data_files;
+use std::collections::{HashMap, HashSet};
+
+/// 修复结果统计
+#[derive(Debug, Default)]
+pub struct RepairSummary {
+    /// 存在问题的目标文件数
+    pub files: usize,
+    /// 存在问题的条目数
+    pub failing: usize,
+    /// 修复后写回的条目数
+    pub repaired: usize,
+}
+
+/// 按键归类问题，保持键首次出现的顺序
+///
+/// 目标文件中多出的键在源文件中没有原文，无法重新翻译，不计入。
+pub fn problems_by_key(problems: Vec<Problem>) -> Vec<(String, Vec<Problem>)> {
+    let mut grouped: Vec<(String, Vec<Problem>)> = Vec::new();
+    for problem in problems {
+        if matches!(problem, Problem::ExtraKey { .. }) {
+            continue;
+        }
+        match grouped.iter_mut().find(|(key, _)| key == problem.key()) {
+            Some((_, list)) => list.push(problem),
+            None => grouped.push((problem.key().to_string(), vec![problem])),
+        }
+    }
+    grouped
+}
+
+/// 规范化文本、去除语言头并修复格式，与 `validate` 命令的处理一致
+fn prepare(lang: &str, content: &str) -> Result<String> {
+    let content = trim_lang_header(lang, &normalize_text(content)).content;
+    Ok(fix_yaml_content(&content)?.content)
+}
+
+/// 重新翻译任务中未通过验证的条目，并写回已有的目标文件
+pub async fn run_repair(
+    task: &TranslationTask,
+    client_settings: ClientSettings,
+) -> Result<RepairSummary> {
+    let max_chunk_tokens = client_settings.max_chunk_tokens;
+    require_data_files(&[REPAIR_PROMPT])?;
+    let translator = Translator::for_task(client_settings, load_glossaries_from_task(task)?, task)?;
+    let validator = FormatValidator::for_task(task);
+    let ignore = ValidationIgnore::for_task(task)?;
+    let source_reader = task.source()?;
+    let source_files = task.source_files_in(source_reader.as_ref())?;
+
+    let mut summary = RepairSummary::default();
+    for target_lang in &task.target_langs {
+        for source_file in &source_files {
+            let source_lang = task.file_source_lang(source_file);
+            if source_lang == target_lang {
+                continue;
+            }
+            let output_path = task.target_file(source_file, target_lang);
+            let target_filename = output_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let Some(target) = read_translated_output(&output_path)? else {
+                log::warn!("Missing translated file: {:?}", output_path);
+                continue;
+            };
+
+            let source = prepare(source_lang, &source_reader.read_file(source_file)?)?;
+            let translated = prepare(target_lang, &target)?;
+            let files = [source_file.as_path(), output_path.as_path()];
+            let mut problems = validator.validate(&source, &translated);
+            ignore.retain(&files, &mut problems);
+            let mut failing = problems_by_key(problems);
+            // 以 `# pmt:keep` 固定的译文已经审校认可，不再修复
+            let pinned = pinned_entries(&target);
+            failing.retain(|(key, _)| !pinned.contains_key(key));
+            if failing.is_empty() {
+                continue;
+            }
+            log::info!("{:?}: {} entries with issues", output_path, failing.len());
+            summary.files += 1;
+            summary.failing += failing.len();
+
+            let problems: HashMap<String, Vec<Problem>> = failing.into_iter().collect();
+            let originals: HashMap<String, String> = parse_entries(&source)
+                .into_iter()
+                .map(|e| (e.key, e.value))
+                .collect();
+            let subset = filter_entries(&source, |k| problems.contains_key(k));
+            // 修复后的译文在文件的所有切片完成后一次写回，某个切片失败时也先写回已修复的条目
+            let mut repaired = Vec::new();
+            let mut failure = None;
+            for chunk in split_yaml_content(&target_filename, &subset, max_chunk_tokens)? {
+                let keys: Vec<String> = parse_entries(&chunk.content)
+                    .into_iter()
+                    .map(|e| e.key)
+                    .collect();
+                let previous = filter_entries(&translated, |k| keys.iter().any(|c| c == k));
+                let chunk_problems: Vec<Problem> = keys
+                    .iter()
+                    .flat_map(|k| problems[k].iter().cloned())
+                    .collect();
+                let slice = match translator
+                    .repair_chunk(&chunk, &previous, &chunk_problems, source_lang, target_lang)
+                    .await
+                {
+                    Ok(slice) => slice,
+                    Err(e) => {
+                        failure = Some(e);
+                        break;
+                    }
+                };
+
+                let mut seen = HashSet::new();
+                for entry in parse_entries(&slice.content) {
+                    let (Some(before), Some(original)) =
+                        (problems.get(&entry.key), originals.get(&entry.key))
+                    else {
+                        continue;
+                    };
+                    // 同一个键出现多次时以第一次为准
+                    if !seen.insert(entry.key.clone()) {
+                        continue;
+                    }
+                    let mut after = validator.validate_entry(&entry.key, original, &entry.value);
+                    ignore.retain(&files, &mut after);
+                    // 只写回问题减少的译文
+                    if after.len() < before.len() {
+                        repaired.push((entry.key, entry.value));
+                    } else {
+                        log::warn!(
+                            "Repair of '{}' in {:?} did not fix its issues, keeping the existing translation",
+                            entry.key,
+                            output_path
+                        );
+                    }
+                }
+            }
+            write_entries(&output_path, &repaired)?;
+            summary.repaired += repaired.len();
+            if let Some(e) = failure {
+                return Err(e);
+            }
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problems_by_key() {
+        let problems = vec![
+            Problem::MissingKey {
+                key: "b".to_string(),
+            },
+            Problem::LengthOutlier {
+                key: "a".to_string(),
+                ratio: 5.0,
+            },
+            Problem::ExtraKey {
+                key: "c".to_string(),
+            },
+            Problem::PatternNotFound {
+                key: "a".to_string(),
+                original: "$x$".to_string(),
+            },
+        ];
+        let grouped = problems_by_key(problems);
+        let keys: Vec<(&str, usize)> = grouped
+            .iter()
+            .map(|(k, list)| (k.as_str(), list.len()))
+            .collect();
+        assert_eq!(keys, [("b", 1), ("a", 2)]);
+    }
+}
@@ -0,0 +1,173 @@
+//! 已有译文的复用
+//!
+//! 汇总翻译前即可确定的译文来源（人工维护的译文、游戏本体的官方译文、翻译记忆），这些条目不再交给大模型翻译。
+
+use super::{HumanOverrides, MemoryOrigin, SharedMemory, VanillaLocalisation};
+use crate::postprocess::EntryOrigin;
+use crate::preprocess::parse_entries;
+use std::collections::HashMap;
+
+/// 翻译时可复用的译文来源
+#[derive(Default)]
+pub struct ReuseSources {
+    /// `overrides/<目标语言>/` 中人工维护的译文，总是优先于其他来源与 AI 译文
+    pub overrides: Option<HumanOverrides>,
+    /// 游戏本体的本地化，Mod 覆盖的原版键直接使用官方译文
+    pub vanilla: Option<VanillaLocalisation>,
+    /// 翻译记忆，源文本相同的条目直接使用记忆中的译文，新的 AI 译文也会记入其中
+    pub memory: Option<SharedMemory>,
+}
+
+impl ReuseSources {
+    /// 找出内容中可以直接复用译文的条目，返回「键 -> 译文」
+    ///
+    /// 人工译文优先于官方译文，官方译文优先于翻译记忆。
+    pub fn known_translations(
+        &self,
+        content: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> HashMap<String, String> {
+        let mut known = self
+            .overrides
+            .as_ref()
+            .map(|o| o.translations(content, target_lang))
+            .unwrap_or_default();
+        if let Some(vanilla) = &self.vanilla {
+            for (key, value) in vanilla.official_translations(content, source_lang, target_lang) {
+                known.entry(key).or_insert(value);
+            }
+        }
+        if let Some(memory) = &self.memory {
+            for entry in parse_entries(content) {
+                if known.contains_key(&entry.key) {
+                    continue;
+                }
+                if let Some(found) = memory.lookup(source_lang, target_lang, &entry.value) {
+                    known.insert(entry.key, found.target.clone());
+                }
+            }
+        }
+        known
+    }
+
+    /// 将目标文件中以 `# pmt:keep` 固定的条目（键 -> 译文）加入已有译文，人工维护的译文仍然优先
+    pub fn keep_pinned(
+        &self,
+        known: &mut HashMap<String, String>,
+        pinned: &HashMap<String, String>,
+        target_lang: &str,
+    ) {
+        for (key, value) in pinned {
+            let overridden = self
+                .overrides
+                .as_ref()
+                .is_some_and(|o| o.get(target_lang, key).is_some());
+            if !overridden {
+                known.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    /// 条目译文的来源，用于在目标文件中标注：`known` 为已有译文，`pinned` 为目标文件中固定的条目
+    pub fn origin(
+        &self,
+        key: &str,
+        target_lang: &str,
+        known: &HashMap<String, String>,
+        pinned: &HashMap<String, String>,
+    ) -> EntryOrigin {
+        if !known.contains_key(key) {
+            EntryOrigin::Ai
+        } else if self
+            .overrides
+            .as_ref()
+            .is_some_and(|o| o.get(target_lang, key).is_some())
+        {
+            EntryOrigin::Override
+        } else if pinned.contains_key(key) {
+            EntryOrigin::Kept
+        } else if self
+            .vanilla
+            .as_ref()
+            .is_some_and(|v| v.get(target_lang, key) == known.get(key).map(String::as_str))
+        {
+            EntryOrigin::Vanilla
+        } else {
+            EntryOrigin::Memory
+        }
+    }
+
+    /// 将新翻译的条目以 AI 来源记入翻译记忆（未启用翻译记忆时不做任何事）
+    pub fn record(
+        &self,
+        source_content: &str,
+        translated_content: &str,
+        known: &HashMap<String, String>,
+        source_lang: &str,
+        target_lang: &str,
+    ) {
+        let Some(memory) = &self.memory else {
+            return;
+        };
+        let translated: HashMap<String, String> = parse_entries(translated_content)
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+        for entry in parse_entries(source_content) {
+            if known.contains_key(&entry.key) || entry.value.trim().is_empty() {
+                continue;
+            }
+            if let Some(target) = translated.get(&entry.key) {
+                memory.record(
+                    source_lang,
+                    target_lang,
+                    &entry.value,
+                    target,
+                    MemoryOrigin::Ai,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_reuse_and_record() {
+        let memory = SharedMemory::default();
+        memory.record(
+            "english",
+            "simp_chinese",
+            "Energy",
+            "能源",
+            MemoryOrigin::Human,
+        );
+        let reuse = ReuseSources {
+            overrides: None,
+            vanilla: None,
+            memory: Some(memory.clone()),
+        };
+
+        let source = "l_english:\n key_a: \"Energy\"\n key_b: \"Minerals\"";
+        let known = reuse.known_translations(source, "english", "simp_chinese");
+        assert_eq!(known.len(), 1);
+        assert_eq!(known["key_a"], "能源");
+
+        reuse.record(
+            source,
+            "l_simp_chinese:\n  key_a: \"能源\"\n  key_b: \"矿物\"",
+            &known,
+            "english",
+            "simp_chinese",
+        );
+        assert_eq!(
+            memory
+                .lookup("english", "simp_chinese", "Minerals")
+                .map(|e| e.target),
+            Some("矿物".to_string())
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! 会话术语表
+//!
+//! 一次运行中，模型第一次翻译 Mod 特有的专有名词（势力、人物、星球等名称）后记录其译法，
+//! 之后的切片中再出现该名词时将译法一并写入提示词，避免同一名称在不同文件中译法不一。
+//!
+//! 专有名词取自值只有一个名称的条目（例如 `faction_x_name:0 "Ketling Star Pack"`），
+//! 其译法即该条目的译文，第一次记录的译法不会被之后的译文覆盖。
+//! 单个单词（例如 `Cancel`、`Science`）多半是界面用语而不是名称，只有键名表明这是名称时才记录。
+
+use crate::preprocess::parse_entries;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+/// 专有名词中允许出现的小写连接词
+const CONNECTORS: &[&str] = &["of", "the", "and", "de", "la", "du", "von", "van"];
+/// 专有名词的最大单词数
+pub(crate) const MAX_WORDS: usize = 4;
+/// 专有名词的最大字符数
+const MAX_CHARS: usize = 40;
+/// 表明条目是名称的键名后缀，这类条目的值只有一个单词时也视为专有名词
+const NAME_KEY_SUFFIXES: &[&str] = &["_name", "_name_plural"];
+/// 表明条目是名称的键名前缀（Stellaris 名称列表的惯例，例如 `NAME_Ketling`）
+const NAME_KEY_PREFIX: &str = "NAME_";
+
+/// 会话术语表
+///
+/// 内部加锁，可在并发翻译时通过共享引用记录与查询。
+#[derive(Debug, Default)]
+pub struct SessionGlossary {
+    /// 语言对（`english>simp_chinese`） -> 专有名词 -> 译法
+    terms: RwLock<BTreeMap<String, BTreeMap<String, String>>>,
+}
+
+static SESSION_GLOSSARY: SessionGlossary = SessionGlossary::new();
+
+/// 获取本次运行的会话术语表，所有任务的翻译器共用
+pub fn session_glossary() -> &'static SessionGlossary {
+    &SESSION_GLOSSARY
+}
+
+fn pair_key(source_lang: &str, target_lang: &str) -> String {
+    format!("{}>{}", source_lang, target_lang)
+}
+
+impl SessionGlossary {
+    /// 创建空的会话术语表
+    pub const fn new() -> Self {
+        Self {
+            terms: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// 从一个切片的原文与译文中记录专有名词的译法，返回新记录的名词与译法
+    ///
+    /// `is_known` 为 true 的名词（通常是术语表或原版本地化中已有的文本）不会记录。
+    pub fn learn(
+        &self,
+        source: &str,
+        translated: &str,
+        source_lang: &str,
+        target_lang: &str,
+        is_known: impl Fn(&str) -> bool,
+    ) -> Vec<(String, String)> {
+        let translations: HashMap<String, String> = parse_entries(translated)
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+        let mut terms = self.terms.write().unwrap_or_else(|e| e.into_inner());
+        let locked = terms.entry(pair_key(source_lang, target_lang)).or_default();
+        let mut learned = Vec::new();
+        for entry in parse_entries(source) {
+            let term = entry.value.trim();
+            if !is_proper_noun(&entry.key, term) || locked.contains_key(term) || is_known(term) {
+                continue;
+            }
+            let Some(translation) = translations.get(&entry.key).map(|t| t.trim()) else {
+                continue;
+            };
+            if translation.is_empty() || translation == term {
+                continue;
+            }
+            locked.insert(term.to_string(), translation.to_string());
+            learned.push((term.to_string(), translation.to_string()));
+        }
+        learned
+    }
+
+    /// 文本中出现的已记录的专有名词及其译法
+    pub fn terms_in_text(
+        &self,
+        text: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Vec<(String, String)> {
+        let terms = self.terms.read().unwrap_or_else(|e| e.into_inner());
+        let Some(locked) = terms.get(&pair_key(source_lang, target_lang)) else {
+            return Vec::new();
+        };
+        locked
+            .iter()
+            .filter(|(term, _)| contains_word(text, term))
+            .map(|(term, translation)| (term.clone(), translation.clone()))
+            .collect()
+    }
+
+    /// 已记录的专有名词数量
+    pub fn len(&self) -> usize {
+        let terms = self.terms.read().unwrap_or_else(|e| e.into_inner());
+        terms.values().map(BTreeMap::len).sum()
+    }
+
+    /// 是否还没有记录任何专有名词
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 键为 `key` 的文本是否像一个专有名词：2 到 4 个单词（键名表明是名称时可以只有 1 个），
+/// 每个单词首字母大写（中间可以有 `of`、`the` 等连接词），不含标点与格式标记
+fn is_proper_noun(key: &str, text: &str) -> bool {
+    if text.chars().count() > MAX_CHARS {
+        return false;
+    }
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let min_words = if is_name_key(key) { 1 } else { 2 };
+    if words.len() < min_words || words.len() > MAX_WORDS {
+        return false;
+    }
+    words.iter().enumerate().all(|(i, word)| {
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase)
+            && word
+                .chars()
+                .all(|c| c.is_alphabetic() || matches!(c, '-' | '\''));
+        let connector = i > 0 && i + 1 < words.len() && CONNECTORS.contains(word);
+        capitalized || connector
+    })
+}
+
+/// 键名是否表明条目是一个名称
+fn is_name_key(key: &str) -> bool {
+    key.starts_with(NAME_KEY_PREFIX) || NAME_KEY_SUFFIXES.iter().any(|s| key.ends_with(s))
+}
+
+/// 文本中是否出现作为完整单词的 `term`
+fn contains_word(text: &str, term: &str) -> bool {
+    text.match_indices(term).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + term.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learn_and_lookup() {
+        let session = SessionGlossary::new();
+        let source = "pf_faction:0 \"Ketling Star Pack\"\npf_desc:0 \"The Ketling Star Pack roams.\"\npf_energy:0 \"Energy\"";
+        let translated =
+            "pf_faction:0 \"柯特林星群\"\npf_desc:0 \"柯特林星群四处游荡。\"\npf_energy:0 \"能量\"";
+        let learned = session.learn(source, translated, "english", "simp_chinese", |term| {
+            term == "Energy"
+        });
+        assert_eq!(
+            learned,
+            [("Ketling Star Pack".to_string(), "柯特林星群".to_string())]
+        );
+
+        // 第一次记录的译法不会被覆盖
+        session.learn(
+            "x:0 \"Ketling Star Pack\"",
+            "x:0 \"凯特林星团\"",
+            "english",
+            "simp_chinese",
+            |_| false,
+        );
+        assert_eq!(
+            session.terms_in_text(
+                "Envoys of the Ketling Star Pack arrive",
+                "english",
+                "simp_chinese"
+            ),
+            [("Ketling Star Pack".to_string(), "柯特林星群".to_string())]
+        );
+        assert!(
+            session
+                .terms_in_text("Ketling Star Packs", "english", "simp_chinese")
+                .is_empty()
+        );
+        assert!(
+            session
+                .terms_in_text("Ketling Star Pack", "english", "russian")
+                .is_empty()
+        );
+        assert!(is_proper_noun("pf_order", "Order of the Veil"));
+        assert!(!is_proper_noun("pf_order_desc", "The pack roams."));
+    }
+
+    #[test]
+    fn test_skip_common_words() {
+        // 单个单词只有键名表明是名称时才记录
+        for (key, text) in [
+            ("pf_cancel", "Cancel"),
+            ("pf_tech_category", "Science"),
+            ("pf_accept_button", "Accept"),
+        ] {
+            assert!(!is_proper_noun(key, text), "{}", text);
+        }
+        assert!(is_proper_noun("pf_species_name", "Ketling"));
+        assert!(is_proper_noun("NAME_Vorrhan", "Vorrhan"));
+
+        // 原版本地化中已有的短语不记录
+        let session = SessionGlossary::new();
+        let source =
+            "pf_cancel:0 \"Cancel\"\npf_energy:0 \"Energy Credits\"\npf_leader_name:0 \"Vorrhan\"";
+        let translated =
+            "pf_cancel:0 \"取消\"\npf_energy:0 \"能量币\"\npf_leader_name:0 \"沃尔汗\"";
+        let learned = session.learn(source, translated, "english", "simp_chinese", |term| {
+            term.eq_ignore_ascii_case("energy credits")
+        });
+        assert_eq!(learned, [("Vorrhan".to_string(), "沃尔汗".to_string())]);
+    }
+}
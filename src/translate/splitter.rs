@@ -5,6 +5,20 @@
 use crate::error::Result;
 use crate::utils::estimate_mixed_tokens;
 
+/// 切片的标识：目标文件名与切片在源文件中的行范围，显示为 `文件名(起始行:结束行)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkId {
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl std::fmt::Display for ChunkId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}({}:{})", self.file, self.start_line, self.end_line)
+    }
+}
+
 /// 文件切片
 #[derive(Clone)]
 pub struct FileChunk {
@@ -16,6 +30,23 @@ pub struct FileChunk {
     pub end_line: usize,
     /// 目标文件名
     pub target_filename: String,
+    /// 切片之前最近的注释行（通常是分节标题），用于提供上下文
+    pub section: Option<String>,
+    /// 与前一个切片重叠的几行原文，只作为上下文发送，不翻译也不合并到译文中
+    pub preceding: Vec<String>,
+    /// 与后一个切片重叠的几行原文，同样只作为上下文
+    pub following: Vec<String>,
+}
+
+impl FileChunk {
+    /// 切片的标识，用于日志与错误信息
+    pub fn id(&self) -> ChunkId {
+        ChunkId {
+            file: self.target_filename.clone(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+        }
+    }
 }
 
 /// 将YAML内容分割为多个切片
@@ -24,61 +55,228 @@ pub fn split_yaml_content(
     content: &str,
     max_chunk_tokens: usize,
 ) -> Result<Vec<FileChunk>> {
-    let lines: Vec<&str> = content.lines().collect();
-    if lines.is_empty() {
-        return Ok(vec![]);
+    Ok(YamlChunks::new(target_filename, content, max_chunk_tokens).collect())
+}
+
+/// 按需生成切片的迭代器，切分规则与 [`split_yaml_content`] 相同
+///
+/// 每次只保留正在累积的一个切片，供流水线翻译超大文件时使用。
+/// 设置 [`with_overlap`](Self::with_overlap) 后，每个切片附带相邻切片边界处的几行原文作为上下文。
+pub struct YamlChunks<'a> {
+    target_filename: &'a str,
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    max_chunk_tokens: usize,
+    current_chunk_lines: Vec<&'a str>,
+    current_token_count: usize,
+    start_line: usize,
+    last_line: usize,
+    /// 已读到的最近一条注释
+    section: Option<String>,
+    /// 当前切片开始前的注释
+    chunk_section: Option<String>,
+    /// 与相邻切片重叠的行数
+    overlap: usize,
+    /// 上一个切片的最后几行，作为当前切片的 `preceding`
+    preceding: Vec<String>,
+}
+
+impl<'a> YamlChunks<'a> {
+    pub fn new(target_filename: &'a str, content: &'a str, max_chunk_tokens: usize) -> Self {
+        Self {
+            target_filename,
+            lines: content.lines().enumerate(),
+            max_chunk_tokens,
+            current_chunk_lines: Vec::new(),
+            current_token_count: 0,
+            start_line: 1,
+            last_line: 0,
+            section: None,
+            chunk_section: None,
+            overlap: 0,
+            preceding: Vec::new(),
+        }
     }
 
-    let mut chunks = Vec::new();
-    let mut current_chunk_lines = Vec::new();
-    let mut current_token_count = 0;
-    let mut start_line = 1;
-
-    for (i, line) in lines.iter().enumerate() {
-        let line_number = i + 1;
-        let line_token_count = estimate_mixed_tokens(line);
-
-        // 如果当前行会使token数超过限制，且当前切片不为空，则结束当前切片
-        if !current_chunk_lines.is_empty()
-            && current_token_count + line_token_count > max_chunk_tokens
-        {
-            let end_line = line_number - 1;
-            chunks.push(FileChunk {
-                content: current_chunk_lines.join("\n"),
-                start_line,
-                end_line,
-                target_filename: target_filename.to_string(),
-            });
-
-            // 开始新切片
-            current_chunk_lines = vec![*line];
-            current_token_count = line_token_count;
-            start_line = line_number;
+    /// 每个切片附带前一个切片的最后 `lines` 行与后一个切片的开头 `lines` 行（不计空行）作为上下文
+    pub fn with_overlap(mut self, lines: usize) -> Self {
+        self.overlap = lines;
+        self
+    }
+
+    /// 从 `lines` 中取出最多 `overlap` 个非空行
+    fn overlap_lines<'l>(&self, lines: impl Iterator<Item = &'l str>) -> Vec<String> {
+        lines
+            .filter(|line| !line.trim().is_empty())
+            .take(self.overlap)
+            .map(String::from)
+            .collect()
+    }
+
+    /// 结束当前切片，`end_line` 为切片的最后一行，`next_line` 为下一个切片的第一行
+    fn finish_chunk(&mut self, end_line: usize, next_line: Option<&str>) -> FileChunk {
+        let following = match next_line {
+            Some(line) => {
+                self.overlap_lines(std::iter::once(line).chain(self.lines.clone().map(|(_, l)| l)))
+            }
+            None => Vec::new(),
+        };
+        let mut tail = self.overlap_lines(self.current_chunk_lines.iter().rev().copied());
+        tail.reverse();
+        let chunk = FileChunk {
+            content: self.current_chunk_lines.join("\n"),
+            start_line: self.start_line,
+            end_line,
+            target_filename: self.target_filename.to_string(),
+            section: self.chunk_section.take(),
+            preceding: std::mem::replace(&mut self.preceding, tail),
+            following,
+        };
+        self.current_chunk_lines.clear();
+        self.current_token_count = 0;
+        chunk
+    }
+}
+
+impl Iterator for YamlChunks<'_> {
+    type Item = FileChunk;
+
+    fn next(&mut self) -> Option<FileChunk> {
+        while let Some((i, line)) = self.lines.next() {
+            let line_number = i + 1;
+            let line_token_count = estimate_mixed_tokens(line);
+            self.last_line = line_number;
+
+            // 如果当前行会使token数超过限制，且当前切片不为空，则结束当前切片
+            let mut finished = None;
+            if !self.current_chunk_lines.is_empty()
+                && self.current_token_count + line_token_count > self.max_chunk_tokens
+            {
+                finished = Some(self.finish_chunk(line_number - 1, Some(line)));
+                // 开始新切片
+                self.start_line = line_number;
+                self.chunk_section = self.section.clone();
+            }
+            self.current_chunk_lines.push(line);
+            self.current_token_count += line_token_count;
+
+            if let Some(comment) = line.trim().strip_prefix('#') {
+                let comment = comment.trim();
+                if !comment.is_empty() {
+                    self.section = Some(comment.to_string());
+                }
+            }
+            if finished.is_some() {
+                return finished;
+            }
+        }
+
+        // 最后一个切片
+        if self.current_chunk_lines.is_empty() {
+            None
         } else {
-            // 添加到当前切片
-            current_chunk_lines.push(*line);
-            current_token_count += line_token_count;
+            Some(self.finish_chunk(self.last_line, None))
         }
     }
+}
 
-    // 添加最后一个切片
-    if !current_chunk_lines.is_empty() {
-        let end_line = lines.len();
-        chunks.push(FileChunk {
-            content: current_chunk_lines.join("\n"),
-            start_line,
-            end_line,
-            target_filename: target_filename.to_string(),
-        });
+/// 按键名前缀将条目分组，供切片前使用：同一组的条目排在一起，组按第一次出现的顺序排列，组内保持原有顺序
+///
+/// 前缀为键名中以下划线分隔的一段，所有键共有的开头几段（例如 Mod 统一使用的 `pf_`）不计，
+/// 因此 `pf_tech_a` 与 `pf_tech_b` 归入 `pf_tech_` 一组。分组后注释与空行失去原来的位置，不再保留。
+/// 译文按键合并，重建目标文件时仍按源文件的顺序写入。
+pub fn group_by_key_prefix(content: &str) -> String {
+    let entries: Vec<(&str, String)> = content
+        .lines()
+        .filter_map(|line| crate::preprocess::parse_entry_line(line).map(|(key, _, _)| (line, key)))
+        .collect();
+    let segments: Vec<Vec<&str>> = entries
+        .iter()
+        .map(|(_, key)| key.split('_').collect())
+        .collect();
+    // 所有键共有的开头段数，至少保留一段用于分组
+    let shared = segments
+        .first()
+        .map(|first| {
+            (0..first.len().saturating_sub(1))
+                .take_while(|&i| segments.iter().all(|s| s.len() > i + 1 && s[i] == first[i]))
+                .count()
+        })
+        .unwrap_or_default();
+
+    let mut groups: Vec<(String, Vec<&str>)> = Vec::new();
+    for ((line, _), segments) in entries.iter().zip(&segments) {
+        let prefix = segments[..(shared + 1).min(segments.len())].join("_");
+        match groups.iter_mut().find(|(p, _)| *p == prefix) {
+            Some((_, lines)) => lines.push(line),
+            None => groups.push((prefix, vec![line])),
+        }
     }
+    groups
+        .into_iter()
+        .flat_map(|(_, lines)| lines)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 将带语言头的目标文件内容按条目数拆分为多个文件的内容，每个文件最多 `max_entries` 个条目
+///
+/// 每一份都以原来的语言头开头；拆分点之前的注释与空行（通常是下一节的标题）留给下一份。
+/// 条目数未超过上限时只返回一份。
+pub fn split_output(content: &str, max_entries: usize) -> Vec<String> {
+    let mut lines = content.lines().peekable();
+    let header = match lines.peek() {
+        Some(line) if crate::preprocess::parse_lang_header(line).is_some() => lines.next(),
+        _ => None,
+    };
+
+    let mut parts: Vec<Vec<&str>> = vec![Vec::new()];
+    let mut entries = 0;
+    // 达到上限后、下一个条目之前的注释与空行
+    let mut between: Vec<&str> = Vec::new();
+    for line in lines {
+        let is_entry = crate::preprocess::parse_entry_line(line).is_some();
+        if entries < max_entries.max(1) {
+            parts.last_mut().unwrap().push(line);
+            entries += is_entry as usize;
+        } else if is_entry {
+            between.push(line);
+            parts.push(std::mem::take(&mut between));
+            entries = 1;
+        } else {
+            between.push(line);
+        }
+    }
+    parts.last_mut().unwrap().extend(between);
 
-    // 如果只有一个切片且未超过限制，直接返回
-    Ok(chunks)
+    parts
+        .into_iter()
+        .map(|lines| match header {
+            Some(header) => std::iter::once(header)
+                .chain(lines)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => lines.join("\n"),
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[test]
+    fn test_group_by_key_prefix() {
+        let content = "# Techs\n pf_tech_a:0 \"A\"\n pf_building_a:0 \"B\"\n\n pf_tech_b:0 \"C\"\n pf_building_b_desc:0 \"D\"\n pf_x:0 \"E\"";
+        assert_eq!(
+            group_by_key_prefix(content),
+            " pf_tech_a:0 \"A\"\n pf_tech_b:0 \"C\"\n pf_building_a:0 \"B\"\n pf_building_b_desc:0 \"D\"\n pf_x:0 \"E\""
+        );
+        // 没有共同前缀时按第一段分组
+        assert_eq!(
+            group_by_key_prefix(" tech_a:0 \"A\"\n trait_a:0 \"B\"\n tech_b:0 \"C\""),
+            " tech_a:0 \"A\"\n tech_b:0 \"C\"\n trait_a:0 \"B\""
+        );
+    }
+
     /// 拆分后立刻将其组合，则应与原内容相同
     #[test]
     fn test_split_yaml_content() {
@@ -92,5 +290,36 @@ mod tests {
         let recombined_lines: Vec<&str> = recombined.lines().collect();
         let original_lines: Vec<&str> = content.lines().collect();
         assert_eq!(recombined_lines, original_lines);
+        assert!(chunks[0].section.is_none());
+        assert!(chunks.iter().skip(1).all(|c| c.section.is_some()));
+        assert!(
+            chunks
+                .iter()
+                .all(|c| c.preceding.is_empty() && c.following.is_empty())
+        );
+
+        // 重叠的行取自相邻切片的边界，切片本身的内容不变
+        let overlapped: Vec<FileChunk> = YamlChunks::new("l_english_pf_misc.yml", content, 500)
+            .with_overlap(2)
+            .collect();
+        assert_eq!(overlapped.len(), chunks.len());
+        for (i, chunk) in overlapped.iter().enumerate() {
+            assert_eq!(chunk.content, chunks[i].content);
+            let lines: Vec<&str> = chunk
+                .content
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .collect();
+            if i > 0 {
+                assert_eq!(overlapped[i - 1].following, lines[..2]);
+                assert_eq!(chunk.preceding.len(), 2);
+            }
+            if i + 1 < overlapped.len() {
+                assert_eq!(chunk.following.len(), 2);
+                assert_eq!(overlapped[i + 1].preceding, lines[lines.len() - 2..]);
+            }
+        }
+        assert!(overlapped[0].preceding.is_empty());
+        assert!(overlapped.last().unwrap().following.is_empty());
     }
 }
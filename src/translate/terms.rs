@@ -0,0 +1,214 @@
+//! 术语提取
+//!
+//! 正式翻译前先由模型通读 Mod 的全部源文本，提取 Mod 自创的专有名词并给出译法建议，
+//! 生成候选术语表供人工审核。审核后的术语表放入 `data/glossary_custom/` 并加入任务的
+//! `glossaries` 即可在正式翻译中使用。
+
+use super::{
+    EXTRACT_TERMS_PROMPT, GlossaryItem, Translator, VanillaIndex, load_glossaries_from_task,
+    split_yaml_content,
+};
+use crate::config::{ClientSettings, TranslationTask};
+use crate::error::{Result, TranslationError};
+use crate::preprocess::parse_entries;
+use crate::utils::require_data_files;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// 与原版名称比较的术语最多的单词数
+const MAX_TERM_WORDS: usize = 4;
+
+/// 候选术语
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermCandidate {
+    /// 原文术语
+    pub source: String,
+    /// 建议的译法
+    pub target: String,
+    /// 首次提取到该术语的源文件
+    pub file: String,
+    /// 在源文本中出现的次数
+    pub occurrences: usize,
+}
+
+/// 解析模型回复中的 `原文术语,译法` 行，忽略代码块标记、表头与格式不符的行
+pub fn parse_term_candidates(reply: &str, source_lang: &str) -> Vec<(String, String)> {
+    reply
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("```"))
+        .filter_map(|line| line.split_once(','))
+        .map(|(source, target)| {
+            (
+                source.trim().trim_matches('"').to_string(),
+                target.trim().trim_matches('"').to_string(),
+            )
+        })
+        // 原样复述的条目（`key:0 "text"`）不是术语
+        .filter(|(source, target)| {
+            !source.is_empty()
+                && !target.is_empty()
+                && source != source_lang
+                && !source.contains('"')
+        })
+        .collect()
+}
+
+/// 从任务的所有源文件中提取候选术语，按出现次数从多到少排列
+///
+/// 只保留确实出现在源文本中、且不在任务术语表中的术语；同一术语（不区分大小写）只保留第一次给出的译法。
+pub async fn run_term_extraction(
+    task: &TranslationTask,
+    client_settings: ClientSettings,
+    target_lang: &str,
+) -> Result<Vec<TermCandidate>> {
+    let max_chunk_tokens = client_settings.max_chunk_tokens;
+    let glossary = load_glossaries_from_task(task)?;
+    require_data_files(&[EXTRACT_TERMS_PROMPT])?;
+    let translator = Translator::for_task(client_settings, glossary.clone(), task)?;
+
+    // 沿用原版名称的术语直接采用官方译法
+    let official = match task.vanilla_dir() {
+        Some(dir) => {
+            VanillaIndex::open(&dir)?.official_terms(&task.source_lang, target_lang, MAX_TERM_WORDS)
+        }
+        None => HashMap::new(),
+    };
+
+    let mut candidates = Vec::new();
+    let mut seen = HashSet::new();
+    let mut source_text = String::new();
+    let source = task.source()?;
+    for path in source.list_files(&task.source_lang)? {
+        let entries = parse_entries(&source.read_file(&path)?);
+        if entries.is_empty() {
+            continue;
+        }
+        let filename = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let content: String = entries
+            .iter()
+            .map(|e| format!(" {}:0 \"{}\"\n", e.key, e.value))
+            .collect();
+        let target_filename = task.target_filename(&filename, target_lang);
+        for chunk in split_yaml_content(&target_filename, &content, max_chunk_tokens)? {
+            let reply = translator
+                .extract_terms(&chunk, &task.source_lang, target_lang)
+                .await?;
+            for (source, mut target) in parse_term_candidates(&reply, &task.source_lang) {
+                if !chunk.content.contains(&source) {
+                    log::debug!("Ignoring term '{}' not found in {}", source, filename);
+                    continue;
+                }
+                let known = glossary
+                    .find_terms_in_text(&source, &task.source_lang)
+                    .iter()
+                    .any(|term| term.eq_ignore_ascii_case(&source));
+                if known || !seen.insert(source.to_lowercase()) {
+                    continue;
+                }
+                if let Some(official) = official.get(&source.to_lowercase()) {
+                    log::debug!("Using official translation of '{}': {}", source, official);
+                    target = official.clone();
+                }
+                candidates.push(TermCandidate {
+                    source,
+                    target,
+                    file: filename.clone(),
+                    occurrences: 0,
+                });
+            }
+        }
+        for entry in &entries {
+            source_text.push_str(&entry.value);
+            source_text.push('\n');
+        }
+    }
+
+    for candidate in &mut candidates {
+        candidate.occurrences = source_text.matches(&candidate.source).count();
+    }
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.occurrences));
+    Ok(candidates)
+}
+
+/// 将候选术语写为术语表文件（与 `data/glossary/` 中的格式相同）
+///
+/// 术语表按小写匹配原文，因此原文术语以小写保存。
+pub fn write_candidate_glossary(
+    candidates: &[TermCandidate],
+    source_lang: &str,
+    target_lang: &str,
+    output: &Path,
+) -> Result<()> {
+    let field = |lang: &str| {
+        GlossaryItem::field_key(lang).ok_or_else(|| {
+            TranslationError::ValidationError(format!(
+                "Language '{}' is not supported by the glossary format",
+                lang
+            ))
+        })
+    };
+    let (source_field, target_field) = (field(source_lang)?, field(target_lang)?);
+
+    let mut glossary = BTreeMap::new();
+    for candidate in candidates {
+        let source = candidate.source.to_lowercase();
+        let item = serde_json::json!({
+            source_field: source,
+            target_field: candidate.target,
+        });
+        glossary.insert(source, item);
+    }
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(&glossary).map_err(std::io::Error::other)?;
+    std::fs::write(output, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::Glossary;
+
+    #[test]
+    fn test_candidate_glossary() {
+        let reply = "```csv\nenglish,simp_chinese\nKetling Star Pack,柯特林星群\n\"Voidborn Spire\", 虚空之尖塔\nno comma here\n pf_x:0 \"Energy, minerals\"\n```";
+        let parsed = parse_term_candidates(reply, "english");
+        assert_eq!(
+            parsed,
+            [
+                ("Ketling Star Pack".to_string(), "柯特林星群".to_string()),
+                ("Voidborn Spire".to_string(), "虚空之尖塔".to_string()),
+            ]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("candidates.json");
+        let candidates: Vec<TermCandidate> = parsed
+            .into_iter()
+            .map(|(source, target)| TermCandidate {
+                source,
+                target,
+                file: "pf_l_english.yml".to_string(),
+                occurrences: 1,
+            })
+            .collect();
+        write_candidate_glossary(&candidates, "english", "simp_chinese", &path).unwrap();
+
+        // 生成的文件可以直接作为术语表使用
+        let glossary = Glossary::from_json_file(&path).unwrap();
+        assert_eq!(
+            glossary.find_terms_in_text("The Ketling Star Pack arrives", "english"),
+            ["ketling star pack"]
+        );
+        assert_eq!(
+            glossary.get_translation_map("english", "simp_chinese")["voidborn spire"],
+            "虚空之尖塔"
+        );
+    }
+}
@@ -2,21 +2,118 @@
 //!
 //! 集成API客户端、术语表和提示词模板，执行翻译任务。
 
-use crate::config::ClientSettings;
-use crate::error::{Result, TranslationError};
-use crate::postprocess::TranslationSlice;
-use crate::translate::FileChunk;
-use crate::translate::api::{ApiClient, system_message, user_message};
-use crate::translate::glossary::Glossary;
-use crate::translate::validator::FormatValidator;
-use crate::utils::{estimate_mixed_tokens, find_data_file_or_error};
+use crate::config::{ClientSettings, TranslationTask};
+use crate::error::{Result, ResultExt, TranslationError};
+use crate::postprocess::{TranslationSlice, align_to_source, is_aligned, strip_context_header};
+use crate::preprocess::{
+    compile_markers, protect_grammar, protect_markers, protect_numbers, restore_grammar,
+    restore_markers, restore_numbers,
+};
+use crate::translate::api::{
+    ApiClient, ChatCompletionResponse, ChatMessage, assistant_message, system_message, user_message,
+};
+use crate::translate::batcher::{AdaptiveConcurrency, RequestOutcome};
+use crate::translate::conversation::{Conversation, Conversations, truncate_conversation};
+use crate::translate::glossary::{Glossary, rank_terms};
+use crate::translate::validator::{FormatValidator, Problem};
+use crate::translate::{FileChunk, StructureDiff, VanillaIndex, render_context_header};
+use crate::utils::{estimate_mixed_tokens, find_data_file_or_error, require_data_files};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
+use std::sync::Mutex;
+
+/// 译文缺少条目时的最大重试次数
+const MAX_ALIGN_RETRIES: u32 = 1;
+
+/// 翻译提示词模板
+pub const TRANSLATE_PROMPT: &str = "prompts/translate_system.txt";
+/// 润色提示词模板
+pub const REFINE_PROMPT: &str = "prompts/refine_system.txt";
+/// 评审提示词模板
+pub const JUDGE_PROMPT: &str = "prompts/judge_system.txt";
+/// 修复提示词模板
+pub const REPAIR_PROMPT: &str = "prompts/repair_system.txt";
+/// 术语提取提示词模板
+pub const EXTRACT_TERMS_PROMPT: &str = "prompts/extract_terms_system.txt";
+/// 所有提示词模板
+pub const PROMPT_TEMPLATES: &[&str] = &[
+    TRANSLATE_PROMPT,
+    REFINE_PROMPT,
+    JUDGE_PROMPT,
+    REPAIR_PROMPT,
+    EXTRACT_TERMS_PROMPT,
+];
+/// 提示词模板中可以使用的 `{{占位符}}`
+pub const PROMPT_PLACEHOLDERS: &[&str] = &["glossary_csv"];
+
+/// 已读取的提示词模板（模板路径 -> 内容），每个模板只从磁盘读取一次
+static PROMPT_CACHE: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// 读取提示词模板
+///
+/// 数据目录按以下顺序寻找，若不存在再寻找下一个：
+/// 1. 当前目录下的提示词： ./data/
+/// 2. 用户级数据目录下的提示词： ~/.local/share/pmt/data/
+fn prompt_template(template: &str) -> Result<String> {
+    let mut cache = PROMPT_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(prompt) = cache.get(template) {
+        return Ok(prompt.clone());
+    }
+    let prompt_path = find_data_file_or_error(template)?;
+    let prompt = fs::read_to_string(&prompt_path).map_err(|e| {
+        TranslationError::Translate(crate::error::TranslateError::ValidationFailed(format!(
+            "Failed to load prompt template from {}: {}",
+            prompt_path.display(),
+            e
+        )))
+    })?;
+    cache.insert(template.to_string(), prompt.clone());
+    Ok(prompt)
+}
+
+/// 多模型投票中的一份候选译文
+struct Candidate {
+    /// 模型名称
+    model: String,
+    /// 占位符尚未还原的译文
+    draft: String,
+    /// 还原占位符后的译文
+    translated: String,
+    /// 验证发现的问题
+    problems: Vec<Problem>,
+}
 
 /// 翻译器
 pub struct Translator {
     api_client: ApiClient,
+    /// 润色轮使用的客户端，未设置时只翻译一轮
+    refiner: Option<ApiClient>,
+    /// 多模型投票的成员，为空时只使用 `api_client` 翻译
+    ensemble: Vec<ApiClient>,
+    /// 多模型投票的评审模型
+    judge: Option<ApiClient>,
     glossary: Glossary,
     validator: FormatValidator,
+    /// 任务定义的自定义标记，发送前替换为占位符
+    custom_markers: Vec<regex::Regex>,
+    /// 记入用量账本时使用的任务名称（本地化目录），不属于任何任务时为空
+    task_name: String,
+    /// 是否按文件保持会话
+    conversation: bool,
+    /// 按文件保持的会话
+    conversations: Conversations,
+    /// 是否在目标文件中标注译文来源
+    provenance: bool,
+    /// 相邻切片之间重叠的行数
+    chunk_overlap: usize,
+    /// 切片前是否按键名前缀将条目分组
+    group_by_prefix: bool,
+    /// 自适应并发控制，未启用时由调用方固定并发数
+    limiter: Option<AdaptiveConcurrency>,
+    /// 服务商过载时的最大重试次数，仅在启用自适应并发时生效
+    overload_retries: u32,
+    /// 原版本地化中的短文本（小写），不记入会话术语表
+    vanilla_texts: HashSet<String>,
 }
 
 impl Translator {
@@ -24,157 +121,996 @@ impl Translator {
     pub fn new(api_client: ApiClient, glossaries: Glossary) -> Self {
         Self {
             api_client,
+            refiner: None,
+            ensemble: Vec::new(),
+            judge: None,
             glossary: glossaries,
             validator: FormatValidator::new(),
+            custom_markers: Vec::new(),
+            task_name: String::new(),
+            conversation: false,
+            conversations: Conversations::default(),
+            provenance: false,
+            chunk_overlap: 0,
+            group_by_prefix: false,
+            limiter: None,
+            overload_retries: 0,
+            vanilla_texts: HashSet::new(),
         }
     }
 
     /// 从设置创建翻译器
     pub fn from_settings(client_settings: ClientSettings, glossary: Glossary) -> Result<Self> {
+        require_data_files(&[TRANSLATE_PROMPT])?;
         let api_key = crate::config::load_openai_api_key()?;
+        let conversation = client_settings.conversation;
+        let chunk_overlap = client_settings.chunk_overlap;
+        let limiter = client_settings.max_concurrency.map(|max| {
+            log::info!(
+                "Adaptive concurrency enabled, starting at {} and up to {}",
+                client_settings.concurrency,
+                max
+            );
+            AdaptiveConcurrency::new(client_settings.concurrency, max)
+        });
+        let max_retries = client_settings.max_retries;
         let api_client = ApiClient::new(client_settings, api_key)?;
-        Ok(Self::new(api_client, glossary))
+        let translator = Self::new(api_client, glossary)
+            .with_conversation(conversation)
+            .with_chunk_overlap(chunk_overlap);
+        Ok(match limiter {
+            Some(limiter) => translator.with_adaptive_concurrency(limiter, max_retries),
+            None => translator,
+        })
+    }
+
+    /// 按服务商的延迟与错误自动调整并发数，服务商过载时退避后最多重试 `max_retries` 次
+    pub fn with_adaptive_concurrency(
+        mut self,
+        limiter: AdaptiveConcurrency,
+        max_retries: u32,
+    ) -> Self {
+        self.limiter = Some(limiter);
+        self.overload_retries = max_retries;
+        self
+    }
+
+    /// 每个切片附带相邻切片边界处的 `lines` 行原文作为只读的上下文
+    pub fn with_chunk_overlap(mut self, lines: usize) -> Self {
+        self.chunk_overlap = lines;
+        self
+    }
+
+    /// 相邻切片之间重叠的行数，切分待翻译的内容时使用
+    pub fn chunk_overlap(&self) -> usize {
+        self.chunk_overlap
+    }
+
+    /// 切片前按键名前缀将条目分组，见 [`group_by_key_prefix`](crate::translate::group_by_key_prefix)
+    pub fn with_prefix_grouping(mut self, enabled: bool) -> Self {
+        self.group_by_prefix = enabled;
+        self
+    }
+
+    /// 切片前是否按键名前缀将条目分组
+    pub fn groups_by_prefix(&self) -> bool {
+        self.group_by_prefix
+    }
+
+    /// 按文件保持会话：同一文件的切片依次在一个会话中翻译，系统提示词只发送一次
+    pub fn with_conversation(mut self, enabled: bool) -> Self {
+        self.conversation = enabled;
+        self
+    }
+
+    /// 在目标文件中标注译文来源：文件头注释与每个条目的来源标记
+    pub fn with_provenance(mut self, enabled: bool) -> Self {
+        self.provenance = enabled;
+        self
+    }
+
+    /// 启用来源标注时写入文件头的模型组合，未启用时为 None
+    pub fn provenance_model(&self) -> Option<String> {
+        self.provenance.then(|| self.model_signature())
+    }
+
+    /// 启用润色轮：初稿完成后再由 `refiner` 对照原文润色
+    pub fn with_refiner(mut self, refiner: ApiClient) -> Self {
+        self.refiner = Some(refiner);
+        self
+    }
+
+    /// 启用多模型投票：每个切片由 `members` 中的所有模型分别翻译，再从中选出最好的译文
+    ///
+    /// 设置 `judge` 时由评审模型挑选，否则选择验证问题最少的译文。
+    pub fn with_ensemble(mut self, members: Vec<ApiClient>, judge: Option<ApiClient>) -> Self {
+        self.ensemble = members;
+        self.judge = judge;
+        self
     }
 
-    /// 加载系统提示词模板
+    /// 按任务设置创建翻译器：`draft_model` 覆盖初稿使用的模型，
+    /// 启用多模型投票或润色时另建对应的客户端
+    pub fn for_task(
+        client_settings: ClientSettings,
+        glossary: Glossary,
+        task: &TranslationTask,
+    ) -> Result<Self> {
+        // 启动时一次列出缺少的提示词模板，而不是在每个切片翻译时分别失败
+        let mut prompts = vec![TRANSLATE_PROMPT];
+        if task.refine_enabled() {
+            prompts.push(REFINE_PROMPT);
+        }
+        if task.judge_model.is_some() {
+            prompts.push(JUDGE_PROMPT);
+        }
+        require_data_files(&prompts)?;
+
+        let with_model = |model: &str| -> Result<ApiClient> {
+            let mut settings = client_settings.clone();
+            settings.model = model.to_string();
+            ApiClient::new(settings, crate::config::load_openai_api_key()?)
+        };
+
+        let mut draft_settings = client_settings.clone();
+        if let Some(model) = &task.draft_model {
+            draft_settings.model = model.clone();
+        }
+        let mut translator = Self::from_settings(draft_settings, glossary)?;
+        if !task.ensemble_models.is_empty() {
+            log::info!("Ensemble enabled with models {:?}", task.ensemble_models);
+            let members = task
+                .ensemble_models
+                .iter()
+                .map(|m| with_model(m))
+                .collect::<Result<Vec<_>>>()?;
+            let judge = task.judge_model.as_deref().map(with_model).transpose()?;
+            translator = translator.with_ensemble(members, judge);
+            if client_settings.conversation {
+                log::warn!("Conversations are not kept per file when the ensemble is enabled");
+            }
+        }
+        if task.refine_enabled() {
+            let model = task
+                .refine_model
+                .clone()
+                .unwrap_or_else(|| client_settings.model.clone());
+            log::info!("Refine pass enabled with model {}", model);
+            translator = translator.with_refiner(with_model(&model)?);
+        }
+        translator.task_name = task.localisation_dir.display().to_string();
+        translator.validator = FormatValidator::for_task(task);
+        translator.custom_markers = compile_markers(&task.custom_markers);
+        if let Some(dir) = task.vanilla_dir() {
+            match VanillaIndex::open(&dir) {
+                Ok(index) => {
+                    translator.vanilla_texts = index.short_texts(
+                        &task.source_lang,
+                        crate::translate::session_glossary::MAX_WORDS,
+                    )
+                }
+                Err(e) => log::warn!("Failed to open the vanilla localisation index: {}", e),
+            }
+        }
+        Ok(translator
+            .with_provenance(task.provenance)
+            .with_prefix_grouping(task.group_by_prefix))
+    }
+
+    /// 生成系统提示词
+    ///
+    /// `user_prompt` 为随后发送给 `client` 的用户消息。提示词连同用户消息超出模型的上下文长度时，
+    /// 从术语表末尾（影响最小的术语）开始删减，直到放得下为止，而不是把必然失败的请求发给服务商。
     fn load_system_prompt(
         &self,
+        template: &str,
         source_lang: &str,
         target_lang: &str,
         source_text: &str,
+        client: &ApiClient,
+        user_prompt: &str,
     ) -> Result<String> {
-        // 数据目录应按照以下顺序寻找，若不存在再寻找下一个：
-        // 1. 当前目录下的提示词： ./data/
-        // 2. 用户级数据目录下的提示词： ~/.local/share/pmt/data/
-        let prompt_path = find_data_file_or_error("prompts/translate_system.txt")?;
-        let mut prompt = fs::read_to_string(&prompt_path).map_err(|e| {
-            TranslationError::Translate(crate::error::TranslateError::ValidationFailed(format!(
-                "Failed to load prompt template from {}: {}",
-                prompt_path.display(),
-                e
-            )))
-        })?;
-
-        // 提取源文本中的术语
-        let mut all_found_terms = Vec::new();
-        let found_terms = self.glossary.find_terms_in_text(source_text, source_lang);
-        all_found_terms.extend(found_terms);
+        let rows = self.glossary_rows(source_lang, target_lang, source_text);
+        let (prompt, _) = self.fit_system_prompt(
+            template,
+            source_lang,
+            target_lang,
+            rows,
+            client,
+            user_prompt,
+        )?;
+        Ok(prompt)
+    }
 
-        // 去重
-        all_found_terms.sort();
-        all_found_terms.dedup();
+    /// 以给定的术语生成系统提示词，返回提示词与删减后保留的术语
+    fn fit_system_prompt(
+        &self,
+        template: &str,
+        source_lang: &str,
+        target_lang: &str,
+        mut rows: Vec<String>,
+        client: &ApiClient,
+        user_prompt: &str,
+    ) -> Result<(String, Vec<String>)> {
+        let template = prompt_template(template)?;
+        let budget = system_prompt_budget(client, user_prompt);
+        let (prompt, kept) = fit_glossary(&template, source_lang, target_lang, &rows, budget);
+        if kept < rows.len() {
+            log::warn!(
+                "Trimmed glossary from {} to {} terms to fit the context window of {}",
+                rows.len(),
+                kept,
+                client.model()
+            );
+            rows.truncate(kept);
+        }
+        Ok((prompt, rows))
+    }
 
-        // 生成术语表CSV
-        let glossary_csv = if all_found_terms.is_empty() {
-            String::new()
-        } else {
-            // 合并所有术语表的术语
-            let mut terms_count = 0;
-            let mut csv_data = String::new();
-            csv_data.push_str(&format!("{},{}", source_lang, target_lang));
-
-            let source_terms: Vec<&str> = all_found_terms.iter().map(|s| s.as_str()).collect();
-
-            let csv = self
-                .glossary
-                .to_csv(source_lang, target_lang, &source_terms);
-            if !csv.is_empty() && csv.contains('\n') {
-                // 跳过表头行（第一行）
-                let lines: Vec<&str> = csv.lines().collect();
-                if lines.len() > 1 {
-                    for line in &lines[1..] {
-                        if !line.trim().is_empty() {
-                            csv_data.push('\n');
-                            csv_data.push_str(line);
-                            terms_count += 1;
-                        }
-                    }
+    /// 按文件保持会话时的请求消息：文件的第一个切片开始新的会话，之后的切片接在之前的对话之后，
+    /// 并在用户消息中附带系统提示词中没有的术语。会话超出上下文长度时丢弃最早的几轮对话。
+    ///
+    /// 返回要发送的消息，以及收到回复后应记录的会话（调用方追加回复后传给 [`Conversations::record`]）。
+    fn conversation_messages(
+        &self,
+        chunk: &FileChunk,
+        source_lang: &str,
+        target_lang: &str,
+        user_prompt: String,
+    ) -> Result<(Vec<ChatMessage>, Conversation)> {
+        let rows = self.glossary_rows(source_lang, target_lang, &chunk.content);
+        let (mut conversation, user_prompt) =
+            match self.conversations.get(&chunk.target_filename, target_lang) {
+                Some(conversation) => {
+                    let extra: Vec<&str> = rows
+                        .iter()
+                        .filter(|row| !conversation.system_terms.contains(*row))
+                        .map(|row| row.as_str())
+                        .collect();
+                    let user_prompt = if extra.is_empty() {
+                        user_prompt
+                    } else {
+                        format!(
+                            "## 术语\n{},{}\n{}\n\n{}",
+                            source_lang,
+                            target_lang,
+                            extra.join("\n"),
+                            user_prompt
+                        )
+                    };
+                    (conversation, user_prompt)
                 }
-            }
-
-            log::info!("Found {} terms for translation", terms_count);
-            csv_data
-        };
+                None => {
+                    let (system_prompt, kept) = self.fit_system_prompt(
+                        TRANSLATE_PROMPT,
+                        source_lang,
+                        target_lang,
+                        rows,
+                        &self.api_client,
+                        &user_prompt,
+                    )?;
+                    let conversation = Conversation {
+                        messages: vec![system_message(system_prompt)],
+                        system_terms: kept.into_iter().collect(),
+                    };
+                    (conversation, user_prompt)
+                }
+            };
 
-        // 替换模板中的占位符
-        if !glossary_csv.is_empty() {
-            prompt = prompt.replace("{{glossary_csv}}", &glossary_csv);
-            log::debug!(
-                "\n======DEBUG Using glossary CSV======\n{}\n======DEBUG END======\n",
-                &glossary_csv
+        let reply = self
+            .api_client
+            .max_tokens()
+            .map(|tokens| tokens as usize)
+            .unwrap_or(estimate_mixed_tokens(&user_prompt) * 2);
+        conversation.messages.push(user_message(user_prompt));
+        let budget = self.api_client.context_window().saturating_sub(reply);
+        let dropped = truncate_conversation(&mut conversation.messages, budget);
+        if dropped > 0 {
+            log::info!(
+                "Dropped {} earlier turns of the conversation for {} to fit the context window",
+                dropped,
+                chunk.target_filename
             );
-        } else {
-            prompt = prompt.replace("{{glossary_csv}}", "（无相关术语）");
         }
+        Ok((conversation.messages.clone(), conversation))
+    }
 
-        Ok(prompt)
+    /// 是否按文件保持会话：设置了 `conversation` 且未启用多模型投票
+    pub fn keeps_conversations(&self) -> bool {
+        self.conversation && self.ensemble.is_empty()
     }
 
-    /// 翻译单个文本片段
-    pub async fn translate_chunk(
+    /// 文件翻译完成后结束其会话
+    pub fn end_conversation(&self, target_filename: &str, target_lang: &str) {
+        self.conversations.end(target_filename, target_lang);
+    }
+
+    /// 源文本中出现的术语（`原文,译文`），术语表中的术语在前，本次运行中锁定的译法在后，
+    /// 两者各自按 [`rank_terms`] 排序
+    fn glossary_rows(
         &self,
-        chunk: &FileChunk,
         source_lang: &str,
         target_lang: &str,
-    ) -> Result<TranslationSlice> {
-        // 加载系统提示词
-        let source_text = &chunk.content;
-        let system_prompt = self.load_system_prompt(source_lang, target_lang, source_text)?;
+        source_text: &str,
+    ) -> Vec<String> {
+        // 提取源文本中的术语
+        let mut found_terms = self.glossary.find_terms_in_text(source_text, source_lang);
+        found_terms.sort();
+        found_terms.dedup();
+        rank_terms(&mut found_terms, source_text, |term| term);
 
-        // 准备消息
-        let messages = vec![
-            system_message(system_prompt),
-            user_message(source_text.to_string()),
-        ];
+        let source_terms: Vec<&str> = found_terms.iter().map(|s| s.as_str()).collect();
+        // 跳过表头行（第一行）
+        let mut rows: Vec<String> = self
+            .glossary
+            .to_csv(source_lang, target_lang, &source_terms)
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect();
+        if !rows.is_empty() {
+            log::info!("Found {} terms for translation", rows.len());
+        }
 
-        let id = format!(
-            "{}({}:{})",
-            chunk.target_filename, chunk.start_line, chunk.end_line
+        // 本次运行中已确定译法的专有名词，保证同一名称在各文件中的译法一致
+        let mut locked_terms = crate::translate::session_glossary().terms_in_text(
+            source_text,
+            source_lang,
+            target_lang,
         );
-        log::info!(
-            "Sending translation request [{}] with {} characters, estimated {} tokens...",
-            id,
-            source_text.chars().count(),
-            estimate_mixed_tokens(&source_text)
+        rank_terms(&mut locked_terms, source_text, |(term, _)| term);
+        if !locked_terms.is_empty() {
+            log::info!(
+                "Found {} terms locked earlier in this run",
+                locked_terms.len()
+            );
+        }
+        rows.extend(
+            locked_terms
+                .iter()
+                .map(|(term, translation)| format!("{},{}", term, translation)),
+        );
+        rows
+    }
+
+    /// 切片连同最简的系统提示词（不含术语表）也超出上下文长度时，将切片从中间拆为两半
+    ///
+    /// 只有一行的切片无法再拆分，照常发送。
+    fn split_to_fit(&self, chunk: &FileChunk) -> Result<Option<[FileChunk; 2]>> {
+        let minimal = estimate_mixed_tokens(&prompt_template(TRANSLATE_PROMPT)?);
+        let user_prompt = format!("{}{}", render_context_header(chunk), chunk.content);
+        let fits = self
+            .translation_clients()
+            .iter()
+            .all(|client| system_prompt_budget(client, &user_prompt) >= minimal);
+        let lines: Vec<&str> = chunk.content.lines().collect();
+        if fits || lines.len() < 2 {
+            return Ok(None);
+        }
+        let middle = lines.len() / 2;
+        let part = |lines: &[&str], start_line: usize, end_line: usize| FileChunk {
+            content: lines.join("\n"),
+            start_line,
+            end_line,
+            target_filename: chunk.target_filename.clone(),
+            section: chunk.section.clone(),
+            // 拆分后的两半不再附带相邻切片的原文，以节省上下文
+            preceding: Vec::new(),
+            following: Vec::new(),
+        };
+        Ok(Some([
+            part(
+                &lines[..middle],
+                chunk.start_line,
+                chunk.start_line + middle - 1,
+            ),
+            part(&lines[middle..], chunk.start_line + middle, chunk.end_line),
+        ]))
+    }
+
+    /// 翻译切片使用的客户端：多模型投票的全部成员，未启用投票时为 `api_client`
+    fn translation_clients(&self) -> Vec<&ApiClient> {
+        if self.ensemble.is_empty() {
+            vec![&self.api_client]
+        } else {
+            self.ensemble.iter().collect()
+        }
+    }
+
+    /// 调用模型并返回回复内容，token 用量记入 `chunk` 所属文件与 `target_lang`
+    ///
+    /// `attempt` 为同一切片的第几次重试，计入请求的种子
+    async fn request(
+        &self,
+        api_client: &ApiClient,
+        messages: Vec<ChatMessage>,
+        kind: &str,
+        chunk: &FileChunk,
+        target_lang: &str,
+        attempt: u32,
+    ) -> Result<String> {
+        let id = chunk.id();
+        let response = self
+            .send_request(api_client, messages, attempt)
+            .await
+            .inspect_err(|_| crate::metrics::metrics().record_error())
+            .in_chunk(id.clone())?;
+        crate::metrics::metrics().record_tokens(
+            response.usage.prompt_tokens,
+            response.usage.completion_tokens,
+        );
+        crate::translate::usage_ledger().record(
+            &self.task_name,
+            target_lang,
+            &chunk.target_filename,
+            response.usage.prompt_tokens,
+            response.usage.completion_tokens,
         );
-        // 调用API
-        let response = self.api_client.chat_completions(messages).await?;
 
         log::info!(
-            "Received translation response [{}], tokens used: {} + {} = {}",
+            "Received {} response [{}], tokens used: {} + {} = {}",
+            kind,
             id,
             response.usage.prompt_tokens,
             response.usage.completion_tokens,
             response.usage.total_tokens
         );
         // 提取回复内容
-        let translated_text = response
+        Ok(response
             .choices
             .first()
             .ok_or_else(|| {
                 TranslationError::Translate(crate::error::TranslateError::InvalidResponse(
                     "No choices in API response".to_string(),
                 ))
-            })?
+            })
+            .in_chunk(id)?
             .message
             .content
-            .clone();
+            .clone())
+    }
+
+    /// 发送请求；启用自适应并发时先等待并发许可，按请求的结果调整并发数，服务商过载时退避后重试
+    async fn send_request(
+        &self,
+        api_client: &ApiClient,
+        messages: Vec<ChatMessage>,
+        attempt: u32,
+    ) -> Result<ChatCompletionResponse> {
+        let Some(limiter) = &self.limiter else {
+            return api_client.chat_completions(messages, attempt).await;
+        };
+        // 过载重试发送相同的内容，沿用同一个种子
+        let mut retries = 0;
+        loop {
+            let permit = limiter.acquire().await;
+            let started = std::time::Instant::now();
+            let result = api_client.chat_completions(messages.clone(), attempt).await;
+            let outcome = match &result {
+                Ok(response) => RequestOutcome::Success {
+                    latency: started.elapsed(),
+                    tokens: response.usage.completion_tokens,
+                },
+                Err(e) if e.is_overloaded() => RequestOutcome::Overloaded,
+                Err(_) => RequestOutcome::Failed,
+            };
+            limiter.release(permit, outcome);
+            match result {
+                Err(e) if e.is_overloaded() && retries < self.overload_retries => {
+                    retries += 1;
+                    let delay = std::time::Duration::from_secs(2u64.pow(retries));
+                    log::warn!(
+                        "{}, retrying in {}s ({}/{})",
+                        e,
+                        delay.as_secs(),
+                        retries,
+                        self.overload_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 从多模型投票的候选译文中选出一份
+    ///
+    /// 只有一份通过验证时直接采用；否则在通过验证的候选（都未通过时为全部候选）中，
+    /// 由评审模型挑选，未设置评审模型或评审失败时选择验证问题最少的一份。
+    #[allow(clippy::too_many_arguments)]
+    async fn choose_candidate(
+        &self,
+        mut candidates: Vec<Candidate>,
+        source_lang: &str,
+        target_lang: &str,
+        source_text: &str,
+        header: &str,
+        protected_text: &str,
+        chunk: &FileChunk,
+    ) -> Result<Candidate> {
+        let id = chunk.id();
+        if candidates.iter().any(|c| c.problems.is_empty()) {
+            candidates.retain(|c| c.problems.is_empty());
+        }
+        let fewest_problems = |candidates: &[Candidate]| {
+            candidates
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, c)| c.problems.len())
+                .map(|(i, _)| i)
+                .unwrap_or(0)
+        };
+
+        let mut index = fewest_problems(&candidates);
+        if candidates.len() > 1
+            && let Some(judge) = &self.judge
+        {
+            let mut user = format!("{}## 原文\n{}\n", header, protected_text);
+            for (i, candidate) in candidates.iter().enumerate() {
+                user.push_str(&format!("\n## 译文 {}\n{}\n", i + 1, candidate.draft));
+            }
+            let system_prompt = self.load_system_prompt(
+                JUDGE_PROMPT,
+                source_lang,
+                target_lang,
+                source_text,
+                judge,
+                &user,
+            )?;
+            let messages = vec![system_message(system_prompt), user_message(user)];
+            log::info!("Sending judge request [{}]...", id);
+            match self
+                .request(judge, messages, "judge", chunk, target_lang, 0)
+                .await
+            {
+                Ok(verdict) => match parse_verdict(&verdict, candidates.len()) {
+                    Some(i) => index = i,
+                    None => log::warn!(
+                        "Unrecognised verdict for [{}]: {}, using the candidate with fewest issues",
+                        id,
+                        verdict.trim()
+                    ),
+                },
+                Err(e) => log::warn!("Judge request [{}] failed: {}", id, e),
+            }
+        }
+        let chosen = candidates.swap_remove(index);
+        log::info!(
+            "Picked translation of [{}] from model {} ({} issues)",
+            id,
+            chosen.model,
+            chosen.problems.len()
+        );
+        Ok(chosen)
+    }
+
+    /// 翻译单个文本片段
+    ///
+    /// 译文与源切片的行不对应时按键重新对齐；译文缺少条目时重新请求，
+    /// 重试后仍然缺少的条目以原文填充，保证切片的行数与源切片一致。
+    pub async fn translate_chunk(
+        &self,
+        chunk: &FileChunk,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<TranslationSlice> {
+        let model = self.model_signature();
+        let store = crate::translate::chunk_store();
+        let prompt = self.stored_prompt(chunk, source_lang, target_lang)?;
+        if let Some(content) = store.get(source_lang, target_lang, &model, &prompt, &chunk.content)
+        {
+            log::info!("Reusing stored translation of {}", chunk.id());
+            let slice = TranslationSlice {
+                content,
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+            };
+            self.lock_terms(chunk, &slice, source_lang, target_lang);
+            return Ok(slice);
+        }
+
+        let mut attempt = 0;
+        loop {
+            let mut slice = self
+                .translate_chunk_once(chunk, source_lang, target_lang, attempt)
+                .await?;
+            if is_aligned(&chunk.content, &slice.content) {
+                store.put(
+                    source_lang,
+                    target_lang,
+                    &model,
+                    &prompt,
+                    &chunk.content,
+                    &slice.content,
+                );
+                self.lock_terms(chunk, &slice, source_lang, target_lang);
+                return Ok(slice);
+            }
+            let aligned = align_to_source(&chunk.content, &slice.content);
+            if aligned.missing.is_empty() {
+                log::info!(
+                    "Realigned translation of {} to the source lines",
+                    chunk.id()
+                );
+                store.put(
+                    source_lang,
+                    target_lang,
+                    &model,
+                    &prompt,
+                    &chunk.content,
+                    &aligned.content,
+                );
+            } else if attempt < MAX_ALIGN_RETRIES {
+                attempt += 1;
+                log::warn!(
+                    "Translation of {} is missing {} entries, retrying ({}/{})",
+                    chunk.id(),
+                    aligned.missing.len(),
+                    attempt,
+                    MAX_ALIGN_RETRIES
+                );
+                continue;
+            } else {
+                log::warn!(
+                    "Translation of {} is still missing entries, keeping the source text for: {}",
+                    chunk.id(),
+                    aligned.missing.join(", ")
+                );
+            }
+            slice.content = aligned.content;
+            self.lock_terms(chunk, &slice, source_lang, target_lang);
+            return Ok(slice);
+        }
+    }
+
+    /// 切片译文存储区分请求的提示词：不保持会话时的系统提示词（含术语表与本次运行中锁定的译法）与用户消息（含上下文块）
+    ///
+    /// 术语表、提示词模板或切片的上下文变化后不再复用之前的译文。
+    fn stored_prompt(
+        &self,
+        chunk: &FileChunk,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<String> {
+        let (protected_text, _) = protect_markers(&chunk.content, &self.custom_markers);
+        let (protected_text, _) = protect_grammar(&protected_text);
+        let (protected_text, _) = protect_numbers(&protected_text);
+        let user_prompt = format!("{}{}", render_context_header(chunk), protected_text);
+        let system_prompt = self.load_system_prompt(
+            TRANSLATE_PROMPT,
+            source_lang,
+            target_lang,
+            &chunk.content,
+            &self.api_client,
+            &user_prompt,
+        )?;
+        Ok(format!("{}\n{}", system_prompt, user_prompt))
+    }
+
+    /// 产生译文的模型组合，作为切片译文存储的键的一部分：更换任一模型后不再复用之前的译文
+    fn model_signature(&self) -> String {
+        let mut signature = if self.ensemble.is_empty() {
+            self.api_client.model().to_string()
+        } else {
+            let members: Vec<&str> = self.ensemble.iter().map(|c| c.model()).collect();
+            format!("ensemble({})", members.join(","))
+        };
+        if let Some(judge) = &self.judge {
+            signature.push_str(&format!("+judge({})", judge.model()));
+        }
+        if let Some(refiner) = &self.refiner {
+            signature.push_str(&format!("+refine({})", refiner.model()));
+        }
+        signature
+    }
+
+    /// 将切片中首次出现的专有名词的译法记入会话术语表，术语表中已有的术语与原版本地化中已有的文本除外
+    fn lock_terms(
+        &self,
+        chunk: &FileChunk,
+        slice: &TranslationSlice,
+        source_lang: &str,
+        target_lang: &str,
+    ) {
+        let learned = crate::translate::session_glossary().learn(
+            &chunk.content,
+            &slice.content,
+            source_lang,
+            target_lang,
+            |term| {
+                self.vanilla_texts.contains(&term.to_lowercase())
+                    || self
+                        .glossary
+                        .find_terms_in_text(term, source_lang)
+                        .iter()
+                        .any(|known| known.eq_ignore_ascii_case(term))
+            },
+        );
+        for (term, translation) in learned {
+            log::info!(
+                "Locked translation of '{}' as '{}' for the rest of this run",
+                term,
+                translation
+            );
+        }
+    }
+
+    /// 请求一次翻译，不检查译文与源切片是否对齐；`attempt` 为该切片的第几次重试
+    async fn translate_chunk_once(
+        &self,
+        chunk: &FileChunk,
+        source_lang: &str,
+        target_lang: &str,
+        attempt: u32,
+    ) -> Result<TranslationSlice> {
+        // 切片连同提示词超出上下文长度时拆为两半分别翻译
+        if let Some(parts) = self.split_to_fit(chunk)? {
+            log::warn!(
+                "{} does not fit the context window, translating it in two halves",
+                chunk.id()
+            );
+            let mut contents = Vec::new();
+            for part in &parts {
+                let slice =
+                    Box::pin(self.translate_chunk_once(part, source_lang, target_lang, attempt))
+                        .await?;
+                contents.push(slice.content);
+            }
+            return Ok(TranslationSlice {
+                content: contents.join("\n"),
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+            });
+        }
+
+        // 自定义标记、语法变体与数字替换为占位符，避免模型改写 Mod 标记、本地化函数调用或数值
+        let source_text = &chunk.content;
+        let (protected_text, markers) = protect_markers(source_text, &self.custom_markers);
+        let (protected_text, constructs) = protect_grammar(&protected_text);
+        let (protected_text, numbers) = protect_numbers(&protected_text);
+        let header = render_context_header(chunk);
+        let user_prompt = format!("{}{}", header, protected_text);
+
+        // 准备消息：按文件保持会话时接在之前的对话之后，否则加载系统提示词，按上下文长度最小的模型删减术语表
+        let clients = self.translation_clients();
+        let (messages, conversation) = if self.keeps_conversations() {
+            let (messages, conversation) =
+                self.conversation_messages(chunk, source_lang, target_lang, user_prompt)?;
+            (messages, Some(conversation))
+        } else {
+            let smallest = clients
+                .iter()
+                .min_by_key(|client| client.context_window())
+                .copied()
+                .unwrap_or(&self.api_client);
+            let system_prompt = self.load_system_prompt(
+                TRANSLATE_PROMPT,
+                source_lang,
+                target_lang,
+                source_text,
+                smallest,
+                &user_prompt,
+            )?;
+            (
+                vec![system_message(system_prompt), user_message(user_prompt)],
+                None,
+            )
+        };
+
+        let id = chunk.id();
+        log::info!(
+            "Sending translation request [{}] with {} characters, estimated {} tokens...",
+            id,
+            source_text.chars().count(),
+            estimate_mixed_tokens(source_text)
+        );
+        let restore = |text: &str| {
+            restore_markers(
+                &restore_grammar(&restore_numbers(text, &numbers), &constructs),
+                &markers,
+            )
+        };
+        let responses = futures::future::join_all(clients.iter().map(|client| {
+            self.request(
+                client,
+                messages.clone(),
+                "translation",
+                chunk,
+                target_lang,
+                attempt,
+            )
+        }))
+        .await;
+        let mut candidates = Vec::new();
+        for (client, response) in clients.iter().zip(responses) {
+            match response {
+                Ok(response) => {
+                    let draft = strip_context_header(&response);
+                    let translated = restore(&draft);
+                    let problems = self.validator.validate(source_text, &translated);
+                    candidates.push(Candidate {
+                        model: client.model().to_string(),
+                        draft,
+                        translated,
+                        problems,
+                    });
+                }
+                // 投票时单个模型失败不影响其余模型
+                Err(e) if clients.len() > 1 => {
+                    log::warn!("Model {} failed on [{}]: {}", client.model(), id, e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if candidates.is_empty() {
+            return Err(TranslationError::ApiError(format!(
+                "All ensemble models failed on [{}]",
+                id
+            )));
+        }
+        let chosen = if candidates.len() > 1 {
+            self.choose_candidate(
+                candidates,
+                source_lang,
+                target_lang,
+                source_text,
+                &header,
+                &protected_text,
+                chunk,
+            )
+            .await?
+        } else {
+            candidates.remove(0)
+        };
+        if let Some(mut conversation) = conversation {
+            conversation
+                .messages
+                .push(assistant_message(chosen.draft.clone()));
+            self.conversations
+                .record(&chunk.target_filename, target_lang, conversation);
+        }
+        let draft = chosen.draft;
+        let mut translated_text = chosen.translated;
+        let mut checked = chosen.problems;
 
-        // 验证格式
-        let checked = self.validator.validate(&source_text, &translated_text);
+        // 润色轮：将原文与初稿一并交给模型，改善流畅度与术语一致性
+        if let Some(refiner) = &self.refiner {
+            let user = format!(
+                "{}## 原文\n{}\n\n## 初稿\n{}",
+                header, protected_text, draft
+            );
+            let system_prompt = self.load_system_prompt(
+                REFINE_PROMPT,
+                source_lang,
+                target_lang,
+                source_text,
+                refiner,
+                &user,
+            )?;
+            let messages = vec![system_message(system_prompt), user_message(user)];
+            log::info!("Sending refine request [{}]...", id);
+            match self
+                .request(refiner, messages, "refine", chunk, target_lang, 0)
+                .await
+            {
+                Ok(refined) => {
+                    let refined = restore(&strip_context_header(&refined));
+                    let refined_checked = self.validator.validate(source_text, &refined);
+                    // 润色结果的问题比初稿多时保留初稿
+                    if refined_checked.len() <= checked.len() {
+                        translated_text = refined;
+                        checked = refined_checked;
+                    } else {
+                        log::warn!(
+                            "Refined translation of [{}] has more issues than the draft, keeping the draft",
+                            id
+                        );
+                    }
+                }
+                Err(e) => log::warn!("Refine request [{}] failed, keeping the draft: {}", id, e),
+            }
+        }
 
-        for problem in checked {
-            log::warn!("Found issue in {}: {}", &chunk.target_filename, problem);
+        if !checked.is_empty() {
+            let report = crate::translate::issue_report();
+            let diff = StructureDiff::new(&self.validator, source_text, &translated_text);
+            report.record_diff(&chunk.target_filename, diff);
+            for problem in checked {
+                report.record(&chunk.target_filename, problem);
+            }
         }
 
         let slice = TranslationSlice {
-            content: translated_text.to_owned(),
+            content: translated_text,
             start_line: chunk.start_line,
             end_line: chunk.end_line,
         };
         Ok(slice)
     }
 
+    /// 修复验证未通过的条目
+    ///
+    /// `chunk` 为待修复条目的原文，`previous` 为这些条目的现有译文（可能缺少部分条目），
+    /// `problems` 为验证发现的问题。三者一并交给模型重新翻译；返回的译文不与源切片对齐，
+    /// 模型遗漏的条目不会以原文填充，由调用方按键取用。
+    pub async fn repair_chunk(
+        &self,
+        chunk: &FileChunk,
+        previous: &str,
+        problems: &[Problem],
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<TranslationSlice> {
+        let source_text = &chunk.content;
+        let (protected_text, markers) = protect_markers(source_text, &self.custom_markers);
+        let (protected_text, constructs) = protect_grammar(&protected_text);
+        let (protected_text, numbers) = protect_numbers(&protected_text);
+        let header = render_context_header(chunk);
+        let issues: Vec<String> = problems.iter().map(|p| format!("- {}", p)).collect();
+        let user = format!(
+            "{}## 原文\n{}\n\n## 现有译文\n{}\n\n## 问题\n{}",
+            header,
+            protected_text,
+            previous,
+            issues.join("\n")
+        );
+        let system_prompt = self.load_system_prompt(
+            REPAIR_PROMPT,
+            source_lang,
+            target_lang,
+            source_text,
+            &self.api_client,
+            &user,
+        )?;
+        let messages = vec![system_message(system_prompt), user_message(user)];
+
+        let id = chunk.id();
+        log::info!(
+            "Sending repair request [{}] for {} issues...",
+            id,
+            problems.len()
+        );
+        let response = self
+            .request(&self.api_client, messages, "repair", chunk, target_lang, 0)
+            .await?;
+        Ok(TranslationSlice {
+            content: restore_markers(
+                &restore_grammar(
+                    &restore_numbers(&strip_context_header(&response), &numbers),
+                    &constructs,
+                ),
+                &markers,
+            ),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+        })
+    }
+
+    /// 请求模型从切片中提取专有名词并给出译法，返回模型的原始回复（每行 `原文术语,译法`）
+    pub async fn extract_terms(
+        &self,
+        chunk: &FileChunk,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<String> {
+        let system_prompt = self.load_system_prompt(
+            EXTRACT_TERMS_PROMPT,
+            source_lang,
+            target_lang,
+            &chunk.content,
+            &self.api_client,
+            &chunk.content,
+        )?;
+        let messages = vec![
+            system_message(system_prompt),
+            user_message(chunk.content.clone()),
+        ];
+        log::info!("Sending term extraction request [{}]...", chunk.id());
+        self.request(&self.api_client, messages, "terms", chunk, target_lang, 0)
+            .await
+    }
+
     /// 批量翻译文本片段
     /// 每个片段独立翻译，适用于并发请求
     /// 返回按顺序排列的翻译结果
@@ -189,10 +1125,8 @@ impl Translator {
         let mut handles = Vec::new();
         for chunk in chunks {
             let chunk = chunk.to_owned();
-            let handle = async move {
-                self.translate_chunk(&chunk, &source_lang, &target_lang)
-                    .await
-            };
+            let handle =
+                async move { self.translate_chunk(&chunk, source_lang, target_lang).await };
             handles.push(handle);
         }
         let translated = futures::future::join_all(handles).await;
@@ -217,3 +1151,107 @@ impl Translator {
         Ok(results)
     }
 }
+
+/// 系统提示词可用的 token 数：模型的上下文长度减去用户消息，再减去预留给回复的 token
+///
+/// 未设置 `max_tokens` 时按用户消息的两倍预留（译文通常比英文原文占用更多 token）。
+fn system_prompt_budget(client: &ApiClient, user_prompt: &str) -> usize {
+    let user = estimate_mixed_tokens(user_prompt);
+    let reply = client
+        .max_tokens()
+        .map(|tokens| tokens as usize)
+        .unwrap_or(user * 2);
+    client.context_window().saturating_sub(user + reply)
+}
+
+/// 将术语表填入模板，保留 `rows` 中尽可能多的前几项，使提示词不超过 `budget` 个 token
+///
+/// 返回提示词与保留的术语数；没有术语时填入「无相关术语」。
+fn fit_glossary(
+    template: &str,
+    source_lang: &str,
+    target_lang: &str,
+    rows: &[String],
+    budget: usize,
+) -> (String, usize) {
+    let render = |count: usize| {
+        let glossary_csv = if count == 0 {
+            "（无相关术语）".to_string()
+        } else {
+            let mut csv = format!("{},{}", source_lang, target_lang);
+            for row in &rows[..count] {
+                csv.push('\n');
+                csv.push_str(row);
+            }
+            csv
+        };
+        template.replace("{{glossary_csv}}", &glossary_csv)
+    };
+    let fits = |count: usize| estimate_mixed_tokens(&render(count)) <= budget;
+
+    let kept = if fits(rows.len()) {
+        rows.len()
+    } else {
+        // 术语越多提示词越长，二分查找放得下的最多术语数
+        let (mut low, mut high) = (0, rows.len());
+        while low < high {
+            let middle = (low + high).div_ceil(2);
+            if fits(middle) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        low
+    };
+    let prompt = render(kept);
+    if kept > 0 {
+        log::debug!(
+            "\n======DEBUG Using glossary CSV======\n{}\n======DEBUG END======\n",
+            rows[..kept].join("\n")
+        );
+    }
+    (prompt, kept)
+}
+
+/// 解析评审模型的回复：取第一个在候选范围内的编号（从 1 开始），返回下标
+fn parse_verdict(verdict: &str, count: usize) -> Option<usize> {
+    verdict
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse::<usize>().ok())
+        .find(|n| (1..=count).contains(n))
+        .map(|n| n - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_verdict() {
+        assert_eq!(parse_verdict("2", 3), Some(1));
+        assert_eq!(parse_verdict("最佳译文：译文 3。", 3), Some(2));
+        assert_eq!(parse_verdict("4", 3), None);
+        assert_eq!(parse_verdict("无法判断", 2), None);
+    }
+
+    #[test]
+    fn test_fit_glossary() {
+        let template = "术语表：\n{{glossary_csv}}\n";
+        let rows: Vec<String> = (0..50).map(|i| format!("term{},术语{}", i, i)).collect();
+
+        let (prompt, kept) = fit_glossary(template, "english", "simp_chinese", &rows, 100_000);
+        assert_eq!(kept, 50);
+        assert!(prompt.starts_with("术语表：\nenglish,simp_chinese\nterm0,术语0\n"));
+
+        let full = estimate_mixed_tokens(&prompt);
+        let (prompt, kept) = fit_glossary(template, "english", "simp_chinese", &rows, full / 2);
+        assert!(kept > 0 && kept < 50);
+        assert!(estimate_mixed_tokens(&prompt) <= full / 2);
+        assert!(prompt.contains("term0,术语0") && !prompt.contains("term49"));
+
+        let (prompt, kept) = fit_glossary(template, "english", "simp_chinese", &rows, 0);
+        assert_eq!(kept, 0);
+        assert_eq!(prompt, "术语表：\n（无相关术语）\n");
+    }
+}
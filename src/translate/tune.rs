@@ -0,0 +1,185 @@
+//! 参数调优
+//!
+//! 从任务中抽取少量有代表性的条目，用不同的模型与温度组合分别翻译，
+//! 生成并排对比的表格，帮助用户在完整运行前选择合适的设置。
+
+use super::{Translator, load_glossaries_from_task};
+use crate::config::{ClientSettings, TranslationTask};
+use crate::error::{Result, TranslationError};
+use crate::preprocess::{LocEntry, parse_entries};
+use crate::translate_content;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// 一组待比较的模型与温度
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuneCombination {
+    pub model: String,
+    pub temperature: f32,
+}
+
+impl TuneCombination {
+    /// 对比表格中的列名，例如 `deepseek-chat@0.7`
+    pub fn label(&self) -> String {
+        format!("{}@{}", self.model, self.temperature)
+    }
+}
+
+/// 调优结果：抽样条目以及每组设置的译文
+#[derive(Debug, Default)]
+pub struct TuneReport {
+    pub entries: Vec<LocEntry>,
+    /// (设置, 键 -> 译文)
+    pub results: Vec<(TuneCombination, HashMap<String, String>)>,
+}
+
+/// 所有模型与温度的组合
+pub fn tune_grid(models: &[String], temperatures: &[f32]) -> Vec<TuneCombination> {
+    models
+        .iter()
+        .flat_map(|model| {
+            temperatures
+                .iter()
+                .map(move |&temperature| TuneCombination {
+                    model: model.clone(),
+                    temperature,
+                })
+        })
+        .collect()
+}
+
+/// 从条目中等间隔地抽取 `n` 条，使样本覆盖文件的各个部分
+pub fn sample_evenly<T: Clone>(items: &[T], n: usize) -> Vec<T> {
+    if n == 0 || items.is_empty() {
+        return Vec::new();
+    }
+    if items.len() <= n {
+        return items.to_vec();
+    }
+    (0..n).map(|i| items[i * items.len() / n].clone()).collect()
+}
+
+/// 将条目拼为本地化文件内容（带源语言头）
+pub fn entries_to_content(source_lang: &str, entries: &[LocEntry]) -> String {
+    let mut content = format!("l_{}:\n", source_lang);
+    for entry in entries {
+        content.push_str(&format!(" {}:0 \"{}\"\n", entry.key, entry.value));
+    }
+    content
+}
+
+/// 读取任务的所有源条目，键重复时只保留第一次出现的条目
+pub(crate) fn task_entries(task: &TranslationTask) -> Result<Vec<LocEntry>> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    let source = task.source()?;
+    for file in source.list_files(&task.source_lang)? {
+        for entry in parse_entries(&source.read_file(&file)?) {
+            if seen.insert(entry.key.clone()) {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// 用每组设置翻译抽样条目
+pub async fn run_tune(
+    task: &TranslationTask,
+    client_settings: &ClientSettings,
+    target_lang: &str,
+    combinations: &[TuneCombination],
+    sample: usize,
+) -> Result<TuneReport> {
+    let entries = sample_evenly(&task_entries(task)?, sample);
+    if entries.is_empty() {
+        return Err(TranslationError::ValidationError(format!(
+            "No entries to sample in {:?}",
+            task.source_dir()
+        )));
+    }
+    let content = entries_to_content(&task.source_lang, &entries);
+    log::info!(
+        "Sampled {} entries, trying {} combinations",
+        entries.len(),
+        combinations.len()
+    );
+
+    let glossary = load_glossaries_from_task(task)?;
+    let mut report = TuneReport {
+        entries,
+        results: Vec::new(),
+    };
+    for combination in combinations {
+        let mut settings = client_settings.clone();
+        settings.model = combination.model.clone();
+        settings.temperature = combination.temperature;
+        let translator = Translator::from_settings(settings, glossary.clone())?;
+        log::info!("Translating sample with {}", combination.label());
+        let translated = translate_content(
+            &translator,
+            &task.source_lang,
+            target_lang,
+            client_settings.max_chunk_tokens,
+            &format!("tune:{}", combination.label()),
+            &content,
+            &Default::default(),
+        )
+        .await?;
+        let values = parse_entries(&translated)
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+        report.results.push((combination.clone(), values));
+    }
+    Ok(report)
+}
+
+/// 将调优结果写为 CSV 对比表格：键、原文，以及每组设置的译文各占一列
+pub fn write_tune_report(report: &TuneReport, output: &Path) -> Result<()> {
+    let csv_error = |e: csv::Error| {
+        TranslationError::Io(std::io::Error::other(format!(
+            "Failed to write {:?}: {}",
+            output, e
+        )))
+    };
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::File::create(output)?;
+    std::io::Write::write_all(&mut file, "\u{FEFF}".as_bytes())?;
+    let mut writer = csv::Writer::from_writer(file);
+
+    let mut header = vec!["key".to_string(), "source".to_string()];
+    header.extend(report.results.iter().map(|(c, _)| c.label()));
+    writer.write_record(&header).map_err(csv_error)?;
+    for entry in &report.entries {
+        let mut row = vec![entry.key.clone(), entry.value.clone()];
+        row.extend(
+            report
+                .results
+                .iter()
+                .map(|(_, values)| values.get(&entry.key).cloned().unwrap_or_default()),
+        );
+        writer.write_record(&row).map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tune_grid_and_sample() {
+        let grid = tune_grid(&["a".to_string(), "b".to_string()], &[0.3, 1.0]);
+        let labels: Vec<String> = grid.iter().map(|c| c.label()).collect();
+        assert_eq!(labels, ["a@0.3", "a@1", "b@0.3", "b@1"]);
+
+        let items: Vec<usize> = (0..10).collect();
+        assert_eq!(sample_evenly(&items, 3), [0, 3, 6]);
+        assert_eq!(sample_evenly(&items, 20).len(), 10);
+        assert!(sample_evenly(&items, 0).is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+//! token 用量账本
+//!
+//! 按任务、目标语言与文件累计每次请求的 token 用量，运行结束时输出用量明细。
+//! 设置保存路径后每次记录都会写入文件，运行中断或失败后再次运行时从文件中的累计值继续统计，
+//! 全部完成后由调用方删除该文件。
+
+use crate::error::{Result, TranslationError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// 默认的用量账本文件路径（相对于任务配置文件所在目录）
+pub const DEFAULT_USAGE_PATH: &str = ".pmt/usage.json";
+
+/// 累计的 token 用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// 请求次数
+    pub requests: u64,
+    /// 提示词 token 数
+    pub prompt_tokens: u64,
+    /// 输出 token 数
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// 总 token 数
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn add(&mut self, other: &TokenUsage) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// 一个文件的 token 用量
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// 任务（本地化目录）
+    pub task: String,
+    /// 目标语言
+    pub target_lang: String,
+    /// 目标文件名
+    pub file: String,
+    /// 用量
+    pub usage: TokenUsage,
+}
+
+/// (任务, 目标语言, 文件) -> 用量
+type UsageEntries = BTreeMap<(String, String, String), TokenUsage>;
+
+/// token 用量账本
+///
+/// 内部加锁，可在并发翻译时通过共享引用记录。
+#[derive(Debug, Default)]
+pub struct UsageLedger {
+    entries: Mutex<UsageEntries>,
+    path: OnceLock<PathBuf>,
+}
+
+static LEDGER: UsageLedger = UsageLedger::new();
+
+/// 获取全局用量账本，翻译器的每次请求都记入其中
+pub fn usage_ledger() -> &'static UsageLedger {
+    &LEDGER
+}
+
+impl UsageLedger {
+    /// 创建空的用量账本
+    pub const fn new() -> Self {
+        Self {
+            entries: Mutex::new(BTreeMap::new()),
+            path: OnceLock::new(),
+        }
+    }
+
+    /// 将账本保存到 `path`：文件已存在时（上次运行未完成）先载入其中的累计值，之后每次记录都写回文件
+    pub fn persist_to(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            let content = std::fs::read_to_string(path)?;
+            let records: Vec<UsageRecord> = serde_json::from_str(&content).map_err(|e| {
+                TranslationError::ValidationError(format!(
+                    "Failed to parse token usage ledger {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            let mut entries = self.lock();
+            for record in records {
+                entries
+                    .entry((record.task, record.target_lang, record.file))
+                    .or_default()
+                    .add(&record.usage);
+            }
+        }
+        if self.path.set(path.to_path_buf()).is_err() {
+            log::warn!(
+                "Token usage is already saved elsewhere, ignoring {:?}",
+                path
+            );
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, UsageEntries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记录一次请求的用量，设置了保存路径时写回文件（失败只给出警告）
+    pub fn record(
+        &self,
+        task: &str,
+        target_lang: &str,
+        file: &str,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+    ) {
+        let mut entries = self.lock();
+        entries
+            .entry((task.to_string(), target_lang.to_string(), file.to_string()))
+            .or_default()
+            .add(&TokenUsage {
+                requests: 1,
+                prompt_tokens: prompt_tokens as u64,
+                completion_tokens: completion_tokens as u64,
+            });
+        if let Some(path) = self.path.get()
+            && let Err(e) = write_records(path, &to_records(&entries))
+        {
+            log::warn!("Failed to save token usage to {:?}: {}", path, e);
+        }
+    }
+
+    /// 按文件列出的用量
+    pub fn records(&self) -> Vec<UsageRecord> {
+        to_records(&self.lock())
+    }
+
+    /// 按目标语言汇总的用量
+    pub fn by_language(&self) -> BTreeMap<String, TokenUsage> {
+        self.group_by(|record| record.target_lang.clone())
+    }
+
+    /// 按任务汇总的用量
+    pub fn by_task(&self) -> BTreeMap<String, TokenUsage> {
+        self.group_by(|record| record.task.clone())
+    }
+
+    fn group_by(&self, key: impl Fn(&UsageRecord) -> String) -> BTreeMap<String, TokenUsage> {
+        let mut groups: BTreeMap<String, TokenUsage> = BTreeMap::new();
+        for record in self.records() {
+            groups.entry(key(&record)).or_default().add(&record.usage);
+        }
+        groups
+    }
+
+    /// 全部用量
+    pub fn total(&self) -> TokenUsage {
+        let mut total = TokenUsage::default();
+        for usage in self.lock().values() {
+            total.add(usage);
+        }
+        total
+    }
+
+    /// 运行全部完成后删除保存的账本文件，下次运行重新统计
+    pub fn finish(&self) -> Result<()> {
+        if let Some(path) = self.path.get()
+            && path.exists()
+        {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// 以文本表格输出用量明细：按文件、按目标语言、按任务，最后一行为合计
+    pub fn render_table(&self) -> String {
+        let mut out = String::new();
+        let row = |out: &mut String, name: &str, usage: &TokenUsage| {
+            let _ = writeln!(
+                out,
+                "{:<48} {:>8} {:>12} {:>12} {:>12}",
+                name,
+                usage.requests,
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total_tokens()
+            );
+        };
+        let section = |out: &mut String, title: &str| {
+            let _ = writeln!(
+                out,
+                "{:<48} {:>8} {:>12} {:>12} {:>12}",
+                title, "requests", "prompt", "completion", "total"
+            );
+        };
+
+        section(&mut out, "File");
+        for record in self.records() {
+            row(
+                &mut out,
+                &format!("{} [{}]", record.file, record.target_lang),
+                &record.usage,
+            );
+        }
+        section(&mut out, "Language");
+        for (lang, usage) in self.by_language() {
+            row(&mut out, &lang, &usage);
+        }
+        section(&mut out, "Task");
+        for (task, usage) in self.by_task() {
+            row(&mut out, if task.is_empty() { "-" } else { &task }, &usage);
+        }
+        row(&mut out, "Total", &self.total());
+        out
+    }
+}
+
+fn to_records(entries: &UsageEntries) -> Vec<UsageRecord> {
+    entries
+        .iter()
+        .map(|((task, target_lang, file), usage)| UsageRecord {
+            task: task.clone(),
+            target_lang: target_lang.clone(),
+            file: file.clone(),
+            usage: *usage,
+        })
+        .collect()
+}
+
+/// 先写入临时文件再替换，中断时不会留下损坏的账本
+fn write_records(path: &Path, records: &[UsageRecord]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(records).map_err(std::io::Error::other)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".pmt/usage.json");
+
+        let first = UsageLedger::new();
+        first.persist_to(&path).unwrap();
+        first.record("loc", "simp_chinese", "a_l_simp_chinese.yml", 100, 50);
+        first.record("loc", "simp_chinese", "a_l_simp_chinese.yml", 10, 5);
+        first.record("loc", "russian", "a_l_russian.yml", 20, 10);
+
+        // 中断后再次运行时从文件中的累计值继续
+        let resumed = UsageLedger::new();
+        resumed.persist_to(&path).unwrap();
+        resumed.record("loc", "russian", "a_l_russian.yml", 1, 1);
+        assert_eq!(
+            resumed.by_language()["simp_chinese"],
+            TokenUsage {
+                requests: 2,
+                prompt_tokens: 110,
+                completion_tokens: 55
+            }
+        );
+        assert_eq!(resumed.by_language()["russian"].total_tokens(), 32);
+        assert_eq!(resumed.by_task()["loc"].requests, 4);
+        assert_eq!(resumed.total().total_tokens(), 197);
+        assert!(resumed.render_table().contains("a_l_russian.yml [russian]"));
+
+        resumed.finish().unwrap();
+        assert!(!path.exists());
+    }
+}
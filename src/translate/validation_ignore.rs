@@ -0,0 +1,182 @@
+//! 验证忽略列表
+//!
+//! 部分验证警告是已经确认可以接受的例外（例如刻意缩短的译文、Mod 作者自己去掉的图标），
+//! 在 `validation_ignore.toml` 中按键名或文件名忽略指定的检查规则，避免这些警告淹没真正的问题：
+//!
+//! ```toml
+//! [[ignore]]
+//! rules = ["length_outlier", "marker_moved"]  # 省略时忽略所有检查
+//! keys = "tooltip_.*"                          # 键名的正则表达式（完整匹配），省略时匹配所有键
+//! files = "pf_events_.*"                       # 源文件名或目标文件名的正则表达式（完整匹配），省略时匹配所有文件
+//! ```
+
+use std::path::Path;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use super::{PROBLEM_RULES, Problem};
+use crate::config::TranslationTask;
+use crate::error::ConfigError;
+
+/// 配置文件中的一条忽略规则
+#[derive(Debug, Deserialize)]
+struct IgnoreEntry {
+    #[serde(default)]
+    rules: Vec<String>,
+    keys: Option<String>,
+    files: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IgnoreFile {
+    #[serde(default)]
+    ignore: Vec<IgnoreEntry>,
+}
+
+/// 编译后的忽略规则
+#[derive(Debug)]
+struct IgnoreRule {
+    /// 忽略的检查规则，为空时忽略所有检查
+    rules: Vec<String>,
+    keys: Option<Regex>,
+    files: Option<Regex>,
+}
+
+/// 验证忽略列表
+#[derive(Debug, Default)]
+pub struct ValidationIgnore {
+    rules: Vec<IgnoreRule>,
+}
+
+/// 编译匹配完整名称的正则表达式
+fn full_match(pattern: &str, field: &str) -> Result<Regex, ConfigError> {
+    Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| {
+        ConfigError::InvalidValue(crate::tr!(
+            "config.ignore_pattern_invalid",
+            field,
+            pattern,
+            e
+        ))
+    })
+}
+
+impl ValidationIgnore {
+    /// 解析忽略列表
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        let file: IgnoreFile = toml::from_str(content)?;
+        let mut rules = Vec::new();
+        for entry in file.ignore {
+            if let Some(rule) = entry
+                .rules
+                .iter()
+                .find(|r| !PROBLEM_RULES.contains(&r.as_str()))
+            {
+                return Err(ConfigError::InvalidValue(crate::tr!(
+                    "config.ignore_rule_unknown",
+                    rule,
+                    PROBLEM_RULES.join(", ")
+                )));
+            }
+            rules.push(IgnoreRule {
+                rules: entry.rules,
+                keys: entry
+                    .keys
+                    .as_deref()
+                    .map(|p| full_match(p, "keys"))
+                    .transpose()?,
+                files: entry
+                    .files
+                    .as_deref()
+                    .map(|p| full_match(p, "files"))
+                    .transpose()?,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// 从文件加载忽略列表，文件不存在时返回空列表
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::InvalidPath(format!("{:?}: {}", path, e)))?;
+        let ignore = Self::parse(&content)?;
+        log::info!(
+            "Loaded {} validation ignore rules from {:?}",
+            ignore.rules.len(),
+            path
+        );
+        Ok(ignore)
+    }
+
+    /// 加载任务的忽略列表
+    pub fn for_task(task: &TranslationTask) -> Result<Self, ConfigError> {
+        Self::from_file(&task.validation_ignore)
+    }
+
+    /// 问题是否被忽略，`files` 为该条目所在的源文件与目标文件
+    pub fn is_ignored(&self, files: &[&Path], problem: &Problem) -> bool {
+        let file_names: Vec<String> = files
+            .iter()
+            .filter_map(|f| f.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .collect();
+        self.rules.iter().any(|rule| {
+            (rule.rules.is_empty() || rule.rules.iter().any(|r| r == problem.rule()))
+                && rule.keys.as_ref().is_none_or(|k| k.is_match(problem.key()))
+                && rule
+                    .files
+                    .as_ref()
+                    .is_none_or(|f| file_names.iter().any(|n| f.is_match(n)))
+        })
+    }
+
+    /// 移除被忽略的问题，返回移除的数量
+    pub fn retain(&self, files: &[&Path], problems: &mut Vec<Problem>) -> usize {
+        let before = problems.len();
+        problems.retain(|p| !self.is_ignored(files, p));
+        before - problems.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_ignore() {
+        let ignore = ValidationIgnore::parse(
+            r#"
+[[ignore]]
+rules = ["length_outlier"]
+keys = "tooltip_.*"
+
+[[ignore]]
+files = "pf_events_l_english\\.yml"
+"#,
+        )
+        .unwrap();
+        let file = Path::new("loc/english/pf_misc_l_english.yml");
+        let events = Path::new("loc/english/pf_events_l_english.yml");
+        let outlier = |key: &str| Problem::LengthOutlier {
+            key: key.to_string(),
+            ratio: 6.0,
+        };
+        let missing = Problem::MissingKey {
+            key: "tooltip_a".to_string(),
+        };
+
+        assert!(ignore.is_ignored(&[file], &outlier("tooltip_a")));
+        assert!(!ignore.is_ignored(&[file], &outlier("desc_a")));
+        assert!(!ignore.is_ignored(&[file], &missing));
+        assert!(ignore.is_ignored(&[events], &missing));
+
+        let mut problems = vec![outlier("tooltip_a"), outlier("desc_a"), missing];
+        assert_eq!(ignore.retain(&[file], &mut problems), 1);
+        assert_eq!(problems.len(), 2);
+
+        assert!(ValidationIgnore::parse("[[ignore]]\nrules = [\"typo\"]").is_err());
+    }
+}
@@ -9,30 +9,86 @@ use std::{
 
 use regex::Regex;
 
+use crate::config::{LengthBudget, MarkerCheck, TranslationTask};
+use crate::preprocess::{compile_markers, find_grammar_constructs, find_numbers};
+use crate::translate::KnownReferences;
+use crate::utils::cached_regex;
+use std::sync::{Arc, OnceLock};
+
+/// 参与长度检查的原文最小字符数
+const MIN_LENGTH_CHECK_CHARS: usize = 20;
+/// 译文与原文字符数之比的下限
+const MIN_LENGTH_RATIO: f32 = 0.15;
+/// 译文与原文字符数之比的上限
+const MAX_LENGTH_RATIO: f32 = 5.0;
+
 /// 特殊格式验证器
 pub struct FormatValidator {
     /// £...£ 格式（图标）
-    icon_pattern: Regex,
+    icon_pattern: &'static Regex,
     /// $...$ 格式（变量）
-    variable_pattern: Regex,
+    variable_pattern: &'static Regex,
     /// §x 格式（颜色代码），并非成对出现
-    color_pattern: Regex,
-    /// [...] 格式（指令）
-    command_pattern: Regex,
+    color_pattern: &'static Regex,
+    /// [...] 格式（指令），包含函数调用的语法变体单独检查
+    command_pattern: &'static Regex,
+    /// 界面文本的长度限制：匹配完整键名的正则表达式与最大显示宽度
+    length_budgets: Vec<(Regex, usize)>,
+    /// 任务定义的自定义标记
+    custom_patterns: Vec<Regex>,
+    /// 比较标记时是否要求顺序一致
+    marker_check: MarkerCheck,
+    /// 位于文本开头的图标（允许前面有颜色代码）
+    leading_icon_pattern: &'static Regex,
+    /// 位于文本结尾的图标（允许后面有颜色代码与句末标点）
+    trailing_icon_pattern: &'static Regex,
+    /// 紧跟数字的图标，例如 `£energy£ 5`
+    icon_number_pattern: &'static Regex,
+    /// 紧跟图标的数字，例如 `5 £energy£`
+    number_icon_pattern: &'static Regex,
+    /// 原版与 Mod 中已知的键与图标，设置后检查译文中的概念与图标引用
+    references: Option<Arc<KnownReferences>>,
 }
 
+// 内置标记的正则表达式，所有验证器共用
+static ICON_PATTERN: OnceLock<Regex> = OnceLock::new();
+static VARIABLE_PATTERN: OnceLock<Regex> = OnceLock::new();
+static COLOR_PATTERN: OnceLock<Regex> = OnceLock::new();
+static COMMAND_PATTERN: OnceLock<Regex> = OnceLock::new();
+static LEADING_ICON_PATTERN: OnceLock<Regex> = OnceLock::new();
+static TRAILING_ICON_PATTERN: OnceLock<Regex> = OnceLock::new();
+static ICON_NUMBER_PATTERN: OnceLock<Regex> = OnceLock::new();
+static NUMBER_ICON_PATTERN: OnceLock<Regex> = OnceLock::new();
+
 impl Default for FormatValidator {
     fn default() -> Self {
         Self {
-            icon_pattern: Regex::new(r#"£[^£]+£"#).unwrap(),
-            variable_pattern: Regex::new(r#"\$[^$]+\$"#).unwrap(),
-            color_pattern: Regex::new(r#"§[^§]"#).unwrap(),
-            command_pattern: Regex::new(r#"\[[^\]]+\]"#).unwrap(),
+            icon_pattern: cached_regex(&ICON_PATTERN, || r#"£[^£]+£"#),
+            variable_pattern: cached_regex(&VARIABLE_PATTERN, || r#"\$[^$]+\$"#),
+            color_pattern: cached_regex(&COLOR_PATTERN, || r#"§[^§]"#),
+            command_pattern: cached_regex(&COMMAND_PATTERN, || r#"\[[^\]()]+\]"#),
+            length_budgets: Vec::new(),
+            custom_patterns: Vec::new(),
+            marker_check: MarkerCheck::default(),
+            leading_icon_pattern: cached_regex(&LEADING_ICON_PATTERN, || r#"^(?:\s|§.)*(£[^£]+£)"#),
+            trailing_icon_pattern: cached_regex(
+                &TRAILING_ICON_PATTERN,
+                || r#"(£[^£]+£)(?:\s|§.|[.。!！?？])*$"#,
+            ),
+            icon_number_pattern: cached_regex(
+                &ICON_NUMBER_PATTERN,
+                || r#"(£[^£]+£)\s*([+\-]?\d+(?:[.,]\d+)*%?)"#,
+            ),
+            number_icon_pattern: cached_regex(
+                &NUMBER_ICON_PATTERN,
+                || r#"([+\-]?\d+(?:[.,]\d+)*%?)\s*(£[^£]+£)"#,
+            ),
+            references: None,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Problem {
     /// 键缺失
     MissingKey { key: String },
@@ -46,8 +102,98 @@ pub enum Problem {
         original: String,
         translated: String,
     },
+    /// 译文中多出了原文没有的标记
+    ExtraPattern { key: String, translated: String },
+    /// 译文长度与原文相差过大（可能漏译或混入额外内容）
+    LengthOutlier { key: String, ratio: f32 },
+    /// 界面文本的译文超出长度限制，游戏中可能被截断
+    OverLengthBudget {
+        key: String,
+        width: usize,
+        max_width: usize,
+    },
+    /// 标记的位置与原文不一致（从句首移到句尾，或与相邻的数字分开），影响阅读
+    MarkerMoved {
+        key: String,
+        marker: String,
+        reason: String,
+    },
+    /// 语法变体（本地化函数调用）与原文不一致
+    VariantMismatch {
+        key: String,
+        missing: Vec<String>,
+        extra: Vec<String>,
+    },
+    /// 数字与原文不一致
+    NumberMismatch {
+        key: String,
+        missing: Vec<String>,
+        extra: Vec<String>,
+    },
+    /// 私用区字符或表情符号与原文不一致
+    GlyphMismatch {
+        key: String,
+        missing: Vec<String>,
+        extra: Vec<String>,
+    },
+    /// 译文中新出现的概念或图标引用指向未定义的键或图标
+    BrokenReference { key: String, reference: String },
 }
 
+impl Problem {
+    /// 问题所在的键名
+    pub fn key(&self) -> &str {
+        match self {
+            Problem::MissingKey { key }
+            | Problem::ExtraKey { key }
+            | Problem::PatternNotFound { key, .. }
+            | Problem::PatternMismatch { key, .. }
+            | Problem::ExtraPattern { key, .. }
+            | Problem::LengthOutlier { key, .. }
+            | Problem::OverLengthBudget { key, .. }
+            | Problem::MarkerMoved { key, .. }
+            | Problem::VariantMismatch { key, .. }
+            | Problem::NumberMismatch { key, .. }
+            | Problem::GlyphMismatch { key, .. }
+            | Problem::BrokenReference { key, .. } => key,
+        }
+    }
+
+    /// 问题对应的检查规则名称，用于 `validation_ignore.toml`
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Problem::MissingKey { .. } => "missing_key",
+            Problem::ExtraKey { .. } => "extra_key",
+            Problem::PatternNotFound { .. } => "pattern_not_found",
+            Problem::PatternMismatch { .. } => "pattern_mismatch",
+            Problem::ExtraPattern { .. } => "extra_pattern",
+            Problem::LengthOutlier { .. } => "length_outlier",
+            Problem::OverLengthBudget { .. } => "over_length_budget",
+            Problem::MarkerMoved { .. } => "marker_moved",
+            Problem::VariantMismatch { .. } => "variant_mismatch",
+            Problem::NumberMismatch { .. } => "number_mismatch",
+            Problem::GlyphMismatch { .. } => "glyph_mismatch",
+            Problem::BrokenReference { .. } => "broken_reference",
+        }
+    }
+}
+
+/// 所有检查规则的名称
+pub const PROBLEM_RULES: &[&str] = &[
+    "missing_key",
+    "extra_key",
+    "pattern_not_found",
+    "pattern_mismatch",
+    "extra_pattern",
+    "length_outlier",
+    "over_length_budget",
+    "marker_moved",
+    "variant_mismatch",
+    "number_mismatch",
+    "glyph_mismatch",
+    "broken_reference",
+];
+
 impl Display for Problem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -65,6 +211,66 @@ impl Display for Problem {
                 "Pattern mismatch for key '{}': '{}' => '{}'",
                 key, original, translated
             ),
+            Problem::ExtraPattern { key, translated } => {
+                write!(f, "Extra pattern '{}' for key '{}'", translated, key)
+            }
+            Problem::LengthOutlier { key, ratio } => write!(
+                f,
+                "Translation length of key '{}' is {:.2}x the original",
+                key, ratio
+            ),
+            Problem::OverLengthBudget {
+                key,
+                width,
+                max_width,
+            } => write!(
+                f,
+                "Translation of key '{}' is {} columns wide, over the limit of {}",
+                key, width, max_width
+            ),
+            Problem::MarkerMoved {
+                key,
+                marker,
+                reason,
+            } => write!(f, "Marker '{}' of key '{}' {}", marker, key, reason),
+            Problem::VariantMismatch {
+                key,
+                missing,
+                extra,
+            } => write!(
+                f,
+                "Grammar variants of key '{}' differ from the original: missing [{}], extra [{}]",
+                key,
+                missing.join(", "),
+                extra.join(", ")
+            ),
+            Problem::NumberMismatch {
+                key,
+                missing,
+                extra,
+            } => write!(
+                f,
+                "Numbers of key '{}' differ from the original: missing [{}], extra [{}]",
+                key,
+                missing.join(", "),
+                extra.join(", ")
+            ),
+            Problem::GlyphMismatch {
+                key,
+                missing,
+                extra,
+            } => write!(
+                f,
+                "Special glyphs of key '{}' differ from the original: missing [{}], extra [{}]",
+                key,
+                missing.join(", "),
+                extra.join(", ")
+            ),
+            Problem::BrokenReference { key, reference } => write!(
+                f,
+                "Reference '{}' of key '{}' points to an undefined concept or icon",
+                reference, key
+            ),
         }
     }
 }
@@ -75,6 +281,48 @@ impl FormatValidator {
         Self::default()
     }
 
+    /// 按任务配置创建验证器（长度限制、自定义标记与标记的比较方式）
+    pub fn for_task(task: &TranslationTask) -> Self {
+        Self::new()
+            .with_length_budgets(&task.length_budgets)
+            .with_custom_markers(&task.custom_markers)
+            .with_marker_check(task.marker_check)
+            .with_known_references(KnownReferences::for_task(task))
+    }
+
+    /// 检查译文中新出现的概念与图标引用是否指向已知的键与图标
+    pub fn with_known_references(mut self, references: Option<Arc<KnownReferences>>) -> Self {
+        self.references = references;
+        self
+    }
+
+    /// 设置标记的比较方式
+    pub fn with_marker_check(mut self, marker_check: MarkerCheck) -> Self {
+        self.marker_check = marker_check;
+        self
+    }
+
+    /// 同时检查任务定义的自定义标记，无效的正则表达式给出警告并忽略
+    pub fn with_custom_markers(mut self, markers: &[String]) -> Self {
+        self.custom_patterns = compile_markers(markers);
+        self
+    }
+
+    /// 检查界面文本的长度限制，键名匹配多条限制时使用第一条
+    pub fn with_length_budgets(mut self, budgets: &[LengthBudget]) -> Self {
+        self.length_budgets = budgets
+            .iter()
+            .filter_map(|budget| match budget.key_pattern() {
+                Ok(pattern) => Some((pattern, budget.max_width)),
+                Err(e) => {
+                    log::warn!("Ignoring length budget {:?}: {}", budget.keys, e);
+                    None
+                }
+            })
+            .collect();
+        self
+    }
+
     /// 验证翻译前后的格式是否一致
     /// 传入的文本为一个切片的完整内容
     pub fn validate(&self, original: &str, translated: &str) -> Vec<Problem> {
@@ -113,24 +361,254 @@ impl FormatValidator {
             .collect();
 
         self.validate_keys(&original_items, &translated_items, &mut problems);
-        let translated_items_map: HashMap<&str, &str> =
-            translated_items.into_iter().map(|(k, v)| (k, v)).collect();
+        let translated_items_map: HashMap<&str, &str> = translated_items.into_iter().collect();
         for (key, original_value) in &original_items {
             if let Some(translated_value) = translated_items_map.get(key) {
-                self.validate_patterns(key, original_value, translated_value, &mut problems);
+                problems.extend(self.validate_entry(key, original_value, translated_value));
             }
         }
         problems
     }
 
+    /// 验证单个条目的译文（标记及其位置、语法变体、数字、特殊字符与长度），适用于已经按键配对的条目
+    pub fn validate_entry(&self, key: &str, original: &str, translated: &str) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        self.validate_patterns(key, original, translated, &mut problems);
+        self.validate_positions(key, original, translated, &mut problems);
+        self.validate_variants(key, original, translated, &mut problems);
+        self.validate_numbers(key, original, translated, &mut problems);
+        self.validate_glyphs(key, original, translated, &mut problems);
+        self.validate_length(key, original, translated, &mut problems);
+        self.validate_budget(key, translated, &mut problems);
+        self.validate_references(key, original, translated, &mut problems);
+        problems
+    }
+
+    /// 检查译文中新出现的概念与图标引用，未设置已知引用时不检查
+    fn validate_references(
+        &self,
+        key: &str,
+        original: &str,
+        translated: &str,
+        problems: &mut Vec<Problem>,
+    ) {
+        let Some(references) = &self.references else {
+            return;
+        };
+        let broken = references.broken_references(original, translated);
+        problems.extend(
+            broken
+                .into_iter()
+                .map(|reference| Problem::BrokenReference {
+                    key: key.to_string(),
+                    reference,
+                }),
+        );
+    }
+
+    /// 检查图标的位置是否与原文一致
+    ///
+    /// 只检查两类常见的错误：原文句首的图标被移到句尾，以及图标与紧邻的数字分开或调换了顺序
+    /// （例如 `£energy£ 5` 译为 `5 £energy£`）。图标或数字缺失时由其他检查报告，这里不再重复。
+    fn validate_positions(
+        &self,
+        key: &str,
+        original: &str,
+        translated: &str,
+        problems: &mut Vec<Problem>,
+    ) {
+        let original = quoted_text(original);
+        let translated = quoted_text(translated);
+        let mut moved = Vec::new();
+
+        let edge =
+            |pattern: &Regex, text: &str| pattern.captures(text).map(|caps| caps[1].to_string());
+        if let Some(icon) = edge(self.leading_icon_pattern, original)
+            && edge(self.trailing_icon_pattern, original).as_ref() != Some(&icon)
+            && edge(self.leading_icon_pattern, translated).as_ref() != Some(&icon)
+            && edge(self.trailing_icon_pattern, translated).as_ref() == Some(&icon)
+        {
+            moved.push((
+                icon,
+                "moved from the start to the end of the text".to_string(),
+            ));
+        }
+
+        let pairs = |pattern: &Regex, text: &str, icon_first: bool| -> Vec<(String, String)> {
+            pattern
+                .captures_iter(text)
+                .map(|caps| {
+                    let (icon, number) = if icon_first { (1, 2) } else { (2, 1) };
+                    (caps[icon].to_string(), caps[number].to_string())
+                })
+                .collect()
+        };
+        for (pattern, icon_first, side) in [
+            (self.icon_number_pattern, true, "before"),
+            (self.number_icon_pattern, false, "after"),
+        ] {
+            let translated_pairs = pairs(pattern, translated, icon_first);
+            for (icon, number) in pairs(pattern, original, icon_first) {
+                if translated.contains(&icon)
+                    && translated.contains(&number)
+                    && !translated_pairs.contains(&(icon.clone(), number.clone()))
+                {
+                    let reason = format!("is no longer directly {} the number '{}'", side, number);
+                    moved.push((icon, reason));
+                }
+            }
+        }
+
+        problems.extend(
+            moved
+                .into_iter()
+                .map(|(marker, reason)| Problem::MarkerMoved {
+                    key: key.to_string(),
+                    marker,
+                    reason,
+                }),
+        );
+    }
+
+    /// 检查语法变体是否与原文一致（不计顺序，译文中的语序可以调整）
+    fn validate_variants(
+        &self,
+        key: &str,
+        original: &str,
+        translated: &str,
+        problems: &mut Vec<Problem>,
+    ) {
+        let (missing, extra) = multiset_difference(
+            find_grammar_constructs(original),
+            find_grammar_constructs(translated),
+        );
+        if missing.is_empty() && extra.is_empty() {
+            return;
+        }
+        problems.push(Problem::VariantMismatch {
+            key: key.to_string(),
+            missing,
+            extra,
+        });
+    }
+
+    /// 检查数字是否与原文一致（不计顺序）
+    fn validate_numbers(
+        &self,
+        key: &str,
+        original: &str,
+        translated: &str,
+        problems: &mut Vec<Problem>,
+    ) {
+        let (missing, extra) =
+            multiset_difference(find_numbers(original), find_numbers(translated));
+        if missing.is_empty() && extra.is_empty() {
+            return;
+        }
+        problems.push(Problem::NumberMismatch {
+            key: key.to_string(),
+            missing,
+            extra,
+        });
+    }
+
+    /// 检查私用区字符与表情符号是否原样保留
+    ///
+    /// 部分界面图标以私用区字符的形式直接嵌入文本，模型容易将其丢弃或替换。
+    fn validate_glyphs(
+        &self,
+        key: &str,
+        original: &str,
+        translated: &str,
+        problems: &mut Vec<Problem>,
+    ) {
+        let glyphs = |text: &str| -> Vec<String> {
+            text.chars()
+                .filter(|c| is_special_glyph(*c))
+                .map(|c| format!("U+{:04X}", c as u32))
+                .collect()
+        };
+        let (missing, extra) = multiset_difference(glyphs(original), glyphs(translated));
+        if missing.is_empty() && extra.is_empty() {
+            return;
+        }
+        problems.push(Problem::GlyphMismatch {
+            key: key.to_string(),
+            missing,
+            extra,
+        });
+    }
+
+    /// 检查译文长度是否异常
+    ///
+    /// 不同语言的文本长度差异很大（中文通常只有英文字符数的三分之一），
+    /// 因此只对足够长的原文做宽松的比例检查。
+    fn validate_length(
+        &self,
+        key: &str,
+        original: &str,
+        translated: &str,
+        problems: &mut Vec<Problem>,
+    ) {
+        let original_len = original.chars().count();
+        if original_len < MIN_LENGTH_CHECK_CHARS {
+            return;
+        }
+        let ratio = translated.chars().count() as f32 / original_len as f32;
+        if !(MIN_LENGTH_RATIO..=MAX_LENGTH_RATIO).contains(&ratio) {
+            problems.push(Problem::LengthOutlier {
+                key: key.to_string(),
+                ratio,
+            });
+        }
+    }
+
+    /// 检查界面文本的译文是否超出长度限制
+    fn validate_budget(&self, key: &str, translated: &str, problems: &mut Vec<Problem>) {
+        let Some((_, max_width)) = self
+            .length_budgets
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(key))
+        else {
+            return;
+        };
+        let width = self.display_width(quoted_text(translated));
+        if width <= *max_width {
+            return;
+        }
+        problems.push(Problem::OverLengthBudget {
+            key: key.to_string(),
+            width,
+            max_width: *max_width,
+        });
+    }
+
+    /// 文本在游戏中的大致显示宽度
+    ///
+    /// 颜色代码不占宽度，图标计为 2；变量、指令与自定义标记在游戏中会被替换，无法确定宽度，不计入。
+    fn display_width(&self, text: &str) -> usize {
+        let icons = self.icon_pattern.find_iter(text).count();
+        let text = self.icon_pattern.replace_all(text, "");
+        let text = self.variable_pattern.replace_all(&text, "");
+        let text = self.command_pattern.replace_all(&text, "");
+        let mut text = self.color_pattern.replace_all(&text, "").replace('§', "");
+        for pattern in &self.custom_patterns {
+            text = pattern.replace_all(&text, "").into_owned();
+        }
+        icons * 2
+            + text
+                .chars()
+                .map(|c| if is_wide(c) { 2 } else { 1 })
+                .sum::<usize>()
+    }
+
     /// 验证翻译前后条目的数量是否一致
     fn validate_keys(
         &self,
         original: &Vec<(&str, &str)>,
         translated: &Vec<(&str, &str)>,
         problems: &mut Vec<Problem>,
-    ) -> usize {
-        let mut problems_added = 0;
+    ) {
         let original_keys: HashSet<&str> = original.iter().map(|(k, _)| *k).collect();
         let translated_keys: HashSet<&str> = translated.iter().map(|(k, _)| *k).collect();
         let missing = original_keys.difference(&translated_keys);
@@ -139,7 +617,6 @@ impl FormatValidator {
                 problems.push(Problem::MissingKey {
                     key: key.to_string(),
                 });
-                problems_added += 1;
             }
         }
         let extra = translated_keys.difference(&original_keys);
@@ -148,47 +625,62 @@ impl FormatValidator {
                 problems.push(Problem::ExtraKey {
                     key: key.to_string(),
                 });
-                problems_added += 1;
             }
         }
-        problems_added
     }
 
     /// 验证特定键的格式标记是否一致
+    ///
+    /// 缺失与多出的标记两两配对视为内容被改变，其余分别报告；按顺序比较时，还要求共有的标记保持原文的顺序。
     fn validate_patterns(
         &self,
         key: &str,
         original: &str,
         translated: &str,
         problems: &mut Vec<Problem>,
-    ) -> usize {
-        let mut problems_added = 0;
-        for pattern in [
-            &self.icon_pattern,
-            &self.variable_pattern,
-            &self.color_pattern,
-            &self.command_pattern,
-        ] {
-            let mut original: Vec<&str> = pattern.find_iter(original).map(|m| m.as_str()).collect();
-            let translated: Vec<&str> = pattern.find_iter(translated).map(|m| m.as_str()).collect();
-
-            // 标记数量不匹配，检查缺失的标记，并将缺失标记从 original 中排除，以便后续检查内容一致性
-            if original.len() != translated.len() {
-                let original_set: HashSet<&str> = original.iter().cloned().collect();
-                let translated_set: HashSet<&str> = translated.iter().cloned().collect();
-                let missing: Vec<&&str> = original_set.difference(&translated_set).collect();
-                for it in missing.iter() {
-                    problems.push(Problem::PatternNotFound {
-                        key: key.to_string(),
-                        original: it.to_string(),
-                    });
-                    problems_added += 1;
-                }
+    ) {
+        let builtin = [
+            self.icon_pattern,
+            self.variable_pattern,
+            self.color_pattern,
+            self.command_pattern,
+        ];
+        for pattern in builtin.into_iter().chain(&self.custom_patterns) {
+            let mut original: Vec<String> = pattern
+                .find_iter(original)
+                .map(|m| m.as_str().to_string())
+                .collect();
+            let mut translated: Vec<String> = pattern
+                .find_iter(translated)
+                .map(|m| m.as_str().to_string())
+                .collect();
 
-                // 排除缺失的标记
-                original.retain(|it| !missing.contains(&it));
+            let (missing, extra) = multiset_difference(original.clone(), translated.clone());
+            for i in 0..missing.len().max(extra.len()) {
+                problems.push(match (missing.get(i), extra.get(i)) {
+                    (Some(original), Some(translated)) => Problem::PatternMismatch {
+                        key: key.to_string(),
+                        original: original.clone(),
+                        translated: translated.clone(),
+                    },
+                    (Some(original), None) => Problem::PatternNotFound {
+                        key: key.to_string(),
+                        original: original.clone(),
+                    },
+                    (None, Some(translated)) => Problem::ExtraPattern {
+                        key: key.to_string(),
+                        translated: translated.clone(),
+                    },
+                    (None, None) => unreachable!(),
+                });
             }
-            // 标记数量相等，检查内容是否一致
+            if self.marker_check == MarkerCheck::Multiset {
+                continue;
+            }
+
+            // 排除缺失与多出的标记后，两边的标记相同，逐个比较顺序
+            remove_each(&mut original, &missing);
+            remove_each(&mut translated, &extra);
             for (it_original, it_translated) in original.iter().zip(translated.iter()) {
                 if it_original != it_translated {
                     problems.push(Problem::PatternMismatch {
@@ -196,31 +688,224 @@ impl FormatValidator {
                         original: it_original.to_string(),
                         translated: it_translated.to_string(),
                     });
-                    problems_added += 1;
                 }
             }
         }
-        problems_added
     }
 
-    /// 提取所有特殊标记
+    /// 提取所有特殊标记（图标、变量、颜色代码、指令与自定义标记），按在文本中的位置排列
     pub fn extract_markers(&self, text: &str) -> Vec<String> {
-        let mut markers = Vec::new();
-        markers.extend(
-            self.icon_pattern
-                .find_iter(text)
-                .map(|m| m.as_str().to_string()),
+        let builtin = [
+            self.icon_pattern,
+            self.variable_pattern,
+            self.color_pattern,
+            self.command_pattern,
+        ];
+        let mut markers: Vec<(usize, String)> = builtin
+            .into_iter()
+            .chain(&self.custom_patterns)
+            .flat_map(|pattern| pattern.find_iter(text))
+            .map(|m| (m.start(), m.as_str().to_string()))
+            .collect();
+        markers.sort_by_key(|(start, _)| *start);
+        markers.into_iter().map(|(_, marker)| marker).collect()
+    }
+}
+
+/// 条目值中引号内的文本，没有引号时返回原值
+fn quoted_text(value: &str) -> &str {
+    match (value.find('"'), value.rfind('"')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value,
+    }
+}
+
+/// 全角字符（中日韩文字、全角符号等），显示宽度为 2
+fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x20000..=0x3FFFD
+    )
+}
+
+/// 私用区字符（游戏图标）或表情符号
+fn is_special_glyph(c: char) -> bool {
+    matches!(
+        c as u32,
+        0xE000..=0xF8FF
+            | 0xF0000..=0xFFFFD
+            | 0x100000..=0x10FFFD
+            | 0x1F000..=0x1FAFF
+            | 0x2600..=0x27BF
+    )
+}
+
+/// 从 `items` 中逐个移除 `removed` 中的元素（每个只移除一次）
+fn remove_each(items: &mut Vec<String>, removed: &[String]) {
+    for item in removed {
+        if let Some(i) = items.iter().position(|v| v == item) {
+            items.remove(i);
+        }
+    }
+}
+
+/// 比较两组元素（可重复、不计顺序），返回原文中缺失的与译文中多出的元素
+fn multiset_difference(
+    original: Vec<String>,
+    translated: Vec<String>,
+) -> (Vec<String>, Vec<String>) {
+    let mut missing = original;
+    let mut extra = Vec::new();
+    for item in translated {
+        match missing.iter().position(|v| *v == item) {
+            Some(i) => {
+                missing.remove(i);
+            }
+            None => extra.push(item),
+        }
+    }
+    (missing, extra)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_variants() {
+        let validator = FormatValidator::new();
+        let original = "[GetPlayer.GetCustom('PlayerTitle')] of [ROOT.Char.Custom('HerHis')] land";
+        let reordered = "[ROOT.Char.Custom('HerHis')]土地的[GetPlayer.GetCustom('PlayerTitle')]";
+        assert!(
+            validator
+                .validate_entry("k", original, reordered)
+                .is_empty()
         );
-        markers.extend(
-            self.variable_pattern
-                .find_iter(text)
-                .map(|m| m.as_str().to_string()),
+
+        let changed = "[GetPlayer.GetCustom('PlayerTitle')]的[ROOT.Char.Custom('HisHer')]土地";
+        let problems = validator.validate_entry("k", original, changed);
+        assert_eq!(problems.len(), 1);
+        match &problems[0] {
+            Problem::VariantMismatch { missing, extra, .. } => {
+                assert_eq!(missing, &["[ROOT.Char.Custom('HerHis')]"]);
+                assert_eq!(extra, &["[ROOT.Char.Custom('HisHer')]"]);
+            }
+            other => panic!("unexpected problem: {}", other),
+        }
+
+        let problems = validator.validate_entry("k", "+10% fire rate", "射速 +15%");
+        assert!(matches!(&problems[..], [Problem::NumberMismatch { .. }]));
+
+        let problems = validator.validate_entry("k", "\u{E001} Ready ⚔", "\u{E001} 就绪");
+        match &problems[..] {
+            [Problem::GlyphMismatch { missing, extra, .. }] => {
+                assert_eq!(missing, &["U+2694"]);
+                assert!(extra.is_empty());
+            }
+            other => panic!("unexpected problems: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_length_budget() {
+        let validator = FormatValidator::new().with_length_budgets(&[LengthBudget {
+            keys: "trait_.*".to_string(),
+            max_width: 8,
+        }]);
+        // 颜色代码与变量不计入宽度
+        assert!(
+            validator
+                .validate_entry("trait_a", "§YStrong$X$§!", "§Y强壮$X$§!")
+                .is_empty()
         );
-        markers.extend(
-            self.color_pattern
-                .find_iter(text)
-                .map(|m| m.as_str().to_string()),
+        let problems = validator.validate_entry("trait_b", "Very Strong", "\"非常非常强壮\"");
+        assert!(matches!(
+            problems.as_slice(),
+            [Problem::OverLengthBudget {
+                width: 12,
+                max_width: 8,
+                ..
+            }]
+        ));
+        assert!(
+            validator
+                .validate_entry("desc_b", "Very Strong", "非常非常强壮")
+                .is_empty()
         );
-        markers
+    }
+
+    #[test]
+    fn test_marker_positions() {
+        let validator = FormatValidator::new();
+        let problems =
+            validator.validate_entry("k", "\"£energy£ 5 per month\"", "\"每月 5 £energy£\"");
+        assert_eq!(problems.len(), 2);
+        assert!(
+            problems
+                .iter()
+                .all(|p| matches!(p, Problem::MarkerMoved { .. }))
+        );
+        assert!(
+            problems[1]
+                .to_string()
+                .contains("directly before the number '5'")
+        );
+
+        // 图标与数字保持相邻、句首图标仍在句首时不报告
+        assert!(
+            validator
+                .validate_entry("k", "\"£energy£ 5 per month\"", "\"£energy£ 5 每月\"")
+                .is_empty()
+        );
+        assert!(
+            validator
+                .validate_entry("k", "\"Gain +10% £unity£\"", "\"获得 +10% £unity£。\"")
+                .is_empty()
+        );
+        // 句首图标移到句尾
+        let problems =
+            validator.validate_entry("k", "\"£alloys£ Build a ship\"", "\"建造舰船 £alloys£\"");
+        assert!(matches!(
+            problems.as_slice(),
+            [Problem::MarkerMoved { marker, .. }] if marker == "£alloys£"
+        ));
+    }
+
+    #[test]
+    fn test_marker_check() {
+        let original = "$COUNTRY$ attacks $PLANET$";
+        let reordered = "$PLANET$ 遭到 $COUNTRY$ 攻击";
+        let ordered = FormatValidator::new();
+        assert!(matches!(
+            ordered.validate_entry("k", original, reordered).as_slice(),
+            [
+                Problem::PatternMismatch { .. },
+                Problem::PatternMismatch { .. }
+            ]
+        ));
+        let multiset = FormatValidator::new().with_marker_check(MarkerCheck::Multiset);
+        assert!(multiset.validate_entry("k", original, reordered).is_empty());
+
+        // 重复的标记少了一个时两种方式都要报告
+        for validator in [&ordered, &multiset] {
+            let problems = validator.validate_entry("k", "£energy£ or £energy£", "£energy£ 或");
+            assert!(matches!(
+                problems.as_slice(),
+                [Problem::PatternNotFound { original, .. }] if original == "£energy£"
+            ));
+            let problems = validator.validate_entry("k", "$X$", "$X$ §Y$X$§!");
+            assert_eq!(problems.len(), 3, "{:?}", problems);
+        }
     }
 }
@@ -0,0 +1,124 @@
+//! 原版本地化
+//!
+//! 查询游戏本体的本地化，使 Mod 覆盖的原版键能直接复用官方译文，保持与游戏本体一致。
+//! 条目来自 [`VanillaIndex`]，第一次使用时建立索引并缓存，之后的运行无需再解析游戏的本地化文件。
+
+use super::VanillaIndex;
+use crate::error::Result;
+use crate::preprocess::parse_entries;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 游戏本体的本地化条目
+#[derive(Debug, Default)]
+pub struct VanillaLocalisation {
+    /// 未找到游戏本地化时为 `None`，查询不到任何条目
+    index: Option<VanillaIndex>,
+}
+
+impl VanillaLocalisation {
+    /// 从游戏本地化目录（`<游戏安装目录>/localisation`）加载，`langs` 中没有原版本地化的语言给出警告
+    pub fn load<P: AsRef<Path>>(localisation_dir: P, langs: &[String]) -> Result<Self> {
+        Self::from_index(VanillaIndex::open(localisation_dir.as_ref())?, langs)
+    }
+
+    /// 使用已打开的索引
+    pub fn from_index(index: VanillaIndex, langs: &[String]) -> Result<Self> {
+        for lang in langs {
+            match index.len(lang) {
+                0 => log::warn!("No vanilla localisation for {}", lang),
+                count => log::info!("Loaded {} vanilla {} entries", count, lang),
+            }
+        }
+        Ok(Self { index: Some(index) })
+    }
+
+    /// 底层的索引，未找到游戏本地化时为 `None`
+    pub fn index(&self) -> Option<&VanillaIndex> {
+        self.index.as_ref()
+    }
+
+    /// 查找原版键在指定语言下的文本
+    pub fn get(&self, lang: &str, key: &str) -> Option<&str> {
+        self.index.as_ref()?.get(lang, key)
+    }
+
+    /// 原版键的官方译文，仅当 Mod 中的原文 `source` 与原版的原文相同时返回
+    ///
+    /// Mod 覆盖原版键往往正是为了修改文本，改过的条目沿用旧的官方译文会与原文不符。
+    pub fn official_translation(
+        &self,
+        key: &str,
+        source_lang: &str,
+        target_lang: &str,
+        source: &str,
+    ) -> Option<&str> {
+        let original = self.get(source_lang, key)?;
+        if original.trim() != source.trim() {
+            return None;
+        }
+        self.get(target_lang, key)
+    }
+
+    /// 找出内容中覆盖了原版键且原文未改动的条目，返回「键 -> 官方译文」
+    pub fn official_translations(
+        &self,
+        content: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> HashMap<String, String> {
+        parse_entries(content)
+            .into_iter()
+            .filter_map(|e| {
+                self.official_translation(&e.key, source_lang, target_lang, &e.value)
+                    .map(|value| (e.key, value.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_official_translations() {
+        let dir = tempfile::tempdir().unwrap();
+        for (lang, content) in [
+            (
+                "english",
+                "l_english:\n pc_desert:0 \"Desert World\"\n pc_arid:0 \"Arid World\"\n",
+            ),
+            (
+                "simp_chinese",
+                "l_simp_chinese:\n pc_desert:0 \"沙漠星球\"\n pc_arid:0 \"干旱星球\"\n",
+            ),
+        ] {
+            let lang_dir = dir.path().join("localisation").join(lang);
+            std::fs::create_dir_all(&lang_dir).unwrap();
+            std::fs::write(
+                lang_dir.join(format!("planets_l_{}.yml", lang)),
+                format!("\u{FEFF}{}", content),
+            )
+            .unwrap();
+        }
+
+        let index = VanillaIndex::open_with_cache(
+            &dir.path().join("localisation"),
+            &dir.path().join("cache"),
+        )
+        .unwrap();
+        let vanilla =
+            VanillaLocalisation::from_index(index, &["simp_chinese".to_string()]).unwrap();
+        // pc_arid 被 Mod 改写了文本，不沿用官方译文
+        let content = " pc_desert:0 \"Desert World\"\n pc_arid:0 \"Scorched World\"\n my_mod_key:0 \"Custom\"";
+        let official = vanilla.official_translations(content, "english", "simp_chinese");
+        assert_eq!(official.len(), 1);
+        assert_eq!(official["pc_desert"], "沙漠星球");
+        assert!(
+            vanilla
+                .official_translation("pc_arid", "english", "simp_chinese", "Scorched World")
+                .is_none()
+        );
+    }
+}
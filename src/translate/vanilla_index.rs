@@ -0,0 +1,417 @@
+//! 原版本地化索引
+//!
+//! 游戏本体的本地化有数十万条目、十余种语言，每次运行都重新解析很慢。索引在第一次使用时解析全部语言，
+//! 按语言与键排序后写入缓存目录中的二进制文件，之后直接内存映射该文件，按键二分查找，无需再解析。
+//! 游戏更新后（本地化文件的大小或修改时间变化）自动重建。
+//!
+//! 文件格式（整数均为小端序 u32，偏移量相对于文件开头）：
+//!
+//! ```text
+//! 文件头    "PMTVIDX1"  指纹(u64)  语言数  保留
+//! 语言表    每种语言：名称偏移  名称长度  条目表偏移  条目数
+//! 条目表    每个条目：键偏移  键长度  文本偏移  文本长度（按键排序）
+//! 字符串区  UTF-8 文本
+//! ```
+
+use crate::error::{Result, TranslationError};
+use crate::preprocess::parse_entries;
+use crate::utils::{FNV_OFFSET, find_yaml_files, fnv1a, get_user_cache_dir, read_file_with_bom};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"PMTVIDX1";
+const HEADER_LEN: usize = 24;
+const RECORD_LEN: usize = 16;
+
+/// 索引数据：内存映射的缓存文件，缓存目录不可写时为内存中的数据
+#[derive(Debug)]
+enum Storage {
+    Mapped(memmap2::Mmap),
+    Owned(Vec<u8>),
+}
+
+impl std::ops::Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Storage::Mapped(map) => map,
+            Storage::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// 一种语言的条目表
+#[derive(Debug)]
+struct LangTable {
+    name: String,
+    offset: usize,
+    count: usize,
+}
+
+/// 只读的原版本地化索引：键 -> 各语言的文本
+#[derive(Debug)]
+pub struct VanillaIndex {
+    data: Storage,
+    langs: Vec<LangTable>,
+}
+
+/// 游戏本地化目录中的语言目录
+fn language_dirs(localisation_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut langs: Vec<(String, PathBuf)> = std::fs::read_dir(localisation_dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| Some((e.file_name().to_str()?.to_string(), e.path())))
+        .filter(|(name, _)| name != "replace")
+        .collect();
+    langs.sort();
+    Ok(langs)
+}
+
+/// 本地化文件的指纹：所有文件的路径、大小与修改时间
+fn fingerprint(localisation_dir: &Path) -> Result<u64> {
+    let mut files = Vec::new();
+    for (_, dir) in language_dirs(localisation_dir)? {
+        files.extend(find_yaml_files(&dir)?);
+    }
+    files.sort();
+    let mut hash = FNV_OFFSET;
+    for file in files {
+        let metadata = std::fs::metadata(&file)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        fnv1a(&mut hash, file.to_string_lossy().as_bytes());
+        fnv1a(&mut hash, &metadata.len().to_le_bytes());
+        fnv1a(&mut hash, &modified.to_le_bytes());
+    }
+    Ok(hash)
+}
+
+/// 解析全部语言的条目并编码为索引数据
+fn build(localisation_dir: &Path, fingerprint: u64) -> Result<Vec<u8>> {
+    let mut langs: Vec<(String, BTreeMap<String, String>)> = Vec::new();
+    for (lang, dir) in language_dirs(localisation_dir)? {
+        let mut entries = BTreeMap::new();
+        for file in find_yaml_files(&dir)? {
+            for entry in parse_entries(&read_file_with_bom(&file)?) {
+                entries.insert(entry.key, entry.value);
+            }
+        }
+        log::debug!("Indexed {} vanilla {} entries", entries.len(), lang);
+        langs.push((lang, entries));
+    }
+
+    let records: usize = langs.iter().map(|(_, e)| e.len()).sum::<usize>() + langs.len();
+    let strings_start = HEADER_LEN + records * RECORD_LEN;
+    let mut strings: Vec<u8> = Vec::new();
+    let mut add_string = |text: &str| -> Result<[u32; 2]> {
+        let offset = to_u32(strings_start + strings.len())?;
+        strings.extend_from_slice(text.as_bytes());
+        Ok([offset, to_u32(text.len())?])
+    };
+
+    let mut lang_table = Vec::new();
+    let mut entry_table = Vec::new();
+    let mut entries_offset = HEADER_LEN + langs.len() * RECORD_LEN;
+    for (lang, entries) in &langs {
+        let [name_offset, name_len] = add_string(lang)?;
+        lang_table.extend([
+            name_offset,
+            name_len,
+            to_u32(entries_offset)?,
+            to_u32(entries.len())?,
+        ]);
+        for (key, value) in entries {
+            entry_table.extend(add_string(key)?);
+            entry_table.extend(add_string(value)?);
+        }
+        entries_offset += entries.len() * RECORD_LEN;
+    }
+
+    let mut data = Vec::with_capacity(strings_start + strings.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&fingerprint.to_le_bytes());
+    data.extend_from_slice(&to_u32(langs.len())?.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    for value in lang_table.into_iter().chain(entry_table) {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(&strings);
+    Ok(data)
+}
+
+fn to_u32(value: usize) -> Result<u32> {
+    u32::try_from(value).map_err(|_| {
+        TranslationError::ValidationError("Vanilla localisation too large to index".to_string())
+    })
+}
+
+impl VanillaIndex {
+    /// 打开游戏本地化目录（`<游戏安装目录>/localisation`）的索引，缓存在用户缓存目录中
+    pub fn open(localisation_dir: &Path) -> Result<Self> {
+        Self::open_with_cache(localisation_dir, &get_user_cache_dir()?.join("vanilla"))
+    }
+
+    /// 打开索引，缓存文件放在 `cache_dir` 中
+    ///
+    /// 每个游戏本地化目录一个缓存文件，本地化文件变化后重建并替换。缓存目录不可写时在内存中建立索引。
+    pub fn open_with_cache(localisation_dir: &Path, cache_dir: &Path) -> Result<Self> {
+        let canonical = localisation_dir
+            .canonicalize()
+            .unwrap_or_else(|_| localisation_dir.to_path_buf());
+        let mut dir_hash = FNV_OFFSET;
+        fnv1a(&mut dir_hash, canonical.to_string_lossy().as_bytes());
+        let cache_path = cache_dir.join(format!("{:016x}.idx", dir_hash));
+        let fingerprint = fingerprint(localisation_dir)?;
+
+        if let Some(index) = Self::map(&cache_path)
+            .inspect_err(|e| log::warn!("Ignoring vanilla index {:?}: {}", cache_path, e))
+            .ok()
+            .flatten()
+            .filter(|index| index.fingerprint() == fingerprint)
+        {
+            log::debug!("Using vanilla index {:?}", cache_path);
+            return Ok(index);
+        }
+
+        log::info!(
+            "Indexing vanilla localisation in {:?}, this only happens once per game version",
+            localisation_dir
+        );
+        let data = build(localisation_dir, fingerprint)?;
+        match Self::write(&cache_path, &data).and_then(|_| Self::map(&cache_path)) {
+            Ok(Some(index)) => Ok(index),
+            result => {
+                if let Err(e) = result {
+                    log::warn!("Cannot cache vanilla index in {:?}: {}", cache_path, e);
+                }
+                Self::parse(Storage::Owned(data))
+            }
+        }
+    }
+
+    /// 先写入临时文件再替换，其他进程映射的旧文件不受影响
+    fn write(path: &Path, data: &[u8]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension("idx.tmp");
+        std::fs::write(&temp, data)?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// 映射缓存文件，文件不存在时返回 `None`
+    fn map(path: &Path) -> Result<Option<Self>> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // SAFETY: 缓存文件只会被整体替换（先写临时文件再重命名），不会在映射期间被原地修改
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Self::parse(Storage::Mapped(map)).map(Some)
+    }
+
+    /// 检查文件头与各表的范围，读出语言表
+    fn parse(data: Storage) -> Result<Self> {
+        let invalid = || TranslationError::ValidationError("Corrupt vanilla index".to_string());
+        if data.len() < HEADER_LEN || &data[..8] != MAGIC {
+            return Err(invalid());
+        }
+        let mut index = Self {
+            data,
+            langs: Vec::new(),
+        };
+        let lang_count = index.u32_at(16).ok_or_else(invalid)?;
+        for i in 0..lang_count {
+            let record = HEADER_LEN + i * RECORD_LEN;
+            let name = index.str_at(record).ok_or_else(invalid)?.to_string();
+            let offset = index.u32_at(record + 8).ok_or_else(invalid)?;
+            let count = index.u32_at(record + 12).ok_or_else(invalid)?;
+            if offset + count * RECORD_LEN > index.data.len() {
+                return Err(invalid());
+            }
+            index.langs.push(LangTable {
+                name,
+                offset,
+                count,
+            });
+        }
+        Ok(index)
+    }
+
+    fn fingerprint(&self) -> u64 {
+        u64::from_le_bytes(self.data[8..16].try_into().unwrap_or_default())
+    }
+
+    fn u32_at(&self, pos: usize) -> Option<usize> {
+        let bytes = self.data.get(pos..pos + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+    }
+
+    /// 位于 `pos` 的（偏移, 长度）所指的字符串
+    fn str_at(&self, pos: usize) -> Option<&str> {
+        let offset = self.u32_at(pos)?;
+        let len = self.u32_at(pos + 4)?;
+        std::str::from_utf8(self.data.get(offset..offset + len)?).ok()
+    }
+
+    fn table(&self, lang: &str) -> Option<&LangTable> {
+        self.langs.iter().find(|t| t.name == lang)
+    }
+
+    fn entry(&self, table: &LangTable, i: usize) -> Option<(&str, &str)> {
+        let record = table.offset + i * RECORD_LEN;
+        Some((self.str_at(record)?, self.str_at(record + 8)?))
+    }
+
+    /// 索引中的语言
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.langs.iter().map(|t| t.name.as_str())
+    }
+
+    /// 一种语言的条目数
+    pub fn len(&self, lang: &str) -> usize {
+        self.table(lang).map(|t| t.count).unwrap_or(0)
+    }
+
+    /// 索引中是否没有任何条目
+    pub fn is_empty(&self) -> bool {
+        self.langs.iter().all(|t| t.count == 0)
+    }
+
+    /// 查找键在指定语言下的文本
+    pub fn get(&self, lang: &str, key: &str) -> Option<&str> {
+        let table = self.table(lang)?;
+        let (mut low, mut high) = (0, table.count);
+        while low < high {
+            let mid = (low + high) / 2;
+            let (k, value) = self.entry(table, mid)?;
+            match k.cmp(key) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(value),
+            }
+        }
+        None
+    }
+
+    /// 键在各语言下的文本：(语言, 文本)
+    pub fn values(&self, key: &str) -> Vec<(&str, &str)> {
+        self.languages()
+            .filter_map(|lang| Some((lang, self.get(lang, key)?)))
+            .collect()
+    }
+
+    /// 原版中作为独立文本出现的短语的官方译法：源语言文本（小写）-> 目标语言文本
+    ///
+    /// 只收录不超过 `max_words` 个单词的文本，用于判断 Mod 中的术语是否沿用了原版名称。
+    pub fn official_terms(
+        &self,
+        source_lang: &str,
+        target_lang: &str,
+        max_words: usize,
+    ) -> HashMap<String, String> {
+        self.entries(source_lang)
+            .filter(|(_, text)| !text.is_empty() && text.split_whitespace().count() <= max_words)
+            .filter_map(|(key, text)| {
+                let official = self.get(target_lang, key)?;
+                Some((text.to_lowercase(), official.to_string()))
+            })
+            .collect()
+    }
+
+    /// 一种语言中不超过 `max_words` 个单词的文本（小写），用于判断短语是否是原版已有的通用文本
+    pub fn short_texts(&self, lang: &str, max_words: usize) -> HashSet<String> {
+        self.entries(lang)
+            .filter(|(_, text)| !text.is_empty() && text.split_whitespace().count() <= max_words)
+            .map(|(_, text)| text.to_lowercase())
+            .collect()
+    }
+
+    /// 一种语言的全部条目，按键排序
+    pub fn entries(&self, lang: &str) -> impl Iterator<Item = (&str, &str)> {
+        let table = self.table(lang);
+        (0..table.map(|t| t.count).unwrap_or(0)).filter_map(move |i| self.entry(table?, i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vanilla_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let loc = dir.path().join("localisation");
+        let write = |file: &str, content: &str| {
+            let path = loc.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(
+            "english/planets_l_english.yml",
+            "l_english:\n pc_desert:0 \"Arid World\"\n pc_arctic:0 \"Arctic World\"\n",
+        );
+        write(
+            "simp_chinese/planets_l_simp_chinese.yml",
+            "\u{FEFF}l_simp_chinese:\n pc_desert:0 \"沙漠星球\"\n",
+        );
+        let cache = dir.path().join("cache");
+
+        let index = VanillaIndex::open_with_cache(&loc, &cache).unwrap();
+        assert!(matches!(index.data, Storage::Mapped(_)));
+        assert_eq!(
+            index.languages().collect::<Vec<_>>(),
+            ["english", "simp_chinese"]
+        );
+        assert_eq!(index.get("simp_chinese", "pc_desert"), Some("沙漠星球"));
+        assert_eq!(index.get("simp_chinese", "pc_arctic"), None);
+        assert_eq!(index.get("german", "pc_desert"), None);
+        assert_eq!(
+            index.values("pc_desert"),
+            [("english", "Arid World"), ("simp_chinese", "沙漠星球")]
+        );
+        assert_eq!(
+            index.entries("english").map(|(k, _)| k).collect::<Vec<_>>(),
+            ["pc_arctic", "pc_desert"]
+        );
+        assert_eq!(
+            index.official_terms("english", "simp_chinese", 4),
+            HashMap::from([("arid world".to_string(), "沙漠星球".to_string())])
+        );
+        assert_eq!(
+            index.short_texts("english", 2),
+            HashSet::from(["arid world".to_string(), "arctic world".to_string()])
+        );
+        assert!(index.short_texts("english", 1).is_empty());
+
+        // 本地化文件变化后重建，缓存目录中仍只有一个索引文件
+        write(
+            "simp_chinese/planets_l_simp_chinese.yml",
+            "l_simp_chinese:\n pc_desert:0 \"干旱星球\"\n pc_arctic:0 \"极地星球\"\n",
+        );
+        let index = VanillaIndex::open_with_cache(&loc, &cache).unwrap();
+        assert_eq!(index.get("simp_chinese", "pc_desert"), Some("干旱星球"));
+        assert_eq!(index.len("simp_chinese"), 2);
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 1);
+
+        std::fs::write(
+            std::fs::read_dir(&cache)
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap()
+                .path(),
+            "junk",
+        )
+        .unwrap();
+        let index = VanillaIndex::open_with_cache(&loc, &cache).unwrap();
+        assert_eq!(index.get("english", "pc_arctic"), Some("Arctic World"));
+    }
+}
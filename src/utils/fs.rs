@@ -5,7 +5,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-/// 递归查找所有YAML文件
+/// 递归查找所有YAML文件，结果按路径排序
 pub fn find_yaml_files(dir: &Path) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
@@ -15,30 +15,202 @@ pub fn find_yaml_files(dir: &Path) -> Result<Vec<PathBuf>> {
         .filter_map(|e| e.ok())
     {
         let path = entry.path();
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext == "yml" || ext == "yaml" {
-                    files.push(path.to_path_buf());
-                }
+        if path.is_file()
+            && let Some(ext) = path.extension()
+            && (ext == "yml" || ext == "yaml")
+        {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// 递归查找所有本地化文件，跳过内容不像本地化文件的 YAML 文件并给出警告
+pub fn find_localisation_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in find_yaml_files(dir)? {
+        if crate::preprocess::is_localisation_content(&read_file_with_bom(&path)?) {
+            files.push(path);
+        } else {
+            log::warn!("Skipping {:?}: not a localisation file", path);
+        }
+    }
+    Ok(files)
+}
+
+/// 并行读取目录与文件时同时进行的操作数
+pub const FS_PARALLELISM: usize = 32;
+
+/// 递归查找所有YAML文件，各目录并行读取，结果按路径排序
+///
+/// 与 [`find_yaml_files`] 一样跟随符号链接，已访问过的目录不再重复进入。
+pub async fn find_yaml_files_async(dir: &Path) -> Result<Vec<PathBuf>> {
+    use futures::StreamExt;
+    use std::collections::HashSet;
+
+    /// 一个目录中的 YAML 文件与子目录，与 WalkDir 一样跳过无法读取的目录与条目
+    async fn read_dir(dir: PathBuf) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+            return (files, dirs);
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            // 跟随符号链接，无法访问的条目跳过
+            let Ok(metadata) = tokio::fs::metadata(&path).await else {
+                continue;
+            };
+            if metadata.is_dir() {
+                dirs.push(path);
+            } else if metadata.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == "yml" || ext == "yaml")
+            {
+                files.push(path);
             }
         }
+        (files, dirs)
     }
 
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while !pending.is_empty() {
+        let level: Vec<PathBuf> = pending
+            .drain(..)
+            .filter(|d| visited.insert(d.canonicalize().unwrap_or_else(|_| d.clone())))
+            .collect();
+        let results: Vec<(Vec<PathBuf>, Vec<PathBuf>)> = futures::stream::iter(level)
+            .map(read_dir)
+            .buffer_unordered(FS_PARALLELISM)
+            .collect()
+            .await;
+        for (level_files, dirs) in results {
+            files.extend(level_files);
+            pending.extend(dirs);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// 并行读取并检查目录中的 YAML 文件，返回其中的本地化文件（按路径排序），结果与 [`find_localisation_files`] 相同
+///
+/// 本地化文件有成千上万个、磁盘又较慢时，逐个读取的耗时相当可观。
+pub async fn find_localisation_files_async(dir: &Path) -> Result<Vec<PathBuf>> {
+    use futures::{StreamExt, TryStreamExt};
+
+    let checked: Vec<(PathBuf, bool)> = futures::stream::iter(find_yaml_files_async(dir).await?)
+        .map(|path| async move {
+            let bytes = tokio::fs::read(&path).await?;
+            let content = decode_file_content(&bytes, &path);
+            Ok::<_, crate::error::TranslationError>((
+                path,
+                crate::preprocess::is_localisation_content(&content),
+            ))
+        })
+        .buffered(FS_PARALLELISM)
+        .try_collect()
+        .await?;
+    let mut files = Vec::new();
+    for (path, is_localisation) in checked {
+        if is_localisation {
+            files.push(path);
+        } else {
+            log::warn!("Skipping {:?}: not a localisation file", path);
+        }
+    }
     Ok(files)
 }
 
 /// 读取文件内容，自动处理BOM
+///
+/// 文件不是 UTF-8 编码时（例如 UTF-16 或 Windows-1252）自动检测编码并转换为 UTF-8，
+/// 读入的内容经过 `normalize_input` 规范化。
 pub fn read_file_with_bom(path: &Path) -> Result<String> {
-    let content = fs::read_to_string(path)?;
+    Ok(decode_file_content(&fs::read(path)?, path))
+}
 
-    // 移除UTF-8 BOM
-    let content = if content.starts_with('\u{feff}') {
-        &content[3..]
-    } else {
-        &content
-    };
+/// 将读入的文件内容解码为规范化的 UTF-8 文本，`path` 只用于日志
+pub fn decode_file_content(bytes: &[u8], path: &Path) -> String {
+    let (content, encoding) = decode_bytes(bytes);
+    if encoding != encoding_rs::UTF_8 {
+        log::info!(
+            "Detected {} encoding in {:?}, transcoding to UTF-8",
+            encoding.name(),
+            path
+        );
+    }
+
+    // 移除 BOM，统一换行符与行尾空白
+    crate::preprocess::normalize_input(&content)
+}
 
-    Ok(content.to_string())
+/// 拉丁字母的单字节编码，非 ASCII 字节太少、检测结果不可靠时按 Windows-1252 解码
+const LATIN_SINGLE_BYTE: [&encoding_rs::Encoding; 8] = [
+    encoding_rs::WINDOWS_1250,
+    encoding_rs::WINDOWS_1254,
+    encoding_rs::WINDOWS_1257,
+    encoding_rs::ISO_8859_2,
+    encoding_rs::ISO_8859_4,
+    encoding_rs::ISO_8859_13,
+    encoding_rs::ISO_8859_15,
+    encoding_rs::WINDOWS_1252,
+];
+
+/// 非 ASCII 字节少于该数量时，chardetng 对拉丁字母编码的猜测不可靠
+const MIN_CONFIDENT_NON_ASCII: usize = 8;
+
+/// 将文件的原始字节解码为文本，返回文本（不含 BOM）与检测到的编码
+///
+/// 依次根据 BOM、UTF-8 合法性与 UTF-16 的零字节分布判断，仍无法确定时交给 chardetng 猜测。
+pub fn decode_bytes(bytes: &[u8]) -> (String, &'static encoding_rs::Encoding) {
+    use encoding_rs::{UTF_8, UTF_16BE, UTF_16LE};
+
+    let (encoding, body) = match encoding_rs::Encoding::for_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, &bytes[bom_len..]),
+        None => {
+            let encoding = if std::str::from_utf8(bytes).is_ok() {
+                UTF_8
+            } else {
+                // 没有 BOM 的 UTF-16 文本中，ASCII 字符的高字节为零
+                let zeros_at = |parity: usize| {
+                    bytes
+                        .iter()
+                        .skip(parity)
+                        .step_by(2)
+                        .filter(|&&b| b == 0)
+                        .count()
+                };
+                let half = bytes.len() / 2;
+                if half > 0 && zeros_at(1) * 2 > half {
+                    UTF_16LE
+                } else if half > 0 && zeros_at(0) * 2 > half {
+                    UTF_16BE
+                } else {
+                    let mut detector =
+                        chardetng::EncodingDetector::new(chardetng::Iso2022JpDetection::Deny);
+                    detector.feed(bytes, true);
+                    let guess = detector.guess(None, chardetng::Utf8Detection::Deny);
+                    // 游戏的旧版文件多为 Windows-1252，而只有几个重音字母的文本常被误判为其他拉丁字母编码；
+                    // 非 ASCII 字符足够多时（例如波兰语、捷克语、土耳其语的文本）相信检测结果
+                    let non_ascii = bytes.iter().filter(|b| !b.is_ascii()).count();
+                    if LATIN_SINGLE_BYTE.contains(&guess) && non_ascii < MIN_CONFIDENT_NON_ASCII {
+                        encoding_rs::WINDOWS_1252
+                    } else {
+                        guess
+                    }
+                }
+            };
+            (encoding, bytes)
+        }
+    };
+    let (content, _) = encoding.decode_without_bom_handling(body);
+    (content.into_owned(), encoding)
 }
 
 /// 安全创建目录（如果不存在）
@@ -94,6 +266,12 @@ pub fn get_user_data_dir() -> Result<PathBuf> {
     }
 }
 
+/// 获取用户缓存目录路径：用户数据目录旁的 `cache/`，例如 ~/.local/share/pmt/cache/
+pub fn get_user_cache_dir() -> Result<PathBuf> {
+    let data_dir = get_user_data_dir()?;
+    Ok(data_dir.parent().unwrap_or(&data_dir).join("cache"))
+}
+
 /// 查找数据文件
 ///
 /// 按照以下顺序查找文件：
@@ -122,8 +300,8 @@ pub fn find_data_file(relative_path: &str) -> Result<Option<PathBuf>> {
 /// 查找数据文件，如果找不到则返回错误
 pub fn find_data_file_or_error(relative_path: &str) -> Result<PathBuf> {
     find_data_file(relative_path)?.ok_or_else(|| {
-        let user_data_dir =
-            get_user_data_dir().unwrap_or_else(|_| PathBuf::from("[无法获取用户数据目录]"));
+        let user_data_dir = get_user_data_dir()
+            .unwrap_or_else(|_| PathBuf::from(crate::tr!("fs.no_user_data_dir")));
         crate::error::TranslationError::FileNotFound(format!(
             "Data file not found: '{}'. Searched in:\n1. ./data/{}\n2. {}/{}",
             relative_path,
@@ -133,3 +311,55 @@ pub fn find_data_file_or_error(relative_path: &str) -> Result<PathBuf> {
         ))
     })
 }
+
+/// 检查数据文件都能找到，一次列出所有缺少的文件与查找的位置
+pub fn require_data_files(relative_paths: &[&str]) -> Result<()> {
+    let mut missing = Vec::new();
+    for relative_path in relative_paths {
+        if find_data_file(relative_path)?.is_none() {
+            missing.push(*relative_path);
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let user_data_dir =
+        get_user_data_dir().unwrap_or_else(|_| PathBuf::from(crate::tr!("fs.no_user_data_dir")));
+    Err(crate::error::TranslationError::FileNotFound(format!(
+        "Data files not found: {}. Searched in:\n1. ./data/\n2. {}/",
+        missing.join(", "),
+        user_data_dir.display()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_bytes() {
+        let text = "l_english:\n key: \"Café\"";
+        let (decoded, encoding) = decode_bytes(format!("\u{FEFF}{}", text).as_bytes());
+        assert_eq!((decoded.as_str(), encoding.name()), (text, "UTF-8"));
+
+        let utf16: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        let (decoded, encoding) = decode_bytes(&utf16);
+        assert_eq!((decoded.as_str(), encoding.name()), (text, "UTF-16LE"));
+
+        let (windows_1252, _, _) =
+            encoding_rs::WINDOWS_1252.encode("l_english:\n key: \"Café «test»\"");
+        let (decoded, encoding) = decode_bytes(&windows_1252);
+        assert_eq!(
+            (decoded.as_str(), encoding.name()),
+            ("l_english:\n key: \"Café «test»\"", "windows-1252")
+        );
+
+        let polish = "l_polish:\n key: \"Zażółć gęślą jaźń. Źródło światła oświetla łąkę.\"";
+        let (windows_1250, _, _) = encoding_rs::WINDOWS_1250.encode(polish);
+        let (decoded, encoding) = decode_bytes(&windows_1250);
+        assert_eq!(
+            (decoded.as_str(), encoding.name()),
+            (polish, "windows-1250")
+        );
+    }
+}
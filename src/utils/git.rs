@@ -0,0 +1,87 @@
+//! Git 辅助函数
+//!
+//! 调用 `git` 命令查询源语言目录自指定修订版本以来的变化，以及目标文件在之前修订版本中的内容。
+
+use crate::error::{Result, TranslationError};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 在 `dir` 中执行 git 命令，返回标准输出
+fn run_git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| {
+            TranslationError::Io(std::io::Error::other(format!("Failed to run git: {}", e)))
+        })?;
+    if !output.status.success() {
+        return Err(TranslationError::Io(std::io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 列出 `dir` 下自 `since` 以来有变化的文件（包括工作区中未提交的修改和未跟踪的新文件）
+pub fn changed_files(dir: &Path, since: &str) -> Result<Vec<PathBuf>> {
+    let diff = run_git(
+        dir,
+        &["diff", "--name-only", "--relative", since, "--", "."],
+    )?;
+    let untracked = run_git(
+        dir,
+        &["ls-files", "--others", "--exclude-standard", "--", "."],
+    )?;
+
+    let mut files: Vec<PathBuf> = diff
+        .lines()
+        .chain(untracked.lines())
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| dir.join(line.trim()))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// 列出 `dir` 下在 `revision` 修订版本中已提交的文件
+pub fn files_at_revision(dir: &Path, revision: &str) -> Result<Vec<PathBuf>> {
+    let listing = run_git(
+        dir,
+        &["ls-tree", "-r", "-z", "--name-only", revision, "--", "."],
+    )?;
+    Ok(listing
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| dir.join(path))
+        .collect())
+}
+
+/// 读取文件在 `since` 修订版本中的内容，该版本中不存在时返回 None
+///
+/// `file` 需要位于 `dir` 之下。
+pub fn file_at_revision(dir: &Path, since: &str, file: &Path) -> Result<Option<String>> {
+    let relative = file.strip_prefix(dir).unwrap_or(file);
+    let spec = format!(
+        "{}:./{}",
+        since,
+        relative.to_string_lossy().replace('\\', "/")
+    );
+    // 先确认对象存在，以区分「文件是新增的」与其他错误
+    let exists = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["cat-file", "-e", &spec])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if !exists {
+        return Ok(None);
+    }
+    run_git(dir, &["show", &spec]).map(Some)
+}
@@ -0,0 +1,14 @@
+//! 稳定的哈希
+//!
+//! 写入缓存文件或决定请求种子的哈希必须不随编译器版本与平台变化，因此不使用标准库的哈希器。
+
+/// 64 位 FNV-1a 哈希的初始值
+pub const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64 位 FNV-1a 哈希，结果不随编译器版本变化，可以写入缓存文件
+pub fn fnv1a(hash: &mut u64, bytes: &[u8]) {
+    for &b in bytes {
+        *hash ^= u64::from(b);
+        *hash = hash.wrapping_mul(0x100_0000_01b3);
+    }
+}
@@ -3,9 +3,19 @@
 //! 提供通用辅助函数，如文件系统操作、正则表达式模式等。
 
 mod fs;
-mod token_estimator;
+mod git;
+mod hash;
 mod logger;
+mod notifier;
+mod patterns;
+mod shutdown;
+mod token_estimator;
 
 pub use fs::*;
+pub use git::*;
+pub use hash::*;
+pub use logger::*;
+pub use notifier::*;
+pub use patterns::*;
+pub use shutdown::*;
 pub use token_estimator::*;
-pub use logger::*;
\ No newline at end of file
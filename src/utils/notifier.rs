@@ -0,0 +1,118 @@
+//! Webhook 通知
+//!
+//! 在任务完成、失败或超出 token 预算时向配置的 webhook 发送通知。
+
+use crate::config::{NotificationEvent, NotificationSettings};
+use crate::metrics::metrics;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 预算检查间隔
+const BUDGET_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Webhook 通知器
+pub struct Notifier {
+    client: reqwest::Client,
+    settings: NotificationSettings,
+}
+
+impl Notifier {
+    /// 创建新的通知器
+    pub fn new(settings: NotificationSettings) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            settings,
+        }
+    }
+
+    /// 用事件信息和当前运行指标填充请求体模板
+    pub fn render_payload(&self, event: NotificationEvent, task: &str, message: &str) -> String {
+        let m = metrics();
+        let values = [
+            ("event", event.as_str().to_string()),
+            ("message", message.to_string()),
+            ("task", task.to_string()),
+            ("files", m.files_completed_count().to_string()),
+            ("tokens", m.total_tokens().to_string()),
+            ("elapsed", format!("{}s", m.elapsed().as_secs())),
+        ];
+        let mut payload = self.settings.payload_template.clone();
+        for (name, value) in values {
+            payload = payload.replace(&format!("{{{{{}}}}}", name), &escape_json(&value));
+        }
+        payload
+    }
+
+    /// 发送通知，失败时只记录警告而不影响翻译任务
+    pub async fn notify(&self, event: NotificationEvent, task: &str, message: &str) {
+        if !self.settings.wants(event) {
+            return;
+        }
+        let payload = self.render_payload(event, task, message);
+        let result = self
+            .client
+            .post(&self.settings.webhook_url)
+            .header("Content-Type", "application/json")
+            .body(payload)
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => {
+                log::info!("Sent '{}' notification", event.as_str());
+            }
+            Ok(response) => {
+                log::warn!("Notification webhook returned status {}", response.status())
+            }
+            Err(e) => log::warn!("Failed to send notification: {}", e),
+        }
+    }
+
+    /// 若配置了 token 预算，则在后台定期检查用量，首次超出时发送一次通知
+    pub fn watch_budget(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let budget = self.settings.token_budget?;
+        let notifier = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(BUDGET_CHECK_INTERVAL).await;
+                let used = metrics().total_tokens();
+                if used > budget {
+                    let message = format!(
+                        "[pmt] Token budget exceeded: {} tokens used, budget is {}",
+                        used, budget
+                    );
+                    log::warn!("{}", message);
+                    notifier
+                        .notify(NotificationEvent::BudgetExceeded, "", &message)
+                        .await;
+                    break;
+                }
+            }
+        }))
+    }
+}
+
+/// 转义字符串以便嵌入 JSON 字符串字面量
+fn escape_json(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_payload_escapes_values() {
+        let settings: NotificationSettings =
+            toml::from_str(r#"webhook_url = "https://example.com/hook""#).unwrap();
+        let notifier = Notifier::new(settings);
+        let payload =
+            notifier.render_payload(NotificationEvent::Failed, "mod", "bad \"quote\"\nline");
+        let value: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(value["content"], "bad \"quote\"\nline");
+    }
+}
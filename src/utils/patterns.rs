@@ -0,0 +1,51 @@
+//! 内置正则表达式
+//!
+//! 内置的正则表达式在首次使用时编译一次并缓存，之后的调用直接复用。
+//! 模式有误时记录错误并退化为不匹配任何文本的表达式，不会在翻译途中 panic。
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// 取得缓存在 `cell` 中的正则表达式，首次调用时由 `pattern` 给出模式并编译
+///
+/// ```ignore
+/// fn placeholder_pattern() -> &'static Regex {
+///     static PATTERN: OnceLock<Regex> = OnceLock::new();
+///     cached_regex(&PATTERN, || r"\{N(\d+)\}")
+/// }
+/// ```
+pub fn cached_regex<S: AsRef<str>>(
+    cell: &'static OnceLock<Regex>,
+    pattern: impl FnOnce() -> S,
+) -> &'static Regex {
+    cell.get_or_init(|| {
+        let pattern = pattern();
+        Regex::new(pattern.as_ref()).unwrap_or_else(|e| {
+            log::error!("Invalid built-in pattern {:?}: {}", pattern.as_ref(), e);
+            never_matching()
+        })
+    })
+}
+
+/// 不匹配任何文本的正则表达式
+fn never_matching() -> Regex {
+    Regex::new(r"[^\s\S]").expect("an empty character class is a valid pattern")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_regex() {
+        static VALID: OnceLock<Regex> = OnceLock::new();
+        let pattern = cached_regex(&VALID, || r"\{N(\d+)\}");
+        assert!(pattern.is_match("{N0}"));
+        assert!(std::ptr::eq(pattern, cached_regex(&VALID, || "unused")));
+
+        static INVALID: OnceLock<Regex> = OnceLock::new();
+        let pattern = cached_regex(&INVALID, || format!("({}", "unclosed"));
+        assert!(!pattern.is_match("unclosed"));
+        assert!(!pattern.is_match(""));
+    }
+}
@@ -0,0 +1,99 @@
+//! 中断处理
+//!
+//! 收到 Ctrl-C 时，进行中的命令会被直接取消。需要在退出前保存的状态（例如翻译记忆）在此登记，
+//! 由 `run_shutdown_hooks` 在退出前依次执行。
+//!
+//! 登记的 guard 通常由被取消的命令持有，因此必须在丢弃命令之前执行这些操作，见 [`run_until_interrupted`]。
+
+use crate::error::Result;
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+type Hook = Box<dyn Fn() -> Result<()> + Send>;
+
+/// 已登记的退出前操作（编号、名称、操作）
+static HOOKS: Mutex<Vec<(u64, String, Hook)>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// 登记的退出前操作，被丢弃时撤销登记
+#[must_use = "the hook is unregistered when the guard is dropped"]
+pub struct ShutdownGuard(u64);
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        let mut hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+        hooks.retain(|(id, _, _)| *id != self.0);
+    }
+}
+
+/// 登记中断退出前需要执行的操作，在返回的 guard 被丢弃前有效
+pub fn on_shutdown(name: &str, hook: impl Fn() -> Result<()> + Send + 'static) -> ShutdownGuard {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+    hooks.push((id, name.to_string(), Box::new(hook)));
+    ShutdownGuard(id)
+}
+
+/// 依次执行所有已登记的退出前操作，单个操作失败不影响其余操作
+pub fn run_shutdown_hooks() {
+    let hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+    for (_, name, hook) in hooks.iter() {
+        match hook() {
+            Ok(()) => log::info!("Saved {} before exiting", name),
+            Err(e) => log::error!("Failed to save {} before exiting: {}", name, e),
+        }
+    }
+}
+
+/// 执行 `future`，`interrupt` 先完成时在丢弃 `future` 之前执行已登记的退出前操作，返回 None
+///
+/// `future` 中登记的 guard 随 `future` 一起被丢弃，若先丢弃再执行，这些操作已被撤销登记。
+pub async fn run_until_interrupted<F: Future>(
+    future: F,
+    interrupt: impl Future,
+) -> Option<F::Output> {
+    let mut future = std::pin::pin!(future);
+    tokio::select! {
+        output = &mut future => Some(output),
+        _ = interrupt => {
+            run_shutdown_hooks();
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_run_until_interrupted() {
+        let saved = Arc::new(AtomicUsize::new(0));
+        let command = {
+            let saved = saved.clone();
+            async move {
+                let _guard = on_shutdown("test state", move || {
+                    saved.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                });
+                std::future::pending::<()>().await
+            }
+        };
+        let interrupt = async {
+            // 让命令先登记退出前操作
+            tokio::task::yield_now().await;
+        };
+        assert!(run_until_interrupted(command, interrupt).await.is_none());
+        assert_eq!(saved.load(Ordering::SeqCst), 1);
+
+        // 命令被丢弃后其 guard 撤销了登记
+        run_shutdown_hooks();
+        assert_eq!(saved.load(Ordering::SeqCst), 1);
+
+        let finished = run_until_interrupted(async { 42 }, std::future::pending::<()>()).await;
+        assert_eq!(finished, Some(42));
+    }
+}